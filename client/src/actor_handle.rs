@@ -0,0 +1,29 @@
+use naia_shared::LocalActorKey;
+
+/// Refers to one particular Actor in the Client's scope. The Server reuses the
+/// LocalActorKeys of deleted Actors, so a LocalActorKey kept after its Actor
+/// is deleted may later refer to another Actor. An ActorHandle also records
+/// which generation of the key it was taken for, and stops resolving once its
+/// Actor is deleted, even if a new Actor takes over the key
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ActorHandle {
+    key: LocalActorKey,
+    generation: u32,
+}
+
+impl ActorHandle {
+    pub(crate) fn new(key: LocalActorKey, generation: u32) -> Self {
+        ActorHandle { key, generation }
+    }
+
+    /// Gets the LocalActorKey the Actor had when the handle was taken
+    pub fn key(&self) -> LocalActorKey {
+        return self.key;
+    }
+
+    /// Gets how many Actors, including this one, have been created with the
+    /// same LocalActorKey during the connection
+    pub fn generation(&self) -> u32 {
+        return self.generation;
+    }
+}
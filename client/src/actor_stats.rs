@@ -0,0 +1,20 @@
+/// Counts of the Actor messages applied by the Client, for example to
+/// measure how much the Server is sending each tick
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ActorStats {
+    /// The number of Actors created
+    pub creates: u16,
+    /// The number of Actor & Pawn updates applied
+    pub updates: u16,
+    /// The number of Actors deleted
+    pub deletes: u16,
+}
+
+impl ActorStats {
+    /// Adds the counts of another ActorStats onto these
+    pub fn add(&mut self, other: &ActorStats) {
+        self.creates = self.creates.saturating_add(other.creates);
+        self.updates = self.updates.saturating_add(other.updates);
+        self.deletes = self.deletes.saturating_add(other.deletes);
+    }
+}
@@ -0,0 +1,35 @@
+use std::fmt::{Debug, Formatter, Result};
+
+use naia_shared::LocalActorKey;
+
+/// Holds the callback to be called with each Actor the Server updates, so
+/// that applications can react to updates without looking the Actor up again
+pub struct ActorUpdateListener<U> {
+    update_func: Option<Box<dyn FnMut(LocalActorKey, &U)>>,
+}
+
+impl<U> ActorUpdateListener<U> {
+    pub fn new() -> Self {
+        ActorUpdateListener { update_func: None }
+    }
+
+    /// Sets the callback to be called with each updated Actor
+    pub fn on_update(&mut self, update_func: Box<dyn FnMut(LocalActorKey, &U)>) {
+        self.update_func = Some(update_func);
+    }
+
+    /// Calls the callback, if any, with an Actor which has been updated
+    pub fn notify(&mut self, key: LocalActorKey, actor: &U) {
+        if let Some(update_func) = &mut self.update_func {
+            (update_func)(key, actor);
+        }
+    }
+}
+
+impl<U> Debug for ActorUpdateListener<U> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.debug_struct("ActorUpdateListener")
+            .field("has_callback", &self.update_func.is_some())
+            .finish()
+    }
+}
@@ -1,10 +1,13 @@
 use log::warn;
 use naia_shared::{
-    ActorType, EventType, LocalActorKey, Manifest, PacketReader, SequenceBuffer, StateMask,
+    ActorType, EventType, LocalActorKey, Manifest, PacketReader, PacketReaderExt, SequenceBuffer,
+    StateMask, PREDICTION_TOKEN_FLAG, SPAWN_EVENT_FLAG,
 };
 use std::collections::{HashMap, VecDeque};
 
-use super::client_actor_message::ClientActorMessage;
+use super::{
+    actor_handle::ActorHandle, actor_stats::ActorStats, client_actor_message::ClientActorMessage,
+};
 use crate::{command_receiver::CommandReceiver, interpolation_manager::InterpolationManager};
 use std::collections::hash_map::Keys;
 
@@ -16,15 +19,31 @@ pub struct ClientActorManager<U: ActorType> {
     queued_incoming_messages: VecDeque<ClientActorMessage>,
     pawn_store: HashMap<LocalActorKey, U>,
     pawn_history: HashMap<LocalActorKey, SequenceBuffer<U>>,
+    last_update_ticks: HashMap<LocalActorKey, u16>,
+    // how many Actors have been created with each key, so that handles to
+    // deleted Actors don't resolve to an Actor which reuses their key
+    generations: HashMap<LocalActorKey, u32>,
+    predicted_actors: HashMap<u16, U>,
+    next_prediction_token: u16,
 }
 
 impl<U: ActorType> ClientActorManager<U> {
     pub fn new() -> Self {
+        return ClientActorManager::with_capacity(0);
+    }
+
+    /// Creates a new ClientActorManager, with room for `capacity` Actors
+    /// before its Actor store needs to grow
+    pub fn with_capacity(capacity: usize) -> Self {
         ClientActorManager {
             queued_incoming_messages: VecDeque::new(),
-            local_actor_store: HashMap::new(),
+            local_actor_store: HashMap::with_capacity(capacity),
             pawn_store: HashMap::new(),
             pawn_history: HashMap::new(),
+            last_update_ticks: HashMap::with_capacity(capacity),
+            generations: HashMap::with_capacity(capacity),
+            predicted_actors: HashMap::new(),
+            next_prediction_token: 0,
         }
     }
 
@@ -36,31 +55,91 @@ impl<U: ActorType> ClientActorManager<U> {
         packet_tick: u16,
         packet_index: u16,
         reader: &mut PacketReader,
-    ) {
-        let actor_message_count = reader.read_u8();
+    ) -> (Vec<Option<T>>, ActorStats) {
+        // the Event, if any, attached to each queued Create message, in order
+        let mut spawn_events = Vec::new();
+        let mut stats = ActorStats::default();
+        let actor_message_count = match reader.try_read_u8() {
+            Some(actor_message_count) => actor_message_count,
+            None => {
+                warn!("discarding truncated actor data");
+                return (spawn_events, stats);
+            }
+        };
         //info!("reading {} actor messages", actor_message_count);
         for _x in 0..actor_message_count {
-            let message_type: u8 = reader.read_u8();
+            let message_type: u8 = match reader.try_read_u8() {
+                Some(message_type) => message_type,
+                None => {
+                    warn!("discarding truncated actor data");
+                    reader.skip_remaining();
+                    break;
+                }
+            };
 
             match message_type {
                 0 => {
                     // Creation
-                    let naia_id: u16 = reader.read_u16();
-                    let local_key: u16 = reader.read_u16();
+                    let naia_id: u16 = match reader.try_read_u16() {
+                        Some(naia_id) => naia_id,
+                        None => {
+                            warn!("discarding truncated actor data");
+                            reader.skip_remaining();
+                            break;
+                        }
+                    };
+                    let local_key: u16 = match reader.try_read_u16() {
+                        Some(local_key) => local_key,
+                        None => {
+                            warn!("discarding truncated actor data");
+                            reader.skip_remaining();
+                            break;
+                        }
+                    };
 
                     match manifest.create_actor(naia_id, reader) {
                         Some(new_actor) => {
+                            let create_flags = reader.try_read_u8().unwrap_or_default();
+                            let spawn_event = if create_flags & SPAWN_EVENT_FLAG != 0 {
+                                read_spawn_event(manifest, reader)
+                            } else {
+                                None
+                            };
+                            let prediction_token = if create_flags & PREDICTION_TOKEN_FLAG != 0 {
+                                reader.try_read_u16()
+                            } else {
+                                None
+                            };
                             if self.local_actor_store.contains_key(&local_key) {
                                 warn!("duplicate local key inserted");
                             } else {
                                 //info!("creation of actor w/ key of {}", local_key);
                                 let is_interpolated = new_actor.is_interpolated();
+                                let type_id = new_actor.inner_ref().as_ref().borrow().get_type_id();
                                 self.local_actor_store.insert(local_key, new_actor);
+                                self.last_update_ticks.insert(local_key, packet_tick);
+                                let generation = self.generations.entry(local_key).or_default();
+                                *generation = generation.wrapping_add(1);
                                 if is_interpolated {
                                     interpolator.create_interpolation(&self, &local_key);
                                 }
-                                self.queued_incoming_messages
-                                    .push_back(ClientActorMessage::Create(local_key));
+                                spawn_events.push(spawn_event);
+                                stats.creates += 1;
+                                // the confirmed Actor takes the place of its prediction
+                                match prediction_token {
+                                    Some(token)
+                                        if self.predicted_actors.remove(&token).is_some() =>
+                                    {
+                                        self.queued_incoming_messages.push_back(
+                                            ClientActorMessage::ConfirmSpawn(token, local_key),
+                                        );
+                                    }
+                                    _ => {
+                                        self.queued_incoming_messages.push_back(
+                                            ClientActorMessage::Create(local_key, type_id),
+                                        );
+                                    }
+                                }
                             }
                         }
                         _ => {}
@@ -68,8 +147,20 @@ impl<U: ActorType> ClientActorManager<U> {
                 }
                 1 => {
                     // Deletion
-                    let local_key = reader.read_u16();
-                    self.local_actor_store.remove(&local_key);
+                    let local_key: u16 = match reader.try_read_u16() {
+                        Some(local_key) => local_key,
+                        None => {
+                            warn!("discarding truncated actor data");
+                            reader.skip_remaining();
+                            break;
+                        }
+                    };
+                    if self.local_actor_store.remove(&local_key).is_none() {
+                        // the Actor's Create never arrived
+                        continue;
+                    }
+                    stats.deletes += 1;
+                    self.last_update_ticks.remove(&local_key);
                     interpolator.delete_interpolation(&local_key);
 
                     if self.pawn_store.contains_key(&local_key) {
@@ -84,21 +175,40 @@ impl<U: ActorType> ClientActorManager<U> {
                 }
                 2 => {
                     // Update Actor
-                    let local_key = reader.read_u16();
+                    let local_key: u16 = match reader.try_read_u16() {
+                        Some(local_key) => local_key,
+                        None => {
+                            warn!("discarding truncated actor data");
+                            reader.skip_remaining();
+                            break;
+                        }
+                    };
 
                     if let Some(actor_ref) = self.local_actor_store.get_mut(&local_key) {
                         // Actor is not a Pawn
                         let state_mask: StateMask = StateMask::read(reader);
 
                         actor_ref.read_partial(&state_mask, reader, packet_index);
+                        self.last_update_ticks.insert(local_key, packet_tick);
+                        stats.updates += 1;
 
                         self.queued_incoming_messages
-                            .push_back(ClientActorMessage::Update(local_key));
+                            .push_back(ClientActorMessage::Update(local_key, state_mask));
+
+                        // interpolation starts fresh from the authoritative state
+                        interpolator.sync_interpolation(self, &local_key);
                     }
                 }
                 3 => {
                     // Assign Pawn
-                    let local_key: u16 = reader.read_u16();
+                    let local_key: u16 = match reader.try_read_u16() {
+                        Some(local_key) => local_key,
+                        None => {
+                            warn!("discarding truncated actor data");
+                            reader.skip_remaining();
+                            break;
+                        }
+                    };
 
                     if let Some(actor_ref) = self.local_actor_store.get_mut(&local_key) {
                         self.pawn_store.insert(
@@ -121,22 +231,41 @@ impl<U: ActorType> ClientActorManager<U> {
                 }
                 4 => {
                     // Unassign Pawn
-                    let local_key: u16 = reader.read_u16();
+                    let local_key: u16 = match reader.try_read_u16() {
+                        Some(local_key) => local_key,
+                        None => {
+                            warn!("discarding truncated actor data");
+                            reader.skip_remaining();
+                            break;
+                        }
+                    };
                     if self.pawn_store.contains_key(&local_key) {
                         self.pawn_store.remove(&local_key);
                         self.pawn_history.remove(&local_key);
                         command_receiver.pawn_cleanup(&local_key);
                         interpolator.delete_pawn_interpolation(&local_key);
                     }
+                    // the snapshots buffered while this was a pawn would
+                    // rewind it by the interpolation delay
+                    interpolator.sync_interpolation(&self, &local_key);
                     self.queued_incoming_messages
                         .push_back(ClientActorMessage::UnassignPawn(local_key));
                 }
                 5 => {
                     // Update Pawn
-                    let local_key = reader.read_u16();
+                    let local_key: u16 = match reader.try_read_u16() {
+                        Some(local_key) => local_key,
+                        None => {
+                            warn!("discarding truncated actor data");
+                            reader.skip_remaining();
+                            break;
+                        }
+                    };
 
                     if let Some(actor_ref) = self.local_actor_store.get_mut(&local_key) {
                         actor_ref.read_full(reader, packet_index);
+                        self.last_update_ticks.insert(local_key, packet_tick);
+                        stats.updates += 1;
 
                         // check it against it's history
                         if let Some(pawn_history) = self.pawn_history.get_mut(&local_key) {
@@ -153,13 +282,27 @@ impl<U: ActorType> ClientActorManager<U> {
                         // remove command history until the tick that has already been checked
                         command_receiver.remove_history_until(packet_tick, local_key);
 
+                        // Pawns are updated in full, so every Property may have changed
+                        let state_mask_size = actor_ref
+                            .inner_ref()
+                            .as_ref()
+                            .borrow()
+                            .get_state_mask_size();
+                        let mut state_mask = StateMask::new(state_mask_size);
+                        for index in 0..(state_mask_size * 8) {
+                            state_mask.set_bit(index, true);
+                        }
+
                         self.queued_incoming_messages
-                            .push_back(ClientActorMessage::Update(local_key));
+                            .push_back(ClientActorMessage::Update(local_key, state_mask));
+
+                        interpolator.sync_pawn_interpolation(self, &local_key);
                     }
                 }
                 _ => {}
             }
         }
+        return (spawn_events, stats);
     }
 
     pub fn pop_incoming_message(&mut self) -> Option<ClientActorMessage> {
@@ -174,6 +317,47 @@ impl<U: ActorType> ClientActorManager<U> {
         return self.local_actor_store.get(key);
     }
 
+    pub fn get_actor_handle(&self, key: &LocalActorKey) -> Option<ActorHandle> {
+        if !self.local_actor_store.contains_key(key) {
+            return None;
+        }
+        let generation = self.generations.get(key).copied().unwrap_or_default();
+        return Some(ActorHandle::new(*key, generation));
+    }
+
+    /// Returns whether the Actor the handle was taken for is still in scope
+    pub fn is_current(&self, handle: &ActorHandle) -> bool {
+        return self.get_actor_handle(&handle.key()).as_ref() == Some(handle);
+    }
+
+    pub fn actors(&self) -> impl Iterator<Item = (LocalActorKey, &U)> {
+        return self
+            .local_actor_store
+            .iter()
+            .map(|(key, actor)| (*key, actor));
+    }
+
+    pub fn get_last_update_tick(&self, key: &LocalActorKey) -> Option<u16> {
+        return self.last_update_ticks.get(key).copied();
+    }
+
+    /// Holds a locally spawned Actor until the Server confirms it, returning
+    /// the token to match the confirmation by
+    pub fn predict_spawn(&mut self, actor: U) -> u16 {
+        let token = self.next_prediction_token;
+        self.next_prediction_token = self.next_prediction_token.wrapping_add(1);
+        self.predicted_actors.insert(token, actor);
+        return token;
+    }
+
+    pub fn get_predicted_actor(&self, token: &u16) -> Option<&U> {
+        return self.predicted_actors.get(token);
+    }
+
+    pub fn cancel_predicted_spawn(&mut self, token: &u16) -> Option<U> {
+        return self.predicted_actors.remove(token);
+    }
+
     pub fn pawn_keys(&self) -> Keys<LocalActorKey, U> {
         return self.pawn_store.keys();
     }
@@ -182,6 +366,10 @@ impl<U: ActorType> ClientActorManager<U> {
         return self.pawn_store.get(key);
     }
 
+    pub fn pawns(&self) -> impl Iterator<Item = (LocalActorKey, &U)> {
+        return self.pawn_store.iter().map(|(key, pawn)| (*key, pawn));
+    }
+
     pub fn pawn_reset(&mut self, key: &LocalActorKey) {
         if let Some(actor_ref) = self.local_actor_store.get_mut(key) {
             self.pawn_store.remove(key);
@@ -209,3 +397,884 @@ impl<U: ActorType> ClientActorManager<U> {
         }
     }
 }
+
+impl<U: ActorType> Default for ClientActorManager<U> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Reads the Event attached to an Actor's creation, if it can be read
+fn read_spawn_event<T: EventType, U: ActorType>(
+    manifest: &Manifest<T, U>,
+    reader: &mut PacketReader,
+) -> Option<T> {
+    let naia_id: u16 = reader.try_read_u16()?;
+    let payload_length: u16 = reader.try_read_u16()?;
+    if usize::from(payload_length) > reader.remaining() {
+        warn!("discarding spawn event with a payload length past the end of the packet");
+        reader.skip_remaining();
+        return None;
+    }
+
+    let payload_start = reader.get_cursor().position() as usize;
+    let payload_end = payload_start + usize::from(payload_length);
+    let mut payload_reader = PacketReader::new(&reader.get_buffer()[payload_start..payload_end]);
+    let spawn_event = manifest.create_event(naia_id, &mut payload_reader);
+    if spawn_event.is_none() {
+        warn!("skipping spawn event with unknown naia_id: {}", naia_id);
+    }
+    reader.get_cursor().set_position(payload_end as u64);
+    return spawn_event;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{any::TypeId, cell::RefCell, rc::Rc};
+
+    use std::time::Duration;
+
+    use naia_shared::{
+        Actor, ActorBuilder, ActorMutator, ActorType, ConnectionConfig, DeliveryGuarantee, Event,
+        EventBuilder, EventType, LocalActorKey, Manifest, PacketReader, SequenceBuffer, StateMask,
+    };
+
+    use super::{ClientActorManager, PAWN_HISTORY_SIZE};
+    use crate::{
+        actor_update_listener::ActorUpdateListener, client_actor_message::ClientActorMessage,
+        client_tick_manager::ClientTickManager, command_receiver::CommandReceiver,
+        interpolation_manager::InterpolationManager, server_connection::ServerConnection,
+    };
+
+    #[derive(Clone, Debug)]
+    struct TestActor {
+        x: i16,
+    }
+
+    impl Actor<TestActorType> for TestActor {
+        fn get_state_mask_size(&self) -> u8 {
+            return 1;
+        }
+        fn get_typed_copy(&self) -> TestActorType {
+            return TestActorType::TestActor(Rc::new(RefCell::new(self.clone())));
+        }
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<TestActor>();
+        }
+        fn write(&self, _: &mut Vec<u8>) {}
+        fn write_partial(&self, _: &StateMask, _: &mut Vec<u8>) {}
+        fn read_full(&mut self, _: &mut PacketReader, _: u16) {}
+        fn read_partial(&mut self, _: &StateMask, _: &mut PacketReader, _: u16) {}
+        fn set_mutator(&mut self, _: &Rc<RefCell<dyn ActorMutator>>) {}
+        fn is_interpolated(&self) -> bool {
+            return false;
+        }
+        fn is_predicted(&self) -> bool {
+            return true;
+        }
+    }
+
+    // An Actor without state, to tell apart from TestActor
+    #[derive(Clone, Debug)]
+    struct MarkerActor;
+
+    impl Actor<TestActorType> for MarkerActor {
+        fn get_state_mask_size(&self) -> u8 {
+            return 1;
+        }
+        fn get_typed_copy(&self) -> TestActorType {
+            return TestActorType::MarkerActor(Rc::new(RefCell::new(self.clone())));
+        }
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<MarkerActor>();
+        }
+        fn write(&self, _: &mut Vec<u8>) {}
+        fn write_partial(&self, _: &StateMask, _: &mut Vec<u8>) {}
+        fn read_full(&mut self, _: &mut PacketReader, _: u16) {}
+        fn read_partial(&mut self, _: &StateMask, _: &mut PacketReader, _: u16) {}
+        fn set_mutator(&mut self, _: &Rc<RefCell<dyn ActorMutator>>) {}
+        fn is_interpolated(&self) -> bool {
+            return false;
+        }
+        fn is_predicted(&self) -> bool {
+            return false;
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    enum TestActorType {
+        TestActor(Rc<RefCell<TestActor>>),
+        MarkerActor(Rc<RefCell<MarkerActor>>),
+    }
+
+    impl TestActorType {
+        fn new(x: i16) -> Self {
+            return TestActorType::TestActor(Rc::new(RefCell::new(TestActor { x })));
+        }
+
+        fn x(&self) -> i16 {
+            match self {
+                TestActorType::TestActor(actor) => return actor.borrow().x,
+                TestActorType::MarkerActor(_) => return 0,
+            }
+        }
+    }
+
+    impl ActorType for TestActorType {
+        fn read_full(&mut self, _: &mut PacketReader, _: u16) {}
+        fn read_partial(&mut self, state_mask: &StateMask, reader: &mut PacketReader, _: u16) {
+            if state_mask.get_bit(0) == Some(true) {
+                match self {
+                    TestActorType::TestActor(actor) => {
+                        actor.borrow_mut().x = reader.read_u8() as i16
+                    }
+                    TestActorType::MarkerActor(_) => {}
+                }
+            }
+        }
+        fn inner_ref(&self) -> Rc<RefCell<dyn Actor<TestActorType>>> {
+            match self {
+                TestActorType::TestActor(actor) => return actor.clone(),
+                TestActorType::MarkerActor(actor) => return actor.clone(),
+            }
+        }
+        fn equals(&self, other: &TestActorType) -> bool {
+            return self.x() == other.x();
+        }
+        fn equals_prediction(&self, other: &TestActorType) -> bool {
+            return self.x() == other.x();
+        }
+        fn set_to_interpolation(&mut self, _: &TestActorType, _: &TestActorType, _: f32) {}
+        fn set_to_extrapolation(&mut self, _: &TestActorType, _: &TestActorType, _: f32) {}
+        fn mirror(&mut self, other: &TestActorType) {
+            match self {
+                TestActorType::TestActor(actor) => actor.borrow_mut().x = other.x(),
+                TestActorType::MarkerActor(_) => {}
+            }
+        }
+        fn is_interpolated(&self) -> bool {
+            return false;
+        }
+        fn is_predicted(&self) -> bool {
+            return true;
+        }
+    }
+
+    struct TestActorBuilder;
+
+    impl ActorBuilder<TestActorType> for TestActorBuilder {
+        fn build(&self, reader: &mut PacketReader) -> TestActorType {
+            return TestActorType::new(reader.read_u8() as i16);
+        }
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<TestActor>();
+        }
+    }
+
+    struct MarkerActorBuilder;
+
+    impl ActorBuilder<TestActorType> for MarkerActorBuilder {
+        fn build(&self, _: &mut PacketReader) -> TestActorType {
+            return TestActorType::MarkerActor(Rc::new(RefCell::new(MarkerActor)));
+        }
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<MarkerActor>();
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    struct TestCommand {
+        tick: u16,
+    }
+
+    #[derive(Clone, Debug)]
+    enum TestEventType {
+        TestCommand(TestCommand),
+    }
+
+    impl EventType for TestEventType {
+        fn write(&self, _: &mut Vec<u8>) {}
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<TestCommand>();
+        }
+    }
+
+    impl Event<TestEventType> for TestCommand {
+        fn guarantee_delivery(&self) -> DeliveryGuarantee {
+            return DeliveryGuarantee::Unreliable;
+        }
+        fn write(&self, _: &mut Vec<u8>) {}
+        fn get_typed_copy(&self) -> TestEventType {
+            return TestEventType::TestCommand(self.clone());
+        }
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<TestCommand>();
+        }
+    }
+
+    struct TestCommandBuilder;
+
+    impl EventBuilder<TestEventType> for TestCommandBuilder {
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<TestCommand>();
+        }
+        fn build(&self, reader: &mut PacketReader) -> TestEventType {
+            return TestEventType::TestCommand(TestCommand {
+                tick: reader.read_u16(),
+            });
+        }
+    }
+
+    fn command_tick(command: &Rc<Box<dyn Event<TestEventType>>>) -> u16 {
+        match command.as_ref().get_typed_copy() {
+            TestEventType::TestCommand(command) => return command.tick,
+        }
+    }
+
+    #[test]
+    fn mispredicted_pawn_snaps_to_server_state_and_replays_commands() {
+        let pawn_key: LocalActorKey = 0;
+        let mut actor_manager = ClientActorManager::<TestActorType>::new();
+        let mut command_receiver = CommandReceiver::<TestEventType>::new();
+
+        // server authoritative state, and the locally predicted pawn
+        actor_manager
+            .local_actor_store
+            .insert(pawn_key, TestActorType::new(5));
+        actor_manager
+            .pawn_store
+            .insert(pawn_key, TestActorType::new(9));
+        actor_manager
+            .pawn_history
+            .insert(pawn_key, SequenceBuffer::with_capacity(PAWN_HISTORY_SIZE));
+        command_receiver.pawn_init(&pawn_key);
+
+        // commands are applied locally & buffered by tick
+        for tick in 10..=12 {
+            let command: Rc<Box<dyn Event<TestEventType>>> =
+                Rc::new(Box::new(TestCommand { tick }));
+            command_receiver.queue_command(tick, pawn_key, &command);
+        }
+        while command_receiver.pop_command().is_some() {}
+
+        // server update for tick 11 disagrees with the prediction
+        command_receiver.replay_commands(11, pawn_key);
+
+        let (tick, key, command) = command_receiver
+            .pop_command_replay(&mut actor_manager)
+            .unwrap();
+        assert!(actor_manager.get_pawn(&pawn_key).unwrap().x() == 5);
+        assert!(tick == 11 && key == pawn_key && command_tick(&command) == 11);
+
+        let (tick, _, command) = command_receiver
+            .pop_command_replay(&mut actor_manager)
+            .unwrap();
+        assert!(tick == 12 && command_tick(&command) == 12);
+
+        assert!(command_receiver
+            .pop_command_replay(&mut actor_manager)
+            .is_none());
+    }
+
+    #[test]
+    fn replayed_commands_are_ordered_and_surfaced_once() {
+        let pawn_key: LocalActorKey = 0;
+        let mut actor_manager = ClientActorManager::<TestActorType>::new();
+        let mut command_receiver = CommandReceiver::<TestEventType>::new();
+        actor_manager
+            .local_actor_store
+            .insert(pawn_key, TestActorType::new(0));
+        command_receiver.pawn_init(&pawn_key);
+
+        for tick in 20..=24 {
+            let command: Rc<Box<dyn Event<TestEventType>>> =
+                Rc::new(Box::new(TestCommand { tick }));
+            command_receiver.queue_command(tick, pawn_key, &command);
+        }
+        while command_receiver.pop_command().is_some() {}
+
+        // the Pawn is reset to the latest Server state, so multiple
+        // mispredictions replay from the most recent one
+        command_receiver.replay_commands(21, pawn_key);
+        command_receiver.replay_commands(23, pawn_key);
+
+        let mut replayed_ticks = Vec::new();
+        while let Some((tick, _, command)) = command_receiver.pop_command_replay(&mut actor_manager)
+        {
+            assert!(tick == command_tick(&command));
+            replayed_ticks.push(tick);
+        }
+
+        assert!(replayed_ticks == vec![23, 24]);
+        assert!(command_receiver
+            .pop_command_replay(&mut actor_manager)
+            .is_none());
+    }
+
+    #[test]
+    fn assigned_actor_is_predicted_until_unassigned() {
+        let key: LocalActorKey = 3;
+        let manifest = Manifest::<TestEventType, TestActorType>::new();
+        let mut actor_manager = ClientActorManager::<TestActorType>::new();
+        let mut command_receiver = CommandReceiver::<TestEventType>::new();
+        let mut interpolator = InterpolationManager::new(
+            Duration::from_millis(100),
+            false,
+            Duration::default(),
+            false,
+        );
+        actor_manager
+            .local_actor_store
+            .insert(key, TestActorType::new(7));
+        interpolator.create_interpolation(&actor_manager, &key);
+
+        // Assign Pawn message
+        let assign_bytes: Vec<u8> = vec![1, 3, 0, 3];
+        actor_manager.process_data(
+            &manifest,
+            &mut command_receiver,
+            &mut interpolator,
+            0,
+            0,
+            &mut PacketReader::new(&assign_bytes),
+        );
+        match actor_manager.pop_incoming_message() {
+            Some(ClientActorMessage::AssignPawn(assigned_key)) => assert!(assigned_key == key),
+            _ => panic!("expected an AssignPawn message"),
+        }
+        assert!(actor_manager.get_pawn(&key).unwrap().x() == 7);
+        assert!(actor_manager.pawn_history.contains_key(&key));
+
+        // Unassign Pawn message
+        let unassign_bytes: Vec<u8> = vec![1, 4, 0, 3];
+        actor_manager.process_data(
+            &manifest,
+            &mut command_receiver,
+            &mut interpolator,
+            0,
+            0,
+            &mut PacketReader::new(&unassign_bytes),
+        );
+        match actor_manager.pop_incoming_message() {
+            Some(ClientActorMessage::UnassignPawn(unassigned_key)) => {
+                assert!(unassigned_key == key)
+            }
+            _ => panic!("expected an UnassignPawn message"),
+        }
+        assert!(actor_manager.get_pawn(&key).is_none());
+        assert!(!actor_manager.pawn_history.contains_key(&key));
+        assert!(actor_manager.get_actor(&key).is_some());
+        assert!(interpolator.get_interpolation(&key).unwrap().x() == 7);
+    }
+
+    #[test]
+    fn synced_interpolation_discards_stale_snapshots() {
+        let key: LocalActorKey = 4;
+        let mut actor_manager = ClientActorManager::<TestActorType>::new();
+        let mut interpolator = InterpolationManager::new(
+            Duration::from_millis(100),
+            false,
+            Duration::default(),
+            false,
+        );
+        actor_manager
+            .local_actor_store
+            .insert(key, TestActorType::new(7));
+        interpolator.create_interpolation(&actor_manager, &key);
+
+        // an authoritative update arrives
+        actor_manager
+            .local_actor_store
+            .insert(key, TestActorType::new(9));
+
+        // within the interpolation delay, the stale snapshot is still rendered
+        assert!(interpolator.get_interpolation(&key).unwrap().x() == 7);
+
+        interpolator.sync_interpolation(&actor_manager, &key);
+        assert!(interpolator.get_interpolation(&key).unwrap().x() == 9);
+    }
+
+    #[test]
+    fn update_message_syncs_the_interpolation() {
+        let key: LocalActorKey = 4;
+        let manifest = Manifest::<TestEventType, TestActorType>::new();
+        let mut actor_manager = ClientActorManager::<TestActorType>::new();
+        let mut command_receiver = CommandReceiver::<TestEventType>::new();
+        let mut interpolator = InterpolationManager::new(
+            Duration::from_millis(100),
+            false,
+            Duration::default(),
+            false,
+        );
+        actor_manager
+            .local_actor_store
+            .insert(key, TestActorType::new(7));
+        interpolator.create_interpolation(&actor_manager, &key);
+
+        // Update Actor message setting x to 9
+        let mut update_bytes: Vec<u8> = vec![1, 2, 0, 4];
+        let mut state_mask = StateMask::new(1);
+        state_mask.set_bit(0, true);
+        state_mask.write(&mut update_bytes);
+        update_bytes.push(9);
+        actor_manager.process_data(
+            &manifest,
+            &mut command_receiver,
+            &mut interpolator,
+            1,
+            0,
+            &mut PacketReader::new(&update_bytes),
+        );
+
+        // the new state is rendered at once, not after the interpolation delay
+        assert!(interpolator.get_interpolation(&key).unwrap().x() == 9);
+    }
+
+    #[test]
+    fn iteration_yields_every_created_actor() {
+        let mut manifest = Manifest::<TestEventType, TestActorType>::new();
+        manifest.register_actor(Box::new(TestActorBuilder));
+        let mut actor_manager = ClientActorManager::<TestActorType>::new();
+        let mut command_receiver = CommandReceiver::<TestEventType>::new();
+        let mut interpolator = InterpolationManager::new(
+            Duration::from_millis(100),
+            false,
+            Duration::default(),
+            false,
+        );
+
+        // two Create messages, for keys 1 & 2 with x of 10 & 20
+        let create_bytes: Vec<u8> = vec![2, 0, 0, 0, 0, 1, 10, 0, 0, 0, 0, 0, 2, 20, 0];
+        actor_manager.process_data(
+            &manifest,
+            &mut command_receiver,
+            &mut interpolator,
+            0,
+            0,
+            &mut PacketReader::new(&create_bytes),
+        );
+
+        let mut actors: Vec<(LocalActorKey, i16)> = actor_manager
+            .actors()
+            .map(|(key, actor)| (key, actor.x()))
+            .collect();
+        actors.sort();
+        assert!(actors == vec![(1, 10), (2, 20)]);
+        assert!(actor_manager.pawns().next().is_none());
+    }
+
+    #[test]
+    fn creating_actors_within_capacity_does_not_reallocate() {
+        let mut manifest = Manifest::<TestEventType, TestActorType>::new();
+        manifest.register_actor(Box::new(TestActorBuilder));
+        let mut actor_manager = ClientActorManager::<TestActorType>::with_capacity(32);
+        let mut command_receiver = CommandReceiver::<TestEventType>::new();
+        let mut interpolator = InterpolationManager::new(
+            Duration::from_millis(100),
+            false,
+            Duration::default(),
+            false,
+        );
+        let store_capacity = actor_manager.local_actor_store.capacity();
+        assert!(store_capacity >= 32);
+
+        // 32 Create messages, for keys 0 to 31
+        let mut create_bytes: Vec<u8> = vec![32];
+        for key in 0..32 {
+            create_bytes.extend_from_slice(&[0, 0, 0, 0, key, 10, 0]);
+        }
+        actor_manager.process_data(
+            &manifest,
+            &mut command_receiver,
+            &mut interpolator,
+            0,
+            0,
+            &mut PacketReader::new(&create_bytes),
+        );
+
+        assert!(actor_manager.actors().count() == 32);
+        assert!(actor_manager.local_actor_store.capacity() == store_capacity);
+    }
+
+    #[test]
+    fn update_message_advances_last_update_tick() {
+        let key: LocalActorKey = 3;
+        let manifest = Manifest::<TestEventType, TestActorType>::new();
+        let mut actor_manager = ClientActorManager::<TestActorType>::new();
+        let mut command_receiver = CommandReceiver::<TestEventType>::new();
+        let mut interpolator = InterpolationManager::new(
+            Duration::from_millis(100),
+            false,
+            Duration::default(),
+            false,
+        );
+        actor_manager
+            .local_actor_store
+            .insert(key, TestActorType::new(0));
+        assert!(actor_manager.get_last_update_tick(&key).is_none());
+
+        // Update Actor message with an empty StateMask, at ticks 10 & 12
+        let update_bytes: Vec<u8> = vec![1, 2, 0, 3, 0];
+        for tick in &[10, 12] {
+            actor_manager.process_data(
+                &manifest,
+                &mut command_receiver,
+                &mut interpolator,
+                *tick,
+                0,
+                &mut PacketReader::new(&update_bytes),
+            );
+            assert!(actor_manager.get_last_update_tick(&key) == Some(*tick));
+        }
+
+        // Delete Actor message
+        let delete_bytes: Vec<u8> = vec![1, 1, 0, 3];
+        actor_manager.process_data(
+            &manifest,
+            &mut command_receiver,
+            &mut interpolator,
+            13,
+            0,
+            &mut PacketReader::new(&delete_bytes),
+        );
+        assert!(actor_manager.get_last_update_tick(&key).is_none());
+    }
+
+    #[test]
+    fn update_message_reports_the_changed_properties() {
+        let key: LocalActorKey = 3;
+        let manifest = Manifest::<TestEventType, TestActorType>::new();
+        let mut actor_manager = ClientActorManager::<TestActorType>::new();
+        let mut command_receiver = CommandReceiver::<TestEventType>::new();
+        let mut interpolator = InterpolationManager::new(
+            Duration::from_millis(100),
+            false,
+            Duration::default(),
+            false,
+        );
+        actor_manager
+            .local_actor_store
+            .insert(key, TestActorType::new(0));
+
+        // Update Actor message, where the second of three Properties changed
+        let mut update_bytes: Vec<u8> = vec![1, 2, 0, 3];
+        let mut state_mask = StateMask::new(1);
+        state_mask.set_bit(1, true);
+        state_mask.write(&mut update_bytes);
+        actor_manager.process_data(
+            &manifest,
+            &mut command_receiver,
+            &mut interpolator,
+            0,
+            0,
+            &mut PacketReader::new(&update_bytes),
+        );
+
+        match actor_manager.pop_incoming_message() {
+            Some(ClientActorMessage::Update(updated_key, changed_properties)) => {
+                assert!(updated_key == key);
+                assert!(changed_properties.get_bit(0) == Some(false));
+                assert!(changed_properties.get_bit(1) == Some(true));
+                assert!(changed_properties.get_bit(2) == Some(false));
+            }
+            _ => panic!("expected an Update message"),
+        }
+    }
+
+    #[test]
+    fn spawn_event_arrives_with_its_actor() {
+        let mut manifest = Manifest::<TestEventType, TestActorType>::new();
+        manifest.register_actor(Box::new(TestActorBuilder));
+        manifest.register_event(Box::new(TestCommandBuilder));
+        let mut actor_manager = ClientActorManager::<TestActorType>::new();
+        let mut command_receiver = CommandReceiver::<TestEventType>::new();
+        let mut interpolator = InterpolationManager::new(
+            Duration::from_millis(100),
+            false,
+            Duration::default(),
+            false,
+        );
+
+        // Create message for key 5 with x of 10, carrying an Event with tick 7
+        let create_bytes: Vec<u8> = vec![1, 0, 0, 0, 0, 5, 10, 1, 0, 0, 0, 2, 0, 7];
+        let (spawn_events, _) = actor_manager.process_data(
+            &manifest,
+            &mut command_receiver,
+            &mut interpolator,
+            0,
+            0,
+            &mut PacketReader::new(&create_bytes),
+        );
+
+        match actor_manager.pop_incoming_message() {
+            Some(ClientActorMessage::Create(created_key, type_id)) => {
+                assert!(created_key == 5);
+                assert!(type_id == TypeId::of::<TestActor>());
+            }
+            _ => panic!("expected a Create message"),
+        }
+        assert!(actor_manager.get_actor(&5).unwrap().x() == 10);
+        assert!(spawn_events.len() == 1);
+        match &spawn_events[0] {
+            Some(TestEventType::TestCommand(command)) => assert!(command.tick == 7),
+            None => panic!("expected a spawn event"),
+        }
+    }
+
+    #[test]
+    fn create_messages_report_the_type_of_actor_created() {
+        let mut manifest = Manifest::<TestEventType, TestActorType>::new();
+        manifest.register_actor(Box::new(TestActorBuilder));
+        manifest.register_actor(Box::new(MarkerActorBuilder));
+        let mut actor_manager = ClientActorManager::<TestActorType>::new();
+        let mut command_receiver = CommandReceiver::<TestEventType>::new();
+        let mut interpolator = InterpolationManager::new(
+            Duration::from_millis(100),
+            false,
+            Duration::default(),
+            false,
+        );
+
+        // Create messages for a TestActor at key 5 & a MarkerActor at key 6
+        let create_bytes: Vec<u8> = vec![2, 0, 0, 0, 0, 5, 10, 0, 0, 0, 1, 0, 6, 0];
+        actor_manager.process_data(
+            &manifest,
+            &mut command_receiver,
+            &mut interpolator,
+            0,
+            0,
+            &mut PacketReader::new(&create_bytes),
+        );
+
+        let mut created = Vec::new();
+        while let Some(message) = actor_manager.pop_incoming_message() {
+            if let ClientActorMessage::Create(key, type_id) = message {
+                created.push((key, type_id));
+            }
+        }
+        assert!(
+            created
+                == vec![
+                    (5, TypeId::of::<TestActor>()),
+                    (6, TypeId::of::<MarkerActor>())
+                ]
+        );
+    }
+
+    #[test]
+    fn assigned_pawn_is_available_predicted_and_interpolated() {
+        let mut manifest = Manifest::<TestEventType, TestActorType>::new();
+        manifest.register_actor(Box::new(TestActorBuilder));
+        let tick_manager = ClientTickManager::new(Duration::from_millis(50), 0, 0.1, 8);
+        let mut connection = ServerConnection::<TestEventType, TestActorType>::new(
+            "127.0.0.1:14191".parse().unwrap(),
+            &ConnectionConfig::default(),
+            Duration::from_millis(100),
+            false,
+            Duration::default(),
+            false,
+            0,
+        );
+
+        // a Create message for key 5, which is then assigned as a Pawn
+        let bytes: Vec<u8> = vec![2, 2, 0, 0, 0, 0, 5, 10, 0, 3, 0, 5];
+        connection.process_incoming_data(0, 0, &manifest, &bytes);
+
+        assert!(connection.get_pawn_predicted(&5).unwrap().x() == 10);
+        assert!(connection.get_pawn(&tick_manager, &5).unwrap().x() == 10);
+        assert!(connection.get_pawn_predicted(&3).is_none());
+    }
+
+    #[test]
+    fn update_listener_sees_each_update_with_the_received_state() {
+        let mut manifest = Manifest::<TestEventType, TestActorType>::new();
+        manifest.register_actor(Box::new(TestActorBuilder));
+        let mut connection = ServerConnection::<TestEventType, TestActorType>::new(
+            "127.0.0.1:14191".parse().unwrap(),
+            &ConnectionConfig::default(),
+            Duration::from_millis(100),
+            false,
+            Duration::default(),
+            false,
+            0,
+        );
+        let updates = Rc::new(RefCell::new(Vec::new()));
+        let updates_ref = updates.clone();
+        let mut listener = ActorUpdateListener::new();
+        listener.on_update(Box::new(move |key, actor: &TestActorType| {
+            updates_ref.borrow_mut().push((key, actor.x()));
+        }));
+
+        // Create messages for keys 5 & 6, then an Update moving key 5 to 20
+        let mut bytes: Vec<u8> = vec![2, 3, 0, 0, 0, 0, 5, 10, 0, 0, 0, 0, 0, 6, 30, 0];
+        bytes.extend_from_slice(&[2, 0, 5]);
+        let mut state_mask = StateMask::new(1);
+        state_mask.set_bit(0, true);
+        state_mask.write(&mut bytes);
+        bytes.push(20);
+        connection.process_incoming_data(0, 0, &manifest, &bytes);
+
+        // as receive() does for each UpdateActor event
+        while let Some(message) = connection.get_incoming_actor_message() {
+            if let ClientActorMessage::Update(key, _) = message {
+                let actor = connection.get_received_actor(&key).unwrap();
+                listener.notify(key, actor);
+            }
+        }
+
+        assert!(*updates.borrow() == vec![(5, 20)]);
+    }
+
+    #[test]
+    fn handle_to_deleted_actor_does_not_resolve_after_key_reuse() {
+        let mut manifest = Manifest::<TestEventType, TestActorType>::new();
+        manifest.register_actor(Box::new(TestActorBuilder));
+        let mut actor_manager = ClientActorManager::<TestActorType>::new();
+        let mut command_receiver = CommandReceiver::<TestEventType>::new();
+        let mut interpolator = InterpolationManager::new(
+            Duration::from_millis(100),
+            false,
+            Duration::default(),
+            false,
+        );
+        let mut process = |actor_manager: &mut ClientActorManager<TestActorType>, bytes: &[u8]| {
+            actor_manager.process_data(
+                &manifest,
+                &mut command_receiver,
+                &mut interpolator,
+                0,
+                0,
+                &mut PacketReader::new(bytes),
+            );
+        };
+
+        // a Create message for key 5
+        process(&mut actor_manager, &[1, 0, 0, 0, 0, 5, 10, 0]);
+        let old_handle = actor_manager.get_actor_handle(&5).unwrap();
+        assert!(actor_manager.is_current(&old_handle));
+
+        // the Actor is deleted, and its key is reused for a new Actor
+        process(&mut actor_manager, &[1, 1, 0, 5]);
+        assert!(!actor_manager.is_current(&old_handle));
+        process(&mut actor_manager, &[1, 0, 0, 0, 0, 5, 20, 0]);
+
+        assert!(!actor_manager.is_current(&old_handle));
+        let new_handle = actor_manager.get_actor_handle(&5).unwrap();
+        assert!(new_handle != old_handle);
+        assert!(actor_manager.is_current(&new_handle));
+        assert!(actor_manager.get_actor(&new_handle.key()).unwrap().x() == 20);
+    }
+
+    #[test]
+    fn processed_messages_are_counted() {
+        let mut manifest = Manifest::<TestEventType, TestActorType>::new();
+        manifest.register_actor(Box::new(TestActorBuilder));
+        let mut actor_manager = ClientActorManager::<TestActorType>::new();
+        let mut command_receiver = CommandReceiver::<TestEventType>::new();
+        let mut interpolator = InterpolationManager::new(
+            Duration::from_millis(100),
+            false,
+            Duration::default(),
+            false,
+        );
+        actor_manager
+            .local_actor_store
+            .insert(3, TestActorType::new(0));
+
+        // a Create message for key 5, then Update messages for keys 3 & 5
+        let mut bytes: Vec<u8> = vec![3, 0, 0, 0, 0, 5, 10, 0];
+        for key in [3, 5].iter() {
+            bytes.extend_from_slice(&[2, 0, *key]);
+            let mut state_mask = StateMask::new(1);
+            state_mask.set_bit(0, true);
+            state_mask.write(&mut bytes);
+            bytes.push(20);
+        }
+        let (_, stats) = actor_manager.process_data(
+            &manifest,
+            &mut command_receiver,
+            &mut interpolator,
+            0,
+            0,
+            &mut PacketReader::new(&bytes),
+        );
+
+        assert!(stats.creates == 1);
+        assert!(stats.updates == 2);
+        assert!(stats.deletes == 0);
+    }
+
+    #[test]
+    fn predicted_spawn_is_replaced_by_its_confirmation() {
+        let mut manifest = Manifest::<TestEventType, TestActorType>::new();
+        manifest.register_actor(Box::new(TestActorBuilder));
+        let mut actor_manager = ClientActorManager::<TestActorType>::new();
+        let mut command_receiver = CommandReceiver::<TestEventType>::new();
+        let mut interpolator = InterpolationManager::new(
+            Duration::from_millis(100),
+            false,
+            Duration::default(),
+            false,
+        );
+
+        // the Client fires a projectile
+        let token = actor_manager.predict_spawn(TestActorType::new(9));
+        assert!(actor_manager.get_predicted_actor(&token).unwrap().x() == 9);
+
+        // Create message for key 6 with x of 10, confirming the prediction
+        let token_bytes = token.to_be_bytes();
+        let create_bytes: Vec<u8> = vec![1, 0, 0, 0, 0, 6, 10, 2, token_bytes[0], token_bytes[1]];
+        actor_manager.process_data(
+            &manifest,
+            &mut command_receiver,
+            &mut interpolator,
+            0,
+            0,
+            &mut PacketReader::new(&create_bytes),
+        );
+
+        match actor_manager.pop_incoming_message() {
+            Some(ClientActorMessage::ConfirmSpawn(confirmed_token, confirmed_key)) => {
+                assert!(confirmed_token == token);
+                assert!(confirmed_key == 6);
+            }
+            _ => panic!("expected a ConfirmSpawn message"),
+        }
+        assert!(actor_manager.pop_incoming_message().is_none());
+        assert!(actor_manager.get_predicted_actor(&token).is_none());
+        assert!(actor_manager.actors().count() == 1);
+        assert!(actor_manager.get_actor(&6).unwrap().x() == 10);
+    }
+
+    #[test]
+    fn truncated_actor_data_is_discarded() {
+        let mut manifest = Manifest::<TestEventType, TestActorType>::new();
+        manifest.register_actor(Box::new(TestActorBuilder));
+        let mut actor_manager = ClientActorManager::<TestActorType>::new();
+        let mut command_receiver = CommandReceiver::<TestEventType>::new();
+        let mut interpolator = InterpolationManager::new(
+            Duration::from_millis(100),
+            false,
+            Duration::default(),
+            false,
+        );
+
+        // a Create message for key 5, then a Delete message cut off halfway
+        // through its key
+        let bytes: Vec<u8> = vec![2, 0, 0, 0, 0, 5, 10, 0, 1, 0];
+        let mut reader = PacketReader::new(&bytes);
+        let (_, stats) = actor_manager.process_data(
+            &manifest,
+            &mut command_receiver,
+            &mut interpolator,
+            0,
+            0,
+            &mut reader,
+        );
+
+        assert!(stats.creates == 1);
+        assert!(stats.deletes == 0);
+        assert!(actor_manager.get_actor(&5).is_some());
+        assert!(!reader.has_more());
+    }
+}
@@ -1,9 +1,12 @@
-use naia_shared::LocalActorKey;
+use std::any::TypeId;
+
+use naia_shared::{LocalActorKey, StateMask};
 
 #[derive(Debug, Clone)]
 pub enum ClientActorMessage {
-    Create(LocalActorKey),
-    Update(LocalActorKey),
+    Create(LocalActorKey, TypeId),
+    ConfirmSpawn(u16, LocalActorKey),
+    Update(LocalActorKey, StateMask),
     Delete(LocalActorKey),
     AssignPawn(LocalActorKey),
     UnassignPawn(LocalActorKey),
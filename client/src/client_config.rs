@@ -1,12 +1,32 @@
 use std::{default::Default, time::Duration};
 
+use naia_shared::{DisconnectPolicy, OverflowPolicy, MTU_SIZE};
+
 /// Contains Config properties which will be used by a Server or Client
 #[derive(Clone, Debug)]
 pub struct ClientConfig {
     /// The duration between the resend of certain connection handshake messages
     pub send_handshake_interval: Duration,
+    /// If set, the duration between handshake messages doubles after each
+    /// one the Server doesn't answer, starting from `send_handshake_interval`
+    /// and up to this maximum, so that an unreachable Server isn't sent
+    /// messages at a fixed rate. Starts again from `send_handshake_interval`
+    /// whenever the Server answers. If None, the interval is fixed.
+    pub max_handshake_interval: Option<Duration>,
+    /// The maximum number of handshake messages to send before giving up on
+    /// connecting to the Server, and emitting a ClientEvent::ConnectionTimeout.
+    /// Without backoff, the Client gives up after roughly
+    /// `max_handshake_attempts * send_handshake_interval`. If None, the
+    /// Client retries forever.
+    pub max_handshake_attempts: Option<u16>,
+    /// How long to wait for communication from a remote host before
+    /// initiating a disconnect, either as a duration or as a number of missed
+    /// heartbeats
+    pub disconnect_policy: DisconnectPolicy,
     /// The duration to wait for communication from a remote host before
-    /// initiating a disconnect
+    /// initiating a disconnect. Only used while `disconnect_policy` is left at
+    /// its default
+    #[deprecated(note = "use disconnect_policy instead")]
     pub disconnection_timeout_duration: Duration,
     /// The duration to wait before sending a heartbeat message to a remote
     /// host, if the host has not already sent another message within that time.
@@ -17,16 +37,141 @@ pub struct ClientConfig {
     /// Number of samples to measure RTT & Jitter by. A higher number will
     /// smooth out RTT measurements, but at the cost of responsiveness.
     pub rtt_sample_size: u16,
+    /// The largest datagram, in bytes, that will be sent to the remote host.
+    /// Larger packets are split into fragments and reassembled by the remote
+    /// host.
+    pub max_packet_size: usize,
+    /// The duration to wait for the remaining fragments of a packet before
+    /// dropping the fragments which have been received
+    pub fragment_timeout_duration: Duration,
+    /// The maximum number of times a guaranteed Event will be retransmitted
+    /// after being dropped, before giving up on it. If None, guaranteed Events
+    /// will be retransmitted until they are delivered.
+    pub max_retransmits: Option<u16>,
+    /// Number of most recently sent packets over which to measure packet
+    /// loss
+    pub packet_loss_window_size: u16,
+    /// The maximum number of Events written into a single packet, leaving
+    /// room for Actor updates when many Events are queued. Events beyond the
+    /// cap wait for the next packet. If None, Events are written until the
+    /// packet is full.
+    pub max_events_per_packet: Option<u16>,
+    /// The largest payload, in bytes, that Events, Commands & Actor messages
+    /// are packed into before the remainder waits for the next packet. Raise
+    /// it for larger datagrams on a LAN, or lower it for mobile networks.
+    pub max_payload_size: usize,
+    /// Number of ping intervals to wait for a pong from the remote host, after
+    /// which RTT is considered unknown until pongs arrive again. This is
+    /// independent of the disconnection timeout, which heartbeats also reset.
+    pub pong_timeout_intervals: u16,
+    /// The longest duration that outgoing Events, Commands & Actor messages
+    /// are held back for, so that data queued in quick succession is sent in
+    /// fewer, fuller packets at the cost of some latency. Held data is always
+    /// sent at the next tick. If None, data is sent as soon as it is queued.
+    pub coalesce_delay: Option<Duration>,
+    /// The number of Events that the queues of outgoing, incoming & in-flight
+    /// Events have room for from the start, so that a busy connection doesn't
+    /// reallocate them as they grow
+    pub event_capacity: usize,
+    /// Whether to append a checksum to every packet sent over the connection,
+    /// so that packets corrupted in transit are dropped instead of processed.
+    /// Checksums are only used if both the Client & Server enable this, as
+    /// agreed on during the handshake
+    pub integrity_check: bool,
+    /// The maximum number of Events waiting to be sent to the remote host,
+    /// including those queued up again for retransmission. Events queued beyond
+    /// it are handled according to the `overflow_policy`. If None, the queue
+    /// grows without limit.
+    pub max_outgoing_queue_len: Option<usize>,
+    /// What happens to an Event queued while the outgoing queue is already
+    /// `max_outgoing_queue_len` long
+    pub overflow_policy: OverflowPolicy,
+    /// Number of ticks the Client tick should run ahead of the Server tick,
+    /// in addition to the ticks needed to cover the measured RTT. A higher
+    /// number makes it more likely that Commands arrive on time at the
+    /// Server, at the cost of added input latency.
+    pub client_tick_buffer: u16,
+    /// How strongly the Client's tick rate is nudged towards the Server's
+    /// tick when they drift apart. Each tick of error speeds up or slows down
+    /// the local tick by this fraction of its rate, so that the error is
+    /// worked off smoothly instead of the tick jumping
+    pub tick_drift_gain: f32,
+    /// The number of ticks the Client's tick may be off from the Server's
+    /// before it is snapped straight to the Server's tick, rather than
+    /// gradually converging on it
+    pub tick_resync_threshold: u16,
+    /// How far in the past interpolated Actors are rendered. Incoming
+    /// snapshots are buffered by the moment they were received, and Actors
+    /// are displayed between the two snapshots which bracket `now - delay`,
+    /// so this should cover at least one tick interval plus expected jitter.
+    pub interpolation_delay: Duration,
+    /// Whether interpolated Actors should keep moving along their last known
+    /// trajectory when no snapshot newer than `now - interpolation_delay`
+    /// has arrived yet, rather than freezing at the newest snapshot
+    pub allow_extrapolation: bool,
+    /// The furthest an Actor will be extrapolated past its newest snapshot,
+    /// before it holds position until another snapshot arrives
+    pub max_extrapolation: Duration,
+    /// Whether Actors & Pawns are interpolated using fixed-point math, so
+    /// that every platform computes bit-identical states, as deterministic
+    /// lockstep simulations need. Only Actors derived with `#[derive(Actor)]`
+    /// interpolate in fixed-point, others are given the equivalent
+    /// floating-point fraction
+    pub deterministic_interpolation: bool,
+    /// The maximum number of Events which can be sent before a connection
+    /// with the Server is established. These are held until the handshake
+    /// completes, and then sent in order.
+    pub max_pre_connection_events: usize,
+    /// Seeds the simulated loss and jitter of the link conditioner on
+    /// packets received from the Server, so that test runs see the same
+    /// pattern of drops and delays. If None, the pattern is random.
+    pub link_conditioner_seed: Option<u64>,
+    /// How long the Client keeps trying to reconnect with its session token
+    /// after the connection to the Server times out, resending a Reconnect
+    /// message every `send_handshake_interval`. Should not exceed the
+    /// Server's `reconnect_grace_period`. If None, the Client disconnects as
+    /// soon as it times out.
+    pub reconnect_grace_period: Option<Duration>,
+    /// The number of Actors that the Client has room for from the start, so
+    /// that its Actor store doesn't reallocate as Actors come into scope
+    pub actor_capacity: usize,
 }
 
 impl Default for ClientConfig {
+    #[allow(deprecated)]
     fn default() -> Self {
         Self {
+            disconnect_policy: DisconnectPolicy::default(),
             disconnection_timeout_duration: Duration::from_secs(10),
             heartbeat_interval: Duration::from_secs(4),
             send_handshake_interval: Duration::from_secs(1),
+            max_handshake_interval: None,
+            max_handshake_attempts: None,
             ping_interval: Duration::from_secs(1),
             rtt_sample_size: 20,
+            max_packet_size: 508,
+            fragment_timeout_duration: Duration::from_secs(2),
+            max_retransmits: None,
+            packet_loss_window_size: 100,
+            max_events_per_packet: None,
+            max_payload_size: MTU_SIZE,
+            pong_timeout_intervals: 5,
+            coalesce_delay: None,
+            event_capacity: 0,
+            integrity_check: false,
+            max_outgoing_queue_len: None,
+            overflow_policy: OverflowPolicy::DropOldest,
+            client_tick_buffer: 1,
+            tick_drift_gain: 0.1,
+            tick_resync_threshold: 8,
+            interpolation_delay: Duration::from_millis(100),
+            allow_extrapolation: false,
+            max_extrapolation: Duration::from_millis(100),
+            deterministic_interpolation: false,
+            max_pre_connection_events: 32,
+            link_conditioner_seed: None,
+            reconnect_grace_period: None,
+            actor_capacity: 0,
         }
     }
 }
@@ -1,6 +1,29 @@
-#[derive(Debug, PartialEq)]
+/// The current phase of the Client's connection to the Server
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ClientConnectionState {
+    /// The Client is sending challenge requests, and waiting for the Server
+    /// to respond
     AwaitingChallengeResponse,
+    /// The Server has answered the challenge, and the Client is sending
+    /// connect requests (including any auth Event) until the Server accepts
+    /// or rejects the connection
     AwaitingConnectResponse,
+    /// The connection has been established
     Connected,
+    /// The connection has timed out, and the Client is sending its session
+    /// token to the Server in an attempt to restore it, within the
+    /// `reconnect_grace_period` of the ClientConfig
+    Reconnecting,
+    /// The Server has rejected the connection, and the Client will not
+    /// attempt to connect again unless new credentials are supplied with
+    /// NaiaClient::retry_auth()
+    Rejected,
+    /// The Server did not answer within the configured maximum number of
+    /// handshake attempts, and the Client will not attempt to connect again
+    /// unless NaiaClient::retry_auth() is called
+    TimedOut,
+    /// The Server speaks a different version of the wire format, and the
+    /// Client will not attempt to connect again, as no handshake could
+    /// succeed
+    VersionMismatch,
 }
@@ -1,34 +1,92 @@
-use naia_shared::{EventType, LocalActorKey};
+use std::{any::TypeId, time::Duration};
+
+use naia_shared::{EventId, EventType, LocalActorKey, StateMask};
 
 /// An Event that is be emitted by the Client, usually as a result of some
 /// communication with the Server
 #[derive(Debug)]
 pub enum ClientEvent<T: EventType> {
     /// Occurs when the Client has successfully established a connection with
-    /// the Server
-    Connection,
+    /// the Server. Contains the time taken by the handshake, from the first
+    /// challenge request sent to the Server's connect response, which
+    /// includes any handshake messages that had to be resent
+    Connection(Duration),
     /// Occurs when the Client has lost connection with the Server, usually as a
-    /// result of a timeout
-    Disconnection,
+    /// result of a timeout. If the Server disconnected the Client, contains
+    /// the Event the Server gave as the reason, if any
+    Disconnection(Option<T>),
+    /// Occurs when the Client has heard from the Server again after the
+    /// connection timed out, within the `reconnect_grace_period` of the
+    /// ClientConfig. The Client's Actors & Pawns are unchanged
+    Reconnection,
+    /// Occurs when the Server has refused the Client's connection, usually as
+    /// a result of failed authentication. Contains the Event the Server gave
+    /// as the reason, if any. The Client will not attempt to connect again
+    /// until disconnect() is called
+    RejectConnection(Option<T>),
+    /// Occurs when the Server has not answered the Client's handshake within
+    /// `max_handshake_attempts` of the ClientConfig, for example because it
+    /// is offline or unreachable. The Client will not attempt to connect
+    /// again until disconnect() is called
+    ConnectionTimeout,
+    /// Occurs when the Server speaks a different version of the wire format
+    /// than the Client, so that neither could make sense of the other's
+    /// packets. Contains the Server's version, to compare against
+    /// PROTOCOL_VERSION. The Client will not attempt to connect again
+    VersionMismatch(u16),
     /// An Event emitted to the Client from the Server
     Event(T),
-    /// Occurs when an Actor on the Server has come into scope for the Client
-    CreateActor(LocalActorKey),
+    /// An Event emitted to the Client from the Server, addressed to the
+    /// Actor with the given key. The Event is sent alongside the Actor's
+    /// messages, so an Actor which has just come into scope may not yet be
+    /// available through `get_actor()`
+    ActorEvent(LocalActorKey, T),
+    /// A raw message emitted to the Client from the Server, containing the
+    /// channel it was sent on & its bytes
+    Raw(u8, Vec<u8>),
+    /// Occurs when the Server has received a guaranteed Event sent by the
+    /// Client, carrying the id returned by `send_event()`
+    EventDelivered(EventId),
+    /// Occurs when an Actor on the Server has come into scope for the Client.
+    /// Contains the TypeId of the Actor's implementation, so that the right
+    /// spawn handler can be picked without looking the Actor up, and the
+    /// Event the Server attached to the Actor's creation, if any, which is
+    /// delivered in the same message as the Actor itself
+    CreateActor(LocalActorKey, TypeId, Option<T>),
+    /// Occurs instead of CreateActor when the Actor which has come into scope
+    /// is the Server's confirmation of a spawn predicted with the given
+    /// token. The predicted Actor has been discarded, and the confirmed Actor
+    /// is available through `get_actor()` with the given key. Contains the
+    /// Event the Server attached to the Actor's creation, if any
+    ConfirmSpawn(u16, LocalActorKey, Option<T>),
     /// Occurs when an Actor has had a state change on the Server while in
-    /// scope for the Client
-    UpdateActor(LocalActorKey),
+    /// scope for the Client. Contains a StateMask with the bits of the changed
+    /// Properties set, indexed by the `{Actor}Prop` enum generated by the
+    /// Actor derive (for example `PointActorProp::X as u8`). Pawns are
+    /// updated in full, so every bit is set for them
+    UpdateActor(LocalActorKey, StateMask),
     /// Occurs when an Actor on the Server has left the Client's scope
     DeleteActor(LocalActorKey),
     /// A Tick Event, the duration between Tick events is defined in the Config
     /// object passed to the Client on initialization
     Tick,
     /// Occurs when an Actor has been assigned to the local host as a Pawn,
-    /// meaning it can receive Commands from the Client
+    /// meaning it can receive Commands from the Client. From then on the
+    /// Actor is also available through `get_pawn()`, where it is predicted
+    /// locally and reconciled against updates from the Server
     AssignPawn(LocalActorKey),
     /// Occurs when a Pawn has been unassigned from the local host, meaning it
-    /// cannot receive Commands from this Client
+    /// cannot receive Commands from this Client. The Actor remains in scope,
+    /// and is again only available through `get_actor()`, interpolated
+    /// between updates from the Server
     UnassignPawn(LocalActorKey),
     /// A Command received which is to be simulated on the Client as well as on
     /// the Server
     Command(LocalActorKey, T),
+    /// A previously received Command which must be simulated again on the
+    /// Client, because the Server's state for the Pawn disagreed with the
+    /// Client's prediction. The Pawn has already been reset to the Server's
+    /// state, and replayed Commands are emitted in order of ascending tick.
+    /// Contains the tick the Command was originally issued for
+    ReplayCommand(LocalActorKey, u16, T),
 }
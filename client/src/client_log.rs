@@ -0,0 +1,168 @@
+use std::net::SocketAddr;
+
+use naia_shared::PacketType;
+
+/// The log target of messages about the handshake with the Server, so that
+/// handshake messages can be filtered separately from the data path
+pub const HANDSHAKE_LOG_TARGET: &str = "naia_client::handshake";
+/// The log target of messages about Data packets received from the Server
+pub const DATA_LOG_TARGET: &str = "naia_client::data";
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "tracing")] {
+        /// Records a phase of the handshake with the Server as its own span,
+        /// along with the packet sent or received in it & the current tick
+        pub fn handshake_phase(
+            phase: &'static str,
+            server_address: &SocketAddr,
+            packet_type: PacketType,
+            tick: u16,
+        ) {
+            let span = tracing::debug_span!(
+                target: HANDSHAKE_LOG_TARGET,
+                "handshake",
+                phase,
+                server = %server_address,
+                packet_type = ?packet_type,
+                tick
+            );
+            let _entered = span.enter();
+            tracing::debug!(target: HANDSHAKE_LOG_TARGET, "{}", phase);
+        }
+
+        /// Records the receipt of a Data packet from the Server
+        pub fn data_packet(server_address: &SocketAddr, packet_index: u16, tick: u16) {
+            tracing::trace!(
+                target: DATA_LOG_TARGET,
+                server = %server_address,
+                packet_index,
+                tick,
+                "data packet"
+            );
+        }
+    } else {
+        /// Logs a phase of the handshake with the Server, along with the
+        /// packet sent or received in it & the current tick
+        pub fn handshake_phase(
+            phase: &'static str,
+            server_address: &SocketAddr,
+            packet_type: PacketType,
+            tick: u16,
+        ) {
+            log::debug!(
+                target: HANDSHAKE_LOG_TARGET,
+                "{} server={} packet_type={:?} tick={}",
+                phase,
+                server_address,
+                packet_type,
+                tick
+            );
+        }
+
+        /// Logs the receipt of a Data packet from the Server
+        pub fn data_packet(server_address: &SocketAddr, packet_index: u16, tick: u16) {
+            log::trace!(
+                target: DATA_LOG_TARGET,
+                "data packet server={} packet_index={} tick={}",
+                server_address,
+                packet_index,
+                tick
+            );
+        }
+    }
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        sync::{Arc, Mutex},
+    };
+
+    use tracing::{
+        field::{Field, Visit},
+        span::{Attributes, Id, Record},
+        Event, Metadata, Subscriber,
+    };
+
+    use naia_shared::PacketType;
+
+    use super::{handshake_phase, HANDSHAKE_LOG_TARGET};
+
+    // Records the `phase` field of every span it sees
+    struct PhaseRecorder {
+        phases: Arc<Mutex<Vec<String>>>,
+    }
+
+    struct PhaseVisitor<'a> {
+        phases: &'a mut Vec<String>,
+    }
+
+    impl Visit for PhaseVisitor<'_> {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            if field.name() == "phase" {
+                self.phases.push(value.to_string());
+            }
+        }
+        fn record_debug(&mut self, _: &Field, _: &dyn std::fmt::Debug) {}
+    }
+
+    impl Subscriber for PhaseRecorder {
+        fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+            return metadata.target() == HANDSHAKE_LOG_TARGET;
+        }
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut phases = self.phases.lock().unwrap();
+            span.record(&mut PhaseVisitor {
+                phases: &mut phases,
+            });
+            return Id::from_u64(phases.len() as u64);
+        }
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, _: &Event<'_>) {}
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn each_handshake_phase_is_a_span() {
+        let phases = Arc::new(Mutex::new(Vec::new()));
+        let recorder = PhaseRecorder {
+            phases: phases.clone(),
+        };
+        let address: SocketAddr = "127.0.0.1:14191".parse().unwrap();
+
+        tracing::subscriber::with_default(recorder, || {
+            handshake_phase(
+                "challenge_request",
+                &address,
+                PacketType::ClientChallengeRequest,
+                0,
+            );
+            handshake_phase(
+                "challenge_response",
+                &address,
+                PacketType::ServerChallengeResponse,
+                0,
+            );
+            handshake_phase(
+                "connect_request",
+                &address,
+                PacketType::ClientConnectRequest,
+                7,
+            );
+            handshake_phase("connected", &address, PacketType::ServerConnectResponse, 7);
+        });
+
+        assert!(
+            *phases.lock().unwrap()
+                == vec![
+                    "challenge_request",
+                    "challenge_response",
+                    "connect_request",
+                    "connected"
+                ]
+        );
+    }
+}
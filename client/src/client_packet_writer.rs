@@ -17,13 +17,19 @@ pub struct ClientPacketWriter {
 }
 
 impl ClientPacketWriter {
-    /// Construct a new instance of `PacketReader`, the given `buffer` will be
-    /// used to read information from.
+    /// Construct a new instance of `ClientPacketWriter`, which packs Commands
+    /// & Events into a payload of at most `MTU_SIZE` bytes
     pub fn new() -> ClientPacketWriter {
+        return ClientPacketWriter::with_capacity(MTU_SIZE);
+    }
+
+    /// Construct a new instance of `ClientPacketWriter`, which packs Commands
+    /// & Events into a payload of at most `max_payload_size` bytes
+    pub fn with_capacity(max_payload_size: usize) -> ClientPacketWriter {
         ClientPacketWriter {
             command_working_bytes: Vec::<u8>::new(),
             command_count: 0,
-            event_writer: EventPacketWriter::new(),
+            event_writer: EventPacketWriter::with_capacity(max_payload_size),
         }
     }
 
@@ -111,7 +117,7 @@ impl ClientPacketWriter {
         if self.command_count == 0 {
             hypothetical_next_payload_size += 2;
         }
-        if hypothetical_next_payload_size < MTU_SIZE {
+        if hypothetical_next_payload_size < self.event_writer.max_payload_size() {
             self.command_count += 1;
             self.command_working_bytes.append(&mut command_total_bytes);
             return true;
@@ -120,13 +126,20 @@ impl ClientPacketWriter {
         }
     }
 
-    /// Writes an Event into the Writer's internal buffer, which will eventually
-    /// be put into the outgoing packet
-    pub fn write_event<T: EventType, U: ActorType>(
-        &mut self,
-        manifest: &Manifest<T, U>,
-        event: &Box<dyn Event<T>>,
-    ) -> bool {
-        return self.event_writer.write_event(manifest, event);
+    /// Gets the writer for the Events & raw messages of the outgoing packet
+    pub(crate) fn event_writer_mut(&mut self) -> &mut EventPacketWriter {
+        return &mut self.event_writer;
+    }
+
+    /// Writes a raw message into the Writer's internal buffer, which will
+    /// eventually be put into the outgoing packet
+    pub fn write_raw(&mut self, channel: u8, data: &[u8]) -> bool {
+        return self.event_writer.write_raw(channel, data);
+    }
+}
+
+impl Default for ClientPacketWriter {
+    fn default() -> Self {
+        Self::new()
     }
 }
@@ -2,6 +2,10 @@ use std::time::Duration;
 
 use naia_shared::{wrapping_diff, Instant};
 
+// The most that drift correction will speed up or slow down the local tick,
+// as a fraction of the tick rate
+const MAX_RATE_ADJUST: f32 = 0.5;
+
 /// Manages the current tick for the host
 #[derive(Debug)]
 pub struct ClientTickManager {
@@ -9,25 +13,47 @@ pub struct ClientTickManager {
     tick_interval_f32: f32,
     server_tick: u16,
     client_tick_adjust: u16,
+    client_tick_buffer: u16,
     server_tick_adjust: u16,
-    server_tick_running_diff: i16,
+    tick_drift: f32,
+    drift_gain: f32,
+    resync_threshold: u16,
+    server_epoch: Option<u32>,
     last_tick_instant: Instant,
+    epoch: Instant,
+    server_tick_millis: u64,
     pub fraction: f32,
     accumulator: f32,
     has_ticked: bool,
 }
 
 impl ClientTickManager {
-    /// Create a new HostTickManager with a given tick interval duration
-    pub fn new(tick_interval: Duration) -> Self {
+    /// Create a new HostTickManager with a given tick interval duration, and a
+    /// number of ticks the client tick should lead the server tick by, in
+    /// addition to the ticks required to cover the measured RTT. Drift from
+    /// the server tick is corrected by running the local tick faster or
+    /// slower in proportion to `drift_gain`, unless the error exceeds
+    /// `resync_threshold` ticks, in which case the tick snaps to the Server's
+    pub fn new(
+        tick_interval: Duration,
+        client_tick_buffer: u16,
+        drift_gain: f32,
+        resync_threshold: u16,
+    ) -> Self {
         ClientTickManager {
             tick_interval,
             tick_interval_f32: tick_interval.as_nanos() as f32 / 1000000000.0,
             server_tick: 1,
             client_tick_adjust: 0,
+            client_tick_buffer,
             server_tick_adjust: 0,
-            server_tick_running_diff: 0,
+            tick_drift: 0.0,
+            drift_gain,
+            resync_threshold,
+            server_epoch: None,
             last_tick_instant: Instant::now(),
+            epoch: Instant::now(),
+            server_tick_millis: 0,
             accumulator: 0.0,
             fraction: 0.0,
             has_ticked: false,
@@ -35,13 +61,25 @@ impl ClientTickManager {
     }
 
     pub fn mark_frame(&mut self) -> bool {
+        let frame_time = self.last_tick_instant.elapsed().as_nanos() as f32 / 1000000000.0;
+        self.last_tick_instant = Instant::now();
+        return self.advance(frame_time);
+    }
+
+    // Advances the local clock by the given number of seconds, sped up or
+    // slowed down to work off any measured drift from the server tick
+    fn advance(&mut self, mut frame_time: f32) -> bool {
         let mut ticked = false;
-        let mut frame_time = self.last_tick_instant.elapsed().as_nanos() as f32 / 1000000000.0;
         if frame_time > 0.25 {
             frame_time = 0.25;
         }
-        self.accumulator += frame_time;
-        self.last_tick_instant = Instant::now();
+
+        let rate_adjust =
+            (self.drift_gain * self.tick_drift).clamp(-MAX_RATE_ADJUST, MAX_RATE_ADJUST);
+        let adjustment = frame_time * rate_adjust;
+        self.tick_drift -= adjustment / self.tick_interval_f32;
+        self.accumulator += frame_time + adjustment;
+
         if self.accumulator >= self.tick_interval_f32 {
             while self.accumulator >= self.tick_interval_f32 {
                 self.accumulator -= self.tick_interval_f32;
@@ -50,9 +88,17 @@ impl ClientTickManager {
             ticked = true;
             self.has_ticked = true;
             self.server_tick = self.server_tick.wrapping_add(1);
+            self.mark_server_tick_start();
         }
         self.fraction = self.accumulator / self.tick_interval_f32;
-        ticked
+        return ticked;
+    }
+
+    /// Makes the next marked frame begin a tick, however little time has
+    /// passed since the last
+    #[cfg(all(any(test, feature = "testing"), not(target_arch = "wasm32")))]
+    pub fn ring_manual(&mut self) {
+        self.accumulator = self.accumulator.max(self.tick_interval_f32);
     }
 
     /// If the tick interval duration has elapsed, increment the current tick
@@ -64,12 +110,25 @@ impl ClientTickManager {
         return false;
     }
 
-    /// Use tick data from initial server handshake to set the initial tick
-    pub fn set_initial_tick(&mut self, server_tick: u16) {
+    /// Use tick data from a server handshake or reconnection to set the
+    /// tick, discarding any drift measured before. Returns whether the
+    /// Server's epoch differs from that of the Server last synced with, in
+    /// which case the Server has restarted and its ticks can't be compared
+    /// with those seen before
+    pub fn set_initial_tick(&mut self, server_tick: u16, server_epoch: u32) -> bool {
+        let epoch_changed = self.server_epoch.is_some() && self.server_epoch != Some(server_epoch);
+        self.tick_drift = 0.0;
+        self.accumulator = 0.0;
+        self.has_ticked = false;
+        self.server_epoch = Some(server_epoch);
         self.server_tick = server_tick;
+        self.mark_server_tick_start();
         self.server_tick_adjust = ((1000 / (self.tick_interval.as_millis())) + 1) as u16;
 
-        self.client_tick_adjust = ((3000 / (self.tick_interval.as_millis())) + 1) as u16;
+        self.client_tick_adjust =
+            ((3000 / (self.tick_interval.as_millis())) as u16) + self.client_tick_buffer;
+
+        return epoch_changed;
     }
 
     /// Using information from the Server and RTT/Jitter measurements, determine
@@ -80,35 +139,31 @@ impl ClientTickManager {
         rtt_average: f32,
         jitter_deviation: f32,
     ) {
-        self.server_tick_running_diff += wrapping_diff(self.server_tick, server_tick);
+        let tick_error = wrapping_diff(self.server_tick, server_tick);
 
-        // Decay the diff so that small fluctuations are acceptable
-        if self.server_tick_running_diff > 0 {
-            self.server_tick_running_diff = self.server_tick_running_diff.wrapping_sub(1);
-        }
-        if self.server_tick_running_diff < 0 {
-            self.server_tick_running_diff = self.server_tick_running_diff.wrapping_add(1);
-        }
-
-        // If the server tick is far off enough, reset to the received server tick
-        if self.server_tick_running_diff.abs() > 8 {
+        // If the server tick is far off enough, reset to the received server
+        // tick, otherwise converge on it gradually as frames are marked
+        if tick_error.unsigned_abs() > self.resync_threshold {
             self.server_tick = server_tick;
-            self.server_tick_running_diff = 0;
+            self.tick_drift = 0.0;
+            self.mark_server_tick_start();
+        } else {
+            self.tick_drift = f32::from(tick_error);
         }
 
         // Calculate incoming & outgoing jitter buffer tick offsets
         self.server_tick_adjust =
             ((((jitter_deviation * 3.0) / 2.0) / self.tick_interval.as_millis() as f32) + 1.0)
                 .ceil() as u16;
-        self.client_tick_adjust = (((rtt_average + (jitter_deviation * 3.0) / 2.0)
+        self.client_tick_adjust = ((rtt_average + (jitter_deviation * 3.0) / 2.0)
             / (self.tick_interval.as_millis() as f32))
-            + 1.0)
-            .ceil() as u16;
+            .ceil() as u16
+            + self.client_tick_buffer;
     }
 
-    /// Gets a reference to the tick interval used
-    pub fn get_tick_interval(&self) -> &Duration {
-        return &self.tick_interval;
+    /// Gets the epoch of the Server last synced with, if any
+    pub fn get_server_epoch(&self) -> Option<u32> {
+        return self.server_epoch;
     }
 
     /// Gets the server tick with the incoming jitter buffer offset applied
@@ -120,4 +175,142 @@ impl ClientTickManager {
     pub fn get_client_tick(&self) -> u16 {
         return self.server_tick.wrapping_add(self.client_tick_adjust);
     }
+
+    /// Gets the approximate moment at which the given server tick starts,
+    /// based on when the current server tick started. The tick is assumed to
+    /// be within half the sequence space of the current server tick. Ticks
+    /// which would have started before the ClientTickManager was created are
+    /// clamped to that moment
+    pub fn tick_to_instant(&self, tick: u16) -> Instant {
+        let tick_diff = i64::from(wrapping_diff(self.server_tick, tick));
+        let millis = self.server_tick_millis as i64 + (tick_diff * self.tick_interval_millis());
+        let mut instant = self.epoch.clone();
+        if millis > 0 {
+            instant.add_millis(millis as u32);
+        }
+        return instant;
+    }
+
+    /// Gets the server tick which is running at the given moment, based on
+    /// when the current server tick started. The result wraps around in the
+    /// same way as the tick itself, and is only meaningful for moments
+    /// within half the sequence space of the current server tick
+    pub fn instant_to_tick(&self, instant: &Instant) -> u16 {
+        let millis = Self::millis_since(&self.epoch, instant) - self.server_tick_millis as i64;
+        let tick_diff = millis.div_euclid(self.tick_interval_millis());
+        return self.server_tick.wrapping_add(tick_diff as u16);
+    }
+
+    // Records the current moment as the start of the current server tick
+    fn mark_server_tick_start(&mut self) {
+        self.server_tick_millis = self.epoch.elapsed().as_millis() as u64;
+    }
+
+    fn tick_interval_millis(&self) -> i64 {
+        return (self.tick_interval.as_millis() as i64).max(1);
+    }
+
+    // Gets the number of whole milliseconds from one Instant to another,
+    // which is negative if the second comes first
+    fn millis_since(earlier: &Instant, later: &Instant) -> i64 {
+        let earlier_micros = Self::micros_from_now(earlier);
+        let later_micros = Self::micros_from_now(later);
+        return ((later_micros - earlier_micros) as f64 / 1000.0).round() as i64;
+    }
+
+    fn micros_from_now(instant: &Instant) -> i64 {
+        if *instant > Instant::now() {
+            return instant.until().as_micros() as i64;
+        }
+        return -(instant.elapsed().as_micros() as i64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::ClientTickManager;
+
+    #[test]
+    fn client_tick_leads_server_tick_by_rtt_and_buffer() {
+        let mut tick_manager = ClientTickManager::new(Duration::from_millis(100), 2, 0.1, 8);
+        tick_manager.set_initial_tick(50, 0);
+
+        // 250ms of RTT requires 3 ticks to cover, plus a buffer of 2 ticks
+        tick_manager.record_server_tick(50, 250.0, 0.0);
+
+        assert!(tick_manager.get_client_tick() == 55);
+        assert!(tick_manager.get_server_tick() == 49);
+    }
+
+    #[test]
+    fn resync_discards_drift_and_reports_a_restarted_server() {
+        let mut tick_manager = ClientTickManager::new(Duration::from_millis(100), 0, 0.1, 8);
+        assert!(!tick_manager.set_initial_tick(500, 7));
+        tick_manager.record_server_tick(503, 0.0, 0.0);
+        assert!(tick_manager.tick_drift != 0.0);
+
+        // drift measured before is discarded even by the same run of the Server
+        assert!(!tick_manager.set_initial_tick(510, 7));
+        assert!(tick_manager.tick_drift == 0.0);
+        assert!(tick_manager.get_server_epoch() == Some(7));
+
+        // while a restarted Server, back at tick 0, is reported
+        tick_manager.record_server_tick(513, 0.0, 0.0);
+        assert!(tick_manager.set_initial_tick(0, 8));
+        assert!(tick_manager.tick_drift == 0.0);
+        assert!(tick_manager.get_server_epoch() == Some(8));
+    }
+
+    #[test]
+    fn tick_instants_round_trip_across_the_wrap() {
+        let mut tick_manager = ClientTickManager::new(Duration::from_millis(50), 0, 0.1, 8);
+        tick_manager.set_initial_tick(65533, 0);
+
+        for tick in [65533, 65534, 65535, 0, 1, 2] {
+            let instant = tick_manager.tick_to_instant(tick);
+            assert!(tick_manager.instant_to_tick(&instant) == tick);
+        }
+
+        // later ticks start later, even once the tick has wrapped around
+        assert!(tick_manager.tick_to_instant(1) > tick_manager.tick_to_instant(65535));
+
+        // a moment partway through a tick belongs to that tick
+        let mut instant = tick_manager.tick_to_instant(0);
+        instant.add_millis(25);
+        assert!(tick_manager.instant_to_tick(&instant) == 0);
+    }
+
+    #[test]
+    fn small_tick_error_converges_gradually() {
+        let mut tick_manager = ClientTickManager::new(Duration::from_millis(100), 0, 0.1, 8);
+        tick_manager.set_initial_tick(100, 0);
+        tick_manager.record_server_tick(103, 0.0, 0.0);
+
+        // no snap to the server tick
+        assert!(tick_manager.server_tick == 100);
+
+        // 10 seconds of 10ms frames, which would be 100 ticks at the nominal rate
+        let mut previous_tick = tick_manager.server_tick;
+        for _ in 0..1000 {
+            tick_manager.advance(0.01);
+            assert!(tick_manager.server_tick.wrapping_sub(previous_tick) <= 1);
+            previous_tick = tick_manager.server_tick;
+        }
+
+        // the 3 ticks of error were made up along the way
+        assert!(tick_manager.server_tick >= 202);
+        assert!(tick_manager.tick_drift.abs() < 0.01);
+    }
+
+    #[test]
+    fn large_tick_error_snaps() {
+        let mut tick_manager = ClientTickManager::new(Duration::from_millis(100), 0, 0.1, 8);
+        tick_manager.set_initial_tick(100, 0);
+        tick_manager.record_server_tick(120, 0.0, 0.0);
+
+        assert!(tick_manager.server_tick == 120);
+        assert!(tick_manager.tick_drift == 0.0);
+    }
 }
@@ -130,4 +130,16 @@ impl<T: EventType> CommandReceiver<T> {
     pub fn pawn_cleanup(&mut self, pawn_key: &LocalActorKey) {
         self.command_history.remove(pawn_key);
     }
+
+    /// Discards all queued Commands, replays and Command history, which are
+    /// keyed by ticks that no longer apply, while keeping every pawn
+    /// initialized
+    pub fn clear_history(&mut self) {
+        self.queued_incoming_commands.clear();
+        self.queued_command_replays.clear();
+        self.replay_trigger.clear();
+        for command_buffer in self.command_history.values_mut() {
+            *command_buffer = SequenceBuffer::with_capacity(COMMAND_HISTORY_SIZE);
+        }
+    }
 }
@@ -0,0 +1,49 @@
+use std::fmt::{Debug, Formatter, Result};
+
+use super::client_connection_state::ClientConnectionState;
+
+/// Holds the current phase of the Client's connection to the Server, and
+/// notifies a callback each time it changes
+pub struct ConnectionStateTracker {
+    state: ClientConnectionState,
+    state_change_func: Option<Box<dyn FnMut(ClientConnectionState)>>,
+}
+
+impl ConnectionStateTracker {
+    pub fn new(state: ClientConnectionState) -> Self {
+        ConnectionStateTracker {
+            state,
+            state_change_func: None,
+        }
+    }
+
+    /// Gets the current phase of the connection
+    pub fn get(&self) -> ClientConnectionState {
+        return self.state;
+    }
+
+    /// Moves the connection into a new phase, calling the callback only if
+    /// the phase is different from the current one
+    pub fn set(&mut self, state: ClientConnectionState) {
+        if self.state == state {
+            return;
+        }
+        self.state = state;
+        if let Some(state_change_func) = &mut self.state_change_func {
+            (state_change_func)(state);
+        }
+    }
+
+    /// Sets the callback to be called with each new phase of the connection
+    pub fn on_change(&mut self, state_change_func: Box<dyn FnMut(ClientConnectionState)>) {
+        self.state_change_func = Some(state_change_func);
+    }
+}
+
+impl Debug for ConnectionStateTracker {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.debug_struct("ConnectionStateTracker")
+            .field("state", &self.state)
+            .finish()
+    }
+}
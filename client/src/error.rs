@@ -4,6 +4,22 @@ use std::{error::Error, fmt};
 pub enum NaiaClientError {
     Message(String),
     Wrapped(Box<dyn Error + Send>),
+    /// A packet could not be sent through the socket. The connection is
+    /// still intact, and sending will be attempted again on the next call to
+    /// receive()
+    SendFailed(Box<dyn Error + Send + Sync>),
+    /// An Event was sent before a connection with the Server was established,
+    /// and the queue holding such Events until the connection completes is
+    /// full. The Event was dropped
+    PreConnectionQueueFull,
+    /// An Event was sent while the outgoing Event queue was full, and the
+    /// configured OverflowPolicy did not make room for it. The Event was not
+    /// queued
+    OutgoingQueueFull,
+    /// Credentials were retried while the Client was still connecting or
+    /// connected. Retrying is only possible once the connection has been
+    /// rejected or has timed out
+    RetryAuthNotAllowed,
 }
 
 impl fmt::Display for NaiaClientError {
@@ -11,6 +27,19 @@ impl fmt::Display for NaiaClientError {
         match self {
             NaiaClientError::Message(msg) => write!(f, "Naia Client Error: {}", msg),
             NaiaClientError::Wrapped(boxed_err) => fmt::Display::fmt(boxed_err.as_ref(), f),
+            NaiaClientError::SendFailed(boxed_err) => {
+                write!(f, "Naia Client Error: send failed: {}", boxed_err)
+            }
+            NaiaClientError::PreConnectionQueueFull => {
+                write!(f, "Naia Client Error: pre-connection event queue is full")
+            }
+            NaiaClientError::OutgoingQueueFull => {
+                write!(f, "Naia Client Error: outgoing event queue is full")
+            }
+            NaiaClientError::RetryAuthNotAllowed => write!(
+                f,
+                "Naia Client Error: credentials can only be retried after a rejection or timeout"
+            ),
         }
     }
 }
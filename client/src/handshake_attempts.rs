@@ -0,0 +1,173 @@
+use std::time::Duration;
+
+use naia_shared::Instant;
+
+/// Counts the handshake messages sent while trying to connect to the Server,
+/// so that the Client can give up on a Server which never answers, backs off
+/// between messages the Server hasn't answered, and times how long the
+/// handshake takes
+#[derive(Debug)]
+pub struct HandshakeAttempts {
+    max_attempts: Option<u16>,
+    attempts: u16,
+    unanswered_attempts: u16,
+    base_interval: Duration,
+    max_interval: Option<Duration>,
+    // when the first attempt was made, timed since `epoch`
+    epoch: Instant,
+    first_attempt: Option<Duration>,
+}
+
+impl HandshakeAttempts {
+    pub fn new(
+        max_attempts: Option<u16>,
+        base_interval: Duration,
+        max_interval: Option<Duration>,
+    ) -> Self {
+        HandshakeAttempts {
+            max_attempts,
+            attempts: 0,
+            unanswered_attempts: 0,
+            base_interval,
+            max_interval,
+            epoch: Instant::now(),
+            first_attempt: None,
+        }
+    }
+
+    /// Records another handshake attempt, returning false instead if the
+    /// maximum number of attempts have already been made
+    pub fn record_attempt(&mut self) -> bool {
+        return self.record_attempt_at(self.epoch.elapsed());
+    }
+
+    fn record_attempt_at(&mut self, now: Duration) -> bool {
+        if let Some(max_attempts) = self.max_attempts {
+            if self.attempts >= max_attempts {
+                return false;
+            }
+        }
+        if self.first_attempt.is_none() {
+            self.first_attempt = Some(now);
+        }
+        self.attempts = self.attempts.saturating_add(1);
+        self.unanswered_attempts = self.unanswered_attempts.saturating_add(1);
+        return true;
+    }
+
+    /// Returns how many handshake messages have been resent so far
+    pub fn retry_count(&self) -> u16 {
+        return self.attempts.saturating_sub(1);
+    }
+
+    /// Returns how long to wait before sending the next handshake message.
+    /// Without a maximum interval this is always the base interval. With
+    /// one, the wait doubles after each message the Server hasn't answered,
+    /// up to that maximum
+    pub fn interval(&self) -> Duration {
+        let max_interval = match self.max_interval {
+            Some(max_interval) => max_interval,
+            None => return self.base_interval,
+        };
+        let mut interval = self.base_interval;
+        for _ in 1..self.unanswered_attempts {
+            if interval >= max_interval {
+                break;
+            }
+            interval *= 2;
+        }
+        return interval.min(max_interval);
+    }
+
+    /// Records that the Server has answered, so that the wait before the next
+    /// message starts again from the base interval
+    pub fn mark_answered(&mut self) {
+        self.unanswered_attempts = 0;
+    }
+
+    /// Returns the time since the first attempt was made, or zero if none
+    /// has been made yet
+    pub fn elapsed(&self) -> Duration {
+        return self.elapsed_at(self.epoch.elapsed());
+    }
+
+    fn elapsed_at(&self, now: Duration) -> Duration {
+        match self.first_attempt {
+            Some(first_attempt) => return now.checked_sub(first_attempt).unwrap_or_default(),
+            None => return Duration::default(),
+        }
+    }
+
+    /// Starts counting attempts from zero again, for the next connection
+    pub fn reset(&mut self) {
+        self.attempts = 0;
+        self.unanswered_attempts = 0;
+        self.first_attempt = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::HandshakeAttempts;
+
+    #[test]
+    fn interval_doubles_up_to_the_max_until_answered() {
+        let mut attempts = HandshakeAttempts::new(
+            None,
+            Duration::from_millis(100),
+            Some(Duration::from_millis(500)),
+        );
+        let mut intervals = Vec::new();
+        for _ in 0..5 {
+            attempts.record_attempt();
+            intervals.push(attempts.interval().as_millis());
+        }
+        assert!(intervals == vec![100, 200, 400, 500, 500]);
+        assert!(attempts.retry_count() == 4);
+
+        // an answer from the Server starts the backoff over
+        attempts.mark_answered();
+        attempts.record_attempt();
+        assert!(attempts.interval() == Duration::from_millis(100));
+        assert!(attempts.retry_count() == 5);
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts_until_reset() {
+        let mut attempts = HandshakeAttempts::new(Some(3), Duration::from_secs(1), None);
+
+        for _ in 0..3 {
+            assert!(attempts.record_attempt());
+        }
+        assert!(!attempts.record_attempt());
+        assert!(!attempts.record_attempt());
+
+        attempts.reset();
+        assert!(attempts.record_attempt());
+    }
+
+    #[test]
+    fn elapsed_is_timed_from_the_first_attempt() {
+        let mut attempts = HandshakeAttempts::new(None, Duration::from_secs(1), None);
+        assert!(attempts.elapsed() == Duration::default());
+
+        attempts.record_attempt_at(Duration::from_millis(10));
+        // a resent handshake message does not restart the timing
+        attempts.record_attempt_at(Duration::from_millis(60));
+        assert!(attempts.elapsed_at(Duration::from_millis(110)) == Duration::from_millis(100));
+
+        attempts.reset();
+        assert!(attempts.elapsed() == Duration::default());
+    }
+
+    #[test]
+    fn retries_forever_without_max() {
+        let mut attempts = HandshakeAttempts::new(None, Duration::from_secs(1), None);
+
+        for _ in 0..1000 {
+            assert!(attempts.record_attempt());
+        }
+    }
+}
@@ -1,30 +1,50 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 
 use crate::{client_actor_manager::ClientActorManager, client_tick_manager::ClientTickManager};
-use naia_shared::{ActorType, LocalActorKey};
+use naia_shared::{ActorType, FixedFraction, Instant, LocalActorKey};
 use std::time::Duration;
 
 #[derive(Debug)]
 pub struct InterpolationManager<U: ActorType> {
+    ////////temp_actor, snapshots keyed by receive time since `epoch`
+    actor_store: HashMap<LocalActorKey, (U, VecDeque<(Duration, U)>)>,
     ////////temp_actor, prev_actor, next_actor
-    actor_store: HashMap<LocalActorKey, (U, U)>,
     pawn_store: HashMap<LocalActorKey, (U, U, U)>,
-    interp_duration: f32,
+    interpolation_delay: Duration,
+    allow_extrapolation: bool,
+    max_extrapolation: Duration,
+    deterministic_interpolation: bool,
+    epoch: Instant,
 }
 
 impl<U: ActorType> InterpolationManager<U> {
-    pub fn new(tick_duration: &Duration) -> Self {
+    pub fn new(
+        interpolation_delay: Duration,
+        allow_extrapolation: bool,
+        max_extrapolation: Duration,
+        deterministic_interpolation: bool,
+    ) -> Self {
         InterpolationManager {
             actor_store: HashMap::new(),
             pawn_store: HashMap::new(),
-            interp_duration: tick_duration.as_millis() as f32,
+            interpolation_delay,
+            allow_extrapolation,
+            max_extrapolation,
+            deterministic_interpolation,
+            epoch: Instant::now(),
         }
     }
 
+    /// Records a snapshot of the current state of every interpolated actor,
+    /// keyed by the moment it was received. Call this after applying
+    /// incoming updates.
     pub fn update_actors(&mut self, actor_manager: &ClientActorManager<U>) {
-        for (key, (_, prev_ent)) in self.actor_store.iter_mut() {
-            if let Some(now_ent) = actor_manager.get_actor(key) {
-                prev_ent.mirror(now_ent);
+        let received_at = self.epoch.elapsed();
+        let keys: Vec<LocalActorKey> = self.actor_store.keys().copied().collect();
+        for key in keys {
+            if let Some(now_ent) = actor_manager.get_actor(&key) {
+                let snapshot = now_ent.inner_ref().as_ref().borrow().get_typed_copy();
+                self.push_snapshot(&key, snapshot, received_at);
             }
         }
     }
@@ -50,12 +70,13 @@ impl<U: ActorType> InterpolationManager<U> {
                 .as_ref()
                 .borrow()
                 .get_typed_copy();
-            let prev_actor = existing_actor
+            let snapshot = existing_actor
                 .inner_ref()
                 .as_ref()
                 .borrow()
                 .get_typed_copy();
-            self.actor_store.insert(*key, (temp_actor, prev_actor));
+            self.actor_store.insert(*key, (temp_actor, VecDeque::new()));
+            self.push_snapshot(key, snapshot, self.epoch.elapsed());
         }
     }
 
@@ -63,21 +84,143 @@ impl<U: ActorType> InterpolationManager<U> {
         self.actor_store.remove(key);
     }
 
-    pub fn get_interpolation(
+    /// Returns the actor's state as it was `interpolation_delay` ago,
+    /// interpolated between the two snapshots which bracket that moment. If
+    /// no snapshot newer than that moment has arrived yet, the actor either
+    /// holds its newest snapshot or, if extrapolation is allowed, continues
+    /// along its last trajectory for up to `max_extrapolation`
+    pub fn get_interpolation(&mut self, key: &LocalActorKey) -> Option<&U> {
+        let now = self.epoch.elapsed();
+        return self.interpolate(key, now);
+    }
+
+    /// Discards the actor's buffered snapshots and re-seeds them with its
+    /// current state, as received now, so that interpolation starts fresh
+    /// from that state rather than blending through stale snapshots
+    pub fn sync_interpolation(
         &mut self,
-        tick_manager: &ClientTickManager,
         actor_manager: &ClientActorManager<U>,
         key: &LocalActorKey,
-    ) -> Option<&U> {
-        if let Some((temp_actor, prev_actor)) = self.actor_store.get_mut(key) {
-            if let Some(next_actor) = actor_manager.get_actor(key) {
-                temp_actor.set_to_interpolation(prev_actor, next_actor, tick_manager.fraction);
+    ) {
+        if let Some(now_ent) = actor_manager.get_actor(key) {
+            let snapshot = now_ent.inner_ref().as_ref().borrow().get_typed_copy();
+            if let Some((_, snapshots)) = self.actor_store.get_mut(key) {
+                snapshots.clear();
+            }
+            self.push_snapshot(key, snapshot, self.epoch.elapsed());
+        }
+    }
+
+    /// Re-seeds the snapshots of every actor and pawn with its current
+    /// state, as done by sync_interpolation() & sync_pawn_interpolation()
+    pub fn sync_all(&mut self, actor_manager: &ClientActorManager<U>) {
+        let keys: Vec<LocalActorKey> = self.actor_store.keys().copied().collect();
+        for key in keys {
+            self.sync_interpolation(actor_manager, &key);
+        }
+        let pawn_keys: Vec<LocalActorKey> = self.pawn_store.keys().copied().collect();
+        for key in pawn_keys {
+            self.sync_pawn_interpolation(actor_manager, &key);
+        }
+    }
+
+    fn push_snapshot(&mut self, key: &LocalActorKey, snapshot: U, received_at: Duration) {
+        if let Some((_, snapshots)) = self.actor_store.get_mut(key) {
+            snapshots.push_back((received_at, snapshot));
+        }
+        self.prune_snapshots(key, received_at);
+    }
+
+    // Drop snapshots which can no longer bracket the render time, always
+    // keeping at least two to interpolate between
+    fn prune_snapshots(&mut self, key: &LocalActorKey, now: Duration) {
+        let render_time = now
+            .checked_sub(self.interpolation_delay)
+            .unwrap_or_default();
+        if let Some((_, snapshots)) = self.actor_store.get_mut(key) {
+            while snapshots.len() > 2 && snapshots[1].0 <= render_time {
+                snapshots.pop_front();
+            }
+        }
+    }
+
+    fn interpolate(&mut self, key: &LocalActorKey, now: Duration) -> Option<&U> {
+        self.prune_snapshots(key, now);
+        let render_time = now
+            .checked_sub(self.interpolation_delay)
+            .unwrap_or_default();
+        if let Some((temp_actor, snapshots)) = self.actor_store.get_mut(key) {
+            let (old_time, old_actor) = snapshots.front()?;
+            if snapshots.len() < 2 || render_time <= *old_time {
+                temp_actor.mirror(old_actor);
+                return Some(temp_actor);
+            }
+            let (new_time, new_actor) = &snapshots[1];
+            // snapshots received at the same instant leave no span to
+            // interpolate across, so the newer one is shown
+            if render_time >= *new_time && *new_time <= *old_time {
+                temp_actor.mirror(new_actor);
+                return Some(temp_actor);
+            }
+            if render_time >= *new_time {
+                if !self.allow_extrapolation {
+                    temp_actor.mirror(new_actor);
+                    return Some(temp_actor);
+                }
+                let extrapolation = (render_time - *new_time).min(self.max_extrapolation);
+                if self.deterministic_interpolation {
+                    let fraction = FixedFraction::from_durations(
+                        extrapolation + (*new_time - *old_time),
+                        *new_time - *old_time,
+                    );
+                    temp_actor.set_to_interpolation_fixed(old_actor, new_actor, fraction);
+                    return Some(temp_actor);
+                }
+                let fraction =
+                    1.0 + extrapolation.as_secs_f32() / (*new_time - *old_time).as_secs_f32();
+                temp_actor.set_to_extrapolation(old_actor, new_actor, fraction);
                 return Some(temp_actor);
             }
+            InterpolationManager::set_smooth(
+                temp_actor,
+                (*old_time, old_actor),
+                (*new_time, new_actor),
+                render_time,
+                self.deterministic_interpolation,
+            );
+            return Some(temp_actor);
         }
         return None;
     }
 
+    // Sets the temp actor to its state at `now`, which lies between the
+    // instants the earlier & later snapshots were received at. The fraction
+    // is how far `now` is into the span between them, which is a tick
+    // interval when the Server sends an update every tick
+    fn set_smooth(
+        temp_actor: &mut U,
+        earlier: (Duration, &U),
+        later: (Duration, &U),
+        now: Duration,
+        deterministic: bool,
+    ) {
+        let (earlier_time, earlier_actor) = earlier;
+        let (later_time, later_actor) = later;
+        let elapsed = now.checked_sub(earlier_time).unwrap_or_default();
+        let span = later_time.checked_sub(earlier_time).unwrap_or_default();
+        if span == Duration::default() {
+            temp_actor.mirror(later_actor);
+            return;
+        }
+        if deterministic {
+            let fraction = FixedFraction::from_durations(elapsed, span);
+            temp_actor.set_to_interpolation_fixed(earlier_actor, later_actor, fraction);
+            return;
+        }
+        let fraction = elapsed.as_secs_f32() / span.as_secs_f32();
+        temp_actor.set_to_interpolation(earlier_actor, later_actor, fraction);
+    }
+
     // pawns
     pub fn create_pawn_interpolation(
         &mut self,
@@ -109,15 +252,263 @@ impl<U: ActorType> InterpolationManager<U> {
         self.pawn_store.remove(key);
     }
 
+    /// Sets both the previous and next states of the pawn to its current
+    /// state, so that it is rendered there without blending from a state it
+    /// no longer reaches, such as one that was mispredicted
+    pub fn sync_pawn_interpolation(
+        &mut self,
+        actor_manager: &ClientActorManager<U>,
+        key: &LocalActorKey,
+    ) {
+        if let Some(now_ent) = actor_manager.get_pawn(key) {
+            if let Some((_, prev_ent, next_ent)) = self.pawn_store.get_mut(key) {
+                prev_ent.mirror(now_ent);
+                next_ent.mirror(now_ent);
+            }
+        }
+    }
+
     pub fn get_pawn_interpolation(
         &mut self,
         tick_manager: &ClientTickManager,
         key: &LocalActorKey,
     ) -> Option<&U> {
         if let Some((temp_actor, prev_actor, next_actor)) = self.pawn_store.get_mut(key) {
-            temp_actor.set_to_interpolation(prev_actor, next_actor, tick_manager.fraction);
+            if self.deterministic_interpolation {
+                let fraction = FixedFraction::from_f32(tick_manager.fraction);
+                temp_actor.set_to_interpolation_fixed(prev_actor, next_actor, fraction);
+            } else {
+                temp_actor.set_to_interpolation(prev_actor, next_actor, tick_manager.fraction);
+            }
             return Some(temp_actor);
         }
         return None;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{any::TypeId, cell::RefCell, collections::VecDeque, rc::Rc, time::Duration};
+
+    use naia_shared::{Actor, ActorMutator, ActorType, PacketReader, StateMask};
+
+    use super::InterpolationManager;
+
+    #[derive(Clone, Debug)]
+    struct TestActor {
+        x: f32,
+        y: f32,
+    }
+
+    impl Actor<TestActorType> for TestActor {
+        fn get_state_mask_size(&self) -> u8 {
+            return 1;
+        }
+        fn get_typed_copy(&self) -> TestActorType {
+            return TestActorType::TestActor(Rc::new(RefCell::new(self.clone())));
+        }
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<TestActor>();
+        }
+        fn write(&self, _: &mut Vec<u8>) {}
+        fn write_partial(&self, _: &StateMask, _: &mut Vec<u8>) {}
+        fn read_full(&mut self, _: &mut PacketReader, _: u16) {}
+        fn read_partial(&mut self, _: &StateMask, _: &mut PacketReader, _: u16) {}
+        fn set_mutator(&mut self, _: &Rc<RefCell<dyn ActorMutator>>) {}
+        fn is_interpolated(&self) -> bool {
+            return true;
+        }
+        fn is_predicted(&self) -> bool {
+            return false;
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    enum TestActorType {
+        TestActor(Rc<RefCell<TestActor>>),
+    }
+
+    impl TestActorType {
+        fn new(x: f32) -> Self {
+            return TestActorType::at(x, 0.0);
+        }
+
+        fn at(x: f32, y: f32) -> Self {
+            return TestActorType::TestActor(Rc::new(RefCell::new(TestActor { x, y })));
+        }
+
+        fn x(&self) -> f32 {
+            match self {
+                TestActorType::TestActor(actor) => return actor.borrow().x,
+            }
+        }
+
+        fn y(&self) -> f32 {
+            match self {
+                TestActorType::TestActor(actor) => return actor.borrow().y,
+            }
+        }
+    }
+
+    impl ActorType for TestActorType {
+        fn read_full(&mut self, _: &mut PacketReader, _: u16) {}
+        fn read_partial(&mut self, _: &StateMask, _: &mut PacketReader, _: u16) {}
+        fn inner_ref(&self) -> Rc<RefCell<dyn Actor<TestActorType>>> {
+            match self {
+                TestActorType::TestActor(actor) => return actor.clone(),
+            }
+        }
+        fn equals(&self, other: &TestActorType) -> bool {
+            return self.x() == other.x() && self.y() == other.y();
+        }
+        fn equals_prediction(&self, other: &TestActorType) -> bool {
+            return self.equals(other);
+        }
+        fn set_to_interpolation(
+            &mut self,
+            old: &TestActorType,
+            new: &TestActorType,
+            fraction: f32,
+        ) {
+            match self {
+                TestActorType::TestActor(actor) => {
+                    let mut actor = actor.borrow_mut();
+                    actor.x = old.x() + (new.x() - old.x()) * fraction;
+                    actor.y = old.y() + (new.y() - old.y()) * fraction;
+                }
+            }
+        }
+        fn set_to_extrapolation(
+            &mut self,
+            old: &TestActorType,
+            new: &TestActorType,
+            fraction: f32,
+        ) {
+            return self.set_to_interpolation(old, new, fraction);
+        }
+        fn mirror(&mut self, other: &TestActorType) {
+            match self {
+                TestActorType::TestActor(actor) => {
+                    let mut actor = actor.borrow_mut();
+                    actor.x = other.x();
+                    actor.y = other.y();
+                }
+            }
+        }
+        fn is_interpolated(&self) -> bool {
+            return true;
+        }
+        fn is_predicted(&self) -> bool {
+            return false;
+        }
+    }
+
+    fn interpolated_x(
+        manager: &mut InterpolationManager<TestActorType>,
+        key: u16,
+        now_millis: u64,
+    ) -> f32 {
+        return manager
+            .interpolate(&key, Duration::from_millis(now_millis))
+            .unwrap()
+            .x();
+    }
+
+    #[test]
+    fn renders_between_snapshots_bracketing_delayed_time() {
+        let mut manager = InterpolationManager::new(
+            Duration::from_millis(100),
+            false,
+            Duration::default(),
+            false,
+        );
+        let key: u16 = 1;
+        manager
+            .actor_store
+            .insert(key, (TestActorType::new(0.0), VecDeque::new()));
+
+        manager.push_snapshot(&key, TestActorType::new(0.0), Duration::from_millis(0));
+        manager.push_snapshot(&key, TestActorType::new(10.0), Duration::from_millis(100));
+
+        // before the delay has elapsed, the oldest snapshot is held
+        assert!(interpolated_x(&mut manager, key, 50) == 0.0);
+        // now - delay = 50ms, halfway between the first two snapshots
+        assert!(interpolated_x(&mut manager, key, 150) == 5.0);
+
+        manager.push_snapshot(&key, TestActorType::new(20.0), Duration::from_millis(200));
+        // now - delay = 150ms, halfway between the last two snapshots
+        assert!(interpolated_x(&mut manager, key, 250) == 15.0);
+        // once past the newest snapshot, it is held rather than overshot
+        assert!(interpolated_x(&mut manager, key, 400) == 20.0);
+
+        // stale snapshots are pruned, but two are always kept
+        assert!(manager.actor_store.get(&key).unwrap().1.len() == 2);
+    }
+
+    #[test]
+    fn extrapolates_along_trajectory_up_to_clamp() {
+        let mut manager = InterpolationManager::new(
+            Duration::from_millis(100),
+            true,
+            Duration::from_millis(50),
+            false,
+        );
+        let key: u16 = 1;
+        manager
+            .actor_store
+            .insert(key, (TestActorType::new(0.0), VecDeque::new()));
+
+        manager.push_snapshot(&key, TestActorType::new(0.0), Duration::from_millis(0));
+        manager.push_snapshot(&key, TestActorType::new(10.0), Duration::from_millis(100));
+
+        // the third snapshot, due at 200ms, never arrives
+        // now - delay = 125ms, 25ms past the newest snapshot
+        assert!(interpolated_x(&mut manager, key, 225) == 12.5);
+        // now - delay = 150ms, reaching the clamp
+        assert!(interpolated_x(&mut manager, key, 250) == 15.0);
+        // beyond the clamp the actor stops rather than drifting further
+        assert!(interpolated_x(&mut manager, key, 400) == 15.0);
+    }
+
+    #[test]
+    fn snapshots_received_together_snap_to_the_newest() {
+        for deterministic in [false, true].iter() {
+            let mut manager = InterpolationManager::new(
+                Duration::from_millis(100),
+                true,
+                Duration::from_millis(50),
+                *deterministic,
+            );
+            let key: u16 = 1;
+            manager
+                .actor_store
+                .insert(key, (TestActorType::new(0.0), VecDeque::new()));
+
+            manager.push_snapshot(&key, TestActorType::new(0.0), Duration::from_millis(100));
+            manager.push_snapshot(&key, TestActorType::new(10.0), Duration::from_millis(100));
+
+            assert!(interpolated_x(&mut manager, key, 210) == 10.0);
+            assert!(interpolated_x(&mut manager, key, 225) == 10.0);
+        }
+    }
+
+    #[test]
+    fn position_is_halfway_at_the_midpoint_instant() {
+        for deterministic in [false, true].iter() {
+            let earlier = TestActorType::at(0.0, 10.0);
+            let later = TestActorType::at(20.0, 30.0);
+            let mut temp_actor = TestActorType::new(0.0);
+
+            InterpolationManager::set_smooth(
+                &mut temp_actor,
+                (Duration::from_millis(100), &earlier),
+                (Duration::from_millis(150), &later),
+                Duration::from_millis(125),
+                *deterministic,
+            );
+
+            assert!(temp_actor.x() == 10.0);
+            assert!(temp_actor.y() == 20.0);
+        }
+    }
+}
@@ -12,25 +12,43 @@
     unused_import_braces
 )]
 
+mod actor_handle;
+mod actor_stats;
+mod actor_update_listener;
 mod client_actor_manager;
 mod client_actor_message;
 mod client_config;
 mod client_connection_state;
 mod client_event;
+mod client_log;
 mod client_packet_writer;
 mod client_tick_manager;
 mod command_receiver;
 mod command_sender;
+mod connection_state_tracker;
 mod error;
+mod handshake_attempts;
 mod interpolation_manager;
+mod link_conditioner;
+#[cfg(all(any(test, feature = "testing"), not(target_arch = "wasm32")))]
+mod mock_client_socket;
 mod naia_client;
-mod ping_manager;
+mod packet_sender;
+mod pre_connection_queue;
+mod round_robin;
 mod server_connection;
 mod tick_queue;
 
-pub use naia_shared::{find_my_ip_address, Instant, LinkConditionerConfig, Random};
+pub use naia_shared::{
+    find_my_ip_address, find_my_ip_address_towards, find_my_ipv6_address, DisconnectPolicy,
+    EventId, Instant, LinkConditionerConfig, PacketTraffic, Random, TrafficStats, PROTOCOL_VERSION,
+};
 
+pub use actor_handle::ActorHandle;
+pub use actor_stats::ActorStats;
 pub use client_config::ClientConfig;
+pub use client_connection_state::ClientConnectionState;
 pub use client_event::ClientEvent;
+pub use client_log::{DATA_LOG_TARGET, HANDSHAKE_LOG_TARGET};
 pub use naia_client::NaiaClient;
 pub use naia_client_socket::Packet;
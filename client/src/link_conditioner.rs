@@ -0,0 +1,229 @@
+use log::info;
+use naia_client_socket::{ClientSocketTrait, NaiaClientSocketError, Packet};
+use naia_shared::{Instant, LinkConditionerConfig, Random, TimeQueue};
+
+// spreads the bits of small seeds across the state
+const SEED_MIX: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Simulates network conditions on packets received from the Server. Unlike
+/// wrapping the ClientSocket in a LinkConditioner, the simulated conditions
+/// can be changed or disabled at any time without reconnecting.
+#[derive(Debug)]
+pub struct LinkConditioner {
+    config: Option<LinkConditionerConfig>,
+    time_queue: TimeQueue<Packet>,
+    random: ConditionerRandom,
+}
+
+impl LinkConditioner {
+    /// Creates a new LinkConditioner. If a seed is given, packets are lost
+    /// and delayed in the same pattern on every run, otherwise the pattern
+    /// is drawn from entropy
+    pub fn new(config: Option<LinkConditionerConfig>, seed: Option<u64>) -> Self {
+        LinkConditioner {
+            config,
+            time_queue: TimeQueue::new(),
+            random: ConditionerRandom::new(seed),
+        }
+    }
+
+    /// Replaces the simulated network conditions. Packets which were already
+    /// delayed under the previous conditions are still delivered when due,
+    /// and if None, new packets are delivered immediately
+    pub fn set_config(&mut self, config: Option<LinkConditionerConfig>) {
+        self.config = config;
+    }
+
+    /// Receives all available packets from the socket, and returns the next
+    /// packet which the simulated conditions allow through
+    pub fn receive(
+        &mut self,
+        socket: &mut dyn ClientSocketTrait,
+    ) -> Result<Option<Packet>, NaiaClientSocketError> {
+        if self.config.is_none() && self.time_queue.len() == 0 {
+            return socket.receive();
+        }
+
+        loop {
+            match socket.receive() {
+                Ok(Some(packet)) => match &self.config {
+                    Some(config) => {
+                        process_packet(config, &mut self.random, &mut self.time_queue, packet);
+                    }
+                    None => {
+                        self.time_queue.add_item(Instant::now(), packet);
+                    }
+                },
+                Ok(None) => {
+                    break;
+                }
+                Err(error) => {
+                    return Err(error);
+                }
+            }
+        }
+
+        return Ok(self.time_queue.pop_item());
+    }
+}
+
+// Adds the packet to the time queue, to be delivered once its simulated
+// latency has passed, unless it is simulated as lost. Draws in the same order
+// as `link_condition_logic::process_packet` does
+fn process_packet(
+    config: &LinkConditionerConfig,
+    random: &mut ConditionerRandom,
+    time_queue: &mut TimeQueue<Packet>,
+    packet: Packet,
+) {
+    if random.gen_f32() <= config.incoming_loss {
+        info!("link conditioner: packet lost");
+        return;
+    }
+    if random.gen_f32() <= config.incoming_corruption {
+        info!("link conditioner: packet corrupted");
+        return;
+    }
+    let mut latency: u32 = config.incoming_latency;
+    if config.incoming_jitter > 0 {
+        if random.gen_bool() {
+            latency += random.gen_u32_below(config.incoming_jitter);
+        } else {
+            latency = latency.saturating_sub(random.gen_u32_below(config.incoming_jitter));
+        }
+    }
+    let mut packet_timestamp = Instant::now();
+    packet_timestamp.add_millis(latency);
+    time_queue.add_item(packet_timestamp, packet);
+}
+
+// The source of the simulated conditions: either entropy, or a small
+// xorshift generator, so that conditions can be reproduced without depending
+// on a platform's source of randomness
+#[derive(Debug)]
+enum ConditionerRandom {
+    Entropy,
+    Seeded(u64),
+}
+
+impl ConditionerRandom {
+    fn new(seed: Option<u64>) -> Self {
+        match seed {
+            // xorshift stays at zero once there, so a zero state is avoided
+            Some(seed) => match seed ^ SEED_MIX {
+                0 => return ConditionerRandom::Seeded(SEED_MIX),
+                state => return ConditionerRandom::Seeded(state),
+            },
+            None => return ConditionerRandom::Entropy,
+        }
+    }
+
+    fn gen_f32(&mut self) -> f32 {
+        match self {
+            ConditionerRandom::Entropy => return Random::gen_range_f32(0.0, 1.0),
+            ConditionerRandom::Seeded(state) => {
+                return (xorshift(state) >> 40) as f32 / (1u64 << 24) as f32;
+            }
+        }
+    }
+
+    // Returns a value in `0..upper`, where `upper` is not zero
+    fn gen_u32_below(&mut self, upper: u32) -> u32 {
+        match self {
+            ConditionerRandom::Entropy => return Random::gen_range_u32(0, upper),
+            ConditionerRandom::Seeded(state) => return (xorshift(state) >> 32) as u32 % upper,
+        }
+    }
+
+    fn gen_bool(&mut self) -> bool {
+        match self {
+            ConditionerRandom::Entropy => return Random::gen_bool(),
+            ConditionerRandom::Seeded(state) => return xorshift(state) >> 63 == 1,
+        }
+    }
+}
+
+fn xorshift(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    return *state;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use naia_client_socket::{ClientSocketTrait, MessageSender, NaiaClientSocketError, Packet};
+    use naia_shared::LinkConditionerConfig;
+
+    use super::{ConditionerRandom, LinkConditioner, SEED_MIX};
+
+    #[derive(Debug)]
+    struct TestSocket {
+        incoming: VecDeque<Packet>,
+    }
+
+    impl ClientSocketTrait for TestSocket {
+        fn receive(&mut self) -> Result<Option<Packet>, NaiaClientSocketError> {
+            return Ok(self.incoming.pop_front());
+        }
+        fn get_sender(&mut self) -> MessageSender {
+            unimplemented!()
+        }
+        fn with_link_conditioner(
+            self: Box<Self>,
+            _: &LinkConditionerConfig,
+        ) -> Box<dyn ClientSocketTrait> {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    fn disabling_conditioner_restores_immediate_delivery() {
+        let mut socket = TestSocket {
+            incoming: VecDeque::new(),
+        };
+        let mut link_conditioner =
+            LinkConditioner::new(Some(LinkConditionerConfig::new(10000, 0, 0.0, 0.0)), None);
+
+        socket.incoming.push_back(Packet::new(vec![1]));
+        assert!(link_conditioner.receive(&mut socket).unwrap().is_none());
+
+        link_conditioner.set_config(None);
+        socket.incoming.push_back(Packet::new(vec![2]));
+        let packet = link_conditioner.receive(&mut socket).unwrap().unwrap();
+        assert!(packet.payload() == [2]);
+
+        // the packet delayed before disabling is still held until it is due
+        assert!(link_conditioner.receive(&mut socket).unwrap().is_none());
+    }
+
+    #[test]
+    fn seed_which_cancels_the_mix_still_draws() {
+        let mut random = ConditionerRandom::new(Some(SEED_MIX));
+        let draws: Vec<bool> = (0..64).map(|_| random.gen_bool()).collect();
+        assert!(draws.contains(&true) && draws.contains(&false));
+    }
+
+    #[test]
+    fn seeded_conditioner_drops_the_same_packets_every_run() {
+        let drop_pattern = || {
+            let mut socket = TestSocket {
+                incoming: VecDeque::new(),
+            };
+            let mut link_conditioner =
+                LinkConditioner::new(Some(LinkConditionerConfig::new(0, 0, 0.5, 0.0)), Some(42));
+            let mut delivered = Vec::new();
+            for index in 0..64 {
+                socket.incoming.push_back(Packet::new(vec![index]));
+                delivered.push(link_conditioner.receive(&mut socket).unwrap().is_some());
+            }
+            return delivered;
+        };
+
+        let first_run = drop_pattern();
+        assert!(first_run == drop_pattern());
+        assert!(first_run.contains(&true) && first_run.contains(&false));
+    }
+}
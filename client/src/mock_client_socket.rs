@@ -0,0 +1,113 @@
+use std::{
+    error::Error,
+    net::{SocketAddr, UdpSocket},
+};
+
+use naia_client_socket::{
+    ClientSocketTrait, LinkConditionerConfig, MessageSender, NaiaClientSocketError, Packet,
+};
+use naia_shared::{MockLink, Ref};
+
+use crate::packet_sender::PacketSender;
+
+// The largest datagram which can be passed on from a MessageSender
+const LOOPBACK_BUFFER_SIZE: usize = 1472;
+
+/// A Client socket which receives packets from a MockLink instead of the
+/// network. Packets are sent through a MockClientSender, or through the
+/// MessageSender from get_sender()
+#[derive(Debug)]
+pub struct MockClientSocket {
+    link: MockLink,
+    // A MessageSender can only send over a UdpSocket, so the ones handed out
+    // send to this loopback socket, which passes their packets on into the
+    // link when the Client next receives
+    loopback: Ref<UdpSocket>,
+    loopback_address: SocketAddr,
+}
+
+impl MockClientSocket {
+    /// Creates a new MockClientSocket, receiving from the given link
+    pub fn new(link: MockLink) -> Self {
+        let loopback = UdpSocket::bind("127.0.0.1:0").expect("couldn't bind loopback socket");
+        loopback
+            .set_nonblocking(true)
+            .expect("couldn't set loopback socket to non-blocking");
+        let loopback_address = loopback.local_addr().unwrap();
+        MockClientSocket {
+            link,
+            loopback: Ref::new(loopback),
+            loopback_address,
+        }
+    }
+}
+
+impl ClientSocketTrait for MockClientSocket {
+    fn receive(&mut self) -> Result<Option<Packet>, NaiaClientSocketError> {
+        // pass along everything sent through a MessageSender since the last
+        // call
+        let mut buffer = [0; LOOPBACK_BUFFER_SIZE];
+        while let Ok(length) = self.loopback.borrow().recv(&mut buffer) {
+            self.link.send_to_server(buffer[..length].into());
+        }
+
+        match self.link.receive_at_client() {
+            Some(payload) => return Ok(Some(Packet::new_raw(payload))),
+            None => return Ok(None),
+        }
+    }
+
+    fn get_sender(&mut self) -> MessageSender {
+        return MessageSender::new(self.loopback_address, self.loopback.clone());
+    }
+
+    // Link conditioning is done by the NaiaClient's own LinkConditioner
+    fn with_link_conditioner(
+        self: Box<Self>,
+        _: &LinkConditionerConfig,
+    ) -> Box<dyn ClientSocketTrait> {
+        return self;
+    }
+}
+
+/// Sends packets from the Client into a MockLink
+#[derive(Debug)]
+pub struct MockClientSender {
+    link: MockLink,
+}
+
+impl MockClientSender {
+    /// Creates a new MockClientSender, sending into the given link
+    pub fn new(link: MockLink) -> Self {
+        MockClientSender { link }
+    }
+}
+
+impl PacketSender for MockClientSender {
+    fn send(&mut self, packet: Packet) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.link.send_to_server(packet.payload().into());
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use naia_client_socket::{ClientSocketTrait, Packet};
+    use naia_shared::MockLink;
+
+    use super::MockClientSocket;
+
+    #[test]
+    fn packet_sent_through_message_sender_reaches_the_server() {
+        let link = MockLink::new("127.0.0.1:14191".parse().unwrap());
+        let mut socket = MockClientSocket::new(link.clone());
+        let mut sender = socket.get_sender();
+
+        sender.send(Packet::new(vec![1, 2, 3])).unwrap();
+        assert!(link.receive_at_server().is_none());
+
+        // passed on when the Client next receives
+        assert!(socket.receive().unwrap().is_none());
+        assert!(link.receive_at_server().unwrap().as_ref() == [1, 2, 3]);
+    }
+}
@@ -1,23 +1,57 @@
-use std::net::SocketAddr;
+use std::{net::SocketAddr, time::Duration};
 
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use log::warn;
 
-use naia_client_socket::{ClientSocket, ClientSocketTrait, MessageSender};
+use naia_client_socket::{ClientSocket, ClientSocketTrait};
 
 pub use naia_shared::{
-    ActorType, ConnectionConfig, Event, EventType, HostTickManager, Instant, LocalActorKey,
-    ManagerType, Manifest, PacketReader, PacketType, SequenceIterator, SharedConfig,
-    StandardHeader, Timer, Timestamp,
+    ActorType, ConnectionConfig, Event, EventId, EventType, FragmentManager, HostTickManager,
+    Instant, LinkConditionerConfig, LocalActorKey, ManagerType, Manifest, PacketReader, PacketType,
+    PingManager, SequenceIterator, SequenceNumber, SharedConfig, StandardHeader, Timer, Timestamp,
+};
+
+#[cfg(all(any(test, feature = "testing"), not(target_arch = "wasm32")))]
+use naia_shared::MockLink;
+use naia_shared::{
+    ConnectionConfigBuilder, PacketReaderExt, PacketSerde, RawMessageError, TrafficStats,
+    COMPRESSION_FLAG, INTEGRITY_CHECK_FLAG, PROTOCOL_VERSION,
 };
 
 use super::{
-    client_actor_message::ClientActorMessage, client_config::ClientConfig,
-    client_event::ClientEvent, client_tick_manager::ClientTickManager, error::NaiaClientError,
-    server_connection::ServerConnection, Packet,
+    actor_handle::ActorHandle,
+    actor_stats::ActorStats,
+    actor_update_listener::ActorUpdateListener,
+    client_actor_message::ClientActorMessage,
+    client_config::ClientConfig,
+    client_event::ClientEvent,
+    client_log,
+    client_tick_manager::ClientTickManager,
+    connection_state_tracker::ConnectionStateTracker,
+    error::NaiaClientError,
+    handshake_attempts::HandshakeAttempts,
+    link_conditioner::LinkConditioner,
+    packet_sender::{send_packet, PacketSender},
+    pre_connection_queue::PreConnectionQueue,
+    round_robin::RoundRobin,
+    server_connection::ServerConnection,
+    Packet,
 };
 use crate::client_connection_state::{
     ClientConnectionState, ClientConnectionState::AwaitingChallengeResponse,
 };
+#[cfg(all(any(test, feature = "testing"), not(target_arch = "wasm32")))]
+use crate::mock_client_socket::{MockClientSender, MockClientSocket};
+
+// The most packets a single call to flush() will send, so that it always
+// returns even if data keeps being queued
+const MAX_FLUSHED_PACKETS: usize = 1024;
+// after its version, a challenge response holds the Server's tick (2
+// bytes), tick interval (4), the Client's timestamp (8) & its digest (32)
+const CHALLENGE_RESPONSE_LENGTH: usize = 46;
+// a reconnect response holds the next session token, followed by the
+// Server's epoch
+const SERVER_EPOCH_LENGTH: usize = 4;
 
 /// Client can send/receive events to/from a server, and has a pool of in-scope
 /// actors that are synced with the server
@@ -26,13 +60,28 @@ pub struct NaiaClient<T: EventType, U: ActorType> {
     manifest: Manifest<T, U>,
     server_address: SocketAddr,
     connection_config: ConnectionConfig,
+    interpolation_delay: Duration,
+    allow_extrapolation: bool,
+    max_extrapolation: Duration,
+    deterministic_interpolation: bool,
+    actor_capacity: usize,
     socket: Box<dyn ClientSocketTrait>,
-    sender: MessageSender,
+    link_conditioner: LinkConditioner,
+    shared_config: SharedConfig,
+    sender: Box<dyn PacketSender>,
     server_connection: Option<ServerConnection<T, U>>,
     pre_connection_timestamp: Option<Timestamp>,
     pre_connection_digest: Option<Box<[u8]>>,
+    pre_connection_events: PreConnectionQueue<T>,
     handshake_timer: Timer,
-    connection_state: ClientConnectionState,
+    handshake_attempts: HandshakeAttempts,
+    session_token: Option<Box<[u8]>>,
+    reconnect_digest: Option<Box<[u8]>>,
+    reconnect_grace_period: Option<Duration>,
+    reconnect_started: Option<Instant>,
+    connection_state: ConnectionStateTracker,
+    incoming_round_robin: RoundRobin,
+    actor_update_listener: ActorUpdateListener<U>,
     auth_event: Option<T>,
     tick_manager: ClientTickManager,
 }
@@ -46,41 +95,141 @@ impl<T: EventType, U: ActorType> NaiaClient<T, U> {
         client_config: Option<ClientConfig>,
         shared_config: SharedConfig,
         auth: Option<T>,
+    ) -> Self {
+        let mut client_socket = ClientSocket::connect(server_address);
+        let message_sender = client_socket.get_sender();
+
+        return NaiaClient::new_with_socket(
+            server_address,
+            manifest,
+            client_config,
+            shared_config,
+            auth,
+            client_socket,
+            Box::new(message_sender),
+        );
+    }
+
+    /// Create a new client which exchanges packets with a Server through an
+    /// in-memory MockLink instead of the network, for use in tests
+    #[cfg(all(any(test, feature = "testing"), not(target_arch = "wasm32")))]
+    pub fn new_mock(
+        link: &MockLink,
+        manifest: Manifest<T, U>,
+        client_config: Option<ClientConfig>,
+        shared_config: SharedConfig,
+        auth: Option<T>,
+    ) -> Self {
+        return NaiaClient::new_with_socket(
+            link.client_address(),
+            manifest,
+            client_config,
+            shared_config,
+            auth,
+            Box::new(MockClientSocket::new(link.clone())),
+            Box::new(MockClientSender::new(link.clone())),
+        );
+    }
+
+    /// Makes the Client's next tick begin on the next call to receive(),
+    /// however little time has passed, so that tests can release received
+    /// Data packets without waiting on the tick interval
+    #[cfg(all(any(test, feature = "testing"), not(target_arch = "wasm32")))]
+    pub fn ring_tick(&mut self) {
+        self.tick_manager.ring_manual();
+    }
+
+    fn new_with_socket(
+        server_address: SocketAddr,
+        manifest: Manifest<T, U>,
+        client_config: Option<ClientConfig>,
+        shared_config: SharedConfig,
+        auth: Option<T>,
+        client_socket: Box<dyn ClientSocketTrait>,
+        sender: Box<dyn PacketSender>,
     ) -> Self {
         let client_config = match client_config {
             Some(config) => config,
             None => ClientConfig::default(),
         };
 
-        let connection_config = ConnectionConfig::new(
-            client_config.disconnection_timeout_duration,
-            client_config.heartbeat_interval,
-            client_config.ping_interval,
-            client_config.rtt_sample_size,
-        );
-
-        let mut client_socket = ClientSocket::connect(server_address);
-        if let Some(config) = shared_config.link_condition_config {
-            client_socket = client_socket.with_link_conditioner(&config);
+        #[allow(deprecated)]
+        let disconnect_policy = client_config
+            .disconnect_policy
+            .or_disconnection_timeout(client_config.disconnection_timeout_duration);
+        let mut builder = ConnectionConfigBuilder::new()
+            .disconnect_policy(disconnect_policy)
+            .heartbeat_interval(client_config.heartbeat_interval)
+            .ping_interval(client_config.ping_interval)
+            .rtt_sample_size(client_config.rtt_sample_size)
+            .max_packet_size(client_config.max_packet_size)
+            .fragment_timeout(client_config.fragment_timeout_duration)
+            .packet_loss_window_size(client_config.packet_loss_window_size)
+            .max_payload_size(client_config.max_payload_size)
+            .pong_timeout_intervals(client_config.pong_timeout_intervals)
+            .event_capacity(client_config.event_capacity)
+            .integrity_check(client_config.integrity_check);
+        if let Some(max_retransmits) = client_config.max_retransmits {
+            builder = builder.max_retransmits(max_retransmits);
+        }
+        if let Some(max_events_per_packet) = client_config.max_events_per_packet {
+            builder = builder.max_events_per_packet(max_events_per_packet);
+        }
+        if let Some(coalesce_delay) = client_config.coalesce_delay {
+            builder = builder.coalesce_delay(coalesce_delay);
         }
+        if let Some(max_outgoing_queue_len) = client_config.max_outgoing_queue_len {
+            builder =
+                builder.outgoing_queue_limit(max_outgoing_queue_len, client_config.overflow_policy);
+        }
+        let connection_config = match builder.build() {
+            Ok(connection_config) => connection_config,
+            Err(error) => panic!("invalid ClientConfig: {}", error),
+        };
 
         let mut handshake_timer = Timer::new(client_config.send_handshake_interval);
         handshake_timer.ring_manual();
-        let message_sender = client_socket.get_sender();
 
         NaiaClient {
             server_address,
             manifest,
             socket: client_socket,
-            sender: message_sender,
+            link_conditioner: LinkConditioner::new(
+                shared_config.link_condition_config.clone(),
+                client_config.link_conditioner_seed,
+            ),
+            sender,
             connection_config,
+            interpolation_delay: client_config.interpolation_delay,
+            allow_extrapolation: client_config.allow_extrapolation,
+            max_extrapolation: client_config.max_extrapolation,
+            deterministic_interpolation: client_config.deterministic_interpolation,
+            actor_capacity: client_config.actor_capacity,
             handshake_timer,
+            handshake_attempts: HandshakeAttempts::new(
+                client_config.max_handshake_attempts,
+                client_config.send_handshake_interval,
+                client_config.max_handshake_interval,
+            ),
+            session_token: None,
+            reconnect_digest: None,
+            reconnect_grace_period: client_config.reconnect_grace_period,
+            reconnect_started: None,
             server_connection: None,
             pre_connection_timestamp: None,
             pre_connection_digest: None,
-            connection_state: AwaitingChallengeResponse,
+            pre_connection_events: PreConnectionQueue::new(client_config.max_pre_connection_events),
+            connection_state: ConnectionStateTracker::new(AwaitingChallengeResponse),
+            incoming_round_robin: RoundRobin::new(),
+            actor_update_listener: ActorUpdateListener::new(),
             auth_event: auth,
-            tick_manager: ClientTickManager::new(shared_config.tick_interval),
+            tick_manager: ClientTickManager::new(
+                shared_config.tick_interval,
+                client_config.client_tick_buffer,
+                client_config.tick_drift_gain,
+                client_config.tick_resync_threshold,
+            ),
+            shared_config,
         }
     }
 
@@ -92,6 +241,14 @@ impl<T: EventType, U: ActorType> NaiaClient<T, U> {
         // send ticks, handshakes, heartbeats, pings, timeout if need be
         match &mut self.server_connection {
             Some(connection) => {
+                // receive replayed command
+                if let Some((tick, pawn_key, command)) = connection.get_incoming_replay() {
+                    return Some(Ok(ClientEvent::ReplayCommand(
+                        pawn_key,
+                        tick,
+                        command.as_ref().get_typed_copy(),
+                    )));
+                }
                 // receive command
                 if let Some((pawn_key, command)) = connection.get_incoming_command() {
                     return Some(Ok(ClientEvent::Command(
@@ -99,92 +256,174 @@ impl<T: EventType, U: ActorType> NaiaClient<T, U> {
                         command.as_ref().get_typed_copy(),
                     )));
                 }
-                // receive event
-                if let Some(event) = connection.get_incoming_event() {
-                    return Some(Ok(ClientEvent::Event(event)));
-                }
-                // receive actor message
-                if let Some(message) = connection.get_incoming_actor_message() {
-                    match message {
-                        ClientActorMessage::Create(local_key) => {
-                            return Some(Ok(ClientEvent::CreateActor(local_key)));
-                        }
-                        ClientActorMessage::Delete(local_key) => {
-                            return Some(Ok(ClientEvent::DeleteActor(local_key)));
-                        }
-                        ClientActorMessage::Update(local_key) => {
-                            return Some(Ok(ClientEvent::UpdateActor(local_key)));
-                        }
-                        ClientActorMessage::AssignPawn(local_key) => {
-                            return Some(Ok(ClientEvent::AssignPawn(local_key)));
-                        }
-                        ClientActorMessage::UnassignPawn(local_key) => {
-                            return Some(Ok(ClientEvent::UnassignPawn(local_key)));
+                // receive events & actor messages, taking turns between them
+                if let Some(client_event) = self.incoming_round_robin.pop(
+                    connection,
+                    NaiaClient::pop_incoming_event,
+                    NaiaClient::pop_incoming_actor_message,
+                ) {
+                    if let ClientEvent::UpdateActor(local_key, _) = &client_event {
+                        if let Some(actor) = connection.get_received_actor(local_key) {
+                            self.actor_update_listener.notify(*local_key, actor);
                         }
                     }
+                    return Some(Ok(client_event));
+                }
+                // receive raw messages
+                if let Some((channel, data)) = connection.get_incoming_raw() {
+                    return Some(Ok(ClientEvent::Raw(channel, data.into_vec())));
+                }
+                // receive event delivery notification
+                if let Some(event_id) = connection.get_delivered_event() {
+                    return Some(Ok(ClientEvent::EventDelivered(event_id)));
                 }
                 // update current tick
                 if self.tick_manager.take_tick() {
+                    // send anything held back for coalescing on the next call
+                    connection.flush_coalesced();
                     return Some(Ok(ClientEvent::Tick));
                 }
-                // drop connection if necessary
-                if connection.should_drop() {
-                    self.server_connection = None;
-                    self.pre_connection_timestamp = None;
-                    self.pre_connection_digest = None;
-                    self.connection_state = AwaitingChallengeResponse;
-                    return Some(Ok(ClientEvent::Disconnection));
+                // try to reconnect, or drop connection if necessary
+                let now = Instant::now();
+                if connection.should_drop_at(&now) {
+                    let reconnect_started =
+                        self.reconnect_started.get_or_insert_with(|| now.clone());
+                    let can_reconnect = match (&self.session_token, self.reconnect_grace_period) {
+                        (Some(_), Some(grace_period)) => {
+                            NaiaClient::<T, U>::is_within_grace_period(
+                                reconnect_started,
+                                grace_period,
+                                &now,
+                            )
+                        }
+                        _ => false,
+                    };
+                    if can_reconnect {
+                        self.connection_state
+                            .set(ClientConnectionState::Reconnecting);
+                        if self.handshake_timer.ringing() {
+                            client_log::handshake_phase(
+                                "reconnect",
+                                &self.server_address,
+                                PacketType::Reconnect,
+                                self.tick_manager.get_client_tick(),
+                            );
+                            // once challenged, prove that this Client receives at
+                            // the address it is reconnecting from
+                            let mut payload_bytes = self.session_token.as_ref().unwrap().to_vec();
+                            if let Some(reconnect_digest) = &self.reconnect_digest {
+                                payload_bytes.extend_from_slice(reconnect_digest);
+                            }
+                            if let Err(error) = NaiaClient::<T, U>::internal_send_connectionless(
+                                self.sender.as_mut(),
+                                PacketType::Reconnect,
+                                Packet::new(payload_bytes),
+                            ) {
+                                return Some(Err(error));
+                            }
+                            self.handshake_timer.reset();
+                        }
+                    } else {
+                        self.server_connection = None;
+                        self.pre_connection_timestamp = None;
+                        self.pre_connection_digest = None;
+                        self.session_token = None;
+                        self.reconnect_started = None;
+                        self.reconnect_digest = None;
+                        self.connection_state.set(AwaitingChallengeResponse);
+                        return Some(Ok(ClientEvent::Disconnection(None)));
+                    }
                 } else {
+                    // the Server has been heard from again
+                    if self.connection_state.get() == ClientConnectionState::Reconnecting {
+                        self.connection_state.set(ClientConnectionState::Connected);
+                        self.reconnect_started = None;
+                        self.reconnect_digest = None;
+                        return Some(Ok(ClientEvent::Reconnection));
+                    }
                     // send heartbeats
                     if connection.should_send_heartbeat() {
-                        NaiaClient::internal_send_with_connection(
+                        if let Err(error) = NaiaClient::internal_send_with_connection(
                             self.tick_manager.get_client_tick(),
-                            &mut self.sender,
+                            self.sender.as_mut(),
                             connection,
                             PacketType::Heartbeat,
                             Packet::empty(),
-                        );
+                        ) {
+                            return Some(Err(error));
+                        }
                     }
                     // send pings
                     if connection.should_send_ping() {
                         let ping_payload = connection.get_ping_payload();
-                        NaiaClient::internal_send_with_connection(
+                        if let Err(error) = NaiaClient::internal_send_with_connection(
                             self.tick_manager.get_client_tick(),
-                            &mut self.sender,
+                            self.sender.as_mut(),
                             connection,
                             PacketType::Ping,
                             ping_payload,
-                        );
+                        ) {
+                            return Some(Err(error));
+                        }
                     }
                     // send a packet
                     while let Some(payload) = connection
                         .get_outgoing_packet(self.tick_manager.get_client_tick(), &self.manifest)
                     {
-                        self.sender
-                            .send(Packet::new_raw(payload))
-                            .expect("send failed!");
+                        let datagrams = match connection.fragment_outgoing_packet(payload) {
+                            Ok(datagrams) => datagrams,
+                            Err(error) => {
+                                return Some(Err(NaiaClientError::Wrapped(Box::new(error))));
+                            }
+                        };
+                        for datagram in datagrams {
+                            if let Err(error) = send_packet(self.sender.as_mut(), datagram) {
+                                return Some(Err(error));
+                            }
+                        }
                         connection.mark_sent();
                     }
                 }
             }
             None => {
                 if self.handshake_timer.ringing() {
-                    match self.connection_state {
+                    // give up on a Server which never answers
+                    let is_handshaking = self.connection_state.get()
+                        == ClientConnectionState::AwaitingChallengeResponse
+                        || self.connection_state.get()
+                            == ClientConnectionState::AwaitingConnectResponse;
+                    if is_handshaking && !self.handshake_attempts.record_attempt() {
+                        self.connection_state.set(ClientConnectionState::TimedOut);
+                        return Some(Ok(ClientEvent::ConnectionTimeout));
+                    }
+
+                    match self.connection_state.get() {
                         ClientConnectionState::AwaitingChallengeResponse => {
-                            if self.pre_connection_timestamp.is_none() {
-                                self.pre_connection_timestamp = Some(Timestamp::now());
-                            }
+                            // refresh the timestamp on each attempt, so that it is not refused
+                            // as stale by the Server
+                            self.pre_connection_timestamp = Some(Timestamp::now());
 
                             let mut timestamp_bytes = Vec::new();
+                            timestamp_bytes
+                                .write_u16::<BigEndian>(PROTOCOL_VERSION)
+                                .unwrap(); // write protocol version
                             self.pre_connection_timestamp
                                 .as_mut()
                                 .unwrap()
                                 .write(&mut timestamp_bytes);
-                            NaiaClient::<T, U>::internal_send_connectionless(
-                                &mut self.sender,
+                            client_log::handshake_phase(
+                                "challenge_request",
+                                &self.server_address,
                                 PacketType::ClientChallengeRequest,
-                                Packet::new(timestamp_bytes),
+                                self.tick_manager.get_client_tick(),
                             );
+                            if let Err(error) = NaiaClient::<T, U>::internal_send_connectionless(
+                                self.sender.as_mut(),
+                                PacketType::ClientChallengeRequest,
+                                Packet::new(timestamp_bytes),
+                            ) {
+                                return Some(Err(error));
+                            }
                         }
                         ClientConnectionState::AwaitingConnectResponse => {
                             // write timestamp & digest into payload
@@ -197,6 +436,20 @@ impl<T: EventType, U: ActorType> NaiaClient<T, U> {
                             {
                                 payload_bytes.push(*digest_byte);
                             }
+                            // write whether this Client is able to decompress Data packets,
+                            // and whether it wants packets to be checksummed
+                            let mut flags = 0;
+                            if self.shared_config.compressor.is_some() {
+                                flags |= COMPRESSION_FLAG;
+                            }
+                            if self.connection_config.integrity_check {
+                                flags |= INTEGRITY_CHECK_FLAG;
+                            }
+                            payload_bytes.push(flags);
+                            // write which Compressor this Client has
+                            if let Some(compressor) = &self.shared_config.compressor {
+                                payload_bytes.push(compressor.id());
+                            }
                             // write auth event object if there is one
                             if let Some(auth_event) = &mut self.auth_event {
                                 let type_id = auth_event.get_type_id();
@@ -204,79 +457,252 @@ impl<T: EventType, U: ActorType> NaiaClient<T, U> {
                                 payload_bytes.write_u16::<BigEndian>(naia_id).unwrap(); // write naia id
                                 auth_event.write(&mut payload_bytes);
                             }
-                            NaiaClient::<T, U>::internal_send_connectionless(
-                                &mut self.sender,
+                            client_log::handshake_phase(
+                                "connect_request",
+                                &self.server_address,
                                 PacketType::ClientConnectRequest,
-                                Packet::new(payload_bytes),
+                                self.tick_manager.get_client_tick(),
                             );
+                            if let Err(error) = NaiaClient::<T, U>::internal_send_connectionless(
+                                self.sender.as_mut(),
+                                PacketType::ClientConnectRequest,
+                                Packet::new(payload_bytes),
+                            ) {
+                                return Some(Err(error));
+                            }
                         }
                         _ => {}
                     }
 
-                    self.handshake_timer.reset();
+                    self.handshake_timer = Timer::new(self.handshake_attempts.interval());
                 }
             }
         }
 
         // receive from socket
         loop {
-            match self.socket.receive() {
+            match self.link_conditioner.receive(self.socket.as_mut()) {
                 Ok(event) => {
                     if let Some(packet) = event {
                         let server_connection_wrapper = self.server_connection.as_mut();
 
                         if let Some(server_connection) = server_connection_wrapper {
+                            // a reconnect challenge doesn't mean the connection has
+                            // been restored yet
+                            if let Some((header, payload)) = StandardHeader::read(packet.payload())
+                            {
+                                if header.packet_type() == PacketType::ServerReconnectChallenge {
+                                    if self.connection_state.get()
+                                        == ClientConnectionState::Reconnecting
+                                    {
+                                        self.reconnect_digest = Some(payload);
+                                        self.handshake_timer.ring_manual();
+                                    }
+                                    continue;
+                                }
+                            }
                             server_connection.mark_heard();
 
-                            let (header, payload) = StandardHeader::read(packet.payload());
-                            server_connection
-                                .process_incoming_header(&header, &mut self.tick_manager);
+                            let reassembled_packet: Box<[u8]>;
+                            let packet_payload: &[u8] =
+                                if FragmentManager::is_fragment(packet.payload()) {
+                                    match server_connection
+                                        .process_incoming_fragment(packet.payload())
+                                    {
+                                        Some(reassembled_payload) => {
+                                            reassembled_packet = reassembled_payload;
+                                            &reassembled_packet
+                                        }
+                                        // wait for the rest of the fragments
+                                        None => continue,
+                                    }
+                                } else {
+                                    packet.payload()
+                                };
+
+                            let (header, payload) = match StandardHeader::read(packet_payload) {
+                                Some(packet) => packet,
+                                None => {
+                                    warn!("discarding corrupted packet from the Server");
+                                    continue;
+                                }
+                            };
+                            if !server_connection.accepts_header(&header) {
+                                warn!("discarding unchecksummed packet from the Server");
+                                continue;
+                            }
+
+                            // don't apply a duplicated Data packet again
+                            if header.packet_type() == PacketType::Data
+                                && server_connection.is_duplicate(header.local_packet_index())
+                            {
+                                continue;
+                            }
+                            server_connection.process_incoming_header(
+                                &header,
+                                payload.len(),
+                                &mut self.tick_manager,
+                            );
 
                             match header.packet_type() {
                                 PacketType::Data => {
-                                    server_connection.buffer_data_packet(
-                                        header.host_tick(),
+                                    client_log::data_packet(
+                                        &self.server_address,
                                         header.local_packet_index(),
-                                        &payload,
+                                        header.host_tick(),
                                     );
+                                    if let Some(payload) =
+                                        server_connection.decompress_payload(&header, payload)
+                                    {
+                                        server_connection.buffer_data_packet(
+                                            header.host_tick(),
+                                            header.local_packet_index(),
+                                            &payload,
+                                        );
+                                    }
                                     continue;
                                 }
                                 PacketType::Heartbeat => {
                                     continue;
                                 }
+                                PacketType::Ping => {
+                                    // let the Server measure its RTT
+                                    let pong_payload = match PingManager::process_ping(&payload) {
+                                        Some(pong_payload) => pong_payload,
+                                        None => continue,
+                                    };
+                                    if let Err(error) = NaiaClient::internal_send_with_connection(
+                                        self.tick_manager.get_client_tick(),
+                                        self.sender.as_mut(),
+                                        server_connection,
+                                        PacketType::Pong,
+                                        Packet::new_raw(pong_payload),
+                                    ) {
+                                        return Some(Err(error));
+                                    }
+                                    continue;
+                                }
                                 PacketType::Pong => {
                                     server_connection.process_pong(&payload);
                                     continue;
                                 }
+                                PacketType::ServerReconnectResponse => {
+                                    if payload.len() <= SERVER_EPOCH_LENGTH {
+                                        continue;
+                                    }
+                                    let (session_token, epoch_bytes) =
+                                        payload.split_at(payload.len() - SERVER_EPOCH_LENGTH);
+                                    // the token to present if the connection times out
+                                    // again
+                                    self.session_token = Some(session_token.into());
+
+                                    // resync with the Server, keeping the buffers keyed
+                                    // by its ticks unless it has restarted since
+                                    let server_epoch =
+                                        u32::read(&mut PacketReader::new(epoch_bytes))
+                                            .unwrap_or_default();
+                                    if self
+                                        .tick_manager
+                                        .set_initial_tick(header.host_tick(), server_epoch)
+                                    {
+                                        server_connection.clear_tick_buffers();
+                                    }
+                                    continue;
+                                }
+                                PacketType::Disconnect => {
+                                    let reason =
+                                        NaiaClient::read_reason_payload(&self.manifest, &payload);
+
+                                    self.server_connection = None;
+                                    self.pre_connection_timestamp = None;
+                                    self.pre_connection_digest = None;
+                                    self.session_token = None;
+                                    self.reconnect_started = None;
+                                    self.reconnect_digest = None;
+                                    self.connection_state.set(AwaitingChallengeResponse);
+                                    return Some(Ok(ClientEvent::Disconnection(reason)));
+                                }
                                 _ => {}
                             }
                         } else {
-                            let (header, payload) = StandardHeader::read(packet.payload());
+                            let (header, payload) = match StandardHeader::read(packet.payload()) {
+                                Some(packet) => packet,
+                                None => {
+                                    warn!("discarding corrupted packet from the Server");
+                                    continue;
+                                }
+                            };
                             match header.packet_type() {
                                 PacketType::ServerChallengeResponse => {
-                                    if self.connection_state
+                                    if self.connection_state.get()
                                         == ClientConnectionState::AwaitingChallengeResponse
                                     {
                                         if let Some(my_timestamp) = self.pre_connection_timestamp {
                                             let mut reader = PacketReader::new(&payload);
+                                            // a response too short to hold a version is
+                                            // malformed, rather than from another version
+                                            let server_version = match reader.try_read_u16() {
+                                                Some(server_version) => server_version,
+                                                None => continue,
+                                            };
+                                            // the rest can't be read if the versions differ
+                                            if server_version != PROTOCOL_VERSION {
+                                                self.connection_state
+                                                    .set(ClientConnectionState::VersionMismatch);
+                                                return Some(Ok(ClientEvent::VersionMismatch(
+                                                    server_version,
+                                                )));
+                                            }
+                                            if reader.remaining() < CHALLENGE_RESPONSE_LENGTH {
+                                                continue;
+                                            }
                                             let server_tick = reader
                                                 .get_cursor()
                                                 .read_u16::<BigEndian>()
                                                 .unwrap();
+                                            let server_tick_interval =
+                                                SharedConfig::read_tick_interval(&mut reader);
                                             let payload_timestamp = Timestamp::read(&mut reader);
 
                                             if my_timestamp == payload_timestamp {
-                                                let mut digest_bytes: Vec<u8> = Vec::new();
-                                                for _ in 0..32 {
-                                                    digest_bytes.push(reader.read_u8());
-                                                }
-                                                self.pre_connection_digest =
-                                                    Some(digest_bytes.into_boxed_slice());
+                                                let digest_bytes = match reader.try_read_bytes(32) {
+                                                    Some(digest_bytes) => digest_bytes,
+                                                    None => continue,
+                                                };
+                                                self.pre_connection_digest = Some(digest_bytes);
+
+                                                // the ServerConnection made once connected starts
+                                                // with empty tick buffers, whatever the epoch
+                                                let server_epoch =
+                                                    u32::read(&mut reader).unwrap_or_default();
+                                                self.tick_manager
+                                                    .set_initial_tick(server_tick, server_epoch);
+                                                client_log::handshake_phase(
+                                                    "challenge_response",
+                                                    &self.server_address,
+                                                    PacketType::ServerChallengeResponse,
+                                                    server_tick,
+                                                );
+
+                                                self.handshake_attempts.mark_answered();
+                                                self.handshake_timer =
+                                                    Timer::new(self.handshake_attempts.interval());
 
-                                                self.tick_manager.set_initial_tick(server_tick);
+                                                self.connection_state.set(
+                                                    ClientConnectionState::AwaitingConnectResponse,
+                                                );
 
-                                                self.connection_state =
-                                                    ClientConnectionState::AwaitingConnectResponse;
+                                                // tick synchronization will drift, but the
+                                                // connection is still usable
+                                                if let Err(error) = self
+                                                    .shared_config
+                                                    .validate_tick_interval(server_tick_interval)
+                                                {
+                                                    warn!("{}", error);
+                                                    return Some(Err(NaiaClientError::Wrapped(
+                                                        Box::new(error),
+                                                    )));
+                                                }
                                             }
                                         }
                                     }
@@ -284,15 +710,75 @@ impl<T: EventType, U: ActorType> NaiaClient<T, U> {
                                     continue;
                                 }
                                 PacketType::ServerConnectResponse => {
-                                    let server_connection = ServerConnection::new(
+                                    // a late or duplicated response can't revive a
+                                    // handshake that has already ended
+                                    if self.connection_state.get()
+                                        != ClientConnectionState::AwaitingConnectResponse
+                                    {
+                                        continue;
+                                    }
+                                    let mut server_connection = ServerConnection::new(
                                         self.server_address,
                                         &self.connection_config,
-                                        &self.tick_manager,
+                                        self.interpolation_delay,
+                                        self.allow_extrapolation,
+                                        self.max_extrapolation,
+                                        self.deterministic_interpolation,
+                                        self.actor_capacity,
                                     );
 
+                                    let flags = payload.first().copied().unwrap_or_default();
+                                    // the Server has agreed to compress Data packets
+                                    if flags & COMPRESSION_FLAG != 0 {
+                                        server_connection
+                                            .set_compressor(self.shared_config.compressor.clone());
+                                    }
+                                    // the Server has agreed to checksum packets
+                                    if flags & INTEGRITY_CHECK_FLAG != 0 {
+                                        server_connection.set_integrity_check(true);
+                                    }
+
+                                    // the token to present if the connection times out
+                                    if payload.len() > 1 {
+                                        self.session_token = Some(payload[1..].into());
+                                    }
+
+                                    self.pre_connection_events.flush(&mut server_connection);
+
                                     self.server_connection = Some(server_connection);
-                                    self.connection_state = ClientConnectionState::Connected;
-                                    return Some(Ok(ClientEvent::Connection));
+                                    self.connection_state.set(ClientConnectionState::Connected);
+                                    client_log::handshake_phase(
+                                        "connected",
+                                        &self.server_address,
+                                        PacketType::ServerConnectResponse,
+                                        self.tick_manager.get_client_tick(),
+                                    );
+                                    let handshake_duration = self.handshake_attempts.elapsed();
+                                    self.handshake_attempts.reset();
+                                    // reconnect messages are resent at the base interval
+                                    self.handshake_timer =
+                                        Timer::new(self.handshake_attempts.interval());
+                                    return Some(Ok(ClientEvent::Connection(handshake_duration)));
+                                }
+                                PacketType::ServerRejectConnect => {
+                                    if self.connection_state.get()
+                                        == ClientConnectionState::AwaitingConnectResponse
+                                    {
+                                        self.connection_state.set(ClientConnectionState::Rejected);
+                                        client_log::handshake_phase(
+                                            "rejected",
+                                            &self.server_address,
+                                            PacketType::ServerRejectConnect,
+                                            self.tick_manager.get_client_tick(),
+                                        );
+                                        let reason = NaiaClient::read_reason_payload(
+                                            &self.manifest,
+                                            &payload,
+                                        );
+                                        return Some(Ok(ClientEvent::RejectConnection(reason)));
+                                    }
+
+                                    continue;
                                 }
                                 _ => {}
                             }
@@ -315,20 +801,183 @@ impl<T: EventType, U: ActorType> NaiaClient<T, U> {
         return None;
     }
 
-    /// Queues up an Event to be sent to the Server
-    pub fn send_event(&mut self, event: &impl Event<T>) {
-        if let Some(connection) = &mut self.server_connection {
-            connection.queue_event(event);
+    /// An alternative to calling receive() in a loop until it returns None.
+    /// Performs the same work as receive(), clears `events`, and fills it
+    /// with every event retrieved, in the order receive() would have
+    /// returned them. Intended to be called once per frame from a game loop,
+    /// passing the same Vec each time so that no allocation is needed once
+    /// it has grown to fit a frame's events. An error ends the frame's
+    /// events, as a failed send is only retried on the next poll
+    pub fn poll(&mut self, events: &mut Vec<Result<ClientEvent<T>, NaiaClientError>>) {
+        events.clear();
+        while let Some(result) = self.receive() {
+            let is_error = result.is_err();
+            events.push(result);
+            if is_error {
+                break;
+            }
         }
     }
 
-    /// Queues up an Command to be sent to the Server
+    /// Enables, retunes, or (if None) disables simulated latency, jitter and
+    /// packet loss on packets received from the Server, without needing to
+    /// reconstruct the Client. This only affects the simulated layer on top
+    /// of the real socket, and is intended for debugging.
+    pub fn set_link_conditioner(&mut self, config: Option<LinkConditionerConfig>) {
+        self.link_conditioner.set_config(config);
+    }
+
+    /// Queues up an Event to be sent to the Server, returning the id that a
+    /// ClientEvent::EventDelivered will carry once a guaranteed Event has
+    /// been received. Ids are unique within a connection. Events sent before
+    /// a connection is established are held until the handshake completes,
+    /// and an error is returned if too many are already being held
+    pub fn send_event(&mut self, event: &impl Event<T>) -> Result<EventId, NaiaClientError> {
+        match &mut self.server_connection {
+            Some(connection) => {
+                return connection
+                    .queue_event(event)
+                    .ok_or(NaiaClientError::OutgoingQueueFull);
+            }
+            None => {
+                return self.pre_connection_events.queue_event(event);
+            }
+        }
+    }
+
+    /// Queues up an Command to be sent to the Server. The Command is also
+    /// surfaced locally as a ClientEvent::Command to predict the Pawn's
+    /// state, and is surfaced again as a ClientEvent::ReplayCommand if a later
+    /// update from the Server disagrees with that prediction
     pub fn send_command(&mut self, pawn_key: LocalActorKey, command: &impl Event<T>) {
         if let Some(connection) = &mut self.server_connection {
             connection.queue_command(pawn_key, command);
         }
     }
 
+    /// Queues up a raw message to be sent to the Server on the given channel,
+    /// for application-defined data that is not an Event. Raw messages are
+    /// acknowledged & resent like guaranteed Events, and are received by the
+    /// Server as a ServerEvent::Raw. An error is returned if there is no
+    /// connection with the Server, or if the message is too large to fit
+    /// into a packet
+    pub fn send_raw(&mut self, channel: u8, data: &[u8]) -> Result<(), NaiaClientError> {
+        if let Some(connection) = &mut self.server_connection {
+            return connection
+                .queue_raw(channel, data)
+                .map_err(|error| NaiaClientError::Wrapped(Box::new(error)));
+        }
+        return Err(NaiaClientError::Wrapped(Box::new(
+            RawMessageError::NotConnected,
+        )));
+    }
+
+    /// Immediately sends everything queued for the Server which can be sent,
+    /// rather than waiting for the next calls to receive(), for example to
+    /// get reliable Events out before disconnecting. Data held back for
+    /// coalescing is included. Returns the number of packets sent, which is
+    /// 0 if there is no connection with the Server
+    pub fn flush(&mut self) -> Result<usize, NaiaClientError> {
+        let mut flushed_packets = 0;
+        if let Some(connection) = &mut self.server_connection {
+            connection.flush_coalesced();
+            while flushed_packets < MAX_FLUSHED_PACKETS {
+                match connection
+                    .get_outgoing_packet(self.tick_manager.get_client_tick(), &self.manifest)
+                {
+                    Some(payload) => {
+                        let datagrams = connection
+                            .fragment_outgoing_packet(payload)
+                            .map_err(|error| NaiaClientError::Wrapped(Box::new(error)))?;
+                        for datagram in datagrams {
+                            send_packet(self.sender.as_mut(), datagram)?;
+                        }
+                        connection.mark_sent();
+                        flushed_packets += 1;
+                    }
+                    None => break,
+                }
+            }
+        }
+        return Ok(flushed_packets);
+    }
+
+    /// Gracefully closes the connection with the Server, notifying it so that
+    /// it does not need to wait for a timeout. The Client will then attempt to
+    /// establish a new connection on subsequent calls to receive(). If the
+    /// Disconnect packet could not be sent, the Client is still disconnected
+    /// locally, and the Server will time the connection out instead
+    pub fn disconnect(&mut self) -> Result<(), NaiaClientError> {
+        let mut result = Ok(());
+        if let Some(connection) = &mut self.server_connection {
+            result = NaiaClient::internal_send_with_connection(
+                self.tick_manager.get_client_tick(),
+                self.sender.as_mut(),
+                connection,
+                PacketType::Disconnect,
+                Packet::empty(),
+            );
+        }
+
+        self.server_connection = None;
+        self.pre_connection_timestamp = None;
+        self.pre_connection_digest = None;
+        self.session_token = None;
+        self.reconnect_started = None;
+        self.reconnect_digest = None;
+        self.connection_state.set(AwaitingChallengeResponse);
+        self.handshake_attempts.reset();
+
+        return result;
+    }
+
+    /// Replaces the Authentication event, and restarts the handshake with the
+    /// Server, so that new credentials can be supplied after the connection
+    /// was rejected. Only allowed once the Client has been rejected or has
+    /// timed out, as otherwise a handshake is already underway or complete
+    pub fn retry_auth(&mut self, auth: T) -> Result<(), NaiaClientError> {
+        match self.connection_state.get() {
+            ClientConnectionState::Rejected | ClientConnectionState::TimedOut => {}
+            _ => return Err(NaiaClientError::RetryAuthNotAllowed),
+        }
+
+        self.auth_event = Some(auth);
+        self.pre_connection_timestamp = None;
+        self.pre_connection_digest = None;
+        self.connection_state.set(AwaitingChallengeResponse);
+        self.handshake_attempts.reset();
+        self.handshake_timer.ring_manual();
+
+        return Ok(());
+    }
+
+    /// Returns whether a connection with the Server is currently established
+    pub fn is_connected(&self) -> bool {
+        return self.connection_state.get() == ClientConnectionState::Connected;
+    }
+
+    /// Sets a callback to be called each time the phase of the connection
+    /// with the Server changes, for example to update a UI as the handshake
+    /// progresses. Each change is reported once, as it happens, from within
+    /// receive(), disconnect() or retry_auth(). The initial
+    /// AwaitingChallengeResponse phase is not reported
+    pub fn on_state_change(&mut self, state_change_func: Box<dyn FnMut(ClientConnectionState)>) {
+        self.connection_state.on_change(state_change_func);
+    }
+
+    /// Gets the current phase of the connection with the Server, for example
+    /// to show progress while the handshake is underway
+    pub fn connection_state(&self) -> ClientConnectionState {
+        return self.connection_state.get();
+    }
+
+    /// Gets how many handshake messages have been resent while trying to
+    /// connect to the Server. Counts from zero again after each connection,
+    /// disconnect() or retry_auth()
+    pub fn handshake_retry_count(&self) -> u16 {
+        return self.handshake_attempts.retry_count();
+    }
+
     /// Get the address currently associated with the Server
     pub fn server_address(&self) -> SocketAddr {
         return self.server_address;
@@ -344,11 +993,34 @@ impl<T: EventType, U: ActorType> NaiaClient<T, U> {
     /// Get a reference to an Actor currently in scope for the Client, given
     /// that Actor's Key
     pub fn get_actor(&mut self, key: &LocalActorKey) -> Option<&U> {
+        return self.server_connection.as_mut().unwrap().get_actor(key);
+    }
+
+    /// Sets a callback to be called from within receive() each time an
+    /// UpdateActor event is returned, with the Actor's Key & its state as
+    /// received from the Server, so that the Actor need not be looked up
+    /// with get_actor(). The UpdateActor event is still returned as usual
+    pub fn on_actor_update(&mut self, update_func: Box<dyn FnMut(LocalActorKey, &U)>) {
+        self.actor_update_listener.on_update(update_func);
+    }
+
+    /// Get a handle to an Actor currently in scope for the Client, given that
+    /// Actor's Key. Unlike the Key, the handle stops resolving once the Actor
+    /// is deleted, even if the Server reuses the Key for a new Actor
+    pub fn get_actor_handle(&self, key: &LocalActorKey) -> Option<ActorHandle> {
+        return self
+            .server_connection
+            .as_ref()
+            .and_then(|connection| connection.get_actor_handle(key));
+    }
+
+    /// Get a reference to the Actor an ActorHandle was taken for, or None if
+    /// that Actor has since been deleted
+    pub fn get_actor_by_handle(&mut self, handle: &ActorHandle) -> Option<&U> {
         return self
             .server_connection
             .as_mut()
-            .unwrap()
-            .get_actor(&self.tick_manager, key);
+            .and_then(|connection| connection.get_actor_by_handle(handle));
     }
 
     /// Return an iterator to the collection of keys to all actors tracked by
@@ -365,15 +1037,80 @@ impl<T: EventType, U: ActorType> NaiaClient<T, U> {
         return None;
     }
 
+    /// Get the Server tick at which an Actor was created or last updated,
+    /// given that Actor's Key. Useful for spotting Actors which have not
+    /// been refreshed in a while
+    pub fn get_actor_last_update_tick(&self, key: &LocalActorKey) -> Option<u16> {
+        return self
+            .server_connection
+            .as_ref()
+            .and_then(|connection| connection.get_actor_last_update_tick(key));
+    }
+
+    /// Return an iterator over every Actor currently in scope for the Client,
+    /// along with its Key. This yields the latest state received from the
+    /// Server, so use get_actor() to render interpolated Actors.
+    pub fn actors(&self) -> impl Iterator<Item = (LocalActorKey, &U)> {
+        return self
+            .server_connection
+            .iter()
+            .flat_map(|connection| connection.actors());
+    }
+
+    /// Spawns an Actor locally ahead of the Server, for example a projectile
+    /// fired by the Client, returning a token which must be sent to the
+    /// Server along with whatever causes the spawn. Once the Server has
+    /// passed the token to NaiaServer::confirm_predicted_spawn(), the
+    /// Actor's creation arrives as a ClientEvent::ConfirmSpawn, and the
+    /// predicted Actor is replaced by the confirmed one instead of both
+    /// existing. Returns None if there is no connection with the Server
+    pub fn predict_spawn(&mut self, actor: U) -> Option<u16> {
+        return self
+            .server_connection
+            .as_mut()
+            .map(|connection| connection.predict_spawn(actor));
+    }
+
+    /// Get a reference to a predicted Actor which the Server has not yet
+    /// confirmed, given the token returned by predict_spawn()
+    pub fn get_predicted_actor(&self, token: &u16) -> Option<&U> {
+        return self
+            .server_connection
+            .as_ref()
+            .and_then(|connection| connection.get_predicted_actor(token));
+    }
+
+    /// Discards a predicted Actor which the Server will not confirm, for
+    /// example because the spawn was refused, returning it
+    pub fn cancel_predicted_spawn(&mut self, token: &u16) -> Option<U> {
+        return self
+            .server_connection
+            .as_mut()
+            .and_then(|connection| connection.cancel_predicted_spawn(token));
+    }
+
     // pawns
 
-    /// Get a reference to a Pawn
+    /// Get a reference to a Pawn for rendering, interpolated between the
+    /// previous & current tick if the Pawn is interpolated, or its predicted
+    /// state otherwise. Returns None if the Pawn isn't assigned to the
+    /// Client, or if no connection has been established
     pub fn get_pawn(&mut self, key: &LocalActorKey) -> Option<&U> {
+        let tick_manager = &self.tick_manager;
         return self
             .server_connection
             .as_mut()
-            .unwrap()
-            .get_pawn(&self.tick_manager, key);
+            .and_then(|connection| connection.get_pawn(tick_manager, key));
+    }
+
+    /// Get a reference to the predicted state of a Pawn, as of the latest
+    /// tick, without interpolation between ticks. Returns None if the Pawn
+    /// isn't assigned to the Client, or if no connection has been established
+    pub fn get_pawn_predicted(&self, key: &LocalActorKey) -> Option<&U> {
+        return self
+            .server_connection
+            .as_ref()
+            .and_then(|connection| connection.get_pawn_predicted(key));
     }
 
     /// Get a reference to a Pawn, used for setting it's state
@@ -381,6 +1118,16 @@ impl<T: EventType, U: ActorType> NaiaClient<T, U> {
         return self.server_connection.as_mut().unwrap().get_pawn_mut(key);
     }
 
+    /// Return an iterator over every Pawn currently assigned to the Client,
+    /// along with its Key. This yields the predicted state, without
+    /// interpolation between ticks.
+    pub fn pawns(&self) -> impl Iterator<Item = (LocalActorKey, &U)> {
+        return self
+            .server_connection
+            .iter()
+            .flat_map(|connection| connection.pawns());
+    }
+
     /// Return an iterator to the collection of keys to all Pawns tracked by
     /// the Client
     pub fn pawn_keys(&self) -> Option<Vec<LocalActorKey>> {
@@ -397,14 +1144,72 @@ impl<T: EventType, U: ActorType> NaiaClient<T, U> {
 
     // connection metrics
 
-    /// Gets the average Round Trip Time measured to the Server
-    pub fn get_rtt(&self) -> f32 {
-        return self.server_connection.as_ref().unwrap().get_rtt();
+    /// Gets the average Round Trip Time measured to the Server, in
+    /// milliseconds. Returns None if no connection has been established, or
+    /// if no Pong has been received within the configured pong timeout
+    pub fn get_rtt(&self) -> Option<f32> {
+        if let Some(connection) = &self.server_connection {
+            return connection.get_rtt();
+        }
+        return None;
+    }
+
+    /// Gets the totals of the bytes sent to & received from the Server, by
+    /// packet type. Returns None if no connection has been established
+    pub fn traffic_stats(&self) -> Option<TrafficStats> {
+        if let Some(connection) = &self.server_connection {
+            return Some(connection.traffic_stats());
+        }
+        return None;
+    }
+
+    /// Gets the time since the last packet of any kind was received from the
+    /// Server, for example to warn that the connection is unstable before it
+    /// times out. Returns None if no connection has been established
+    pub fn time_since_last_server_packet(&self) -> Option<Duration> {
+        if let Some(connection) = &self.server_connection {
+            return Some(connection.last_heard().elapsed());
+        }
+        return None;
+    }
+
+    /// Gets the counts of Actor creates, updates & deletes applied on the
+    /// most recent tick, for example to graph how much the Server is sending.
+    /// Returns None if no connection has been established
+    pub fn last_tick_actor_stats(&self) -> Option<ActorStats> {
+        if let Some(connection) = &self.server_connection {
+            return Some(connection.last_tick_actor_stats());
+        }
+        return None;
+    }
+
+    /// Gets the average Jitter measured in connection to the Server, in
+    /// milliseconds. Returns None if no connection has been established
+    pub fn get_jitter(&self) -> Option<f32> {
+        if let Some(connection) = &self.server_connection {
+            return Some(connection.get_jitter());
+        }
+        return None;
     }
 
-    /// Gets the average Jitter measured in connection to the Server
-    pub fn get_jitter(&self) -> f32 {
-        return self.server_connection.as_ref().unwrap().get_jitter();
+    /// Gets the fraction (between 0.0 and 1.0) of recently sent packets which
+    /// were dropped on the way to the Server. Returns None if no connection
+    /// has been established
+    pub fn get_packet_loss(&self) -> Option<f32> {
+        if let Some(connection) = &self.server_connection {
+            return Some(connection.get_packet_loss());
+        }
+        return None;
+    }
+
+    /// Gets the index of the next packet that will be sent to the Server,
+    /// which can be used to correlate application logs with packets on the
+    /// wire. Returns None if no connection has been established
+    pub fn get_next_packet_index(&self) -> Option<SequenceNumber> {
+        if let Some(connection) = &self.server_connection {
+            return Some(connection.get_next_packet_index());
+        }
+        return None;
     }
 
     // ticks
@@ -414,6 +1219,26 @@ impl<T: EventType, U: ActorType> NaiaClient<T, U> {
         return self.tick_manager.get_client_tick();
     }
 
+    /// Gets the approximate moment at which the given Server tick starts. The
+    /// tick is assumed to be within half the sequence space of the current
+    /// Server tick
+    pub fn tick_to_instant(&self, tick: u16) -> Instant {
+        return self.tick_manager.tick_to_instant(tick);
+    }
+
+    /// Gets the Server tick which is running at the given moment
+    pub fn instant_to_tick(&self, instant: &Instant) -> u16 {
+        return self.tick_manager.instant_to_tick(instant);
+    }
+
+    /// Gets the epoch of the Server most recently connected to, which
+    /// changes whenever the Server restarts without carrying over its
+    /// session. Ticks from Servers of different epochs shouldn't be compared.
+    /// Returns None if no handshake has been answered yet
+    pub fn server_epoch(&self) -> Option<u32> {
+        return self.tick_manager.get_server_epoch();
+    }
+
     /// Gets the last received tick from the Server
     pub fn get_server_tick(&self) -> u16 {
         return self
@@ -425,34 +1250,627 @@ impl<T: EventType, U: ActorType> NaiaClient<T, U> {
 
     // internal functions
 
+    fn pop_incoming_event(connection: &mut ServerConnection<T, U>) -> Option<ClientEvent<T>> {
+        match connection.get_incoming_event() {
+            Some((Some(local_key), event)) => {
+                return Some(ClientEvent::ActorEvent(local_key, event))
+            }
+            Some((None, event)) => return Some(ClientEvent::Event(event)),
+            None => return None,
+        }
+    }
+
+    fn pop_incoming_actor_message(
+        connection: &mut ServerConnection<T, U>,
+    ) -> Option<ClientEvent<T>> {
+        match connection.get_incoming_actor_message() {
+            Some(ClientActorMessage::Create(local_key, type_id)) => {
+                let spawn_event = connection.pop_spawn_event();
+                return Some(ClientEvent::CreateActor(local_key, type_id, spawn_event));
+            }
+            Some(ClientActorMessage::ConfirmSpawn(token, local_key)) => {
+                let spawn_event = connection.pop_spawn_event();
+                return Some(ClientEvent::ConfirmSpawn(token, local_key, spawn_event));
+            }
+            Some(ClientActorMessage::Delete(local_key)) => {
+                return Some(ClientEvent::DeleteActor(local_key));
+            }
+            Some(ClientActorMessage::Update(local_key, state_mask)) => {
+                return Some(ClientEvent::UpdateActor(local_key, state_mask));
+            }
+            Some(ClientActorMessage::AssignPawn(local_key)) => {
+                return Some(ClientEvent::AssignPawn(local_key));
+            }
+            Some(ClientActorMessage::UnassignPawn(local_key)) => {
+                return Some(ClientEvent::UnassignPawn(local_key));
+            }
+            None => return None,
+        }
+    }
+
+    fn read_reason_payload(manifest: &Manifest<T, U>, payload: &[u8]) -> Option<T> {
+        // read reason event object if there is one, which a truncated
+        // payload can't hold
+        if payload.len() > 0 {
+            let mut reader = PacketReader::new(payload);
+            let naia_id = reader.try_read_u16()?;
+            return manifest.create_event(naia_id, &mut reader);
+        }
+        return None;
+    }
+
+    // Returns whether the grace period for reconnecting, which started at
+    // `reconnect_started`, still lasts at the given moment
+    fn is_within_grace_period(
+        reconnect_started: &Instant,
+        grace_period: Duration,
+        now: &Instant,
+    ) -> bool {
+        let mut deadline = reconnect_started.clone();
+        deadline.add_millis(grace_period.as_millis().min(u128::from(u32::MAX)) as u32);
+        return *now < deadline;
+    }
+
     fn internal_send_with_connection(
         host_tick: u16,
-        sender: &mut MessageSender,
+        sender: &mut dyn PacketSender,
         connection: &mut ServerConnection<T, U>,
         packet_type: PacketType,
         packet: Packet,
-    ) {
+    ) -> Result<(), NaiaClientError> {
         let new_payload = connection.process_outgoing_header(
             host_tick,
             connection.get_last_received_tick(),
             packet_type,
             packet.payload(),
         );
-        sender
-            .send(Packet::new_raw(new_payload))
-            .expect("send failed!");
+        send_packet(sender, new_payload)?;
         connection.mark_sent();
+        return Ok(());
     }
 
     fn internal_send_connectionless(
-        sender: &mut MessageSender,
+        sender: &mut dyn PacketSender,
         packet_type: PacketType,
         packet: Packet,
-    ) {
+    ) -> Result<(), NaiaClientError> {
         let new_payload =
             naia_shared::utils::write_connectionless_payload(packet_type, packet.payload());
-        sender
-            .send(Packet::new_raw(new_payload))
-            .expect("send failed!");
+        return send_packet(sender, new_payload);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{any::TypeId, cell::RefCell, error::Error, io, rc::Rc, time::Duration};
+
+    use byteorder::{BigEndian, WriteBytesExt};
+    use naia_client_socket::Packet;
+
+    use naia_shared::{
+        utils::write_connectionless_payload, DeliveryGuarantee, Event, EventBuilder, EventType,
+        Instant, Manifest, MockLink, NoActorType, PacketReader, PacketType, RawMessageError,
+        SharedConfig, StandardHeader, PROTOCOL_VERSION,
+    };
+
+    use super::NaiaClient;
+    use crate::{
+        error::NaiaClientError, mock_client_socket::MockClientSocket, packet_sender::PacketSender,
+        ClientConfig, ClientConnectionState, ClientEvent,
+    };
+
+    const VALID_PASSWORD: u8 = 42;
+
+    #[derive(Clone)]
+    struct AuthEvent {
+        password: u8,
+    }
+
+    #[derive(Clone)]
+    enum TestEventType {
+        AuthEvent(AuthEvent),
+    }
+
+    impl EventType for TestEventType {
+        fn write(&self, buffer: &mut Vec<u8>) {
+            match self {
+                TestEventType::AuthEvent(event) => event.write(buffer),
+            }
+        }
+
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<AuthEvent>();
+        }
+    }
+
+    impl Event<TestEventType> for AuthEvent {
+        fn guarantee_delivery(&self) -> DeliveryGuarantee {
+            return DeliveryGuarantee::Unreliable;
+        }
+
+        fn write(&self, buffer: &mut Vec<u8>) {
+            buffer.push(self.password);
+        }
+
+        fn get_typed_copy(&self) -> TestEventType {
+            return TestEventType::AuthEvent(self.clone());
+        }
+
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<AuthEvent>();
+        }
+    }
+
+    struct AuthEventBuilder;
+
+    impl EventBuilder<TestEventType> for AuthEventBuilder {
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<AuthEvent>();
+        }
+
+        fn build(&self, reader: &mut PacketReader) -> TestEventType {
+            return TestEventType::AuthEvent(AuthEvent {
+                password: reader.read_u8(),
+            });
+        }
+    }
+
+    // Answers the Client's handshake messages as a Server would, accepting
+    // only connect requests which end with the valid password
+    fn answer_handshake(link: &MockLink, shared_config: &SharedConfig) {
+        while let Some(packet) = link.receive_at_server() {
+            if let Some(response) = handshake_response(&packet, shared_config) {
+                link.send_to_client(response);
+            }
+        }
+    }
+
+    // The Server's response to a handshake message from the Client, if any
+    fn handshake_response(packet: &[u8], shared_config: &SharedConfig) -> Option<Box<[u8]>> {
+        let (header, payload) = StandardHeader::read(packet).unwrap();
+        let (packet_type, response) = match header.packet_type() {
+            PacketType::ClientChallengeRequest => {
+                let mut response = Vec::new();
+                response.write_u16::<BigEndian>(PROTOCOL_VERSION).unwrap(); // write version
+                response.write_u16::<BigEndian>(0).unwrap(); // write server tick
+                shared_config.write_tick_interval(&mut response);
+                response.extend_from_slice(&payload[2..]); // echo timestamp
+                response.extend_from_slice(&[0; 32]); // write digest
+                (PacketType::ServerChallengeResponse, response)
+            }
+            PacketType::ClientConnectRequest => {
+                if payload.last() == Some(&VALID_PASSWORD) {
+                    (PacketType::ServerConnectResponse, vec![0; 17])
+                } else {
+                    (PacketType::ServerRejectConnect, Vec::new())
+                }
+            }
+            _ => return None,
+        };
+        return Some(write_connectionless_payload(packet_type, &response));
+    }
+
+    // The Client's next event, answering its handshake messages until one
+    // is returned
+    fn next_event(
+        client: &mut NaiaClient<TestEventType, NoActorType>,
+        link: &MockLink,
+        shared_config: &SharedConfig,
+    ) -> ClientEvent<TestEventType> {
+        for _ in 0..100 {
+            answer_handshake(link, shared_config);
+            match client.receive() {
+                Some(Ok(event)) => return event,
+                Some(Err(error)) => panic!("{}", error),
+                None => {}
+            }
+        }
+        panic!("no event was received from the Client");
+    }
+
+    #[test]
+    fn full_handshake_reports_each_state_change_once() {
+        let link = MockLink::new("127.0.0.1:14191".parse().unwrap());
+        let shared_config = SharedConfig::new(Duration::from_millis(50), None, None);
+
+        let mut manifest = Manifest::<TestEventType, NoActorType>::new();
+        manifest.register_event(Box::new(AuthEventBuilder));
+        let mut client = NaiaClient::new_mock(
+            &link,
+            manifest,
+            Some(ClientConfig {
+                send_handshake_interval: Duration::from_millis(0),
+                ..ClientConfig::default()
+            }),
+            shared_config.clone(),
+            Some(TestEventType::AuthEvent(AuthEvent {
+                password: VALID_PASSWORD,
+            })),
+        );
+
+        let state_changes = Rc::new(RefCell::new(Vec::new()));
+        let state_changes_ref = state_changes.clone();
+        client.on_state_change(Box::new(move |state| {
+            state_changes_ref.borrow_mut().push(state);
+        }));
+
+        match next_event(&mut client, &link, &shared_config) {
+            ClientEvent::Connection(_) => {}
+            _ => panic!("the Client should connect"),
+        }
+        // staying connected is not reported again
+        for _ in 0..10 {
+            client.receive();
+        }
+        assert!(client.disconnect().is_ok());
+
+        assert!(
+            *state_changes.borrow()
+                == vec![
+                    ClientConnectionState::AwaitingConnectResponse,
+                    ClientConnectionState::Connected,
+                    ClientConnectionState::AwaitingChallengeResponse,
+                ]
+        );
+    }
+
+    #[test]
+    fn flushing_drains_a_backlog_of_events_in_one_call() {
+        let link = MockLink::new("127.0.0.1:14191".parse().unwrap());
+        let shared_config = SharedConfig::new(Duration::from_millis(50), None, None);
+
+        let mut manifest = Manifest::<TestEventType, NoActorType>::new();
+        manifest.register_event(Box::new(AuthEventBuilder));
+        let mut client = NaiaClient::new_mock(
+            &link,
+            manifest,
+            Some(ClientConfig {
+                send_handshake_interval: Duration::from_millis(0),
+                max_events_per_packet: Some(4),
+                ..ClientConfig::default()
+            }),
+            shared_config.clone(),
+            Some(TestEventType::AuthEvent(AuthEvent {
+                password: VALID_PASSWORD,
+            })),
+        );
+        match next_event(&mut client, &link, &shared_config) {
+            ClientEvent::Connection(_) => {}
+            _ => panic!("the Client should connect"),
+        }
+
+        for password in 0..20 {
+            assert!(client.send_event(&AuthEvent { password }).is_ok());
+        }
+        match client.flush() {
+            Ok(flushed_packets) => assert!(flushed_packets == 5),
+            Err(error) => panic!("{}", error),
+        }
+        match client.flush() {
+            Ok(flushed_packets) => assert!(flushed_packets == 0),
+            Err(error) => panic!("{}", error),
+        }
+
+        // every packet has been sent, without any calls to receive()
+        let mut data_packets = 0;
+        while let Some(packet) = link.receive_at_server() {
+            let (header, _) = StandardHeader::read(&packet).unwrap();
+            if header.packet_type() == PacketType::Data {
+                data_packets += 1;
+            }
+        }
+        assert!(data_packets == 5);
+    }
+
+    #[test]
+    fn mismatched_versions_produce_a_mismatch_instead_of_a_connection() {
+        let link = MockLink::new("127.0.0.1:14191".parse().unwrap());
+        let shared_config = SharedConfig::new(Duration::from_millis(50), None, None);
+
+        let mut client = NaiaClient::new_mock(
+            &link,
+            Manifest::<TestEventType, NoActorType>::new(),
+            Some(ClientConfig {
+                send_handshake_interval: Duration::from_millis(0),
+                ..ClientConfig::default()
+            }),
+            shared_config,
+            None,
+        );
+
+        // a Server built with another version answers the challenge with
+        // only its own version. Malformed responses sent ahead of it are
+        // ignored, rather than read as a mismatch
+        let mut event = None;
+        for _ in 0..100 {
+            while let Some(packet) = link.receive_at_server() {
+                let (header, payload) = StandardHeader::read(&packet).unwrap();
+                assert!(header.packet_type() == PacketType::ClientChallengeRequest);
+                assert!(payload[..2] == PROTOCOL_VERSION.to_be_bytes());
+                let too_short = vec![1];
+                let truncated = PROTOCOL_VERSION.to_be_bytes().to_vec();
+                let mut response = Vec::new();
+                response
+                    .write_u16::<BigEndian>(PROTOCOL_VERSION + 1)
+                    .unwrap();
+                for response in [too_short, truncated, response].iter() {
+                    link.send_to_client(write_connectionless_payload(
+                        PacketType::ServerChallengeResponse,
+                        response,
+                    ));
+                }
+            }
+            match client.receive() {
+                Some(Ok(received)) => {
+                    event = Some(received);
+                    break;
+                }
+                Some(Err(error)) => panic!("{}", error),
+                None => {}
+            }
+        }
+
+        match event.expect("no event was received from the Client") {
+            ClientEvent::VersionMismatch(server_version) => {
+                assert!(server_version == PROTOCOL_VERSION + 1)
+            }
+            _ => panic!("the mismatch should be reported instead of a connection"),
+        }
+        assert!(client.connection_state() == ClientConnectionState::VersionMismatch);
+    }
+
+    #[test]
+    fn handshake_gives_up_after_max_attempts_and_stops_sending() {
+        // no Server ever answers on the other end of the link
+        let link = MockLink::new("127.0.0.1:14191".parse().unwrap());
+        let mut client = NaiaClient::new_mock(
+            &link,
+            Manifest::<TestEventType, NoActorType>::new(),
+            Some(ClientConfig {
+                send_handshake_interval: Duration::from_millis(0),
+                max_handshake_attempts: Some(3),
+                ..ClientConfig::default()
+            }),
+            SharedConfig::new(Duration::from_millis(50), None, None),
+            None,
+        );
+
+        let mut challenge_requests = 0;
+        let mut timed_out = false;
+        for _ in 0..100 {
+            while let Some(packet) = link.receive_at_server() {
+                let (header, _) = StandardHeader::read(&packet).unwrap();
+                assert!(header.packet_type() == PacketType::ClientChallengeRequest);
+                challenge_requests += 1;
+            }
+            match client.receive() {
+                Some(Ok(ClientEvent::ConnectionTimeout)) => {
+                    timed_out = true;
+                    break;
+                }
+                Some(Ok(_)) => panic!("only a timeout should be reported"),
+                Some(Err(error)) => panic!("{}", error),
+                None => {}
+            }
+        }
+        assert!(timed_out);
+        assert!(challenge_requests == 3);
+        assert!(client.connection_state() == ClientConnectionState::TimedOut);
+
+        // many handshake intervals later, nothing more has been sent
+        for _ in 0..20 {
+            assert!(client.receive().is_none());
+        }
+        assert!(link.receive_at_server().is_none());
+    }
+
+    // A sender whose socket is never able to send
+    #[derive(Debug)]
+    struct FailingSender;
+
+    impl PacketSender for FailingSender {
+        fn send(&mut self, _: Packet) -> Result<(), Box<dyn Error + Send + Sync>> {
+            return Err(Box::new(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "socket unavailable",
+            )));
+        }
+    }
+
+    #[test]
+    fn poll_stops_at_a_failed_send() {
+        let link = MockLink::new("127.0.0.1:14191".parse().unwrap());
+        let mut client = NaiaClient::new_with_socket(
+            link.client_address(),
+            Manifest::<TestEventType, NoActorType>::new(),
+            None,
+            SharedConfig::new(Duration::from_millis(50), None, None),
+            None,
+            Box::new(MockClientSocket::new(link.clone())),
+            Box::new(FailingSender),
+        );
+
+        // the challenge request stays due, so receive() would fail forever
+        let mut events = Vec::new();
+        client.poll(&mut events);
+        assert!(events.len() == 1);
+        assert!(matches!(events[0], Err(NaiaClientError::SendFailed(_))));
+
+        // retried on the next poll
+        client.poll(&mut events);
+        assert!(events.len() == 1);
+        assert!(matches!(events[0], Err(NaiaClientError::SendFailed(_))));
+    }
+
+    #[test]
+    fn truncated_reason_payload_has_no_reason() {
+        let mut manifest = Manifest::<TestEventType, NoActorType>::new();
+        manifest.register_event(Box::new(AuthEventBuilder));
+
+        let reason = NaiaClient::read_reason_payload(&manifest, &[0]);
+        assert!(reason.is_none());
+
+        let reason = NaiaClient::read_reason_payload(&manifest, &[0, 0, VALID_PASSWORD]);
+        match reason {
+            Some(TestEventType::AuthEvent(event)) => assert!(event.password == VALID_PASSWORD),
+            None => panic!("the reason should be read"),
+        }
+    }
+
+    // Sends a connect response to a Client whose handshake has ended, which
+    // shouldn't connect it
+    fn assert_late_connect_response_is_ignored(
+        client: &mut NaiaClient<TestEventType, NoActorType>,
+        link: &MockLink,
+        state: ClientConnectionState,
+    ) {
+        assert!(client.connection_state() == state);
+        for _ in 0..2 {
+            link.send_to_client(write_connectionless_payload(
+                PacketType::ServerConnectResponse,
+                &[0; 17],
+            ));
+        }
+        assert!(client.receive().is_none());
+        assert!(client.receive().is_none());
+        assert!(client.connection_state() == state);
+        assert!(!client.has_connection());
+    }
+
+    #[test]
+    fn connect_response_after_a_timeout_is_ignored() {
+        let link = MockLink::new("127.0.0.1:14191".parse().unwrap());
+        let mut client = NaiaClient::new_mock(
+            &link,
+            Manifest::<TestEventType, NoActorType>::new(),
+            Some(ClientConfig {
+                max_handshake_attempts: Some(0),
+                ..ClientConfig::default()
+            }),
+            SharedConfig::new(Duration::from_millis(50), None, None),
+            None,
+        );
+        assert!(matches!(
+            client.receive(),
+            Some(Ok(ClientEvent::ConnectionTimeout))
+        ));
+
+        assert_late_connect_response_is_ignored(
+            &mut client,
+            &link,
+            ClientConnectionState::TimedOut,
+        );
+    }
+
+    #[test]
+    fn connect_response_after_a_rejection_is_ignored() {
+        let link = MockLink::new("127.0.0.1:14191".parse().unwrap());
+        let shared_config = SharedConfig::new(Duration::from_millis(50), None, None);
+        let mut client = NaiaClient::new_mock(
+            &link,
+            Manifest::<TestEventType, NoActorType>::new(),
+            None,
+            shared_config.clone(),
+            None,
+        );
+        assert!(client.receive().is_none());
+        let request = link.receive_at_server().unwrap();
+        link.send_to_client(handshake_response(&request, &shared_config).unwrap());
+        assert!(client.receive().is_none());
+        assert!(client.connection_state() == ClientConnectionState::AwaitingConnectResponse);
+
+        link.send_to_client(write_connectionless_payload(
+            PacketType::ServerRejectConnect,
+            &[],
+        ));
+        assert!(matches!(
+            client.receive(),
+            Some(Ok(ClientEvent::RejectConnection(None)))
+        ));
+
+        assert_late_connect_response_is_ignored(
+            &mut client,
+            &link,
+            ClientConnectionState::Rejected,
+        );
+    }
+
+    #[test]
+    fn connect_response_after_a_version_mismatch_is_ignored() {
+        let link = MockLink::new("127.0.0.1:14191".parse().unwrap());
+        let mut client = NaiaClient::new_mock(
+            &link,
+            Manifest::<TestEventType, NoActorType>::new(),
+            None,
+            SharedConfig::new(Duration::from_millis(50), None, None),
+            None,
+        );
+        assert!(client.receive().is_none());
+        assert!(link.receive_at_server().is_some());
+        link.send_to_client(write_connectionless_payload(
+            PacketType::ServerChallengeResponse,
+            &(PROTOCOL_VERSION + 1).to_be_bytes(),
+        ));
+        assert!(matches!(
+            client.receive(),
+            Some(Ok(ClientEvent::VersionMismatch(_)))
+        ));
+
+        assert_late_connect_response_is_ignored(
+            &mut client,
+            &link,
+            ClientConnectionState::VersionMismatch,
+        );
+    }
+
+    #[test]
+    fn reconnecting_stops_at_the_end_of_the_grace_period() {
+        let reconnect_started = Instant::now();
+        let grace_period = Duration::from_millis(100);
+
+        let mut now = reconnect_started.clone();
+        assert!(
+            NaiaClient::<TestEventType, NoActorType>::is_within_grace_period(
+                &reconnect_started,
+                grace_period,
+                &now
+            )
+        );
+        now.add_millis(99);
+        assert!(
+            NaiaClient::<TestEventType, NoActorType>::is_within_grace_period(
+                &reconnect_started,
+                grace_period,
+                &now
+            )
+        );
+        now.add_millis(1);
+        assert!(
+            !NaiaClient::<TestEventType, NoActorType>::is_within_grace_period(
+                &reconnect_started,
+                grace_period,
+                &now
+            )
+        );
+    }
+
+    #[test]
+    fn raw_message_without_a_connection_is_refused() {
+        let link = MockLink::new("127.0.0.1:14191".parse().unwrap());
+        let mut client = NaiaClient::new_mock(
+            &link,
+            Manifest::<TestEventType, NoActorType>::new(),
+            None,
+            SharedConfig::new(Duration::from_millis(50), None, None),
+            None,
+        );
+
+        match client.send_raw(1, &[1, 2, 3]) {
+            Err(NaiaClientError::Wrapped(error)) => assert!(matches!(
+                error.downcast_ref::<RawMessageError>(),
+                Some(RawMessageError::NotConnected)
+            )),
+            _ => panic!("the raw message should be refused"),
+        }
     }
 }
@@ -0,0 +1,76 @@
+use std::{error::Error, fmt::Debug};
+
+use naia_client_socket::{MessageSender, Packet};
+
+use crate::error::NaiaClientError;
+
+/// Sends raw packets to the Server
+pub trait PacketSender: Debug {
+    /// Send a Packet to the Server
+    fn send(&mut self, packet: Packet) -> Result<(), Box<dyn Error + Send + Sync>>;
+}
+
+impl PacketSender for MessageSender {
+    fn send(&mut self, packet: Packet) -> Result<(), Box<dyn Error + Send + Sync>> {
+        return MessageSender::send(self, packet);
+    }
+}
+
+/// Sends a payload to the Server, converting a socket failure into a
+/// NaiaClientError so that the caller can retry rather than panic
+pub fn send_packet(
+    sender: &mut dyn PacketSender,
+    payload: Box<[u8]>,
+) -> Result<(), NaiaClientError> {
+    if let Err(error) = sender.send(Packet::new_raw(payload)) {
+        return Err(NaiaClientError::SendFailed(error));
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{error::Error, io};
+
+    use naia_client_socket::Packet;
+
+    use super::{send_packet, PacketSender};
+    use crate::error::NaiaClientError;
+
+    #[derive(Debug)]
+    struct TestSender {
+        fail: bool,
+        sent: usize,
+    }
+
+    impl PacketSender for TestSender {
+        fn send(&mut self, _: Packet) -> Result<(), Box<dyn Error + Send + Sync>> {
+            if self.fail {
+                return Err(Box::new(io::Error::new(
+                    io::ErrorKind::WouldBlock,
+                    "socket unavailable",
+                )));
+            }
+            self.sent += 1;
+            return Ok(());
+        }
+    }
+
+    #[test]
+    fn send_failure_is_returned_as_error() {
+        let mut sender = TestSender {
+            fail: true,
+            sent: 0,
+        };
+
+        match send_packet(&mut sender, Box::new([1, 2, 3])) {
+            Err(NaiaClientError::SendFailed(_)) => {}
+            _ => panic!("expected a SendFailed error"),
+        }
+
+        // the sender recovers on a later attempt
+        sender.fail = false;
+        assert!(send_packet(&mut sender, Box::new([1, 2, 3])).is_ok());
+        assert!(sender.sent == 1);
+    }
+}
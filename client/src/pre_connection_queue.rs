@@ -0,0 +1,140 @@
+use std::{collections::VecDeque, rc::Rc};
+
+use naia_shared::{ActorType, Event, EventClone, EventId, EventType};
+
+use super::{error::NaiaClientError, server_connection::ServerConnection};
+
+/// Holds Events sent before a connection with the Server is established, so
+/// that they can be handed to the ServerConnection once the handshake
+/// completes
+#[derive(Debug)]
+pub struct PreConnectionQueue<T: EventType> {
+    capacity: usize,
+    events: VecDeque<Rc<Box<dyn Event<T>>>>,
+}
+
+impl<T: EventType> PreConnectionQueue<T> {
+    pub fn new(capacity: usize) -> Self {
+        PreConnectionQueue {
+            capacity,
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Queues an Event, or returns an error if the queue is already full.
+    /// Returns the id the Event will have once flushed, as a new
+    /// ServerConnection hands out ids in order, starting from 0
+    pub fn queue_event(&mut self, event: &impl Event<T>) -> Result<EventId, NaiaClientError> {
+        if self.events.len() >= self.capacity {
+            return Err(NaiaClientError::PreConnectionQueueFull);
+        }
+        let event_id = self.events.len() as EventId;
+        self.events.push_back(Rc::new(EventClone::clone_box(event)));
+        return Ok(event_id);
+    }
+
+    /// Moves all queued Events, in the order they were sent, into the
+    /// outgoing Events of a newly created ServerConnection
+    pub fn flush<U: ActorType>(&mut self, server_connection: &mut ServerConnection<T, U>) {
+        while let Some(event) = self.events.pop_front() {
+            server_connection.queue_shared_event(&event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{any::TypeId, time::Duration};
+
+    use naia_shared::{
+        ConnectionConfig, DeliveryGuarantee, Event, EventBuilder, EventType, Manifest, NoActorType,
+        PacketReader,
+    };
+
+    use super::PreConnectionQueue;
+    use crate::{error::NaiaClientError, server_connection::ServerConnection};
+
+    #[derive(Clone)]
+    struct TestEvent;
+
+    #[derive(Clone)]
+    enum TestEventType {
+        TestEvent(TestEvent),
+    }
+
+    impl EventType for TestEventType {
+        fn write(&self, buffer: &mut Vec<u8>) {
+            match self {
+                TestEventType::TestEvent(event) => event.write(buffer),
+            }
+        }
+
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<TestEvent>();
+        }
+    }
+
+    impl Event<TestEventType> for TestEvent {
+        fn guarantee_delivery(&self) -> DeliveryGuarantee {
+            return DeliveryGuarantee::Reliable;
+        }
+
+        fn write(&self, _: &mut Vec<u8>) {}
+
+        fn get_typed_copy(&self) -> TestEventType {
+            return TestEventType::TestEvent(self.clone());
+        }
+
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<TestEvent>();
+        }
+    }
+
+    struct TestEventBuilder;
+
+    impl EventBuilder<TestEventType> for TestEventBuilder {
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<TestEvent>();
+        }
+
+        fn build(&self, _: &mut PacketReader) -> TestEventType {
+            return TestEventType::TestEvent(TestEvent);
+        }
+    }
+
+    #[test]
+    fn event_sent_during_handshake_is_delivered_after_connecting() {
+        let mut manifest = Manifest::<TestEventType, NoActorType>::new();
+        manifest.register_event(Box::new(TestEventBuilder));
+
+        // sent while AwaitingConnectResponse, before a ServerConnection exists
+        let mut queue = PreConnectionQueue::new(1);
+        assert!(queue.queue_event(&TestEvent).is_ok());
+        match queue.queue_event(&TestEvent) {
+            Err(NaiaClientError::PreConnectionQueueFull) => {}
+            _ => panic!("queue should be full"),
+        }
+
+        // the Server's connect response arrives
+        let mut server_connection = ServerConnection::new(
+            "127.0.0.1:14191".parse().unwrap(),
+            &ConnectionConfig::default(),
+            Duration::from_millis(100),
+            false,
+            Duration::from_millis(100),
+            false,
+            0,
+        );
+        queue.flush(&mut server_connection);
+
+        assert!(server_connection
+            .get_outgoing_packet(0, &manifest)
+            .is_some());
+        assert!(server_connection
+            .get_outgoing_packet(0, &manifest)
+            .is_none());
+
+        // flushing frees up room in the queue
+        assert!(queue.queue_event(&TestEvent).is_ok());
+    }
+}
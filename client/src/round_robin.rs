@@ -0,0 +1,97 @@
+/// Alternates between two sources of incoming messages, so that a flood from
+/// one source cannot starve the other when the application only calls
+/// receive() a bounded number of times per frame
+#[derive(Debug)]
+pub struct RoundRobin {
+    second_is_next: bool,
+}
+
+impl RoundRobin {
+    pub fn new() -> Self {
+        RoundRobin {
+            second_is_next: false,
+        }
+    }
+
+    /// Pops from whichever source's turn it is, passing the turn to the other
+    /// source. If that source is empty, pops from the other source instead,
+    /// and the empty source keeps its turn
+    pub fn pop<S, R>(
+        &mut self,
+        source: &mut S,
+        first: impl Fn(&mut S) -> Option<R>,
+        second: impl Fn(&mut S) -> Option<R>,
+    ) -> Option<R> {
+        if self.second_is_next {
+            return self.pop_in_order(source, second, first);
+        } else {
+            return self.pop_in_order(source, first, second);
+        }
+    }
+
+    fn pop_in_order<S, R>(
+        &mut self,
+        source: &mut S,
+        next: impl Fn(&mut S) -> Option<R>,
+        fallback: impl Fn(&mut S) -> Option<R>,
+    ) -> Option<R> {
+        if let Some(message) = next(source) {
+            self.second_is_next = !self.second_is_next;
+            return Some(message);
+        }
+        return fallback(source);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::RoundRobin;
+
+    struct TestSource {
+        events: VecDeque<&'static str>,
+        actor_messages: VecDeque<&'static str>,
+    }
+
+    fn drain(round_robin: &mut RoundRobin, source: &mut TestSource) -> Vec<&'static str> {
+        let mut output = Vec::new();
+        while let Some(message) = round_robin.pop(
+            source,
+            |source| source.events.pop_front(),
+            |source| source.actor_messages.pop_front(),
+        ) {
+            output.push(message);
+        }
+        return output;
+    }
+
+    #[test]
+    fn sources_are_surfaced_alternately() {
+        let mut round_robin = RoundRobin::new();
+        let mut source = TestSource {
+            events: vec!["event 1", "event 2", "event 3"].into(),
+            actor_messages: vec!["create 1", "update 1"].into(),
+        };
+
+        assert!(
+            drain(&mut round_robin, &mut source)
+                == vec!["event 1", "create 1", "event 2", "update 1", "event 3"]
+        );
+    }
+
+    #[test]
+    fn empty_source_keeps_its_turn() {
+        let mut round_robin = RoundRobin::new();
+        let mut source = TestSource {
+            events: VecDeque::new(),
+            actor_messages: vec!["create 1", "update 1"].into(),
+        };
+        assert!(drain(&mut round_robin, &mut source) == vec!["create 1", "update 1"]);
+
+        // events arrive after a flood of actor messages, and go first
+        source.events.push_back("event 1");
+        source.actor_messages.push_back("update 2");
+        assert!(drain(&mut round_robin, &mut source) == vec!["event 1", "update 2"]);
+    }
+}
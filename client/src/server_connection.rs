@@ -1,17 +1,21 @@
-use std::{net::SocketAddr, rc::Rc};
+use std::{net::SocketAddr, rc::Rc, time::Duration};
+
+use log::warn;
 
 use naia_shared::{
-    ActorType, Connection, ConnectionConfig, Event, EventType, LocalActorKey, ManagerType,
-    Manifest, PacketReader, PacketType, SequenceNumber, StandardHeader,
+    ActorType, Compressor, Connection, ConnectionConfig, Event, EventId, EventType, FragmentError,
+    Instant, LocalActorKey, ManagerType, Manifest, PacketReader, PacketType, PingManager,
+    RawMessageError, SequenceNumber, StandardHeader, TrafficStats,
 };
 
 use super::{
-    client_actor_manager::ClientActorManager, client_actor_message::ClientActorMessage,
-    client_packet_writer::ClientPacketWriter, command_sender::CommandSender,
-    interpolation_manager::InterpolationManager, ping_manager::PingManager, tick_queue::TickQueue,
+    actor_handle::ActorHandle, actor_stats::ActorStats, client_actor_manager::ClientActorManager,
+    client_actor_message::ClientActorMessage, client_packet_writer::ClientPacketWriter,
+    command_sender::CommandSender, interpolation_manager::InterpolationManager,
+    tick_queue::TickQueue,
 };
 use crate::{client_tick_manager::ClientTickManager, command_receiver::CommandReceiver, Packet};
-use std::collections::hash_map::Keys;
+use std::collections::{hash_map::Keys, HashSet, VecDeque};
 
 #[derive(Debug)]
 pub struct ServerConnection<T: EventType, U: ActorType> {
@@ -21,7 +25,10 @@ pub struct ServerConnection<T: EventType, U: ActorType> {
     command_sender: CommandSender<T>,
     command_receiver: CommandReceiver<T>,
     last_replay_tick: Option<(u16, LocalActorKey)>,
+    replayed_pawns: HashSet<LocalActorKey>,
     interpolation_manager: InterpolationManager<U>,
+    spawn_events: VecDeque<Option<T>>,
+    last_tick_actor_stats: ActorStats,
     jitter_buffer: TickQueue<(u16, Box<[u8]>)>,
 }
 
@@ -29,19 +36,32 @@ impl<T: EventType, U: ActorType> ServerConnection<T, U> {
     pub fn new(
         address: SocketAddr,
         connection_config: &ConnectionConfig,
-        tick_manager: &ClientTickManager,
+        interpolation_delay: Duration,
+        allow_extrapolation: bool,
+        max_extrapolation: Duration,
+        deterministic_interpolation: bool,
+        actor_capacity: usize,
     ) -> Self {
         return ServerConnection {
             connection: Connection::new(address, connection_config),
-            actor_manager: ClientActorManager::new(),
-            interpolation_manager: InterpolationManager::new(&tick_manager.get_tick_interval()),
+            actor_manager: ClientActorManager::with_capacity(actor_capacity),
+            interpolation_manager: InterpolationManager::new(
+                interpolation_delay,
+                allow_extrapolation,
+                max_extrapolation,
+                deterministic_interpolation,
+            ),
             ping_manager: PingManager::new(
                 connection_config.ping_interval,
                 connection_config.rtt_sample_size,
+                connection_config.pong_timeout_intervals,
             ),
             command_sender: CommandSender::new(),
             command_receiver: CommandReceiver::new(),
             last_replay_tick: None,
+            replayed_pawns: HashSet::new(),
+            spawn_events: VecDeque::new(),
+            last_tick_actor_stats: ActorStats::default(),
             jitter_buffer: TickQueue::new(),
         };
     }
@@ -51,8 +71,17 @@ impl<T: EventType, U: ActorType> ServerConnection<T, U> {
         host_tick: u16,
         manifest: &Manifest<T, U>,
     ) -> Option<Box<[u8]>> {
-        if self.connection.has_outgoing_events() || self.command_sender.has_command() {
-            let mut writer = ClientPacketWriter::new();
+        if self.connection.has_outgoing_events()
+            || self.connection.has_outgoing_raw()
+            || self.command_sender.has_command()
+        {
+            // wait for more data to fill the packet with
+            if self.connection.is_coalescing() {
+                return None;
+            }
+
+            let mut writer =
+                ClientPacketWriter::with_capacity(self.connection.get_max_payload_size());
 
             while let Some((pawn_key, command)) = self.command_sender.pop_command() {
                 if writer.write_command(
@@ -71,10 +100,16 @@ impl<T: EventType, U: ActorType> ServerConnection<T, U> {
             }
 
             let next_packet_index: u16 = self.get_next_packet_index();
-            while let Some(popped_event) = self.connection.pop_outgoing_event(next_packet_index) {
-                if !writer.write_event(manifest, &popped_event) {
+            self.connection.write_outgoing_events(
+                next_packet_index,
+                manifest,
+                writer.event_writer_mut(),
+            );
+
+            while let Some((channel, data)) = self.connection.pop_outgoing_raw(next_packet_index) {
+                if !writer.write_raw(channel, &data) {
                     self.connection
-                        .unpop_outgoing_event(next_packet_index, &popped_event);
+                        .unpop_outgoing_raw(next_packet_index, channel, data);
                     break;
                 }
             }
@@ -94,6 +129,8 @@ impl<T: EventType, U: ActorType> ServerConnection<T, U> {
             }
         }
 
+        // everything held back has been written
+        self.connection.reset_coalescing();
         return None;
     }
 
@@ -111,8 +148,11 @@ impl<T: EventType, U: ActorType> ServerConnection<T, U> {
                 ManagerType::Event => {
                     self.connection.process_event_data(&mut reader, manifest);
                 }
+                ManagerType::Raw => {
+                    self.connection.process_raw_data(&mut reader);
+                }
                 ManagerType::Actor => {
-                    self.actor_manager.process_data(
+                    let (spawn_events, stats) = self.actor_manager.process_data(
                         manifest,
                         &mut self.command_receiver,
                         &mut self.interpolation_manager,
@@ -120,8 +160,15 @@ impl<T: EventType, U: ActorType> ServerConnection<T, U> {
                         packet_index,
                         &mut reader,
                     );
+                    self.spawn_events.extend(spawn_events);
+                    self.last_tick_actor_stats.add(&stats);
+                }
+                _ => {
+                    // the length of an unknown section can't be known, so
+                    // nothing after it can be read either
+                    warn!("discarding rest of packet after unknown manager type");
+                    break;
                 }
-                _ => {}
             }
         }
     }
@@ -138,6 +185,17 @@ impl<T: EventType, U: ActorType> ServerConnection<T, U> {
         );
     }
 
+    /// Discards the buffers keyed by tick, once they no longer line up with
+    /// the Server's ticks: buffered Data packets, Command history, and the
+    /// snapshots which Actors & pawns are interpolated between
+    pub fn clear_tick_buffers(&mut self) {
+        self.jitter_buffer.clear();
+        self.command_receiver.clear_history();
+        self.last_replay_tick = None;
+        self.replayed_pawns.clear();
+        self.interpolation_manager.sync_all(&self.actor_manager);
+    }
+
     pub fn get_buffered_data_packet(&mut self, current_tick: u16) -> Option<(u16, u16, Box<[u8]>)> {
         if let Some((tick, (index, payload))) = self.jitter_buffer.pop_item(current_tick) {
             return Some((tick, index, payload));
@@ -150,28 +208,77 @@ impl<T: EventType, U: ActorType> ServerConnection<T, U> {
         return self.actor_manager.pop_incoming_message();
     }
 
+    /// Returns the Event attached to the creation of an Actor, to be called
+    /// once for each Create message, in order
+    pub fn pop_spawn_event(&mut self) -> Option<T> {
+        return self.spawn_events.pop_front().flatten();
+    }
+
+    /// Returns the counts of Actor messages applied on the most recent tick
+    pub fn last_tick_actor_stats(&self) -> ActorStats {
+        return self.last_tick_actor_stats;
+    }
+
     pub fn actor_keys(&self) -> Keys<LocalActorKey, U> {
         return self.actor_manager.actor_keys();
     }
 
-    pub fn get_actor(
-        &mut self,
-        tick_manager: &ClientTickManager,
-        key: &LocalActorKey,
-    ) -> Option<&U> {
-        if let Some(interpolated_actor) =
-            self.interpolation_manager
-                .get_interpolation(tick_manager, &self.actor_manager, key)
-        {
+    pub fn actors(&self) -> impl Iterator<Item = (LocalActorKey, &U)> {
+        return self.actor_manager.actors();
+    }
+
+    pub fn get_actor(&mut self, key: &LocalActorKey) -> Option<&U> {
+        if let Some(interpolated_actor) = self.interpolation_manager.get_interpolation(key) {
             return Some(interpolated_actor);
         }
         return self.actor_manager.get_actor(key);
     }
 
+    /// Gets an Actor's state as last received from the Server, without any
+    /// interpolation applied
+    pub fn get_received_actor(&self, key: &LocalActorKey) -> Option<&U> {
+        return self.actor_manager.get_actor(key);
+    }
+
+    pub fn get_actor_handle(&self, key: &LocalActorKey) -> Option<ActorHandle> {
+        return self.actor_manager.get_actor_handle(key);
+    }
+
+    pub fn get_actor_by_handle(&mut self, handle: &ActorHandle) -> Option<&U> {
+        if !self.actor_manager.is_current(handle) {
+            return None;
+        }
+        return self.get_actor(&handle.key());
+    }
+
+    pub fn predict_spawn(&mut self, actor: U) -> u16 {
+        return self.actor_manager.predict_spawn(actor);
+    }
+
+    pub fn get_predicted_actor(&self, token: &u16) -> Option<&U> {
+        return self.actor_manager.get_predicted_actor(token);
+    }
+
+    pub fn cancel_predicted_spawn(&mut self, token: &u16) -> Option<U> {
+        return self.actor_manager.cancel_predicted_spawn(token);
+    }
+
+    pub fn get_actor_last_update_tick(&self, key: &LocalActorKey) -> Option<u16> {
+        return self.actor_manager.get_last_update_tick(key);
+    }
+
     pub fn pawn_keys(&self) -> Keys<LocalActorKey, U> {
         return self.actor_manager.pawn_keys();
     }
 
+    pub fn pawns(&self) -> impl Iterator<Item = (LocalActorKey, &U)> {
+        return self.actor_manager.pawns();
+    }
+
+    pub fn get_pawn_predicted(&self, key: &LocalActorKey) -> Option<&U> {
+        return self.actor_manager.get_pawn(key);
+    }
+
     pub fn get_pawn(
         &mut self,
         tick_manager: &ClientTickManager,
@@ -197,16 +304,22 @@ impl<T: EventType, U: ActorType> ServerConnection<T, U> {
     /// correctly. Call this at the beginning of any frame
     pub fn frame_begin(&mut self, manifest: &Manifest<T, U>, tick_manager: &mut ClientTickManager) {
         if tick_manager.mark_frame() {
-            // interpolation manager snapshots current state of all actors
-            self.interpolation_manager
-                .update_actors(&self.actor_manager);
-
-            // then we apply all received updates to actors at once
+            // apply all received updates to actors at once
             let target_tick = tick_manager.get_server_tick();
+            let mut received_data = false;
+            self.last_tick_actor_stats = ActorStats::default();
             while let Some((tick, packet_index, data_packet)) =
                 self.get_buffered_data_packet(target_tick)
             {
                 self.process_incoming_data(tick, packet_index, manifest, &data_packet);
+                received_data = true;
+            }
+
+            // then the interpolation manager snapshots the received state of
+            // all actors
+            if received_data {
+                self.interpolation_manager
+                    .update_actors(&self.actor_manager);
             }
 
             // finally, we must update pawns since they may have been reconciled
@@ -220,6 +333,10 @@ impl<T: EventType, U: ActorType> ServerConnection<T, U> {
         return self.connection.mark_sent();
     }
 
+    pub fn flush_coalesced(&mut self) {
+        self.connection.flush_coalesced();
+    }
+
     pub fn should_send_heartbeat(&self) -> bool {
         return self.connection.should_send_heartbeat();
     }
@@ -228,21 +345,31 @@ impl<T: EventType, U: ActorType> ServerConnection<T, U> {
         return self.connection.mark_heard();
     }
 
-    pub fn should_drop(&self) -> bool {
-        return self.connection.should_drop();
+    pub fn should_drop_at(&mut self, now: &Instant) -> bool {
+        return self.connection.should_drop_at(now);
+    }
+
+    pub fn last_heard(&self) -> Instant {
+        return self.connection.last_heard();
+    }
+
+    pub fn is_duplicate(&self, packet_index: u16) -> bool {
+        return self.connection.is_duplicate(packet_index);
     }
 
     pub fn process_incoming_header(
         &mut self,
         header: &StandardHeader,
+        payload_size: usize,
         tick_manager: &mut ClientTickManager,
     ) {
         tick_manager.record_server_tick(
             header.host_tick(),
-            self.ping_manager.get_rtt(),
+            self.ping_manager.get_last_rtt(),
             self.ping_manager.get_jitter(),
         );
-        self.connection.process_incoming_header(header, &mut None);
+        self.connection
+            .process_incoming_header(header, payload_size, &mut None);
     }
 
     pub fn process_outgoing_header(
@@ -260,15 +387,62 @@ impl<T: EventType, U: ActorType> ServerConnection<T, U> {
         );
     }
 
+    pub fn fragment_outgoing_packet(
+        &mut self,
+        packet: Box<[u8]>,
+    ) -> Result<Vec<Box<[u8]>>, FragmentError> {
+        return self.connection.fragment_outgoing_packet(packet);
+    }
+
+    pub fn process_incoming_fragment(&mut self, payload: &[u8]) -> Option<Box<[u8]>> {
+        return self.connection.process_incoming_fragment(payload);
+    }
+
+    pub fn set_compressor(&mut self, compressor: Option<Rc<dyn Compressor>>) {
+        return self.connection.set_compressor(compressor);
+    }
+
+    pub fn set_integrity_check(&mut self, integrity_check: bool) {
+        return self.connection.set_integrity_check(integrity_check);
+    }
+
+    pub fn accepts_header(&self, header: &StandardHeader) -> bool {
+        return self.connection.accepts_header(header);
+    }
+
+    pub fn decompress_payload(
+        &self,
+        header: &StandardHeader,
+        payload: Box<[u8]>,
+    ) -> Option<Box<[u8]>> {
+        return self.connection.decompress_payload(header, payload);
+    }
+
     pub fn get_next_packet_index(&self) -> SequenceNumber {
         return self.connection.get_next_packet_index();
     }
 
-    pub fn queue_event(&mut self, event: &impl Event<T>) {
+    pub fn queue_event(&mut self, event: &impl Event<T>) -> Option<EventId> {
         return self.connection.queue_event(event);
     }
 
-    pub fn get_incoming_event(&mut self) -> Option<T> {
+    pub fn queue_shared_event(&mut self, event: &Rc<Box<dyn Event<T>>>) -> Option<EventId> {
+        return self.connection.queue_shared_event(event);
+    }
+
+    pub fn get_delivered_event(&mut self) -> Option<EventId> {
+        return self.connection.get_delivered_event();
+    }
+
+    pub fn queue_raw(&mut self, channel: u8, data: &[u8]) -> Result<(), RawMessageError> {
+        return self.connection.queue_raw(channel, data);
+    }
+
+    pub fn get_incoming_raw(&mut self) -> Option<(u8, Box<[u8]>)> {
+        return self.connection.get_incoming_raw();
+    }
+
+    pub fn get_incoming_event(&mut self) -> Option<(Option<LocalActorKey>, T)> {
         return self.connection.get_incoming_event();
     }
 
@@ -281,20 +455,30 @@ impl<T: EventType, U: ActorType> ServerConnection<T, U> {
         return self.command_sender.queue_command(pawn_key, command);
     }
 
-    pub fn get_incoming_command(&mut self) -> Option<(LocalActorKey, Rc<Box<dyn Event<T>>>)> {
-        if let Some((last_replay_tick, pawn_key)) = self.last_replay_tick {
-            self.actor_manager
-                .save_replay_snapshot(last_replay_tick.wrapping_add(1), &pawn_key);
-            self.last_replay_tick = None;
-        }
+    pub fn get_incoming_replay(&mut self) -> Option<(u16, LocalActorKey, Rc<Box<dyn Event<T>>>)> {
+        self.save_last_replay_snapshot();
 
         if let Some((tick, pawn_key, command)) = self
             .command_receiver
             .pop_command_replay::<U>(&mut self.actor_manager)
         {
             self.last_replay_tick = Some((tick, pawn_key));
-            return Some((pawn_key, command));
+            self.replayed_pawns.insert(pawn_key);
+            return Some((tick, pawn_key, command));
+        }
+
+        // once replay is complete, pawns are rendered at their reconciled
+        // state rather than blending from a mispredicted one
+        for pawn_key in self.replayed_pawns.drain() {
+            self.interpolation_manager
+                .sync_pawn_interpolation(&self.actor_manager, &pawn_key);
         }
+        return None;
+    }
+
+    pub fn get_incoming_command(&mut self) -> Option<(LocalActorKey, Rc<Box<dyn Event<T>>>)> {
+        self.save_last_replay_snapshot();
+
         if let Some((tick, pawn_key, command)) = self.command_receiver.pop_command() {
             self.last_replay_tick = Some((tick, pawn_key));
             return Some((pawn_key, command));
@@ -302,6 +486,14 @@ impl<T: EventType, U: ActorType> ServerConnection<T, U> {
         return None;
     }
 
+    fn save_last_replay_snapshot(&mut self) {
+        if let Some((last_replay_tick, pawn_key)) = self.last_replay_tick {
+            self.actor_manager
+                .save_replay_snapshot(last_replay_tick.wrapping_add(1), &pawn_key);
+            self.last_replay_tick = None;
+        }
+    }
+
     // ping related
     pub fn should_send_ping(&self) -> bool {
         return self.ping_manager.should_send_ping();
@@ -316,11 +508,82 @@ impl<T: EventType, U: ActorType> ServerConnection<T, U> {
         self.ping_manager.process_pong(pong_payload);
     }
 
-    pub fn get_rtt(&self) -> f32 {
+    pub fn get_rtt(&self) -> Option<f32> {
         return self.ping_manager.get_rtt();
     }
 
+    pub fn traffic_stats(&self) -> TrafficStats {
+        return self.connection.traffic_stats();
+    }
+
     pub fn get_jitter(&self) -> f32 {
         return self.ping_manager.get_jitter();
     }
+
+    pub fn get_packet_loss(&self) -> f32 {
+        return self.connection.packet_loss();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{any::TypeId, time::Duration};
+
+    use naia_shared::{ConnectionConfig, EventType, ManagerType, Manifest, NoActorType};
+
+    use super::ServerConnection;
+
+    // No Events are ever received, so these are never called
+    #[derive(Clone)]
+    struct TestEventType;
+
+    impl EventType for TestEventType {
+        fn write(&self, _: &mut Vec<u8>) {}
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<TestEventType>();
+        }
+    }
+
+    #[test]
+    fn unknown_manager_type_stops_processing_the_packet() {
+        let manifest = Manifest::<TestEventType, NoActorType>::new();
+        let mut server_connection = ServerConnection::<TestEventType, NoActorType>::new(
+            "127.0.0.1:14191".parse().unwrap(),
+            &ConnectionConfig::default(),
+            Duration::from_millis(100),
+            false,
+            Duration::from_millis(100),
+            false,
+            0,
+        );
+
+        // the bytes after the unknown manager type would otherwise be read as
+        // an Actor section
+        let data: Vec<u8> = vec![ManagerType::Unknown as u8, ManagerType::Actor as u8, 1, 0];
+        server_connection.process_incoming_data(0, 0, &manifest, &data);
+
+        assert!(server_connection.get_incoming_event().is_none());
+        assert!(server_connection.get_incoming_actor_message().is_none());
+    }
+
+    #[test]
+    fn clearing_tick_buffers_discards_buffered_data_packets() {
+        let mut server_connection = ServerConnection::<TestEventType, NoActorType>::new(
+            "127.0.0.1:14191".parse().unwrap(),
+            &ConnectionConfig::default(),
+            Duration::from_millis(100),
+            false,
+            Duration::from_millis(100),
+            false,
+            0,
+        );
+        let payload: Box<[u8]> = Box::new([0]);
+        server_connection.buffer_data_packet(10, 0, &payload);
+        server_connection.buffer_data_packet(11, 1, &payload);
+        assert!(server_connection.get_buffered_data_packet(10).is_some());
+
+        // the packet buffered for tick 11 belongs to a Server which is gone
+        server_connection.clear_tick_buffers();
+        assert!(server_connection.get_buffered_data_packet(11).is_none());
+    }
 }
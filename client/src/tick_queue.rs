@@ -22,6 +22,11 @@ impl<T: Eq + PartialEq> TickQueue<T> {
         self.queue.push(ItemContainer { tick, item });
     }
 
+    /// Discards every item in the queue
+    pub fn clear(&mut self) {
+        self.queue.clear();
+    }
+
     /// Returns whether or not there is an item that is ready to be returned
     fn has_item(&self, current_tick: u16) -> bool {
         if self.queue.len() == 0 {
@@ -5,7 +5,7 @@ use std::{
     time::Duration,
 };
 
-use naia_client::{ClientConfig, ClientEvent, NaiaClient};
+use naia_client::{ClientConfig, ClientEvent, DisconnectPolicy, NaiaClient};
 
 use naia_example_shared::{
     get_shared_config, manifest_load, AuthEvent, ExampleActor, ExampleEvent, StringEvent,
@@ -35,7 +35,7 @@ impl App {
         // 2x greater than the heartbeat interval, to make it so at the worst case, the
         // server would need to miss 2 heartbeat signals before disconnecting from a
         // given client
-        client_config.disconnection_timeout_duration = Duration::from_secs(5);
+        client_config.disconnect_policy = DisconnectPolicy::Timeout(Duration::from_secs(5));
 
         // This will be evaluated in the Server's 'on_auth()' method
         let auth = ExampleEvent::AuthEvent(AuthEvent::new("charlie", "12345"));
@@ -59,10 +59,14 @@ impl App {
             if let Some(result) = self.client.receive() {
                 match result {
                     Ok(event) => match event {
-                        ClientEvent::Connection => {
-                            info!("Client connected to: {}", self.client.server_address());
+                        ClientEvent::Connection(handshake_duration) => {
+                            info!(
+                                "Client connected to: {}, in {}ms",
+                                self.client.server_address(),
+                                handshake_duration.as_millis()
+                            );
                         }
-                        ClientEvent::Disconnection => {
+                        ClientEvent::Disconnection(_) => {
                             info!("Client disconnected from: {}", self.client.server_address());
                         }
                         ClientEvent::Event(event_type) => match event_type {
@@ -75,12 +79,14 @@ impl App {
                                 info!("Client send: {}", new_message);
 
                                 let string_event = StringEvent::new(new_message);
-                                self.client.send_event(&string_event);
+                                if let Err(error) = self.client.send_event(&string_event) {
+                                    info!("Client failed to send event: {}", error);
+                                }
                                 self.server_event_count += 1;
                             }
                             _ => {}
                         },
-                        ClientEvent::CreateActor(local_key) => {
+                        ClientEvent::CreateActor(local_key, _, _) => {
                             if let Some(actor) = self.client.get_actor(&local_key) {
                                 match actor {
                                     ExampleActor::PointActor(point_actor) => {
@@ -95,7 +101,7 @@ impl App {
                                 }
                             }
                         }
-                        ClientEvent::UpdateActor(local_key) => {
+                        ClientEvent::UpdateActor(local_key, _) => {
                             if let Some(actor) = self.client.get_actor(&local_key) {
                                 match actor {
                                     ExampleActor::PointActor(point_actor) => {
@@ -4,7 +4,7 @@ extern crate log;
 use simple_logger;
 use smol::io;
 
-use naia_server::{NaiaServer, ServerAddresses, ServerConfig, ServerEvent, UserKey};
+use naia_server::{DisconnectPolicy, NaiaServer, ServerAddresses, ServerConfig, ServerEvent};
 
 use naia_example_shared::{
     get_shared_config, manifest_load, ExampleActor, ExampleEvent, PointActor, StringEvent,
@@ -43,7 +43,7 @@ fn main() -> io::Result<()> {
         // 2x greater than the heartbeat interval, to make it so at the worst case, the
         // server would need to miss 2 heartbeat signals before disconnecting from a
         // given client
-        server_config.disconnection_timeout_duration = Duration::from_secs(5);
+        server_config.disconnect_policy = DisconnectPolicy::Timeout(Duration::from_secs(5));
 
         let mut server = NaiaServer::new(
             server_addresses,
@@ -60,9 +60,11 @@ fn main() -> io::Result<()> {
             if let ExampleEvent::AuthEvent(auth_event) = auth_type {
                 let username = auth_event.username.get();
                 let password = auth_event.password.get();
-                return username == "charlie" && password == "12345";
+                if username == "charlie" && password == "12345" {
+                    return Ok(());
+                }
             }
-            return false;
+            return Err(None);
         })));
 
         // Create a new, singular room, which will contain Users and Actors that they
@@ -105,7 +107,7 @@ fn main() -> io::Result<()> {
             match server.receive().await {
                 Ok(event) => {
                     match event {
-                        ServerEvent::Connection(user_key) => {
+                        ServerEvent::Connection(user_key, _) => {
                             server.room_add_user(&main_room_key, &user_key);
                             if let Some(user) = server.get_user(&user_key) {
                                 info!("Naia Server connected to: {}", user.address);
@@ -125,22 +127,15 @@ fn main() -> io::Result<()> {
                                 }
                             }
                         }
-                        ServerEvent::Tick => {
+                        ServerEvent::Tick(_) => {
                             // Game logic, updating of the world, should happen here
 
                             // Event Sending
-                            let mut iter_vec: Vec<UserKey> = Vec::new();
-                            for (user_key, _) in server.users_iter() {
-                                iter_vec.push(user_key);
-                            }
-                            for user_key in iter_vec {
-                                let user = server.get_user(&user_key).unwrap();
-                                let new_message = format!("Server Packet ({})", tick_count);
-                                info!("Naia Server send -> {}: {}", user.address, new_message);
+                            let new_message = format!("Server Packet ({})", tick_count);
+                            info!("Naia Server broadcast -> {}", new_message);
 
-                                let string_event = StringEvent::new(new_message);
-                                server.queue_event(&user_key, &string_event);
-                            }
+                            let string_event = StringEvent::new(new_message);
+                            server.broadcast_event(&string_event);
 
                             // Iterate through Point Actors, marching them from (0,0) to (20, N)
                             for (_, actor) in server.actors_iter() {
@@ -1,6 +1,6 @@
 use crate::ExampleEvent;
 use naia_derive::Event;
-use naia_shared::{Event, Property};
+use naia_shared::{DeliveryGuarantee, Event, Property};
 
 #[derive(Event, Clone)]
 #[type_name = "ExampleEvent"]
@@ -10,8 +10,8 @@ pub struct AuthEvent {
 }
 
 impl AuthEvent {
-    fn is_guaranteed() -> bool {
-        false
+    fn guarantee_delivery() -> DeliveryGuarantee {
+        DeliveryGuarantee::Unreliable
     }
 
     pub fn new(username: &str, password: &str) -> AuthEvent {
@@ -16,3 +16,109 @@ pub use manifest_load::manifest_load;
 pub use point_actor::PointActor;
 pub use shared_config::get_shared_config;
 pub use string_event::StringEvent;
+
+#[cfg(test)]
+mod tests {
+    use naia_derive::PacketSerde;
+    use naia_shared::{Actor, ActorEq, PacketReader, PacketSerde, StateMask};
+
+    use self::flags_actor::FlagsActor;
+
+    // the Actor derives import into the module they are used in
+    mod flags_actor {
+        use naia_derive::Actor;
+        use naia_shared::{Actor, Property};
+
+        use super::flags_actor_type::FlagsActorType;
+
+        #[derive(Actor)]
+        #[type_name = "FlagsActorType"]
+        pub struct FlagsActor {
+            pub health: Property<u8>,
+            #[bit_packed]
+            pub a: Property<bool>,
+            #[bit_packed]
+            pub b: Property<bool>,
+            #[bit_packed]
+            pub c: Property<bool>,
+            #[bit_packed]
+            pub d: Property<bool>,
+            #[bit_packed]
+            pub e: Property<bool>,
+            #[bit_packed]
+            pub f: Property<bool>,
+            #[bit_packed]
+            pub g: Property<bool>,
+            #[bit_packed]
+            pub h: Property<bool>,
+        }
+    }
+
+    mod flags_actor_type {
+        use std::{cell::RefCell, rc::Rc};
+
+        use naia_derive::ActorType;
+
+        use super::FlagsActor;
+
+        #[derive(ActorType, Clone)]
+        pub enum FlagsActorType {
+            FlagsActor(Rc<RefCell<FlagsActor>>),
+        }
+    }
+
+    #[derive(PacketSerde, PartialEq)]
+    struct Snapshot {
+        tick: u16,
+        alive: bool,
+        health: Option<u8>,
+        target: Option<u32>,
+        position: [f32; 2],
+        offset: i64,
+    }
+
+    #[test]
+    fn derived_struct_round_trips() {
+        let snapshot = Snapshot {
+            tick: 500,
+            alive: true,
+            health: None,
+            target: Some(7),
+            position: [1.5, -2.0],
+            offset: -40,
+        };
+
+        let mut buffer = Vec::new();
+        snapshot.write(&mut buffer);
+        assert!(buffer.len() == 2 + 1 + 1 + 5 + 8 + 8);
+
+        let mut reader = PacketReader::new(&buffer);
+        assert!(Snapshot::read(&mut reader) == Some(snapshot));
+
+        // truncated data is rejected rather than read with missing fields
+        let mut reader = PacketReader::new(&buffer[..buffer.len() - 1]);
+        assert!(Snapshot::read(&mut reader).is_none());
+    }
+
+    #[test]
+    fn bit_packed_properties_share_a_byte_in_partial_writes() {
+        let sender =
+            FlagsActor::new_complete(90, true, false, true, true, false, false, true, false);
+
+        let mut state_mask = StateMask::new(2);
+        for index in 0..9 {
+            state_mask.set_bit(index, true);
+        }
+        let mut buffer = Vec::new();
+        sender.write_partial(&state_mask, &mut buffer);
+
+        // a length-prefixed u8, followed by all 8 flags in a single byte
+        assert!(buffer.len() == 2 + 1);
+
+        let mut receiver =
+            FlagsActor::new_complete(0, false, false, false, false, false, false, false, false);
+        let mut reader = PacketReader::new(&buffer);
+        receiver.read_partial(&state_mask, &mut reader, 1);
+        assert!(receiver.equals(&sender));
+    }
+}
@@ -7,5 +7,5 @@ pub fn get_shared_config() -> SharedConfig {
 
     // Simulate network conditions with this configuration property
     let link_condition = Some(LinkConditionerConfig::average_condition());
-    return SharedConfig::new(tick_interval, link_condition);
+    return SharedConfig::new(tick_interval, link_condition, None);
 }
@@ -1,6 +1,6 @@
 use crate::ExampleEvent;
 use naia_derive::Event;
-use naia_shared::{Event, Property};
+use naia_shared::{DeliveryGuarantee, Event, Property};
 
 #[derive(Event, Clone)]
 #[type_name = "ExampleEvent"]
@@ -9,8 +9,8 @@ pub struct StringEvent {
 }
 
 impl StringEvent {
-    fn is_guaranteed() -> bool {
-        true
+    fn guarantee_delivery() -> DeliveryGuarantee {
+        DeliveryGuarantee::Reliable
     }
 
     pub fn new(message: String) -> StringEvent {
@@ -19,6 +19,7 @@ pub fn actor_impl(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let properties = utils::get_properties(&input);
     let interpolated_properties = get_interpolated_properties(&input);
     let predicted_properties = get_predicted_properties(&input);
+    let bit_packed_properties = get_bit_packed_properties(&input);
 
     let enum_name = format_ident!("{}Prop", actor_name);
     let property_enum = get_property_enum(&enum_name, &properties);
@@ -27,16 +28,20 @@ pub fn actor_impl(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let read_to_type_method =
         get_read_to_type_method(&type_name, actor_name, &enum_name, &properties);
     let actor_write_method = utils::get_write_method(&properties);
-    let actor_write_partial_method = get_write_partial_method(&enum_name, &properties);
+    let actor_write_partial_method =
+        get_write_partial_method(&enum_name, &properties, &bit_packed_properties);
     let actor_read_full_method = get_read_full_method(&properties);
-    let actor_read_partial_method = get_read_partial_method(&enum_name, &properties);
+    let actor_read_partial_method =
+        get_read_partial_method(&enum_name, &properties, &bit_packed_properties);
     let set_mutator_method = get_set_mutator_method(&properties);
     let get_typed_copy_method = get_get_typed_copy_method(&type_name, actor_name, &properties);
     let equals_method = get_equals_method(actor_name, &properties);
     let equals_prediction_method = get_equals_prediction_method(actor_name, &predicted_properties);
     let set_to_interpolation_method =
         get_set_to_interpolation_method(actor_name, &properties, &interpolated_properties);
-    let is_interpolated_method = get_is_interpolated_method(&predicted_properties);
+    let set_to_interpolation_fixed_method =
+        get_set_to_interpolation_fixed_method(actor_name, &properties, &interpolated_properties);
+    let is_interpolated_method = get_is_interpolated_method(&interpolated_properties);
     let is_predicted_method = get_is_predicted_method(&predicted_properties);
     let mirror_method = get_mirror_method(actor_name, &properties);
 
@@ -87,6 +92,7 @@ pub fn actor_impl(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
             #equals_method
             #equals_prediction_method
             #set_to_interpolation_method
+            #set_to_interpolation_fixed_method
             #mirror_method
         }
     };
@@ -119,7 +125,7 @@ fn get_property_enum(enum_name: &Ident, properties: &Vec<(Ident, Type)>) -> Toke
 
     return quote! {
         #hashtag[repr(u8)]
-        enum #enum_name {
+        pub enum #enum_name {
             #variant_list
         }
     };
@@ -180,6 +186,7 @@ fn get_new_complete_method(
     }
 
     return quote! {
+        #[allow(clippy::too_many_arguments)]
         pub fn new_complete(#args) -> #actor_name {
             #actor_name {
                 #fields
@@ -259,10 +266,18 @@ fn get_get_typed_copy_method(
     };
 }
 
-fn get_write_partial_method(enum_name: &Ident, properties: &Vec<(Ident, Type)>) -> TokenStream {
+fn get_write_partial_method(
+    enum_name: &Ident,
+    properties: &Vec<(Ident, Type)>,
+    bit_packed_properties: &Vec<Ident>,
+) -> TokenStream {
     let mut output = quote! {};
 
     for (field_name, _) in properties.iter() {
+        if bit_packed_properties.contains(field_name) {
+            continue;
+        }
+
         let uppercase_variant_name = Ident::new(
             field_name.to_string().to_uppercase().as_str(),
             Span::call_site(),
@@ -280,6 +295,36 @@ fn get_write_partial_method(enum_name: &Ident, properties: &Vec<(Ident, Type)>)
         output = new_output_result;
     }
 
+    // bit packed Properties follow all others, sharing as few bytes as needed
+    if !bit_packed_properties.is_empty() {
+        let mut bit_output = quote! {};
+
+        for field_name in bit_packed_properties.iter() {
+            let uppercase_variant_name = Ident::new(
+                field_name.to_string().to_uppercase().as_str(),
+                Span::call_site(),
+            );
+
+            let new_output_right = quote! {
+                if let Some(true) = state_mask.get_bit(#enum_name::#uppercase_variant_name as u8) {
+                    Property::write_bit(&self.#field_name, &mut bit_writer);
+                }
+            };
+            let new_output_result = quote! {
+                #bit_output
+                #new_output_right
+            };
+            bit_output = new_output_result;
+        }
+
+        output = quote! {
+            #output
+            let mut bit_writer = naia_shared::BitWriter::new();
+            #bit_output
+            bit_writer.finish(buffer);
+        };
+    }
+
     return quote! {
         fn write_partial(&self, state_mask: &StateMask, buffer: &mut Vec<u8>) {
 
@@ -309,10 +354,18 @@ fn get_read_full_method(properties: &Vec<(Ident, Type)>) -> TokenStream {
     };
 }
 
-fn get_read_partial_method(enum_name: &Ident, properties: &Vec<(Ident, Type)>) -> TokenStream {
+fn get_read_partial_method(
+    enum_name: &Ident,
+    properties: &Vec<(Ident, Type)>,
+    bit_packed_properties: &Vec<Ident>,
+) -> TokenStream {
     let mut output = quote! {};
 
     for (field_name, _) in properties.iter() {
+        if bit_packed_properties.contains(field_name) {
+            continue;
+        }
+
         let uppercase_variant_name = Ident::new(
             field_name.to_string().to_uppercase().as_str(),
             Span::call_site(),
@@ -330,6 +383,34 @@ fn get_read_partial_method(enum_name: &Ident, properties: &Vec<(Ident, Type)>) -
         output = new_output_result;
     }
 
+    if !bit_packed_properties.is_empty() {
+        let mut bit_output = quote! {};
+
+        for field_name in bit_packed_properties.iter() {
+            let uppercase_variant_name = Ident::new(
+                field_name.to_string().to_uppercase().as_str(),
+                Span::call_site(),
+            );
+
+            let new_output_right = quote! {
+                if let Some(true) = state_mask.get_bit(#enum_name::#uppercase_variant_name as u8) {
+                    Property::read_bit(&mut self.#field_name, &mut bit_reader, packet_index);
+                }
+            };
+            let new_output_result = quote! {
+                #bit_output
+                #new_output_right
+            };
+            bit_output = new_output_result;
+        }
+
+        output = quote! {
+            #output
+            let mut bit_reader = naia_shared::BitReader::new(reader);
+            #bit_output
+        };
+    }
+
     return quote! {
         fn read_partial(&mut self, state_mask: &StateMask, reader: &mut PacketReader, packet_index: u16) {
             #output
@@ -428,6 +509,50 @@ fn get_set_to_interpolation_method(
     };
 }
 
+fn get_set_to_interpolation_fixed_method(
+    actor_name: &Ident,
+    properties: &Vec<(Ident, Type)>,
+    interpolated_properties: &Vec<(Ident, Type)>,
+) -> TokenStream {
+    let mut output = quote! {};
+
+    for (field_name, field_type) in properties.iter() {
+        let is_interpolated = {
+            let mut i_output = false;
+            for (interp_field_name, _) in interpolated_properties.iter() {
+                if interp_field_name == field_name {
+                    i_output = true;
+                    break;
+                }
+            }
+            i_output
+        };
+
+        let new_output_right = {
+            if is_interpolated {
+                quote! {
+                    self.#field_name.set(naia_shared::interp_lerp_fixed::<#field_type>(old.#field_name.get(), new.#field_name.get(), fraction));
+                }
+            } else {
+                quote! {
+                    self.#field_name.mirror(&new.#field_name);
+                }
+            }
+        };
+        let new_output_result = quote! {
+            #output
+            #new_output_right
+        };
+        output = new_output_result;
+    }
+
+    return quote! {
+        fn set_to_interpolation_fixed(&mut self, old: &#actor_name, new: &#actor_name, fraction: naia_shared::FixedFraction) {
+            #output
+        }
+    };
+}
+
 fn get_mirror_method(actor_name: &Ident, properties: &Vec<(Ident, Type)>) -> TokenStream {
     let mut output = quote! {};
 
@@ -556,3 +681,29 @@ fn get_predicted_properties(input: &DeriveInput) -> Vec<(Ident, Type)> {
 
     fields
 }
+
+// Fields marked #[bit_packed] must be of type Property<bool>
+fn get_bit_packed_properties(input: &DeriveInput) -> Vec<Ident> {
+    let mut fields: Vec<Ident> = Vec::new();
+
+    if let Data::Struct(data_struct) = &input.data {
+        if let Fields::Named(fields_named) = &data_struct.fields {
+            for field in fields_named.named.iter() {
+                for attr in field.attrs.iter() {
+                    match attr.parse_meta().unwrap() {
+                        syn::Meta::Path(ref path)
+                            if path.get_ident().unwrap().to_string() == "bit_packed" =>
+                        {
+                            if let Some(property_name) = &field.ident {
+                                fields.push(property_name.clone());
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    fields
+}
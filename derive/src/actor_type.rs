@@ -14,6 +14,9 @@ pub fn actor_type_impl(input: proc_macro::TokenStream) -> proc_macro::TokenStrea
     let equals_method = get_equals_method(&type_name, &input.data);
     let equals_prediction_method = get_equals_prediction_method(&type_name, &input.data);
     let set_to_interpolation_method = get_set_to_interpolation_method(&type_name, &input.data);
+    let set_to_interpolation_fixed_method =
+        get_set_to_interpolation_fixed_method(&type_name, &input.data);
+    let set_to_extrapolation_method = get_set_to_extrapolation_method(&type_name);
     let is_interpolated_method = get_is_interpolated_method(&type_name, &input.data);
     let mirror_method = get_mirror_method(&type_name, &input.data);
     let is_predicted_method = get_is_predicted_method(&type_name, &input.data);
@@ -27,6 +30,8 @@ pub fn actor_type_impl(input: proc_macro::TokenStream) -> proc_macro::TokenStrea
             #equals_method
             #equals_prediction_method
             #set_to_interpolation_method
+            #set_to_interpolation_fixed_method
+            #set_to_extrapolation_method
             #is_interpolated_method
             #is_predicted_method
             #mirror_method
@@ -278,6 +283,55 @@ fn get_set_to_interpolation_method(type_name: &Ident, data: &Data) -> TokenStrea
     };
 }
 
+fn get_set_to_interpolation_fixed_method(type_name: &Ident, data: &Data) -> TokenStream {
+    let variants = match *data {
+        Data::Enum(ref data) => {
+            let mut output = quote! {};
+            for variant in data.variants.iter() {
+                let variant_name = &variant.ident;
+                let new_output_right = quote! {
+                    #type_name::#variant_name(idactor) => {
+                        match old {
+                            #type_name::#variant_name(old_idactor) => {
+                                match new {
+                                    #type_name::#variant_name(new_idactor) => {
+                                        return idactor.borrow_mut().set_to_interpolation_fixed(&old_idactor.as_ref().borrow(), &new_idactor.as_ref().borrow(), fraction);
+                                    }
+                                    _ => {}
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                };
+                let new_output_result = quote! {
+                    #output
+                    #new_output_right
+                };
+                output = new_output_result;
+            }
+            output
+        }
+        _ => unimplemented!(),
+    };
+
+    return quote! {
+        fn set_to_interpolation_fixed(&mut self, old: &#type_name, new: &#type_name, fraction: naia_shared::FixedFraction) {
+            match self {
+                #variants
+            }
+        }
+    };
+}
+
+fn get_set_to_extrapolation_method(type_name: &Ident) -> TokenStream {
+    return quote! {
+        fn set_to_extrapolation(&mut self, old: &#type_name, new: &#type_name, fraction: f32) {
+            return self.set_to_interpolation(old, new, fraction);
+        }
+    };
+}
+
 fn get_mirror_method(type_name: &Ident, data: &Data) -> TokenStream {
     let variants = match *data {
         Data::Enum(ref data) => {
@@ -47,8 +47,8 @@ pub fn event_impl(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
             #read_to_type_method
         }
         impl Event<#type_name> for #event_name {
-            fn is_guaranteed(&self) -> bool {
-                #event_name::is_guaranteed()
+            fn guarantee_delivery(&self) -> DeliveryGuarantee {
+                #event_name::guarantee_delivery()
             }
             #event_write_method
             fn get_typed_copy(&self) -> #type_name {
@@ -14,12 +14,14 @@ mod actor;
 mod actor_type;
 mod event;
 mod event_type;
+mod packet_serde;
 mod utils;
 
 use actor::actor_impl;
 use actor_type::actor_type_impl;
 use event::event_impl;
 use event_type::event_type_impl;
+use packet_serde::packet_serde_impl;
 
 /// Derives the ActorType trait for a given enum
 #[proc_macro_derive(ActorType)]
@@ -40,7 +42,14 @@ pub fn event_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
 }
 
 /// Derives the Actor trait for a given struct
-#[proc_macro_derive(Actor, attributes(type_name, interpolate, predict))]
+#[proc_macro_derive(Actor, attributes(type_name, interpolate, predict, bit_packed))]
 pub fn actor_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     actor_impl(input)
 }
+
+/// Derives the PacketSerde trait for a given struct, writing & reading its
+/// fields in the order they are declared
+#[proc_macro_derive(PacketSerde)]
+pub fn packet_serde_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    packet_serde_impl(input)
+}
@@ -0,0 +1,103 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident, Type};
+
+pub fn packet_serde_impl(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let struct_name = &input.ident;
+
+    let fields = get_fields(&input);
+
+    let write_method = get_write_method(&fields);
+
+    let read_method = get_read_method(&fields);
+
+    let gen = quote! {
+        impl naia_shared::PacketSerde for #struct_name {
+            #write_method
+            #read_method
+        }
+    };
+
+    proc_macro::TokenStream::from(gen)
+}
+
+// Fields are returned in declaration order, which is the order they are
+// written to & read from the byte stream
+fn get_fields(input: &DeriveInput) -> Vec<(Ident, Type)> {
+    let mut fields = Vec::new();
+
+    if let Data::Struct(data_struct) = &input.data {
+        if let Fields::Named(fields_named) = &data_struct.fields {
+            for field in fields_named.named.iter() {
+                if let Some(field_name) = &field.ident {
+                    fields.push((field_name.clone(), field.ty.clone()));
+                }
+            }
+            return fields;
+        }
+    }
+
+    panic!("#[derive(PacketSerde)] is only supported on structs with named fields");
+}
+
+fn get_write_method(fields: &Vec<(Ident, Type)>) -> TokenStream {
+    let mut output = quote! {};
+
+    for (field_name, field_type) in fields.iter() {
+        let new_output_right = quote! {
+            <#field_type as naia_shared::PacketSerde>::write(&self.#field_name, buffer);
+        };
+        let new_output_result = quote! {
+            #output
+            #new_output_right
+        };
+        output = new_output_result;
+    }
+
+    return quote! {
+        fn write(&self, buffer: &mut Vec<u8>) {
+            #output
+        }
+    };
+}
+
+fn get_read_method(fields: &Vec<(Ident, Type)>) -> TokenStream {
+    let mut field_names = quote! {};
+    for (field_name, _) in fields.iter() {
+        let new_output_right = quote! {
+            #field_name
+        };
+        let new_output_result = quote! {
+            #field_names
+            #new_output_right,
+        };
+        field_names = new_output_result;
+    }
+
+    let mut field_reads = quote! {};
+    for (field_name, field_type) in fields.iter() {
+        let new_output_right = quote! {
+            let #field_name = match <#field_type as naia_shared::PacketSerde>::read(reader) {
+                Some(value) => value,
+                None => return None,
+            };
+        };
+        let new_output_result = quote! {
+            #field_reads
+            #new_output_right
+        };
+        field_reads = new_output_result;
+    }
+
+    return quote! {
+        fn read(reader: &mut naia_shared::PacketReader) -> Option<Self> {
+            #field_reads
+
+            return Some(Self {
+                #field_names
+            });
+        }
+    };
+}
@@ -0,0 +1,83 @@
+use std::collections::{HashMap, VecDeque};
+
+use super::actor_key::actor_key::ActorKey;
+
+/// Keeps copies of the state of every Actor at each of the most recent
+/// ticks, so that the Server can look back at where Actors were at the time
+/// a Client saw them, for example to check a shot against a moving target
+#[derive(Debug)]
+pub struct ActorHistory<U> {
+    length: usize,
+    snapshots: VecDeque<(u16, HashMap<ActorKey, U>)>,
+}
+
+impl<U> ActorHistory<U> {
+    /// Creates a new ActorHistory, which holds snapshots for up to the given
+    /// number of ticks
+    pub fn new(length: usize) -> Self {
+        ActorHistory {
+            length,
+            snapshots: VecDeque::new(),
+        }
+    }
+
+    /// Records a snapshot of Actor state for the given tick, dropping the
+    /// oldest snapshot if the history is full
+    pub fn record(&mut self, tick: u16, snapshot: HashMap<ActorKey, U>) {
+        if self.length == 0 {
+            return;
+        }
+        while self.snapshots.len() >= self.length {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back((tick, snapshot));
+    }
+
+    /// Gets the state of an Actor at the given tick, if a snapshot of that
+    /// tick is still held & the Actor was registered at the time
+    pub fn get(&self, key: &ActorKey, tick: u16) -> Option<&U> {
+        for (snapshot_tick, snapshot) in self.snapshots.iter().rev() {
+            if *snapshot_tick == tick {
+                return snapshot.get(key);
+            }
+        }
+        return None;
+    }
+
+    /// Returns whether any snapshots are recorded at all
+    pub fn is_enabled(&self) -> bool {
+        return self.length != 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use slotmap::DenseSlotMap;
+
+    use super::ActorHistory;
+    use crate::actors::actor_key::actor_key::ActorKey;
+
+    #[test]
+    fn past_tick_returns_the_historical_position() {
+        let mut actors = DenseSlotMap::<ActorKey, f32>::with_key();
+        let key = actors.insert(0.0);
+
+        let mut history = ActorHistory::new(3);
+        for tick in 1..=5_u16 {
+            // the actor moves one unit every tick
+            actors[key] = f32::from(tick);
+            let mut snapshot = HashMap::new();
+            snapshot.insert(key, actors[key]);
+            history.record(tick, snapshot);
+        }
+
+        assert!(actors[key] == 5.0);
+        assert!(history.get(&key, 4) == Some(&4.0));
+        assert!(history.get(&key, 3) == Some(&3.0));
+
+        // beyond the length of the history
+        assert!(history.get(&key, 2).is_none());
+    }
+}
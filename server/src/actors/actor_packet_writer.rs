@@ -1,6 +1,6 @@
 use byteorder::{BigEndian, WriteBytesExt};
 
-use naia_shared::{ActorType, EventType, Manifest, MTU_SIZE};
+use naia_shared::{ActorType, EventType, Manifest, PREDICTION_TOKEN_FLAG, SPAWN_EVENT_FLAG};
 
 use super::server_actor_message::ServerActorMessage;
 
@@ -21,7 +21,7 @@ impl ActorPacketWriter {
         let mut actor_total_bytes = Vec::<u8>::new();
 
         match message {
-            ServerActorMessage::CreateActor(_, local_key, actor) => {
+            ServerActorMessage::CreateActor(_, local_key, actor, spawn_event, prediction_token) => {
                 //write actor payload
                 let mut actor_payload_bytes = Vec::<u8>::new();
                 actor.as_ref().borrow().write(&mut actor_payload_bytes);
@@ -38,6 +38,28 @@ impl ActorPacketWriter {
                     .write_u16::<BigEndian>(*local_key)
                     .unwrap(); //write local key
                 actor_total_bytes.append(&mut actor_payload_bytes); // write payload
+
+                //write which of the spawn event & prediction token follow
+                let mut create_flags = 0;
+                if spawn_event.is_some() {
+                    create_flags |= SPAWN_EVENT_FLAG;
+                }
+                if prediction_token.is_some() {
+                    create_flags |= PREDICTION_TOKEN_FLAG;
+                }
+                actor_total_bytes.write_u8(create_flags).unwrap();
+
+                //write spawn event, already serialized as naia id, payload length & payload
+                if let Some(spawn_event_bytes) = spawn_event {
+                    actor_total_bytes.extend_from_slice(spawn_event_bytes);
+                }
+
+                //write prediction token
+                if let Some(prediction_token) = prediction_token {
+                    actor_total_bytes
+                        .write_u16::<BigEndian>(*prediction_token)
+                        .unwrap();
+                }
             }
             ServerActorMessage::DeleteActor(_, local_key) => {
                 actor_total_bytes
@@ -107,7 +129,7 @@ impl ActorPacketWriter {
         if packet_writer.actor_message_count == 0 {
             hypothetical_next_payload_size += 2;
         }
-        if hypothetical_next_payload_size < MTU_SIZE {
+        if hypothetical_next_payload_size < packet_writer.max_payload_size() {
             packet_writer.actor_message_count += 1;
             packet_writer
                 .actor_working_bytes
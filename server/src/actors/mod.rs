@@ -1,3 +1,4 @@
+pub(crate) mod actor_history;
 pub(crate) mod actor_key;
 pub(crate) mod actor_packet_writer;
 pub(crate) mod actor_record;
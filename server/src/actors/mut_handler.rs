@@ -75,4 +75,13 @@ impl MutHandler {
             state_mask_list.remove(address);
         }
     }
+
+    // moves every state mask registered for a Client over to its new address
+    pub fn rekey_masks(&mut self, old_address: &SocketAddr, new_address: &SocketAddr) {
+        for (_, state_mask_list) in self.actor_state_mask_list_map.iter_mut() {
+            if let Some(mask_ref) = state_mask_list.remove(old_address) {
+                state_mask_list.insert(*new_address, mask_ref);
+            }
+        }
+    }
 }
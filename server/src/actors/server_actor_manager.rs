@@ -2,6 +2,7 @@ use std::{
     borrow::Borrow,
     cell::RefCell,
     clone::Clone,
+    cmp::Ordering,
     collections::{HashMap, HashSet, VecDeque},
     net::SocketAddr,
     rc::Rc,
@@ -35,6 +36,7 @@ pub struct ServerActorManager<T: ActorType> {
     mut_handler: Rc<RefCell<MutHandler>>,
     last_popped_state_mask: StateMask,
     pawn_store: HashSet<ActorKey>,
+    update_priorities: SparseSecondaryMap<ActorKey, f32>,
 }
 
 impl<T: ActorType> ServerActorManager<T> {
@@ -56,6 +58,7 @@ impl<T: ActorType> ServerActorManager<T> {
             mut_handler: mut_handler.clone(),
             last_popped_state_mask: StateMask::new(0),
             pawn_store: HashSet::new(),
+            update_priorities: SparseSecondaryMap::new(),
         }
     }
 
@@ -77,7 +80,7 @@ impl<T: ActorType> ServerActorManager<T> {
 
                 //clear state mask of actor if need be
                 match &message {
-                    ServerActorMessage::CreateActor(global_key, _, _) => {
+                    ServerActorMessage::CreateActor(global_key, _, _, _, _) => {
                         if let Some(record) = self.actor_records.get(*global_key) {
                             self.last_popped_state_mask =
                                 record.get_state_mask().as_ref().borrow().clone();
@@ -162,7 +165,7 @@ impl<T: ActorType> ServerActorManager<T> {
         }
 
         match &message {
-            ServerActorMessage::CreateActor(global_key, _, _) => {
+            ServerActorMessage::CreateActor(global_key, _, _, _, _) => {
                 self.mut_handler.as_ref().borrow_mut().set_state(
                     &self.address,
                     global_key,
@@ -223,11 +226,25 @@ impl<T: ActorType> ServerActorManager<T> {
             .clone()
     }
 
+    pub fn set_address(&mut self, address: SocketAddr) {
+        self.mut_handler
+            .as_ref()
+            .borrow_mut()
+            .rekey_masks(&self.address, &address);
+        self.address = address;
+    }
+
     pub fn has_actor(&self, key: &ActorKey) -> bool {
         return self.local_actor_store.contains_key(*key);
     }
 
-    pub fn add_actor(&mut self, key: &ActorKey, actor: &Rc<RefCell<dyn Actor<T>>>) {
+    pub fn add_actor(
+        &mut self,
+        key: &ActorKey,
+        actor: &Rc<RefCell<dyn Actor<T>>>,
+        spawn_event: Option<&Rc<Box<[u8]>>>,
+        prediction_token: Option<u16>,
+    ) {
         if !self.local_actor_store.contains_key(*key) {
             self.local_actor_store.insert(*key, actor.clone());
             let local_key = self.get_new_local_key();
@@ -245,6 +262,8 @@ impl<T: ActorType> ServerActorManager<T> {
                     *key,
                     local_key,
                     actor.clone(),
+                    spawn_event.cloned(),
+                    prediction_token,
                 ));
 
             // if this is a pawn, send a "assign pawn" follow-up message
@@ -256,6 +275,7 @@ impl<T: ActorType> ServerActorManager<T> {
     }
 
     pub fn remove_actor(&mut self, key: &ActorKey) {
+        self.update_priorities.remove(*key);
         if let Some(actor_record) = self.actor_records.get_mut(*key) {
             if actor_record.status != LocalActorStatus::Deleting {
                 actor_record.status = LocalActorStatus::Deleting;
@@ -285,6 +305,8 @@ impl<T: ActorType> ServerActorManager<T> {
     pub fn add_pawn(&mut self, key: &ActorKey) {
         if !self.pawn_store.contains(key) {
             self.pawn_store.insert(*key);
+            // Pawns are updated regardless of priority
+            self.update_priorities.remove(*key);
             if let Some(actor_record) = self.actor_records.get_mut(*key) {
                 self.queued_messages
                     .push_back(ServerActorMessage::AssignPawn(*key, actor_record.local_key));
@@ -305,6 +327,15 @@ impl<T: ActorType> ServerActorManager<T> {
         }
     }
 
+    pub fn get_local_key(&self, key: &ActorKey) -> Option<LocalActorKey> {
+        if let Some(actor_record) = self.actor_records.get(*key) {
+            if actor_record.status != LocalActorStatus::Deleting {
+                return Some(actor_record.local_key);
+            }
+        }
+        return None;
+    }
+
     pub fn get_global_key_from_local(&self, local_key: LocalActorKey) -> Option<&ActorKey> {
         return self.local_to_global_key_map.get(&local_key);
     }
@@ -319,11 +350,58 @@ impl<T: ActorType> ServerActorManager<T> {
         return output;
     }
 
-    pub fn collect_actor_updates(&mut self) {
+    /// Queues up Update messages for the Actors which have changed. If
+    /// `max_updates` is set, every changed Actor accrues priority each call,
+    /// and only that many Actors with the highest accrued priority, weighted
+    /// by their update_priority(), are queued, starting over at no priority.
+    /// Changes to the others are kept until they are queued. Pawns are always
+    /// updated, and do not count towards `max_updates`
+    pub fn collect_actor_updates(&mut self, max_updates: Option<usize>) {
+        let mut updated_keys = Vec::new();
         for (key, record) in self.actor_records.iter() {
             if record.status == LocalActorStatus::Created
                 && !record.get_state_mask().as_ref().borrow().is_clear()
             {
+                updated_keys.push(key);
+            }
+        }
+
+        if let Some(max_updates) = max_updates {
+            let mut pawn_keys = Vec::new();
+            let mut weighted_keys = Vec::new();
+            for key in updated_keys {
+                if self.pawn_store.contains(&key) {
+                    pawn_keys.push(key);
+                    continue;
+                }
+                let priority = match self.update_priorities.get_mut(key) {
+                    Some(priority) => {
+                        *priority += 1.0;
+                        *priority
+                    }
+                    None => {
+                        self.update_priorities.insert(key, 1.0);
+                        1.0
+                    }
+                };
+                let weight = match self.local_actor_store.get(key) {
+                    Some(actor_ref) => actor_ref.as_ref().borrow().update_priority(),
+                    None => 1.0,
+                };
+                weighted_keys.push((key, priority * weight));
+            }
+            weighted_keys.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap_or(Ordering::Equal));
+            weighted_keys.truncate(max_updates);
+
+            updated_keys = pawn_keys;
+            for (key, _) in weighted_keys {
+                self.update_priorities.remove(key);
+                updated_keys.push(key);
+            }
+        }
+
+        for key in updated_keys {
+            if let Some(record) = self.actor_records.get(key) {
                 if let Some(actor_ref) = self.local_actor_store.get(key) {
                     if self.pawn_store.contains(&key) {
                         // handle as a pawn
@@ -355,7 +433,7 @@ impl<T: ActorType> ActorNotifiable for ServerActorManager<T> {
         if let Some(delivered_messages_list) = self.sent_messages.get(&packet_index) {
             for delivered_message in delivered_messages_list.into_iter() {
                 match delivered_message {
-                    ServerActorMessage::CreateActor(global_key, _, _) => {
+                    ServerActorMessage::CreateActor(global_key, _, _, _, _) => {
                         if let Some(actor_record) = self.actor_records.get_mut(*global_key) {
                             // update actor record status
                             actor_record.status = LocalActorStatus::Created;
@@ -393,8 +471,18 @@ impl<T: ActorType> ActorNotifiable for ServerActorManager<T> {
         if let Some(dropped_messages_list) = self.sent_messages.get(&dropped_packet_index) {
             for dropped_message in dropped_messages_list.into_iter() {
                 match dropped_message {
-                    ServerActorMessage::CreateActor(_, _, _)
-                    | ServerActorMessage::DeleteActor(_, _)
+                    ServerActorMessage::CreateActor(global_key, local_key, _, _, _) => {
+                        // a Create resent after the Actor's Delete would leave a ghost
+                        // Actor on the Client, which is never deleted
+                        if let Some(record) = self.actor_records.get(*global_key) {
+                            if record.local_key == *local_key
+                                && record.status != LocalActorStatus::Deleting
+                            {
+                                self.queued_messages.push_back(dropped_message.clone());
+                            }
+                        }
+                    }
+                    ServerActorMessage::DeleteActor(_, _)
                     | ServerActorMessage::AssignPawn(_, _)
                     | ServerActorMessage::UnassignPawn(_, _) => {
                         self.queued_messages.push_back(dropped_message.clone());
@@ -439,3 +527,288 @@ impl<T: ActorType> ActorNotifiable for ServerActorManager<T> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        any::TypeId,
+        cell::RefCell,
+        net::{IpAddr, Ipv4Addr, SocketAddr},
+        rc::Rc,
+    };
+
+    use slotmap::DenseSlotMap;
+
+    use naia_shared::{Actor, ActorMutator, ActorNotifiable, ActorType, PacketReader, StateMask};
+
+    use super::ServerActorManager;
+    use crate::actors::{
+        actor_key::actor_key::ActorKey, mut_handler::MutHandler,
+        server_actor_message::ServerActorMessage,
+    };
+
+    #[derive(Clone, Debug)]
+    struct TestActor {
+        x: i16,
+        priority: f32,
+    }
+
+    impl Actor<TestActorType> for TestActor {
+        fn get_state_mask_size(&self) -> u8 {
+            return 1;
+        }
+        fn get_typed_copy(&self) -> TestActorType {
+            return TestActorType::TestActor(Rc::new(RefCell::new(self.clone())));
+        }
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<TestActor>();
+        }
+        fn write(&self, _: &mut Vec<u8>) {}
+        fn write_partial(&self, _: &StateMask, _: &mut Vec<u8>) {}
+        fn read_full(&mut self, _: &mut PacketReader, _: u16) {}
+        fn read_partial(&mut self, _: &StateMask, _: &mut PacketReader, _: u16) {}
+        fn set_mutator(&mut self, _: &Rc<RefCell<dyn ActorMutator>>) {}
+        fn is_interpolated(&self) -> bool {
+            return false;
+        }
+        fn is_predicted(&self) -> bool {
+            return false;
+        }
+        fn update_priority(&self) -> f32 {
+            return self.priority;
+        }
+    }
+
+    #[derive(Clone)]
+    enum TestActorType {
+        TestActor(Rc<RefCell<TestActor>>),
+    }
+
+    impl ActorType for TestActorType {
+        fn read_full(&mut self, _: &mut PacketReader, _: u16) {}
+        fn read_partial(&mut self, _: &StateMask, _: &mut PacketReader, _: u16) {}
+        fn inner_ref(&self) -> Rc<RefCell<dyn Actor<TestActorType>>> {
+            match self {
+                TestActorType::TestActor(actor) => return actor.clone(),
+            }
+        }
+        fn equals(&self, _: &TestActorType) -> bool {
+            return false;
+        }
+        fn equals_prediction(&self, _: &TestActorType) -> bool {
+            return false;
+        }
+        fn set_to_interpolation(&mut self, _: &TestActorType, _: &TestActorType, _: f32) {}
+        fn set_to_extrapolation(&mut self, _: &TestActorType, _: &TestActorType, _: f32) {}
+        fn mirror(&mut self, _: &TestActorType) {}
+        fn is_interpolated(&self) -> bool {
+            return false;
+        }
+        fn is_predicted(&self) -> bool {
+            return false;
+        }
+    }
+
+    // mirrors the scope evaluation in NaiaServer::update_actor_scopes
+    fn update_scope(
+        manager: &mut ServerActorManager<TestActorType>,
+        key: &ActorKey,
+        actor: &Rc<RefCell<TestActor>>,
+        should_be_in_scope: bool,
+    ) {
+        let currently_in_scope = manager.has_actor(key);
+        if should_be_in_scope {
+            if !currently_in_scope {
+                let actor_ref: Rc<RefCell<dyn Actor<TestActorType>>> = actor.clone();
+                manager.add_actor(key, &actor_ref, None, None);
+            }
+        } else {
+            if currently_in_scope {
+                manager.remove_actor(key);
+            }
+        }
+    }
+
+    // sends & acknowledges all queued messages, returning a summary of them
+    fn deliver_messages(
+        manager: &mut ServerActorManager<TestActorType>,
+        packet_index: u16,
+    ) -> Vec<&'static str> {
+        let mut output = Vec::new();
+        while let Some(message) = manager.pop_outgoing_message(packet_index) {
+            output.push(match message {
+                ServerActorMessage::CreateActor(_, _, _, _, _) => "create",
+                ServerActorMessage::DeleteActor(_, _) => "delete",
+                _ => "other",
+            });
+        }
+        manager.notify_packet_delivered(packet_index);
+        return output;
+    }
+
+    fn in_range(client_x: i16, actor: &Rc<RefCell<TestActor>>) -> bool {
+        return (actor.borrow().x - client_x).abs() <= 10;
+    }
+
+    #[test]
+    fn scope_toggles_per_client_as_actor_moves() {
+        let mut_handler = MutHandler::new();
+        let mut actor_keys = DenseSlotMap::<ActorKey, ()>::with_key();
+        let key = actor_keys.insert(());
+        mut_handler.borrow_mut().register_actor(&key);
+
+        let address_a = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 1000);
+        let address_b = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 1001);
+        let mut manager_a = ServerActorManager::<TestActorType>::new(address_a, &mut_handler);
+        let mut manager_b = ServerActorManager::<TestActorType>::new(address_b, &mut_handler);
+        let (client_a_x, client_b_x) = (0, 100);
+
+        let actor = Rc::new(RefCell::new(TestActor {
+            x: 0,
+            priority: 1.0,
+        }));
+        let mut packet_index = 0;
+
+        // actor starts next to client A
+        update_scope(&mut manager_a, &key, &actor, in_range(client_a_x, &actor));
+        update_scope(&mut manager_b, &key, &actor, in_range(client_b_x, &actor));
+        assert!(deliver_messages(&mut manager_a, packet_index) == vec!["create"]);
+        assert!(deliver_messages(&mut manager_b, packet_index).is_empty());
+        packet_index += 1;
+
+        // actor moves next to client B
+        actor.borrow_mut().x = 100;
+        update_scope(&mut manager_a, &key, &actor, in_range(client_a_x, &actor));
+        update_scope(&mut manager_b, &key, &actor, in_range(client_b_x, &actor));
+        assert!(deliver_messages(&mut manager_a, packet_index) == vec!["delete"]);
+        assert!(deliver_messages(&mut manager_b, packet_index) == vec!["create"]);
+        assert!(!manager_a.has_actor(&key));
+        assert!(manager_b.has_actor(&key));
+        packet_index += 1;
+
+        // actor moves back next to client A, which gets a fresh Create
+        actor.borrow_mut().x = 0;
+        update_scope(&mut manager_a, &key, &actor, in_range(client_a_x, &actor));
+        update_scope(&mut manager_b, &key, &actor, in_range(client_b_x, &actor));
+        assert!(deliver_messages(&mut manager_a, packet_index) == vec!["create"]);
+        assert!(deliver_messages(&mut manager_b, packet_index) == vec!["delete"]);
+        assert!(manager_a.has_actor(&key));
+        assert!(!manager_b.has_actor(&key));
+    }
+
+    #[test]
+    fn create_dropped_after_its_delete_is_not_resent() {
+        let mut_handler = MutHandler::new();
+        let mut actor_keys = DenseSlotMap::<ActorKey, ()>::with_key();
+        let key = actor_keys.insert(());
+        mut_handler.borrow_mut().register_actor(&key);
+
+        let address = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 1000);
+        let mut manager = ServerActorManager::<TestActorType>::new(address, &mut_handler);
+        let actor = Rc::new(RefCell::new(TestActor {
+            x: 0,
+            priority: 1.0,
+        }));
+
+        // the Create is sent, then the Actor leaves scope before it arrives
+        update_scope(&mut manager, &key, &actor, true);
+        assert!(manager.pop_outgoing_message(0).is_some());
+        update_scope(&mut manager, &key, &actor, false);
+
+        // the Delete is lost as well, and is resent
+        assert!(manager.pop_outgoing_message(1).is_some());
+        manager.notify_packet_dropped(1);
+        assert!(deliver_messages(&mut manager, 2) == vec!["delete"]);
+        assert!(!manager.has_actor(&key));
+
+        // the Create is only now reported lost
+        manager.notify_packet_dropped(0);
+        assert!(!manager.has_outgoing_messages());
+    }
+
+    #[test]
+    fn higher_priority_actors_are_updated_more_often_under_a_budget() {
+        let mut_handler = MutHandler::new();
+        let mut actor_keys = DenseSlotMap::<ActorKey, ()>::with_key();
+        let address = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 1000);
+        let mut manager = ServerActorManager::<TestActorType>::new(address, &mut_handler);
+
+        let heavy_key = actor_keys.insert(());
+        let light_key = actor_keys.insert(());
+        for (key, priority) in [(heavy_key, 3.0), (light_key, 1.0)] {
+            mut_handler.borrow_mut().register_actor(&key);
+            let actor = Rc::new(RefCell::new(TestActor { x: 0, priority }));
+            update_scope(&mut manager, &key, &actor, true);
+        }
+        deliver_messages(&mut manager, 0);
+
+        // both Actors change every tick, but only one update fits each tick
+        let mut heavy_updates = 0;
+        let mut light_updates = 0;
+        for packet_index in 1..41 {
+            mut_handler.borrow_mut().mutate(&heavy_key, 0);
+            mut_handler.borrow_mut().mutate(&light_key, 0);
+            manager.collect_actor_updates(Some(1));
+            while let Some(message) = manager.pop_outgoing_message(packet_index) {
+                if let ServerActorMessage::UpdateActor(key, _, _, _) = message {
+                    if key == heavy_key {
+                        heavy_updates += 1;
+                    } else if key == light_key {
+                        light_updates += 1;
+                    }
+                }
+            }
+            manager.notify_packet_delivered(packet_index);
+        }
+
+        assert!(heavy_updates + light_updates == 40);
+        assert!(heavy_updates > light_updates * 2);
+
+        // but the lower priority Actor is not starved
+        assert!(light_updates > 0);
+    }
+
+    #[test]
+    fn pawns_are_updated_outside_the_update_budget() {
+        let mut_handler = MutHandler::new();
+        let mut actor_keys = DenseSlotMap::<ActorKey, ()>::with_key();
+        let address = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 1000);
+        let mut manager = ServerActorManager::<TestActorType>::new(address, &mut_handler);
+
+        let pawn_key = actor_keys.insert(());
+        let other_keys = [actor_keys.insert(()), actor_keys.insert(())];
+        for key in [pawn_key, other_keys[0], other_keys[1]] {
+            mut_handler.borrow_mut().register_actor(&key);
+            let actor = Rc::new(RefCell::new(TestActor {
+                x: 0,
+                priority: 1.0,
+            }));
+            update_scope(&mut manager, &key, &actor, true);
+        }
+        manager.add_pawn(&pawn_key);
+        deliver_messages(&mut manager, 0);
+
+        for packet_index in 1..11 {
+            mut_handler.borrow_mut().mutate(&pawn_key, 0);
+            mut_handler.borrow_mut().mutate(&other_keys[0], 0);
+            mut_handler.borrow_mut().mutate(&other_keys[1], 0);
+            manager.collect_actor_updates(Some(1));
+            let mut pawn_updates = 0;
+            let mut actor_updates = 0;
+            while let Some(message) = manager.pop_outgoing_message(packet_index) {
+                match message {
+                    ServerActorMessage::UpdatePawn(key, _, _, _) => {
+                        assert!(key == pawn_key);
+                        pawn_updates += 1;
+                    }
+                    ServerActorMessage::UpdateActor(_, _, _, _) => actor_updates += 1,
+                    _ => {}
+                }
+            }
+            manager.notify_packet_delivered(packet_index);
+
+            assert!(pawn_updates == 1);
+            assert!(actor_updates == 1);
+        }
+    }
+}
@@ -5,7 +5,13 @@ use super::actor_key::actor_key::ActorKey;
 
 #[derive(Debug)]
 pub enum ServerActorMessage<T: ActorType> {
-    CreateActor(ActorKey, LocalActorKey, Rc<RefCell<dyn Actor<T>>>),
+    CreateActor(
+        ActorKey,
+        LocalActorKey,
+        Rc<RefCell<dyn Actor<T>>>,
+        Option<Rc<Box<[u8]>>>,
+        Option<u16>,
+    ),
     UpdateActor(
         ActorKey,
         LocalActorKey,
@@ -26,7 +32,7 @@ pub enum ServerActorMessage<T: ActorType> {
 impl<T: ActorType> ServerActorMessage<T> {
     pub fn write_message_type(&self) -> u8 {
         match self {
-            ServerActorMessage::CreateActor(_, _, _) => 0,
+            ServerActorMessage::CreateActor(_, _, _, _, _) => 0,
             ServerActorMessage::DeleteActor(_, _) => 1,
             ServerActorMessage::UpdateActor(_, _, _, _) => 2,
             ServerActorMessage::AssignPawn(_, _) => 3,
@@ -39,8 +45,8 @@ impl<T: ActorType> ServerActorMessage<T> {
 impl<T: ActorType> Clone for ServerActorMessage<T> {
     fn clone(&self) -> Self {
         match self {
-            ServerActorMessage::CreateActor(gk, lk, e) => {
-                ServerActorMessage::CreateActor(gk.clone(), lk.clone(), e.clone())
+            ServerActorMessage::CreateActor(gk, lk, e, se, pt) => {
+                ServerActorMessage::CreateActor(gk.clone(), lk.clone(), e.clone(), se.clone(), *pt)
             }
             ServerActorMessage::DeleteActor(gk, lk) => {
                 ServerActorMessage::DeleteActor(gk.clone(), lk.clone())
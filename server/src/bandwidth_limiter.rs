@@ -0,0 +1,81 @@
+use std::time::Instant;
+
+/// Limits how many bytes of Data packets are sent per second to a Client,
+/// using a token bucket which holds up to one second's worth of bytes
+#[derive(Debug)]
+pub struct BandwidthLimiter {
+    max_bytes_per_second: Option<u32>,
+    // remaining bytes, which may go negative after a packet larger than the
+    // remaining budget, and when they were last refilled
+    bytes: f32,
+    last_refill: Instant,
+}
+
+impl BandwidthLimiter {
+    pub fn new(max_bytes_per_second: Option<u32>) -> Self {
+        BandwidthLimiter {
+            max_bytes_per_second,
+            bytes: max_bytes_per_second.unwrap_or_default() as f32,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Uses up part of the budget, for a packet which has been sent
+    pub fn spend(&mut self, bytes: usize) {
+        if self.max_bytes_per_second.is_some() {
+            self.bytes -= bytes as f32;
+        }
+    }
+
+    /// Returns whether there is any budget left for sending another packet,
+    /// after refilling it up to the given moment
+    pub fn has_budget_at(&mut self, now: Instant) -> bool {
+        if let Some(max_bytes_per_second) = self.max_bytes_per_second {
+            let capacity = max_bytes_per_second as f32;
+            let elapsed = now
+                .checked_duration_since(self.last_refill)
+                .unwrap_or_default();
+            self.bytes = (self.bytes + elapsed.as_secs_f32() * capacity).min(capacity);
+            self.last_refill = now;
+            return self.bytes > 0.0;
+        }
+        return true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::BandwidthLimiter;
+
+    #[test]
+    fn budget_refills_over_time() {
+        let mut limiter = BandwidthLimiter::new(Some(1000));
+        let mut now = Instant::now();
+
+        assert!(limiter.has_budget_at(now));
+        limiter.spend(1200);
+        assert!(!limiter.has_budget_at(now));
+
+        // 100 bytes refilled, but 200 bytes were overspent
+        now += Duration::from_millis(100);
+        assert!(!limiter.has_budget_at(now));
+
+        now += Duration::from_millis(200);
+        assert!(limiter.has_budget_at(now));
+
+        // the budget never holds more than a second's worth
+        now += Duration::from_secs(10);
+        assert!(limiter.has_budget_at(now));
+        limiter.spend(1000);
+        assert!(!limiter.has_budget_at(now));
+    }
+
+    #[test]
+    fn unlimited_without_max() {
+        let mut limiter = BandwidthLimiter::new(None);
+        limiter.spend(usize::MAX);
+        assert!(limiter.has_budget_at(Instant::now()));
+    }
+}
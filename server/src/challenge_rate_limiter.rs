@@ -0,0 +1,94 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    time::{Duration, Instant},
+};
+
+/// Limits how many challenge requests are answered per second from each IP
+/// address, using a token bucket which holds up to one second's worth of
+/// requests
+#[derive(Debug)]
+pub struct ChallengeRateLimiter {
+    requests_per_second: u16,
+    // remaining tokens, and when they were last refilled
+    buckets: HashMap<IpAddr, (f32, Instant)>,
+    last_prune: Instant,
+}
+
+impl ChallengeRateLimiter {
+    pub fn new(requests_per_second: u16) -> Self {
+        ChallengeRateLimiter {
+            requests_per_second,
+            buckets: HashMap::new(),
+            last_prune: Instant::now(),
+        }
+    }
+
+    /// Returns whether a challenge request from the given IP address should
+    /// be answered, using up one of its tokens if so
+    pub fn allow(&mut self, ip: &IpAddr) -> bool {
+        return self.allow_at(ip, Instant::now());
+    }
+
+    fn allow_at(&mut self, ip: &IpAddr, now: Instant) -> bool {
+        // a bucket left untouched for a second has refilled completely, and
+        // is no different to a new one
+        if now.duration_since(self.last_prune) >= Duration::from_secs(1) {
+            self.buckets.retain(|_, (_, last_refill)| {
+                now.duration_since(*last_refill) < Duration::from_secs(1)
+            });
+            self.last_prune = now;
+        }
+
+        let capacity = f32::from(self.requests_per_second);
+        let (tokens, last_refill) = self.buckets.entry(*ip).or_insert((capacity, now));
+        let elapsed = now.duration_since(*last_refill).as_secs_f32();
+        *tokens = (*tokens + elapsed * capacity).min(capacity);
+        *last_refill = now;
+
+        if *tokens < 1.0 {
+            return false;
+        }
+        *tokens -= 1.0;
+        return true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{IpAddr, Ipv4Addr},
+        time::{Duration, Instant},
+    };
+
+    use super::ChallengeRateLimiter;
+
+    #[test]
+    fn flood_from_one_ip_is_capped() {
+        let mut rate_limiter = ChallengeRateLimiter::new(5);
+        let flooder = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let now = Instant::now();
+
+        let answered = (0..1000)
+            .filter(|_| rate_limiter.allow_at(&flooder, now))
+            .count();
+        assert!(answered == 5);
+
+        // other addresses are unaffected
+        let other = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+        assert!(rate_limiter.allow_at(&other, now));
+    }
+
+    #[test]
+    fn handshake_retransmits_are_answered() {
+        let mut rate_limiter = ChallengeRateLimiter::new(2);
+        let client = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let mut now = Instant::now();
+
+        // a Client resending its challenge request once per second
+        for _ in 0..10 {
+            assert!(rate_limiter.allow_at(&client, now));
+            now += Duration::from_secs(1);
+        }
+    }
+}
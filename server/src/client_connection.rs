@@ -1,8 +1,11 @@
-use std::{cell::RefCell, net::SocketAddr, rc::Rc};
+use std::{cell::RefCell, net::SocketAddr, rc::Rc, time::Duration};
+
+use log::warn;
 
 use naia_shared::{
-    Actor, ActorType, Connection, ConnectionConfig, Event, EventType, ManagerType, Manifest,
-    PacketReader, PacketType, SequenceNumber, StandardHeader,
+    Actor, ActorType, Compressor, Connection, ConnectionConfig, Event, EventType, FragmentError,
+    Instant, ManagerType, Manifest, PacketReader, PacketType, PingManager, RawMessageError,
+    SequenceNumber, StandardHeader, TrafficStats,
 };
 
 use super::{
@@ -10,8 +13,8 @@ use super::{
         actor_key::actor_key::ActorKey, actor_packet_writer::ActorPacketWriter,
         mut_handler::MutHandler, server_actor_manager::ServerActorManager,
     },
+    bandwidth_limiter::BandwidthLimiter,
     command_receiver::CommandReceiver,
-    ping_manager::PingManager,
     server_packet_writer::ServerPacketWriter,
 };
 
@@ -20,6 +23,10 @@ pub struct ClientConnection<T: EventType, U: ActorType> {
     actor_manager: ServerActorManager<U>,
     ping_manager: PingManager,
     command_receiver: CommandReceiver<T>,
+    bandwidth_limiter: BandwidthLimiter,
+    session_token: [u8; 16],
+    timed_out_at: Option<Instant>,
+    connected_at: Instant,
 }
 
 impl<T: EventType, U: ActorType> ClientConnection<T, U> {
@@ -27,12 +34,21 @@ impl<T: EventType, U: ActorType> ClientConnection<T, U> {
         address: SocketAddr,
         mut_handler: Option<&Rc<RefCell<MutHandler>>>,
         connection_config: &ConnectionConfig,
+        max_send_bytes_per_second: Option<u32>,
     ) -> Self {
         ClientConnection {
             connection: Connection::new(address, connection_config),
             actor_manager: ServerActorManager::new(address, mut_handler.unwrap()),
-            ping_manager: PingManager::new(),
+            ping_manager: PingManager::new(
+                connection_config.ping_interval,
+                connection_config.rtt_sample_size,
+                connection_config.pong_timeout_intervals,
+            ),
             command_receiver: CommandReceiver::new(),
+            bandwidth_limiter: BandwidthLimiter::new(max_send_bytes_per_second),
+            session_token: [0; 16],
+            timed_out_at: None,
+            connected_at: Instant::now(),
         }
     }
 
@@ -41,14 +57,45 @@ impl<T: EventType, U: ActorType> ClientConnection<T, U> {
         host_tick: u16,
         manifest: &Manifest<T, U>,
     ) -> Option<Box<[u8]>> {
-        if self.connection.has_outgoing_events() || self.actor_manager.has_outgoing_messages() {
-            let mut writer = ServerPacketWriter::new();
+        return self.get_outgoing_packet_at(host_tick, manifest, &Instant::now());
+    }
+
+    /// Same as get_outgoing_packet(), at the given moment, up to which the
+    /// send budget is refilled and against which the coalescing window is
+    /// timed
+    pub fn get_outgoing_packet_at(
+        &mut self,
+        host_tick: u16,
+        manifest: &Manifest<T, U>,
+        now: &Instant,
+    ) -> Option<Box<[u8]>> {
+        // anything left over is sent once the budget has refilled
+        if !self.bandwidth_limiter.has_budget_at(now.get_inner()) {
+            return None;
+        }
+
+        if self.connection.has_outgoing_events()
+            || self.connection.has_outgoing_raw()
+            || self.actor_manager.has_outgoing_messages()
+        {
+            // wait for more data to fill the packet with
+            if self.connection.is_coalescing_at(now) {
+                return None;
+            }
+
+            let mut writer =
+                ServerPacketWriter::with_capacity(self.connection.get_max_payload_size());
 
             let next_packet_index: u16 = self.get_next_packet_index();
-            while let Some(popped_event) = self.connection.pop_outgoing_event(next_packet_index) {
-                if !writer.write_event(manifest, &popped_event) {
+            self.connection.write_outgoing_events(
+                next_packet_index,
+                manifest,
+                writer.event_writer_mut(),
+            );
+            while let Some((channel, data)) = self.connection.pop_outgoing_raw(next_packet_index) {
+                if !writer.write_raw(channel, &data) {
                     self.connection
-                        .unpop_outgoing_event(next_packet_index, &popped_event);
+                        .unpop_outgoing_raw(next_packet_index, channel, data);
                     break;
                 }
             }
@@ -77,10 +124,13 @@ impl<T: EventType, U: ActorType> ClientConnection<T, U> {
                     PacketType::Data,
                     &out_bytes,
                 );
+                self.bandwidth_limiter.spend(payload.len());
                 return Some(payload);
             }
         }
 
+        // everything held back has been written
+        self.connection.reset_coalescing();
         return None;
     }
 
@@ -106,7 +156,15 @@ impl<T: EventType, U: ActorType> ClientConnection<T, U> {
                 ManagerType::Event => {
                     self.connection.process_event_data(&mut reader, manifest);
                 }
-                _ => {}
+                ManagerType::Raw => {
+                    self.connection.process_raw_data(&mut reader);
+                }
+                _ => {
+                    // the length of an unknown section can't be known, so
+                    // nothing after it can be read either
+                    warn!("discarding rest of packet after unknown manager type");
+                    break;
+                }
             }
         }
     }
@@ -115,16 +173,27 @@ impl<T: EventType, U: ActorType> ClientConnection<T, U> {
         return self.actor_manager.has_actor(key);
     }
 
-    pub fn add_actor(&mut self, key: &ActorKey, actor: &Rc<RefCell<dyn Actor<U>>>) {
-        self.actor_manager.add_actor(key, actor);
+    pub fn add_actor(
+        &mut self,
+        key: &ActorKey,
+        actor: &Rc<RefCell<dyn Actor<U>>>,
+        spawn_event: Option<&Rc<Box<[u8]>>>,
+        prediction_token: Option<u16>,
+    ) {
+        self.actor_manager
+            .add_actor(key, actor, spawn_event, prediction_token);
     }
 
     pub fn remove_actor(&mut self, key: &ActorKey) {
+        // the Client won't know the Actor, so drop the Events addressed to it
+        if let Some(local_key) = self.actor_manager.get_local_key(key) {
+            self.connection.forget_actor_events(local_key);
+        }
         self.actor_manager.remove_actor(key);
     }
 
-    pub fn collect_actor_updates(&mut self) {
-        self.actor_manager.collect_actor_updates();
+    pub fn collect_actor_updates(&mut self, max_updates: Option<usize>) {
+        self.actor_manager.collect_actor_updates(max_updates);
     }
 
     pub fn has_pawn(&self, key: &ActorKey) -> bool {
@@ -141,10 +210,18 @@ impl<T: EventType, U: ActorType> ClientConnection<T, U> {
 
     // Pass-through methods to underlying common connection
 
+    pub fn set_config(&mut self, connection_config: &ConnectionConfig) {
+        return self.connection.set_config(connection_config);
+    }
+
     pub fn mark_sent(&mut self) {
         return self.connection.mark_sent();
     }
 
+    pub fn flush_coalesced(&mut self) {
+        self.connection.flush_coalesced();
+    }
+
     pub fn should_send_heartbeat(&self) -> bool {
         return self.connection.should_send_heartbeat();
     }
@@ -153,13 +230,50 @@ impl<T: EventType, U: ActorType> ClientConnection<T, U> {
         return self.connection.mark_heard();
     }
 
-    pub fn should_drop(&self) -> bool {
-        return self.connection.should_drop();
+    pub fn last_heard(&self) -> Instant {
+        return self.connection.last_heard();
+    }
+
+    /// Returns whether the Client has timed out, and has not reconnected
+    /// within the given grace period since
+    pub fn should_drop(&mut self, reconnect_grace_period: Option<Duration>) -> bool {
+        return self.should_drop_at(reconnect_grace_period, &Instant::now());
+    }
+
+    /// Same as should_drop(), at the given moment
+    pub fn should_drop_at(
+        &mut self,
+        reconnect_grace_period: Option<Duration>,
+        now: &Instant,
+    ) -> bool {
+        if !self.connection.should_drop_at(now) {
+            self.timed_out_at = None;
+            return false;
+        }
+        match reconnect_grace_period {
+            Some(grace_period) => {
+                let timed_out_at = self.timed_out_at.get_or_insert_with(|| now.clone());
+                return now
+                    .get_inner()
+                    .saturating_duration_since(timed_out_at.get_inner())
+                    >= grace_period;
+            }
+            None => return true,
+        }
     }
 
-    pub fn process_incoming_header(&mut self, header: &StandardHeader) {
-        self.connection
-            .process_incoming_header(header, &mut Some(&mut self.actor_manager));
+    pub fn is_duplicate(&self, packet_index: u16) -> bool {
+        return self.connection.is_duplicate(packet_index);
+    }
+
+    pub fn process_incoming_header(&mut self, header: &StandardHeader, payload_size: usize) {
+        self.connection.process_incoming_header(
+            header,
+            payload_size,
+            &mut Some(&mut self.actor_manager),
+        );
+        // the Server does not report which Events have been delivered
+        while self.connection.get_delivered_event().is_some() {}
     }
 
     pub fn process_outgoing_header(
@@ -177,16 +291,81 @@ impl<T: EventType, U: ActorType> ClientConnection<T, U> {
         );
     }
 
+    pub fn fragment_outgoing_packet(
+        &mut self,
+        packet: Box<[u8]>,
+    ) -> Result<Vec<Box<[u8]>>, FragmentError> {
+        return self.connection.fragment_outgoing_packet(packet);
+    }
+
+    pub fn process_incoming_fragment(&mut self, payload: &[u8]) -> Option<Box<[u8]>> {
+        return self.connection.process_incoming_fragment(payload);
+    }
+
+    pub fn set_compressor(&mut self, compressor: Option<Rc<dyn Compressor>>) {
+        return self.connection.set_compressor(compressor);
+    }
+
+    pub fn set_integrity_check(&mut self, integrity_check: bool) {
+        return self.connection.set_integrity_check(integrity_check);
+    }
+
+    pub fn has_integrity_check(&self) -> bool {
+        return self.connection.has_integrity_check();
+    }
+
+    pub fn accepts_header(&self, header: &StandardHeader) -> bool {
+        return self.connection.accepts_header(header);
+    }
+
+    pub fn has_compressor(&self) -> bool {
+        return self.connection.has_compressor();
+    }
+
+    pub fn decompress_payload(
+        &self,
+        header: &StandardHeader,
+        payload: Box<[u8]>,
+    ) -> Option<Box<[u8]>> {
+        return self.connection.decompress_payload(header, payload);
+    }
+
     pub fn get_next_packet_index(&self) -> SequenceNumber {
         return self.connection.get_next_packet_index();
     }
 
-    pub fn queue_event(&mut self, event: &impl Event<T>) {
-        return self.connection.queue_event(event);
+    pub fn queue_event(&mut self, event: &impl Event<T>) -> bool {
+        return self.connection.queue_event(event).is_some();
+    }
+
+    pub fn queue_shared_event(&mut self, event: &Rc<Box<dyn Event<T>>>) {
+        self.connection.queue_shared_event(event);
+    }
+
+    /// Queues an Event addressed to an Actor, if that Actor is in scope for
+    /// the Client & the outgoing queue has room. Returns whether the Event was
+    /// queued
+    pub fn queue_actor_event(&mut self, key: &ActorKey, event: &impl Event<T>) -> bool {
+        if let Some(local_key) = self.actor_manager.get_local_key(key) {
+            return self
+                .connection
+                .queue_actor_event(local_key, event)
+                .is_some();
+        }
+        return false;
+    }
+
+    pub fn queue_raw(&mut self, channel: u8, data: &[u8]) -> Result<(), RawMessageError> {
+        return self.connection.queue_raw(channel, data);
+    }
+
+    pub fn get_incoming_raw(&mut self) -> Option<(u8, Box<[u8]>)> {
+        return self.connection.get_incoming_raw();
     }
 
     pub fn get_incoming_event(&mut self) -> Option<T> {
-        return self.connection.get_incoming_event();
+        // Clients do not address Events to Actors
+        return self.connection.get_incoming_event().map(|(_, event)| event);
     }
 
     pub fn get_incoming_command(&mut self, server_tick: u16) -> Option<(ActorKey, T)> {
@@ -206,8 +385,46 @@ impl<T: EventType, U: ActorType> ClientConnection<T, U> {
         return self.connection.get_address();
     }
 
-    pub fn process_ping(&self, ping_payload: &[u8]) -> Box<[u8]> {
-        return self.ping_manager.process_ping(ping_payload);
+    pub fn set_address(&mut self, address: SocketAddr) {
+        self.connection.set_address(address);
+        self.actor_manager.set_address(address);
+    }
+
+    pub fn get_session_token(&self) -> &[u8; 16] {
+        return &self.session_token;
+    }
+
+    pub fn set_session_token(&mut self, session_token: [u8; 16]) {
+        self.session_token = session_token;
+    }
+
+    pub fn get_connected_at(&self) -> &Instant {
+        return &self.connected_at;
+    }
+
+    // ping related
+    pub fn process_ping(&self, ping_payload: &[u8]) -> Option<Box<[u8]>> {
+        return PingManager::process_ping(ping_payload);
+    }
+
+    pub fn should_send_ping(&self) -> bool {
+        return self.ping_manager.should_send_ping();
+    }
+
+    pub fn get_ping_payload(&mut self) -> Box<[u8]> {
+        return self.ping_manager.get_ping_payload();
+    }
+
+    pub fn process_pong(&mut self, pong_payload: &[u8]) {
+        self.ping_manager.process_pong(pong_payload);
+    }
+
+    pub fn get_rtt(&self) -> Option<f32> {
+        return self.ping_manager.get_rtt();
+    }
+
+    pub fn traffic_stats(&self) -> TrafficStats {
+        return self.connection.traffic_stats();
     }
 
     pub fn get_last_received_tick(&self) -> u16 {
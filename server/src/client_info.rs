@@ -0,0 +1,18 @@
+use std::net::SocketAddr;
+
+use naia_shared::Instant;
+
+/// A snapshot of the state of a Client's connection, for monitoring
+#[derive(Clone, Debug)]
+pub struct ClientInfo {
+    /// The address the Client is connected from
+    pub address: SocketAddr,
+    /// When the connection with the Client was established
+    pub connected_at: Instant,
+    /// When a packet was last received from the Client
+    pub last_heard: Instant,
+    /// The average Round Trip Time to the Client, in milliseconds, measured
+    /// by the Server's own Pings. None until the first Pong has arrived, or
+    /// if no Pong has arrived within the configured pong timeout
+    pub rtt: Option<f32>,
+}
@@ -1,8 +1,10 @@
 use std::collections::HashMap;
 
+use log::warn;
+
 use naia_shared::{
     sequence_greater_than, ActorType, EventType, LocalActorKey, Manifest, PacketReader,
-    SequenceBuffer,
+    PacketReaderExt, SequenceBuffer,
 };
 
 const COMMAND_BUFFER_MAX_SIZE: u16 = 64;
@@ -46,11 +48,23 @@ impl<T: EventType> CommandReceiver<T> {
         reader: &mut PacketReader,
         manifest: &Manifest<T, U>,
     ) {
-        let command_count = reader.read_u8();
+        let command_count = match reader.try_read_u8() {
+            Some(command_count) => command_count,
+            None => {
+                warn!("discarding truncated command data");
+                return;
+            }
+        };
         for _x in 0..command_count {
-            let local_actor_key: LocalActorKey = reader.read_u16();
-            let naia_id: u16 = reader.read_u16();
-            let past_commands_number: u8 = reader.read_u8();
+            let (local_actor_key, naia_id, past_commands_number) =
+                match CommandReceiver::<T>::read_command_header(reader) {
+                    Some(header) => header,
+                    None => {
+                        warn!("discarding truncated command data");
+                        reader.skip_remaining();
+                        return;
+                    }
+                };
 
             match manifest.create_event(naia_id, reader) {
                 Some(new_command) => {
@@ -66,7 +80,14 @@ impl<T: EventType> CommandReceiver<T> {
             }
 
             for _y in 0..past_commands_number {
-                let tick_diff = reader.read_u8();
+                let tick_diff = match reader.try_read_u8() {
+                    Some(tick_diff) => tick_diff,
+                    None => {
+                        warn!("discarding truncated command data");
+                        reader.skip_remaining();
+                        return;
+                    }
+                };
                 let past_tick = client_tick.wrapping_sub(tick_diff.into());
 
                 match manifest.create_event(naia_id, reader) {
@@ -88,4 +109,13 @@ impl<T: EventType> CommandReceiver<T> {
             }
         }
     }
+
+    // Reads a Command's Pawn key, naia_id & number of past Commands sent
+    // along with it, or None if the packet ends first
+    fn read_command_header(reader: &mut PacketReader) -> Option<(LocalActorKey, u16, u8)> {
+        let local_actor_key: LocalActorKey = reader.try_read_u16()?;
+        let naia_id: u16 = reader.try_read_u16()?;
+        let past_commands_number: u8 = reader.try_read_u8()?;
+        return Some((local_actor_key, naia_id, past_commands_number));
+    }
 }
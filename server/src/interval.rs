@@ -31,6 +31,12 @@ impl Interval {
             timer: Timer::after(duration),
         }
     }
+
+    /// Makes the next event due immediately, rather than after the duration
+    #[cfg(test)]
+    pub fn ring_manual(&mut self) {
+        self.timer.set_at(Instant::now());
+    }
 }
 
 impl Stream for Interval {
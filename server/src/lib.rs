@@ -25,24 +25,35 @@ compile_error!("Naia Server can only use UDP or WebRTC, you must pick one");
 compile_error!("Naia Server requires either the 'use-udp' or 'use-webrtc' feature to be enabled, you must pick one.");
 
 pub use naia_shared::{
-    find_my_ip_address, Actor, ActorType, LinkConditionerConfig, Random, SharedConfig,
+    find_my_ip_address, find_my_ip_address_towards, find_my_ipv6_address, Actor, ActorType,
+    ConnectionConfig, DisconnectPolicy, LinkConditionerConfig, OverflowPolicy, PacketTraffic,
+    Random, SharedConfig, TrafficStats, PROTOCOL_VERSION,
 };
 
 mod actors;
+mod bandwidth_limiter;
+mod challenge_rate_limiter;
 mod client_connection;
+mod client_info;
 mod command_receiver;
 mod error;
 mod interval;
+#[cfg(any(test, feature = "testing"))]
+mod mock_server_socket;
 mod naia_server;
-mod ping_manager;
+mod packet_sender;
 mod room;
 mod server_config;
 mod server_event;
 mod server_packet_writer;
 mod server_tick_manager;
+#[cfg(any(test, feature = "sync"))]
+mod sync_server_socket;
+mod timestamp_validator;
 mod user;
 
 pub use actors::actor_key::actor_key::ActorKey;
+pub use client_info::ClientInfo;
 pub use naia_server::{NaiaServer, ServerAddresses};
 pub use room::room_key::RoomKey;
 pub use server_config::ServerConfig;
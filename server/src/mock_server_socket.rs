@@ -0,0 +1,65 @@
+use async_trait::async_trait;
+use futures_channel::mpsc;
+
+use naia_server_socket::{
+    LinkConditionerConfig, MessageSender, NaiaServerSocketError, Packet, ServerSocketTrait,
+};
+use naia_shared::MockLink;
+
+// The most packets the Server can send between two calls to receive()
+const MOCK_SEND_BUFFER_SIZE: usize = 1024;
+
+/// A Server socket which exchanges packets with a single Client through a
+/// MockLink instead of the network
+#[derive(Debug)]
+pub struct MockServerSocket {
+    link: MockLink,
+    sender: mpsc::Sender<Packet>,
+    outgoing: mpsc::Receiver<Packet>,
+}
+
+impl MockServerSocket {
+    /// Creates a new MockServerSocket, connected to the given link
+    pub fn new(link: MockLink) -> Self {
+        let (sender, outgoing) = mpsc::channel(MOCK_SEND_BUFFER_SIZE);
+        MockServerSocket {
+            link,
+            sender,
+            outgoing,
+        }
+    }
+}
+
+#[async_trait]
+impl ServerSocketTrait for MockServerSocket {
+    async fn receive(&mut self) -> Result<Packet, NaiaServerSocketError> {
+        // pass along everything the Server has sent since the last call
+        while let Ok(packet) = self.outgoing.try_recv() {
+            self.link.send_to_client(packet.payload().into());
+        }
+
+        match self.link.receive_at_server() {
+            Some(payload) => {
+                return Ok(Packet::new_raw(self.link.client_address(), payload));
+            }
+            None => {
+                // nothing arrives until the next call, so let the tick
+                // timer win the race instead
+                return futures_util::future::pending().await;
+            }
+        }
+    }
+
+    fn get_sender(&mut self) -> MessageSender {
+        return MessageSender::new(self.sender.clone());
+    }
+
+    // Only packets on their way to the Client are conditioned, by the
+    // Client's own LinkConditioner
+    fn with_link_conditioner(
+        self: Box<Self>,
+        _: &LinkConditionerConfig,
+    ) -> Box<dyn ServerSocketTrait> {
+        return self;
+    }
+}
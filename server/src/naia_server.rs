@@ -4,6 +4,7 @@ use std::{
     net::SocketAddr,
     panic,
     rc::Rc,
+    time::Duration,
 };
 
 use byteorder::{BigEndian, WriteBytesExt};
@@ -12,52 +13,90 @@ use log::info;
 use ring::{hmac, rand};
 use slotmap::DenseSlotMap;
 
-use naia_server_socket::{
-    MessageSender, NaiaServerSocketError, Packet, ServerSocket, ServerSocketTrait,
-};
+use naia_server_socket::{NaiaServerSocketError, Packet, ServerSocket, ServerSocketTrait};
 pub use naia_shared::{
-    wrapping_diff, Actor, ActorMutator, ActorType, Connection, ConnectionConfig, Event, EventType,
-    HostTickManager, Instant, ManagerType, Manifest, PacketReader, PacketType, SharedConfig, Timer,
-    Timestamp,
+    wrapping_diff, Actor, ActorMutator, ActorType, Connection, ConnectionConfig,
+    ConnectionConfigBuilder, Event, EventClone, EventType, HostTickManager, Instant, ManagerType,
+    Manifest, PacketReader, PacketType, SharedConfig, Timer, Timestamp,
 };
 
+#[cfg(any(test, feature = "testing"))]
+use super::mock_server_socket::MockServerSocket;
+#[cfg(any(test, feature = "sync"))]
+use super::sync_server_socket::SyncServerSocket;
 use super::{
     actors::{
-        actor_key::actor_key::ActorKey, mut_handler::MutHandler,
+        actor_history::ActorHistory, actor_key::actor_key::ActorKey, mut_handler::MutHandler,
         server_actor_mutator::ServerActorMutator,
     },
+    challenge_rate_limiter::ChallengeRateLimiter,
     client_connection::ClientConnection,
+    client_info::ClientInfo,
     error::NaiaServerError,
     interval::Interval,
+    packet_sender::PacketSender,
     room::{room_key::RoomKey, Room},
     server_config::ServerConfig,
     server_event::ServerEvent,
     server_tick_manager::ServerTickManager,
+    timestamp_validator::{ConnectDecision, ConnectRequestStatus, TimestampValidator},
     user::{user_key::UserKey, User},
 };
-use naia_shared::StandardHeader;
+#[cfg(any(test, feature = "testing"))]
+use naia_shared::MockLink;
+use naia_shared::{
+    FragmentManager, PacketReaderExt, RawMessageError, StandardHeader, TrafficStats,
+    COMPRESSION_FLAG, INTEGRITY_CHECK_FLAG, PROTOCOL_VERSION,
+};
+
+// a connect request begins with an 8 byte timestamp, and its 32 byte digest
+const CONNECT_REQUEST_MIN_LENGTH: usize = 40;
+// a reconnect message begins with a 16 byte session token
+const SESSION_TOKEN_LENGTH: usize = 16;
 
 /// A server that uses either UDP or WebRTC communication to send/receive events
 /// to/from connected clients, and syncs registered actors to clients to whom
 /// those actors are in-scope
 pub struct NaiaServer<T: EventType, U: ActorType> {
     connection_config: ConnectionConfig,
+    max_send_bytes_per_second: Option<u32>,
+    reconnect_grace_period: Option<Duration>,
+    max_clients: Option<usize>,
+    max_actor_updates_per_tick: Option<usize>,
+    server_full_reason: Option<T>,
     manifest: Manifest<T, U>,
     socket: Box<dyn ServerSocketTrait>,
-    sender: MessageSender,
+    sender: PacketSender,
     global_actor_store: DenseSlotMap<ActorKey, U>,
+    actor_history: ActorHistory<U>,
+    actor_spawn_events: HashMap<ActorKey, Rc<Box<[u8]>>>,
+    actor_prediction_tokens: HashMap<ActorKey, (UserKey, u16)>,
+    actor_authorities: HashMap<ActorKey, UserKey>,
     scope_actor_func: Option<Rc<Box<dyn Fn(&RoomKey, &UserKey, &ActorKey, U) -> bool>>>,
-    auth_func: Option<Rc<Box<dyn Fn(&UserKey, &T) -> bool>>>,
+    auth_func: Option<Rc<Box<dyn Fn(&UserKey, &T) -> Result<(), Option<T>>>>>,
+    event_filter: Option<Rc<Box<dyn Fn(&UserKey, &T) -> bool>>>,
+    // interval in ticks, ticks since the Event was last queued, and the
+    // closure which creates it
+    periodic_event: Option<(u16, u16, Rc<Box<dyn Fn(u16) -> Box<dyn Event<T>>>>)>,
     mut_handler: Rc<RefCell<MutHandler>>,
     users: DenseSlotMap<UserKey, User>,
     rooms: DenseSlotMap<RoomKey, Room>,
     address_to_user_key_map: HashMap<SocketAddr, UserKey>,
     client_connections: HashMap<UserKey, ClientConnection<T, U>>,
     outstanding_disconnects: VecDeque<UserKey>,
+    session_tokens: HashMap<[u8; 16], UserKey>,
+    // the token each User last reconnected with, along with the address it
+    // reconnected from, so that a Client which missed the response can retry
+    used_session_tokens: HashMap<[u8; 16], (UserKey, SocketAddr)>,
     heartbeat_timer: Timer,
+    ping_timer: Timer,
     connection_hash_key: hmac::Key,
+    timestamp_validator: TimestampValidator,
+    challenge_rate_limiter: ChallengeRateLimiter,
     tick_manager: ServerTickManager,
+    epoch: u32,
     tick_timer: Interval,
+    shared_config: SharedConfig,
 }
 
 /// A collection of IP addresses describing which IP to listen on for new
@@ -91,32 +130,121 @@ impl<T: EventType, U: ActorType> NaiaServer<T, U> {
         manifest: Manifest<T, U>,
         server_config: Option<ServerConfig>,
         shared_config: SharedConfig,
+    ) -> Self {
+        let server_socket = ServerSocket::listen(
+            addresses.session_listen_addr,
+            addresses.webrtc_listen_addr,
+            addresses.public_webrtc_addr,
+        )
+        .await;
+
+        return NaiaServer::new_with_socket(manifest, server_config, shared_config, server_socket);
+    }
+
+    /// Create a new Server which exchanges packets with a single Client
+    /// through an in-memory MockLink instead of the network, for use in tests
+    #[cfg(any(test, feature = "testing"))]
+    pub fn new_mock(
+        link: &MockLink,
+        manifest: Manifest<T, U>,
+        server_config: Option<ServerConfig>,
+        shared_config: SharedConfig,
+    ) -> Self {
+        return NaiaServer::new_with_socket(
+            manifest,
+            server_config,
+            shared_config,
+            Box::new(MockServerSocket::new(link.clone())),
+        );
+    }
+
+    /// Create a new Server listening for UDP packets at the given address,
+    /// which runs without an async runtime. Drive it from a synchronous game
+    /// loop with try_receive() & try_send_all_updates(). Packets are sent
+    /// as soon as they are written, without awaiting
+    #[cfg(any(test, feature = "sync"))]
+    pub fn new_sync(
+        listen_address: SocketAddr,
+        manifest: Manifest<T, U>,
+        server_config: Option<ServerConfig>,
+        shared_config: SharedConfig,
+    ) -> Self {
+        let server_socket = SyncServerSocket::listen(listen_address);
+        let packet_sender = server_socket.packet_sender();
+        let mut server = NaiaServer::new_with_socket(
+            manifest,
+            server_config,
+            shared_config,
+            Box::new(server_socket),
+        );
+        // write packets straight into the socket, so that sending never
+        // waits on the next call to try_receive()
+        server.sender = packet_sender;
+        return server;
+    }
+
+    fn new_with_socket(
+        manifest: Manifest<T, U>,
+        server_config: Option<ServerConfig>,
+        shared_config: SharedConfig,
+        mut server_socket: Box<dyn ServerSocketTrait>,
     ) -> Self {
         let server_config = match server_config {
             Some(config) => config,
             None => ServerConfig::default(),
         };
 
-        let connection_config = ConnectionConfig::new(
-            server_config.disconnection_timeout_duration,
-            server_config.heartbeat_interval,
-            server_config.ping_interval,
-            server_config.rtt_sample_size,
-        );
+        #[allow(deprecated)]
+        let disconnect_policy = server_config
+            .disconnect_policy
+            .or_disconnection_timeout(server_config.disconnection_timeout_duration);
+        let mut builder = ConnectionConfigBuilder::new()
+            .disconnect_policy(disconnect_policy)
+            .heartbeat_interval(server_config.heartbeat_interval)
+            .ping_interval(server_config.ping_interval)
+            .rtt_sample_size(server_config.rtt_sample_size)
+            .max_packet_size(server_config.max_packet_size)
+            .fragment_timeout(server_config.fragment_timeout_duration)
+            .packet_loss_window_size(server_config.packet_loss_window_size)
+            .max_payload_size(server_config.max_payload_size)
+            .pong_timeout_intervals(server_config.pong_timeout_intervals)
+            .event_capacity(server_config.event_capacity)
+            .integrity_check(server_config.integrity_check);
+        if let Some(max_retransmits) = server_config.max_retransmits {
+            builder = builder.max_retransmits(max_retransmits);
+        }
+        if let Some(max_events_per_packet) = server_config.max_events_per_packet {
+            builder = builder.max_events_per_packet(max_events_per_packet);
+        }
+        if let Some(coalesce_delay) = server_config.coalesce_delay {
+            builder = builder.coalesce_delay(coalesce_delay);
+        }
+        if let Some(max_outgoing_queue_len) = server_config.max_outgoing_queue_len {
+            builder =
+                builder.outgoing_queue_limit(max_outgoing_queue_len, server_config.overflow_policy);
+        }
+        let connection_config = match builder.build() {
+            Ok(connection_config) => connection_config,
+            Err(error) => panic!("invalid ServerConfig: {}", error),
+        };
+        if server_config.max_actor_updates_per_tick == Some(0) {
+            panic!("invalid ServerConfig: max_actor_updates_per_tick must be at least 1");
+        }
 
-        let mut server_socket = ServerSocket::listen(
-            addresses.session_listen_addr,
-            addresses.webrtc_listen_addr,
-            addresses.public_webrtc_addr,
-        )
-        .await;
         if let Some(config) = &shared_config.link_condition_config {
             server_socket = server_socket.with_link_conditioner(config);
         }
 
-        let sender = server_socket.get_sender();
-        let clients_map = HashMap::new();
+        let sender = PacketSender::Channel(server_socket.get_sender());
+        let clients_map = HashMap::with_capacity(server_config.client_capacity);
+
+        let epoch = server_config.epoch.unwrap_or_else(|| {
+            let mut epoch_bytes = [0; 4];
+            rand::SecureRandom::fill(&rand::SystemRandom::new(), &mut epoch_bytes).unwrap();
+            u32::from_be_bytes(epoch_bytes)
+        });
         let heartbeat_timer = Timer::new(connection_config.heartbeat_interval);
+        let ping_timer = Timer::new(connection_config.ping_interval);
 
         let connection_hash_key =
             hmac::Key::generate(hmac::HMAC_SHA256, &rand::SystemRandom::new()).unwrap();
@@ -124,21 +252,44 @@ impl<T: EventType, U: ActorType> NaiaServer<T, U> {
         NaiaServer {
             manifest,
             global_actor_store: DenseSlotMap::with_key(),
+            actor_history: ActorHistory::new(server_config.actor_history_length),
+            actor_spawn_events: HashMap::new(),
+            actor_prediction_tokens: HashMap::new(),
+            actor_authorities: HashMap::new(),
             scope_actor_func: None,
             auth_func: None,
+            event_filter: None,
+            periodic_event: None,
             mut_handler: MutHandler::new(),
             socket: server_socket,
             sender,
             connection_config,
-            users: DenseSlotMap::with_key(),
+            max_send_bytes_per_second: server_config.max_send_bytes_per_second,
+            reconnect_grace_period: server_config.reconnect_grace_period,
+            max_clients: server_config.max_clients,
+            max_actor_updates_per_tick: server_config.max_actor_updates_per_tick,
+            server_full_reason: None,
+            users: DenseSlotMap::with_capacity_and_key(server_config.client_capacity),
             rooms: DenseSlotMap::with_key(),
             connection_hash_key,
+            timestamp_validator: TimestampValidator::new(server_config.handshake_timestamp_window),
+            challenge_rate_limiter: ChallengeRateLimiter::new(
+                server_config.challenge_requests_per_second,
+            ),
             client_connections: clients_map,
-            address_to_user_key_map: HashMap::new(),
+            address_to_user_key_map: HashMap::with_capacity(server_config.client_capacity),
             outstanding_disconnects: VecDeque::new(),
+            session_tokens: HashMap::new(),
+            used_session_tokens: HashMap::new(),
             heartbeat_timer,
-            tick_manager: ServerTickManager::new(shared_config.tick_interval),
+            ping_timer,
+            tick_manager: ServerTickManager::new(
+                shared_config.tick_interval,
+                server_config.start_tick,
+            ),
+            epoch,
             tick_timer: Interval::new(shared_config.tick_interval),
+            shared_config,
         }
     }
 
@@ -152,7 +303,7 @@ impl<T: EventType, U: ActorType> NaiaServer<T, U> {
 
                 for (user_key, connection) in self.client_connections.iter_mut() {
                     if let Some(user) = self.users.get(*user_key) {
-                        if connection.should_drop() {
+                        if connection.should_drop(self.reconnect_grace_period) {
                             self.outstanding_disconnects.push_back(*user_key);
                         } else {
                             if connection.should_send_heartbeat() {
@@ -175,6 +326,37 @@ impl<T: EventType, U: ActorType> NaiaServer<T, U> {
                 }
             }
 
+            // pings, to measure each Client's RTT
+            if self.ping_timer.ringing() {
+                self.ping_timer.reset();
+
+                for (_, connection) in self.client_connections.iter_mut() {
+                    if connection.should_send_ping() {
+                        let ping_payload = connection.get_ping_payload();
+                        let payload = connection.process_outgoing_header(
+                            self.tick_manager.get_tick(),
+                            connection.get_last_received_tick(),
+                            PacketType::Ping,
+                            &ping_payload,
+                        );
+                        // a lost Ping only delays the next RTT sample
+                        if let Err(error) = self
+                            .sender
+                            .send(Packet::new_raw(connection.get_address(), payload))
+                            .await
+                        {
+                            warn!(
+                                "failed to send ping to {}: {}",
+                                connection.get_address(),
+                                error
+                            );
+                            continue;
+                        }
+                        connection.mark_sent();
+                    }
+                }
+            }
+
             // timeouts
             if let Some(user_key) = self.outstanding_disconnects.pop_front() {
                 for (_, room) in self.rooms.iter_mut() {
@@ -183,6 +365,9 @@ impl<T: EventType, U: ActorType> NaiaServer<T, U> {
 
                 let address = self.users.get(user_key).unwrap().address;
                 self.address_to_user_key_map.remove(&address);
+                self.session_tokens.retain(|_, key| *key != user_key);
+                self.used_session_tokens
+                    .retain(|_, (key, _)| *key != user_key);
                 let user_clone = self.users.get(user_key).unwrap().clone();
                 self.users.remove(user_key);
                 self.client_connections.remove(&user_key);
@@ -193,16 +378,30 @@ impl<T: EventType, U: ActorType> NaiaServer<T, U> {
             // possible this current technique unfairly favors the 1st users in
             // self.client_connections
             for (user_key, connection) in self.client_connections.iter_mut() {
-                //receive commands from anyone
-                if let Some((pawn_key, command)) =
+                //receive commands from anyone with authority over the Actor
+                while let Some((pawn_key, command)) =
                     connection.get_incoming_command(self.tick_manager.get_tick())
                 {
+                    if let Some(authority) = self.actor_authorities.get(&pawn_key) {
+                        if authority != user_key {
+                            continue;
+                        }
+                    }
                     return Ok(ServerEvent::Command(*user_key, pawn_key, command));
                 }
                 //receive events from anyone
-                if let Some(event) = connection.get_incoming_event() {
+                while let Some(event) = connection.get_incoming_event() {
+                    if let Some(event_filter) = &self.event_filter {
+                        if !(event_filter.as_ref().as_ref())(user_key, &event) {
+                            continue;
+                        }
+                    }
                     return Ok(ServerEvent::Event(*user_key, event));
                 }
+                //receive raw messages from anyone
+                if let Some((channel, data)) = connection.get_incoming_raw() {
+                    return Ok(ServerEvent::Raw(*user_key, channel, data.into_vec()));
+                }
             }
 
             //receive socket events
@@ -233,20 +432,75 @@ impl<T: EventType, U: ActorType> NaiaServer<T, U> {
                     match result {
                         Ok(packet) => {
                             let address = packet.address();
+                            let mut reassembled_packet: Option<Box<[u8]>> = None;
+                            let is_fragment = FragmentManager::is_fragment(packet.payload());
                             if let Some(user_key) = self.address_to_user_key_map.get(&address) {
                                 match self.client_connections.get_mut(&user_key) {
                                     Some(connection) => {
                                         connection.mark_heard();
+                                        if is_fragment {
+                                            reassembled_packet = connection
+                                                .process_incoming_fragment(packet.payload());
+                                        }
                                     }
                                     None => {} //not yet established connection
                                 }
                             }
 
-                            let (header, payload) = StandardHeader::read(packet.payload());
+                            let packet_payload: &[u8] = match &reassembled_packet {
+                                Some(reassembled_payload) => reassembled_payload,
+                                None => {
+                                    if is_fragment {
+                                        // wait for the rest of the fragments
+                                        continue;
+                                    }
+                                    packet.payload()
+                                }
+                            };
+
+                            let (header, payload) = match StandardHeader::read(packet_payload) {
+                                Some(packet) => packet,
+                                None => {
+                                    warn!("discarding corrupted packet from {}", address);
+                                    continue;
+                                }
+                            };
+
+                            if let Some(user_key) = self.address_to_user_key_map.get(&address) {
+                                if let Some(connection) = self.client_connections.get(user_key) {
+                                    if !connection.accepts_header(&header) {
+                                        warn!("discarding unchecksummed packet from {}", address);
+                                        continue;
+                                    }
+                                }
+                            }
 
                             match header.packet_type() {
                                 PacketType::ClientChallengeRequest => {
+                                    if !self.challenge_rate_limiter.allow(&address.ip()) {
+                                        continue;
+                                    }
+
                                     let mut reader = PacketReader::new(&payload);
+                                    let mut payload_bytes = Vec::new();
+
+                                    // write protocol version
+                                    payload_bytes
+                                        .write_u16::<BigEndian>(PROTOCOL_VERSION)
+                                        .unwrap();
+
+                                    // only the version is sent back to a Client which would
+                                    // misparse the rest, so that it can report the mismatch
+                                    if reader.try_read_u16() != Some(PROTOCOL_VERSION) {
+                                        NaiaServer::<T, U>::internal_send_connectionless(
+                                            &mut self.sender,
+                                            PacketType::ServerChallengeResponse,
+                                            Packet::new(address, payload_bytes),
+                                        )
+                                        .await;
+                                        continue;
+                                    }
+
                                     let timestamp = Timestamp::read(&mut reader);
 
                                     let mut timestamp_bytes = Vec::new();
@@ -254,12 +508,14 @@ impl<T: EventType, U: ActorType> NaiaServer<T, U> {
                                     let timestamp_hash: hmac::Tag =
                                         hmac::sign(&self.connection_hash_key, &timestamp_bytes);
 
-                                    let mut payload_bytes = Vec::new();
                                     // write current tick
                                     payload_bytes
                                         .write_u16::<BigEndian>(self.tick_manager.get_tick())
                                         .unwrap();
 
+                                    // write tick interval
+                                    self.shared_config.write_tick_interval(&mut payload_bytes);
+
                                     //write timestamp
                                     payload_bytes.append(&mut timestamp_bytes);
 
@@ -269,6 +525,9 @@ impl<T: EventType, U: ActorType> NaiaServer<T, U> {
                                         payload_bytes.push(*hash_byte);
                                     }
 
+                                    // write epoch
+                                    payload_bytes.write_u32::<BigEndian>(self.epoch).unwrap();
+
                                     NaiaServer::<T, U>::internal_send_connectionless(
                                         &mut self.sender,
                                         PacketType::ServerChallengeResponse,
@@ -279,6 +538,10 @@ impl<T: EventType, U: ActorType> NaiaServer<T, U> {
                                     continue;
                                 }
                                 PacketType::ClientConnectRequest => {
+                                    // a timestamp & its digest must be present
+                                    if payload.len() < CONNECT_REQUEST_MIN_LENGTH {
+                                        continue;
+                                    }
                                     let mut reader = PacketReader::new(&payload);
                                     let timestamp = Timestamp::read(&mut reader);
 
@@ -288,11 +551,13 @@ impl<T: EventType, U: ActorType> NaiaServer<T, U> {
                                         if self.client_connections.contains_key(user_key) {
                                             let user = self.users.get(*user_key).unwrap();
                                             if user.timestamp == timestamp {
+                                                // the Client didn't get the accept, so resend
+                                                // it. As for a new Client, the request's
+                                                // connectionless header isn't recorded
                                                 let mut connection = self
                                                     .client_connections
                                                     .get_mut(user_key)
                                                     .unwrap();
-                                                connection.process_incoming_header(&header);
                                                 NaiaServer::<T, U>::send_connect_accept_message(
                                                     &mut connection,
                                                     &mut self.sender,
@@ -312,10 +577,10 @@ impl<T: EventType, U: ActorType> NaiaServer<T, U> {
                                         // server instance
                                         let mut timestamp_bytes: Vec<u8> = Vec::new();
                                         timestamp.write(&mut timestamp_bytes);
-                                        let mut digest_bytes: Vec<u8> = Vec::new();
-                                        for _ in 0..32 {
-                                            digest_bytes.push(reader.read_u8());
-                                        }
+                                        let digest_bytes = match reader.try_read_bytes(32) {
+                                            Some(digest_bytes) => digest_bytes,
+                                            None => continue,
+                                        };
                                         if !hmac::verify(
                                             &self.connection_hash_key,
                                             &timestamp_bytes,
@@ -326,45 +591,158 @@ impl<T: EventType, U: ActorType> NaiaServer<T, U> {
                                             continue;
                                         }
 
+                                        // Refuse stale connect requests, and answer a resent
+                                        // one as before, in case the answer was lost
+                                        match self
+                                            .timestamp_validator
+                                            .validate(&address, &timestamp, &payload)
+                                        {
+                                            ConnectRequestStatus::Stale => {
+                                                continue;
+                                            }
+                                            ConnectRequestStatus::Decided(
+                                                ConnectDecision::Rejected(payload_bytes),
+                                            ) => {
+                                                NaiaServer::<T, U>::internal_send_connectionless(
+                                                    &mut self.sender,
+                                                    PacketType::ServerRejectConnect,
+                                                    Packet::new(address, payload_bytes.to_vec()),
+                                                )
+                                                .await;
+                                                continue;
+                                            }
+                                            // the connection has since ended, so this is a
+                                            // replay
+                                            ConnectRequestStatus::Decided(
+                                                ConnectDecision::Accepted,
+                                            ) => {
+                                                continue;
+                                            }
+                                            ConnectRequestStatus::New => {}
+                                        }
+
+                                        // Turn away new Clients once the Server is full
+                                        if let Some(max_clients) = self.max_clients {
+                                            if self.client_connections.len() >= max_clients {
+                                                let payload_bytes =
+                                                    NaiaServer::<T, U>::write_reason_payload(
+                                                        &self.manifest,
+                                                        &self.server_full_reason,
+                                                    );
+                                                self.timestamp_validator.decide(
+                                                    &address,
+                                                    &payload,
+                                                    ConnectDecision::Rejected(
+                                                        payload_bytes.clone().into_boxed_slice(),
+                                                    ),
+                                                );
+                                                NaiaServer::<T, U>::internal_send_connectionless(
+                                                    &mut self.sender,
+                                                    PacketType::ServerRejectConnect,
+                                                    Packet::new(address, payload_bytes),
+                                                )
+                                                .await;
+                                                continue;
+                                            }
+                                        }
+
+                                        // Compress & checksum packets if the Client is able
+                                        // to as well
+                                        let client_flags = reader.try_read_u8().unwrap_or_default();
+                                        let mut client_has_compressor = false;
+                                        if client_flags & COMPRESSION_FLAG != 0 {
+                                            // only compress with the same algorithm
+                                            let client_compressor_id = reader.try_read_u8();
+                                            if let Some(compressor) = &self.shared_config.compressor
+                                            {
+                                                client_has_compressor =
+                                                    client_compressor_id == Some(compressor.id());
+                                            }
+                                        }
+                                        let client_checks_integrity =
+                                            client_flags & INTEGRITY_CHECK_FLAG != 0;
+
                                         let user = User::new(address, timestamp);
                                         let user_key = self.users.insert(user);
 
-                                        // Call auth function if there is one
+                                        // Call auth function if there is one, with the auth
+                                        // event the Client sent
+                                        let mut auth_event = None;
                                         if let Some(auth_func) = &self.auth_func {
-                                            let naia_id = reader.read_u16();
-
-                                            match self.manifest.create_event(naia_id, &mut reader) {
-                                                Some(new_actor) => {
-                                                    if !(auth_func.as_ref().as_ref())(
-                                                        &user_key, &new_actor,
-                                                    ) {
-                                                        self.users.remove(user_key);
-                                                        continue;
-                                                    }
-                                                }
-                                                _ => {
-                                                    self.users.remove(user_key);
-                                                    continue;
-                                                }
+                                            if let Some(naia_id) = reader.try_read_u16() {
+                                                auth_event = self
+                                                    .manifest
+                                                    .create_event(naia_id, &mut reader);
+                                            }
+                                            let rejection = match &auth_event {
+                                                Some(auth_event) => (auth_func.as_ref().as_ref())(
+                                                    &user_key, auth_event,
+                                                )
+                                                .err(),
+                                                None => Some(None),
+                                            };
+
+                                            if let Some(reason) = rejection {
+                                                self.users.remove(user_key);
+                                                let payload_bytes =
+                                                    NaiaServer::<T, U>::write_reason_payload(
+                                                        &self.manifest,
+                                                        &reason,
+                                                    );
+                                                self.timestamp_validator.decide(
+                                                    &address,
+                                                    &payload,
+                                                    ConnectDecision::Rejected(
+                                                        payload_bytes.clone().into_boxed_slice(),
+                                                    ),
+                                                );
+                                                NaiaServer::<T, U>::internal_send_connectionless(
+                                                    &mut self.sender,
+                                                    PacketType::ServerRejectConnect,
+                                                    Packet::new(address, payload_bytes),
+                                                )
+                                                .await;
+                                                continue;
                                             }
                                         }
 
                                         self.address_to_user_key_map.insert(address, user_key);
+                                        self.timestamp_validator.decide(
+                                            &address,
+                                            &payload,
+                                            ConnectDecision::Accepted,
+                                        );
 
                                         // Success! Create new connection
                                         let mut new_connection = ClientConnection::new(
                                             address,
                                             Some(&self.mut_handler),
                                             &self.connection_config,
+                                            self.max_send_bytes_per_second,
                                         );
-                                        new_connection.process_incoming_header(&header);
+                                        if client_has_compressor {
+                                            new_connection.set_compressor(
+                                                self.shared_config.compressor.clone(),
+                                            );
+                                        }
+                                        if client_checks_integrity
+                                            && self.connection_config.integrity_check
+                                        {
+                                            new_connection.set_integrity_check(true);
+                                        }
+                                        // the connect request is connectionless, so its
+                                        // header has no packet index to acknowledge. Recording
+                                        // it would discard the Client's first Data packet as a
+                                        // duplicate
+                                        let session_token = self.new_session_token(user_key);
+                                        new_connection.set_session_token(session_token);
                                         NaiaServer::<T, U>::send_connect_accept_message(
                                             &mut new_connection,
                                             &mut self.sender,
                                         )
                                         .await;
                                         self.client_connections.insert(user_key, new_connection);
-                                        return Ok(ServerEvent::Connection(user_key));
+                                        return Ok(ServerEvent::Connection(user_key, auth_event));
                                     }
                                 }
                                 PacketType::Data => {
@@ -373,13 +751,26 @@ impl<T: EventType, U: ActorType> NaiaServer<T, U> {
                                     {
                                         match self.client_connections.get_mut(user_key) {
                                             Some(connection) => {
-                                                connection.process_incoming_header(&header);
-                                                connection.process_incoming_data(
-                                                    self.tick_manager.get_tick(),
-                                                    header.host_tick(),
-                                                    &self.manifest,
-                                                    &payload,
+                                                // don't apply a duplicated Data packet again
+                                                if connection
+                                                    .is_duplicate(header.local_packet_index())
+                                                {
+                                                    continue;
+                                                }
+                                                connection.process_incoming_header(
+                                                    &header,
+                                                    payload.len(),
                                                 );
+                                                if let Some(payload) =
+                                                    connection.decompress_payload(&header, payload)
+                                                {
+                                                    connection.process_incoming_data(
+                                                        self.tick_manager.get_tick(),
+                                                        header.host_tick(),
+                                                        &self.manifest,
+                                                        &payload,
+                                                    );
+                                                }
                                                 continue;
                                             }
                                             None => {
@@ -399,7 +790,10 @@ impl<T: EventType, U: ActorType> NaiaServer<T, U> {
                                             Some(connection) => {
                                                 // Still need to do this so that proper notify
                                                 // events fire based on the heartbeat header
-                                                connection.process_incoming_header(&header);
+                                                connection.process_incoming_header(
+                                                    &header,
+                                                    payload.len(),
+                                                );
                                                 continue;
                                             }
                                             None => {
@@ -411,15 +805,107 @@ impl<T: EventType, U: ActorType> NaiaServer<T, U> {
                                         }
                                     }
                                 }
+                                PacketType::Disconnect => {
+                                    if let Some(user_key) =
+                                        self.address_to_user_key_map.get(&address)
+                                    {
+                                        if self.client_connections.contains_key(user_key)
+                                            && !self.outstanding_disconnects.contains(user_key)
+                                        {
+                                            self.outstanding_disconnects.push_back(*user_key);
+                                        }
+                                        continue;
+                                    }
+                                }
+                                PacketType::Pong => {
+                                    if let Some(user_key) =
+                                        self.address_to_user_key_map.get(&address)
+                                    {
+                                        if let Some(connection) =
+                                            self.client_connections.get_mut(user_key)
+                                        {
+                                            connection
+                                                .process_incoming_header(&header, payload.len());
+                                            connection.process_pong(&payload);
+                                            continue;
+                                        }
+                                    }
+                                }
+                                PacketType::Reconnect => {
+                                    if payload.len() < SESSION_TOKEN_LENGTH {
+                                        continue;
+                                    }
+                                    let (session_token, digest) =
+                                        payload.split_at(SESSION_TOKEN_LENGTH);
+                                    if self.session_user(session_token, &address).is_none() {
+                                        continue;
+                                    }
+                                    let expected_digest =
+                                        self.reconnect_digest(session_token, &address);
+
+                                    // challenge the Client to prove that it can receive
+                                    // at the address it is reconnecting from
+                                    if digest != expected_digest.as_ref() {
+                                        NaiaServer::<T, U>::internal_send_connectionless(
+                                            &mut self.sender,
+                                            PacketType::ServerReconnectChallenge,
+                                            Packet::new(address, expected_digest.as_ref().to_vec()),
+                                        )
+                                        .await;
+                                        continue;
+                                    }
+
+                                    if let Some(user_key) =
+                                        self.reconnect_user(session_token, address)
+                                    {
+                                        if let Some(connection) =
+                                            self.client_connections.get_mut(&user_key)
+                                        {
+                                            // let the Client know that it has been heard,
+                                            // which token to reconnect with next, and which
+                                            // run of the Server it has reconnected to
+                                            let mut payload_bytes =
+                                                connection.get_session_token().to_vec();
+                                            payload_bytes
+                                                .write_u32::<BigEndian>(self.epoch)
+                                                .unwrap();
+                                            let payload = connection.process_outgoing_header(
+                                                self.tick_manager.get_tick(),
+                                                connection.get_last_received_tick(),
+                                                PacketType::ServerReconnectResponse,
+                                                &payload_bytes,
+                                            );
+                                            if let Err(error) = self
+                                                .sender
+                                                .send(Packet::new_raw(address, payload))
+                                                .await
+                                            {
+                                                warn!(
+                                                    "failed to send reconnect response to {}: {}",
+                                                    address, error
+                                                );
+                                                continue;
+                                            }
+                                            connection.mark_sent();
+                                        }
+                                    }
+                                    continue;
+                                }
                                 PacketType::Ping => {
                                     if let Some(user_key) =
                                         self.address_to_user_key_map.get(&address)
                                     {
                                         match self.client_connections.get_mut(user_key) {
                                             Some(connection) => {
-                                                connection.process_incoming_header(&header);
+                                                connection.process_incoming_header(
+                                                    &header,
+                                                    payload.len(),
+                                                );
                                                 let ping_payload =
-                                                    connection.process_ping(&payload);
+                                                    match connection.process_ping(&payload) {
+                                                        Some(ping_payload) => ping_payload,
+                                                        None => continue,
+                                                    };
                                                 let payload_with_header = connection
                                                     .process_outgoing_header(
                                                         self.tick_manager.get_tick(),
@@ -427,13 +913,21 @@ impl<T: EventType, U: ActorType> NaiaServer<T, U> {
                                                         PacketType::Pong,
                                                         &ping_payload,
                                                     );
-                                                self.sender
+                                                if let Err(error) = self
+                                                    .sender
                                                     .send(Packet::new_raw(
                                                         connection.get_address(),
                                                         payload_with_header,
                                                     ))
                                                     .await
-                                                    .expect("send failed!");
+                                                {
+                                                    warn!(
+                                                        "failed to send pong to {}: {}",
+                                                        connection.get_address(),
+                                                        error
+                                                    );
+                                                    continue;
+                                                }
                                                 connection.mark_sent();
                                                 continue;
                                             }
@@ -470,18 +964,60 @@ impl<T: EventType, U: ActorType> NaiaServer<T, U> {
                 }
                 Next::Tick => {
                     self.tick_manager.increment_tick();
-                    return Ok(ServerEvent::Tick);
+                    self.record_actor_history();
+                    self.queue_periodic_event();
+                    // send anything held back for coalescing with this tick's updates
+                    for connection in self.client_connections.values_mut() {
+                        connection.flush_coalesced();
+                    }
+                    return Ok(ServerEvent::Tick(self.tick_manager.get_tick()));
                 }
             }
         }
     }
 
+    /// Like receive(), but returns None immediately rather than waiting when
+    /// no event is ready, for Servers driven by a game loop of their own.
+    /// Each call still performs the same maintenance as receive(), such as
+    /// sending heartbeats and ticking, so it must also be called regularly.
+    /// The packets sent along the way, such as heartbeats, pings, handshake
+    /// replies and disconnects, go through the same sender as every other
+    /// packet. On a Server created with new_sync() they are written straight
+    /// into its UDP socket, so none is left half-sent. On other Servers, a
+    /// send waiting on a full socket channel is abandoned, and that packet is
+    /// lost, so use receive() there instead.
+    pub fn try_receive(&mut self) -> Option<Result<ServerEvent<T>, NaiaServerError>> {
+        return self.receive().now_or_never();
+    }
+
+    /// Like send_all_updates(), but without awaiting, for Servers created
+    /// with new_sync(), whose packets are written straight into the socket.
+    /// On other Servers, packets which can't be sent immediately are dropped
+    pub fn try_send_all_updates(&mut self) {
+        self.send_all_updates().now_or_never();
+    }
+
     async fn send_connect_accept_message(
         connection: &mut ClientConnection<T, U>,
-        sender: &mut MessageSender,
+        sender: &mut PacketSender,
     ) {
-        let payload =
-            connection.process_outgoing_header(0, 0, PacketType::ServerConnectResponse, &[]);
+        // let the Client know whether Data packets will be compressed, whether
+        // packets will be checksummed, and which session token to reconnect with
+        let mut flags = 0;
+        if connection.has_compressor() {
+            flags |= COMPRESSION_FLAG;
+        }
+        if connection.has_integrity_check() {
+            flags |= INTEGRITY_CHECK_FLAG;
+        }
+        let mut payload_bytes = vec![flags];
+        payload_bytes.extend_from_slice(connection.get_session_token());
+        let payload = connection.process_outgoing_header(
+            0,
+            0,
+            PacketType::ServerConnectResponse,
+            &payload_bytes,
+        );
         match sender
             .send(Packet::new_raw(connection.get_address(), payload))
             .await
@@ -494,12 +1030,170 @@ impl<T: EventType, U: ActorType> NaiaServer<T, U> {
         connection.mark_sent();
     }
 
+    // generates a token with which the Client may later reconnect as the
+    // given User
+    fn new_session_token(&mut self, user_key: UserKey) -> [u8; 16] {
+        let mut session_token = [0; 16];
+        rand::SecureRandom::fill(&rand::SystemRandom::new(), &mut session_token).unwrap();
+        self.session_tokens.insert(session_token, user_key);
+        return session_token;
+    }
+
+    // the digest a Client reconnecting with the given session token from the
+    // given address must send back, proving that it receives at that address
+    fn reconnect_digest(&self, session_token: &[u8], address: &SocketAddr) -> hmac::Tag {
+        let mut digest_bytes = session_token.to_vec();
+        digest_bytes.extend_from_slice(address.to_string().as_bytes());
+        return hmac::sign(&self.connection_hash_key, &digest_bytes);
+    }
+
+    // finds the User which may reconnect with a session token from an address.
+    // A token which has been reconnected with already is only accepted again
+    // from the same address, in case the Client missed the response
+    fn session_user(&self, session_token: &[u8], address: &SocketAddr) -> Option<UserKey> {
+        if let Some(user_key) = self.session_tokens.get(session_token) {
+            return Some(*user_key);
+        }
+        match self.used_session_tokens.get(session_token) {
+            Some((user_key, used_address)) if used_address == address => return Some(*user_key),
+            _ => return None,
+        }
+    }
+
+    // restores the connection of the User a session token was issued to,
+    // moving it to the address the Client has reconnected from. The token is
+    // then replaced, so that replaying it can't move the connection again
+    fn reconnect_user(&mut self, session_token: &[u8], address: SocketAddr) -> Option<UserKey> {
+        let user_key = self.session_user(session_token, &address)?;
+        if let Some(other_user_key) = self.address_to_user_key_map.get(&address) {
+            if *other_user_key != user_key {
+                return None;
+            }
+        }
+        let user = self.users.get_mut(user_key)?;
+        let connection = self.client_connections.get_mut(&user_key)?;
+        if user.address != address {
+            self.address_to_user_key_map.remove(&user.address);
+            self.address_to_user_key_map.insert(address, user_key);
+            user.address = address;
+            connection.set_address(address);
+        }
+        connection.mark_heard();
+
+        if let Some(user_key) = self.session_tokens.remove(session_token) {
+            self.used_session_tokens
+                .retain(|_, (key, _)| *key != user_key);
+            let mut used_session_token = [0; SESSION_TOKEN_LENGTH];
+            used_session_token.copy_from_slice(session_token);
+            self.used_session_tokens
+                .insert(used_session_token, (user_key, address));
+            let new_session_token = self.new_session_token(user_key);
+            if let Some(connection) = self.client_connections.get_mut(&user_key) {
+                connection.set_session_token(new_session_token);
+            }
+        }
+        return Some(user_key);
+    }
+
+    /// Forcibly disconnects the Client associated with a given UserKey,
+    /// sending it an optional Event describing the reason for the
+    /// disconnection. The Client's connection is dropped immediately, and a
+    /// ServerEvent::Disconnection is emitted on the next call to receive()
+    pub async fn disconnect_client(&mut self, user_key: &UserKey, reason: Option<T>) {
+        if let Some(mut connection) = self.client_connections.remove(user_key) {
+            let payload_bytes = NaiaServer::<T, U>::write_reason_payload(&self.manifest, &reason);
+
+            let payload = connection.process_outgoing_header(
+                self.tick_manager.get_tick(),
+                connection.get_last_received_tick(),
+                PacketType::Disconnect,
+                &payload_bytes,
+            );
+            match self
+                .sender
+                .send(Packet::new_raw(connection.get_address(), payload))
+                .await
+            {
+                Ok(_) => {}
+                Err(err) => {
+                    info!("send error! {}", err);
+                }
+            }
+
+            self.outstanding_disconnects
+                .retain(|outstanding_key| outstanding_key != user_key);
+            self.outstanding_disconnects.push_front(*user_key);
+        }
+    }
+
+    /// Queues up an Event to be sent to the Client associated with a given
+    /// UserKey. Returns false, without queueing the Event, if the User is not
+    /// connected, or if the Client's outgoing queue is full & its
+    /// OverflowPolicy did not make room for the Event
+    pub fn queue_event(&mut self, user_key: &UserKey, event: &impl Event<T>) -> bool {
+        if let Some(connection) = self.client_connections.get_mut(user_key) {
+            return connection.queue_event(event);
+        }
+        return false;
+    }
+
+    /// Queues up a raw message to be sent on the given channel to the Client
+    /// associated with a given UserKey. Raw messages are acknowledged & resent
+    /// like guaranteed Events, and are received by the Client as a
+    /// ClientEvent::Raw. An error is returned if the message is too large to
+    /// fit into a packet, or if no Client is connected with the UserKey
+    pub fn queue_raw(
+        &mut self,
+        user_key: &UserKey,
+        channel: u8,
+        data: &[u8],
+    ) -> Result<(), NaiaServerError> {
+        if let Some(connection) = self.client_connections.get_mut(user_key) {
+            return connection
+                .queue_raw(channel, data)
+                .map_err(|error| NaiaServerError::Wrapped(Box::new(error)));
+        }
+        return Err(NaiaServerError::Wrapped(Box::new(
+            RawMessageError::NotConnected,
+        )));
+    }
+
     /// Queues up an Event to be sent to the Client associated with a given
-    /// UserKey
-    pub fn queue_event(&mut self, user_key: &UserKey, event: &impl Event<T>) {
+    /// UserKey, addressed to an Actor so that the Client can route it, for
+    /// example to play an animation on that Actor. The Client receives it as a
+    /// ClientEvent::ActorEvent. Returns false, without queueing the Event, if
+    /// the Actor is not in scope for the User, or if the Client's outgoing
+    /// queue is full & its OverflowPolicy did not make room for the Event
+    pub fn queue_actor_event(
+        &mut self,
+        user_key: &UserKey,
+        actor_key: &ActorKey,
+        event: &impl Event<T>,
+    ) -> bool {
         if let Some(connection) = self.client_connections.get_mut(user_key) {
-            connection.queue_event(event);
+            return connection.queue_actor_event(actor_key, event);
         }
+        return false;
+    }
+
+    /// Queues up an Event to be sent to every currently connected Client. The
+    /// Event is copied only once, and that copy is shared between all Client
+    /// connections
+    pub fn broadcast_event(&mut self, event: &impl Event<T>) {
+        NaiaServer::<T, U>::queue_shared_event_except(&mut self.client_connections, None, event);
+    }
+
+    /// Queues up an Event to be sent to every currently connected Client
+    /// except the one associated with the given UserKey, for example to relay
+    /// a chat message from one Client to all the others. If that User is not
+    /// connected, the Event is sent to every Client. As with
+    /// broadcast_event(), the Event is copied only once
+    pub fn broadcast_event_except(&mut self, exclude: &UserKey, event: &impl Event<T>) {
+        NaiaServer::<T, U>::queue_shared_event_except(
+            &mut self.client_connections,
+            Some(exclude),
+            event,
+        );
     }
 
     /// Sends all Actor/Event messages to all Clients. If you don't call this
@@ -512,18 +1206,27 @@ impl<T: EventType, U: ActorType> NaiaServer<T, U> {
         // loop through all connections, send packet
         for (user_key, connection) in self.client_connections.iter_mut() {
             if let Some(user) = self.users.get(*user_key) {
-                connection.collect_actor_updates();
+                connection.collect_actor_updates(self.max_actor_updates_per_tick);
                 while let Some(payload) =
                     connection.get_outgoing_packet(self.tick_manager.get_tick(), &self.manifest)
                 {
-                    match self
-                        .sender
-                        .send(Packet::new_raw(user.address, payload))
-                        .await
-                    {
-                        Ok(_) => {}
-                        Err(err) => {
-                            info!("send error! {}", err);
+                    let datagrams = match connection.fragment_outgoing_packet(payload) {
+                        Ok(datagrams) => datagrams,
+                        Err(error) => {
+                            warn!("{}", error);
+                            Vec::new()
+                        }
+                    };
+                    for datagram in datagrams {
+                        match self
+                            .sender
+                            .send(Packet::new_raw(user.address, datagram))
+                            .await
+                        {
+                            Ok(_) => {}
+                            Err(err) => {
+                                info!("send error! {}", err);
+                            }
                         }
                     }
                     connection.mark_sent();
@@ -565,6 +1268,73 @@ impl<T: EventType, U: ActorType> NaiaServer<T, U> {
 
         self.mut_handler.borrow_mut().deregister_actor(&key);
         self.global_actor_store.remove(key);
+        self.actor_spawn_events.remove(&key);
+        self.actor_prediction_tokens.remove(&key);
+        self.actor_authorities.remove(&key);
+    }
+
+    /// Makes the User associated with the given UserKey the authority over an
+    /// Actor, for example in a mode where a Client hosts part of the game.
+    /// Only the authority's Commands for the Actor are trusted, while Commands
+    /// for it from every other User are dropped. If None, Commands from any
+    /// User the Actor is in scope for are accepted, as they are by default
+    pub fn set_actor_authority(&mut self, key: &ActorKey, authority: Option<UserKey>) {
+        match authority {
+            Some(user_key) => {
+                if self.global_actor_store.contains_key(*key) {
+                    self.actor_authorities.insert(*key, user_key);
+                }
+            }
+            None => {
+                self.actor_authorities.remove(key);
+            }
+        }
+    }
+
+    /// Gets the User which has been made the authority over an Actor, if any
+    pub fn get_actor_authority(&self, key: &ActorKey) -> Option<UserKey> {
+        return self.actor_authorities.get(key).copied();
+    }
+
+    /// Attaches an Event to the creation of an Actor, describing for example
+    /// who spawned it or why. The Event is written into the same message
+    /// which creates the Actor on each Client it comes into scope for, and
+    /// is delivered alongside ClientEvent::CreateActor. Clients which
+    /// already have the Actor in scope do not receive it. Replaces any Event
+    /// previously attached to the Actor.
+    pub fn set_spawn_event(&mut self, key: &ActorKey, event: &impl Event<T>) {
+        if !self.global_actor_store.contains_key(*key) {
+            return;
+        }
+
+        let mut event_payload_bytes = Vec::<u8>::new();
+        event.write(&mut event_payload_bytes);
+
+        let mut spawn_event_bytes = Vec::<u8>::new();
+        let naia_id = self.manifest.get_event_naia_id(&event.get_type_id());
+        spawn_event_bytes.write_u16::<BigEndian>(naia_id).unwrap(); // write naia id
+        spawn_event_bytes
+            .write_u16::<BigEndian>(event_payload_bytes.len() as u16)
+            .unwrap(); // write payload length
+        spawn_event_bytes.append(&mut event_payload_bytes); // write payload
+
+        self.actor_spawn_events
+            .insert(*key, Rc::new(spawn_event_bytes.into_boxed_slice()));
+    }
+
+    /// Marks an Actor as the authoritative version of a spawn which the Client
+    /// associated with a given UserKey predicted, with the token returned by
+    /// its NaiaClient::predict_spawn(), which the Client must send along with
+    /// whatever caused the spawn. When the Actor comes into scope for that
+    /// Client, its predicted Actor is replaced by this one, rather than both
+    /// existing. Must be called before the Actor comes into scope for the
+    /// User, for example right after registering it.
+    pub fn confirm_predicted_spawn(&mut self, key: &ActorKey, user_key: &UserKey, token: u16) {
+        if !self.global_actor_store.contains_key(*key) {
+            return;
+        }
+        self.actor_prediction_tokens
+            .insert(*key, (*user_key, token));
     }
 
     /// Given an ActorKey, get a reference to a registered Actor being tracked
@@ -573,6 +1343,14 @@ impl<T: EventType, U: ActorType> NaiaServer<T, U> {
         return self.global_actor_store.get(key);
     }
 
+    /// Given an ActorKey & a past tick, get a copy of the Actor's state as it
+    /// was when that tick began, if the tick is within the
+    /// `actor_history_length` of the ServerConfig. For lag compensation, the
+    /// tick a Client saw is roughly the current tick minus its RTT in ticks
+    pub fn actor_state_at_tick(&self, key: &ActorKey, tick: u16) -> Option<&U> {
+        return self.actor_history.get(key, tick);
+    }
+
     /// Iterate through all the Server's Actors
     pub fn actors_iter(&self) -> slotmap::dense::Iter<ActorKey, U> {
         return self.global_actor_store.iter();
@@ -654,6 +1432,10 @@ impl<T: EventType, U: ActorType> NaiaServer<T, U> {
     /// While Rooms allow for a very simple scope to which an Actor can belong,
     /// this closure provides complete customization for advanced scopes.
     ///
+    /// When an Actor leaves scope for a User, only that User's Client is sent
+    /// a Delete message. If the Actor re-enters scope, the Client is sent a
+    /// fresh Create message once the prior Delete has been acknowledged.
+    ///
     /// This closure will be called every Tick of the Server, for every User &
     /// Actor in a Room together, so try to keep it performant
     pub fn on_scope_actor(
@@ -667,11 +1449,39 @@ impl<T: EventType, U: ActorType> NaiaServer<T, U> {
     /// with a new Client
     ///
     /// The Event evaluated in this closure should match the Event used
-    /// client-side in the NaiaClient::new() method
-    pub fn on_auth(&mut self, auth_func: Rc<Box<dyn Fn(&UserKey, &T) -> bool>>) {
+    /// client-side in the NaiaClient::new() method. Returning an Err refuses
+    /// the connection, and the optional Event it contains is sent to the
+    /// Client as the reason
+    pub fn on_auth(&mut self, auth_func: Rc<Box<dyn Fn(&UserKey, &T) -> Result<(), Option<T>>>>) {
         self.auth_func = Some(auth_func);
     }
 
+    /// Sets the Event sent to Clients as the reason their connection was
+    /// rejected, when `max_clients` Clients are already connected. If None,
+    /// the rejection carries no reason
+    pub fn set_server_full_reason(&mut self, reason: Option<T>) {
+        self.server_full_reason = reason;
+    }
+
+    /// Registers a closure which is called with every Event received from a
+    /// Client, once it has been read from the packet. Returning false drops
+    /// the Event silently, so that it never surfaces as a ServerEvent::Event
+    pub fn set_event_filter(&mut self, event_filter: Rc<Box<dyn Fn(&UserKey, &T) -> bool>>) {
+        self.event_filter = Some(event_filter);
+    }
+
+    /// Registers a closure which creates an Event to be sent to every
+    /// connected Client once every `interval_ticks` ticks, whether or not
+    /// anything else is sent, for example to keep Client clocks from
+    /// drifting. The closure is given the current tick
+    pub fn set_periodic_event(
+        &mut self,
+        interval_ticks: u16,
+        event_func: Rc<Box<dyn Fn(u16) -> Box<dyn Event<T>>>>,
+    ) {
+        self.periodic_event = Some((interval_ticks, 0, event_func));
+    }
+
     /// Iterate through all currently connected Users
     pub fn users_iter(&self) -> slotmap::dense::Iter<UserKey, User> {
         return self.users.iter();
@@ -682,6 +1492,38 @@ impl<T: EventType, U: ActorType> NaiaServer<T, U> {
         return self.users.get(*user_key);
     }
 
+    /// Get the number of Clients with an established connection
+    pub fn client_count(&self) -> usize {
+        return self.client_connections.len();
+    }
+
+    /// Get a snapshot of the connection of the Client at a given address, or
+    /// None if no Client is connected from there
+    pub fn client_info(&self, address: &SocketAddr) -> Option<ClientInfo> {
+        let user_key = self.address_to_user_key_map.get(address)?;
+        match self.client_connections.get(user_key) {
+            Some(connection) => {
+                return Some(ClientInfo {
+                    address: connection.get_address(),
+                    connected_at: connection.get_connected_at().clone(),
+                    last_heard: connection.last_heard(),
+                    rtt: connection.get_rtt(),
+                });
+            }
+            None => return None,
+        }
+    }
+
+    /// Gets the totals of the bytes sent to & received from the Client
+    /// associated with a given UserKey, by packet type, or None if there is
+    /// no such connection
+    pub fn traffic_stats(&self, user_key: &UserKey) -> Option<TrafficStats> {
+        if let Some(connection) = self.client_connections.get(user_key) {
+            return Some(connection.traffic_stats());
+        }
+        return None;
+    }
+
     /// Get the number of Users currently connected
     pub fn get_users_count(&self) -> usize {
         return self.users.len();
@@ -695,13 +1537,44 @@ impl<T: EventType, U: ActorType> NaiaServer<T, U> {
         return None;
     }
 
+    /// Applies a ConnectionConfig to a single User's connection, replacing the
+    /// Server's global config for that User. For example, admin Users may be
+    /// given a longer disconnection timeout than regular players. The
+    /// heartbeat interval and disconnection timeout take effect the next time
+    /// heartbeats are evaluated, which happens every `heartbeat_interval` of
+    /// the ServerConfig, so shorter per-User heartbeat intervals are rounded
+    /// up to it. Does nothing if the User is not connected, and returns an
+    /// error without applying the config if it is invalid.
+    pub fn set_client_config(
+        &mut self,
+        user_key: &UserKey,
+        connection_config: ConnectionConfig,
+    ) -> Result<(), NaiaServerError> {
+        if let Err(error) = connection_config.validate() {
+            return Err(NaiaServerError::Wrapped(Box::new(error)));
+        }
+        if let Some(user_connection) = self.client_connections.get_mut(user_key) {
+            user_connection.set_config(&connection_config);
+        }
+        return Ok(());
+    }
+
     /// Gets the current tick of the Server
     pub fn get_server_tick(&self) -> u16 {
         self.tick_manager.get_tick()
     }
 
+    /// Gets the epoch which identifies this run of the Server to Clients.
+    /// Persist it along with the current tick, and pass both back through
+    /// the ServerConfig, for a restarted Server to continue the same session
+    pub fn epoch(&self) -> u32 {
+        return self.epoch;
+    }
+
     /// Assigns an Actor to a specific User, making it a Pawn for that User
-    /// (meaning that the User will be able to issue Commands to that Pawn)
+    /// (meaning that the User will be able to issue Commands to that Pawn).
+    /// This can be done at any point in the session, for example when a
+    /// player takes control of a vehicle
     pub fn assign_pawn(&mut self, user_key: &UserKey, actor_key: &ActorKey) {
         if let Some(actor_ref) = self.global_actor_store.get(*actor_key) {
             if !actor_ref.is_predicted() {
@@ -726,6 +1599,18 @@ impl<T: EventType, U: ActorType> NaiaServer<T, U> {
         }
     }
 
+    fn record_actor_history(&mut self) {
+        if !self.actor_history.is_enabled() {
+            return;
+        }
+        let mut snapshot = HashMap::new();
+        for (key, actor) in self.global_actor_store.iter() {
+            snapshot.insert(key, actor.inner_ref().borrow().get_typed_copy());
+        }
+        self.actor_history
+            .record(self.tick_manager.get_tick(), snapshot);
+    }
+
     fn update_actor_scopes(&mut self) {
         for (room_key, room) in self.rooms.iter_mut() {
             while let Some((removed_user, removed_actor)) = room.pop_removal_queue() {
@@ -753,8 +1638,21 @@ impl<T: EventType, U: ActorType> NaiaServer<T, U> {
                                         // add actor to the connections local scope
                                         if let Some(actor) = self.global_actor_store.get(*actor_key)
                                         {
-                                            user_connection
-                                                .add_actor(actor_key, &actor.inner_ref());
+                                            let prediction_token =
+                                                match self.actor_prediction_tokens.get(actor_key) {
+                                                    Some((predicting_user, token))
+                                                        if predicting_user == user_key =>
+                                                    {
+                                                        Some(*token)
+                                                    }
+                                                    _ => None,
+                                                };
+                                            user_connection.add_actor(
+                                                actor_key,
+                                                &actor.inner_ref(),
+                                                self.actor_spawn_events.get(actor_key),
+                                                prediction_token,
+                                            );
                                         }
                                     }
                                 } else {
@@ -771,8 +1669,48 @@ impl<T: EventType, U: ActorType> NaiaServer<T, U> {
         }
     }
 
+    fn write_reason_payload(manifest: &Manifest<T, U>, reason: &Option<T>) -> Vec<u8> {
+        let mut payload_bytes = Vec::new();
+        // write reason event object if there is one
+        if let Some(reason_event) = reason {
+            let type_id = reason_event.get_type_id();
+            let naia_id = manifest.get_event_naia_id(&type_id); // get naia id
+            payload_bytes.write_u16::<BigEndian>(naia_id).unwrap(); // write naia id
+            reason_event.write(&mut payload_bytes);
+        }
+        return payload_bytes;
+    }
+
+    fn queue_periodic_event(&mut self) {
+        if let Some((interval_ticks, ticks_elapsed, event_func)) = &mut self.periodic_event {
+            *ticks_elapsed += 1;
+            if *ticks_elapsed < *interval_ticks {
+                return;
+            }
+            *ticks_elapsed = 0;
+            let shared_event =
+                Rc::new((event_func.as_ref().as_ref())(self.tick_manager.get_tick()));
+            for connection in self.client_connections.values_mut() {
+                connection.queue_shared_event(&shared_event);
+            }
+        }
+    }
+
+    fn queue_shared_event_except(
+        client_connections: &mut HashMap<UserKey, ClientConnection<T, U>>,
+        exclude: Option<&UserKey>,
+        event: &impl Event<T>,
+    ) {
+        let shared_event = Rc::new(EventClone::clone_box(event));
+        for (user_key, connection) in client_connections.iter_mut() {
+            if Some(user_key) != exclude {
+                connection.queue_shared_event(&shared_event);
+            }
+        }
+    }
+
     async fn internal_send_connectionless(
-        sender: &mut MessageSender,
+        sender: &mut PacketSender,
         packet_type: PacketType,
         packet: Packet,
     ) {
@@ -788,3 +1726,1650 @@ impl<T: EventType, U: ActorType> NaiaServer<T, U> {
 fn to_actor_mutator(eref: &Rc<RefCell<ServerActorMutator>>) -> Rc<RefCell<dyn ActorMutator>> {
     eref.clone()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        any::TypeId,
+        cell::RefCell,
+        collections::HashMap,
+        net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
+        rc::Rc,
+        thread::sleep,
+        time::Duration,
+    };
+
+    use futures_util::FutureExt;
+
+    use slotmap::DenseSlotMap;
+
+    use byteorder::{BigEndian, WriteBytesExt};
+    use naia_client::{ClientConfig, ClientConnectionState, ClientEvent, NaiaClient};
+
+    use naia_shared::{
+        Actor, ActorBuilder, ActorMutator, ActorType, Compressor, ConnectionConfig,
+        DeliveryGuarantee, DisconnectPolicy, Event, EventBuilder, EventType, Instant, ManagerType,
+        Manifest, MockLink, PacketReader, PacketType, PingManager, RawMessageError, StandardHeader,
+        StateMask, MTU_SIZE,
+    };
+
+    use super::{NaiaServer, ServerAddresses, Timestamp};
+    use crate::{
+        actors::{actor_key::actor_key::ActorKey, mut_handler::MutHandler},
+        client_connection::ClientConnection,
+        error::NaiaServerError,
+        user::User,
+        ServerConfig, ServerEvent, SharedConfig, UserKey,
+    };
+
+    // Long enough that neither side ticks on its own during a test, so that
+    // ticks only begin where a test rings them
+    const TEST_TICK_INTERVAL: Duration = Duration::from_secs(3600);
+
+    #[derive(Clone)]
+    struct ChatEvent;
+
+    #[derive(Clone)]
+    struct MoveEvent;
+
+    // Holds the TypeId of the Event it was built from
+    #[derive(Clone)]
+    struct TestEventType(TypeId);
+
+    impl EventType for TestEventType {
+        fn write(&self, _: &mut Vec<u8>) {}
+        fn get_type_id(&self) -> TypeId {
+            return self.0;
+        }
+    }
+
+    impl Event<TestEventType> for ChatEvent {
+        fn guarantee_delivery(&self) -> DeliveryGuarantee {
+            return DeliveryGuarantee::Reliable;
+        }
+        fn write(&self, _: &mut Vec<u8>) {}
+        fn get_typed_copy(&self) -> TestEventType {
+            return TestEventType(TypeId::of::<ChatEvent>());
+        }
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<ChatEvent>();
+        }
+    }
+
+    struct ChatEventBuilder;
+
+    impl EventBuilder<TestEventType> for ChatEventBuilder {
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<ChatEvent>();
+        }
+        fn build(&self, _: &mut PacketReader) -> TestEventType {
+            return TestEventType(TypeId::of::<ChatEvent>());
+        }
+    }
+
+    impl Event<TestEventType> for MoveEvent {
+        fn guarantee_delivery(&self) -> DeliveryGuarantee {
+            return DeliveryGuarantee::Reliable;
+        }
+        fn write(&self, _: &mut Vec<u8>) {}
+        fn get_typed_copy(&self) -> TestEventType {
+            return TestEventType(TypeId::of::<MoveEvent>());
+        }
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<MoveEvent>();
+        }
+    }
+
+    struct MoveEventBuilder;
+
+    impl EventBuilder<TestEventType> for MoveEventBuilder {
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<MoveEvent>();
+        }
+        fn build(&self, _: &mut PacketReader) -> TestEventType {
+            return TestEventType(TypeId::of::<MoveEvent>());
+        }
+    }
+
+    // An Actor without state, which is never written out to a Client
+    #[derive(Clone, Debug)]
+    struct TestActor;
+
+    impl Actor<TestActorType> for TestActor {
+        fn get_state_mask_size(&self) -> u8 {
+            return 1;
+        }
+        fn get_typed_copy(&self) -> TestActorType {
+            return TestActorType(Rc::new(RefCell::new(self.clone())));
+        }
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<TestActor>();
+        }
+        fn write(&self, _: &mut Vec<u8>) {}
+        fn write_partial(&self, _: &StateMask, _: &mut Vec<u8>) {}
+        fn read_full(&mut self, _: &mut PacketReader, _: u16) {}
+        fn read_partial(&mut self, _: &StateMask, _: &mut PacketReader, _: u16) {}
+        fn set_mutator(&mut self, _: &Rc<RefCell<dyn ActorMutator>>) {}
+        fn is_interpolated(&self) -> bool {
+            return false;
+        }
+        fn is_predicted(&self) -> bool {
+            return false;
+        }
+    }
+
+    #[derive(Clone)]
+    struct TestActorType(Rc<RefCell<TestActor>>);
+
+    impl ActorType for TestActorType {
+        fn read_full(&mut self, _: &mut PacketReader, _: u16) {}
+        fn read_partial(&mut self, _: &StateMask, _: &mut PacketReader, _: u16) {}
+        fn inner_ref(&self) -> Rc<RefCell<dyn Actor<TestActorType>>> {
+            return self.0.clone();
+        }
+        fn equals(&self, _: &TestActorType) -> bool {
+            return true;
+        }
+        fn equals_prediction(&self, _: &TestActorType) -> bool {
+            return true;
+        }
+        fn set_to_interpolation(&mut self, _: &TestActorType, _: &TestActorType, _: f32) {}
+        fn set_to_extrapolation(&mut self, _: &TestActorType, _: &TestActorType, _: f32) {}
+        fn mirror(&mut self, _: &TestActorType) {}
+        fn is_interpolated(&self) -> bool {
+            return false;
+        }
+        fn is_predicted(&self) -> bool {
+            return false;
+        }
+    }
+
+    // An Actor with a single predicted property, which is written out to
+    // Clients, so that it can be created & updated on a real NaiaClient
+    #[derive(Clone)]
+    struct PositionActor {
+        x: u8,
+        mutator: Option<Rc<RefCell<dyn ActorMutator>>>,
+    }
+
+    impl PositionActor {
+        fn new(x: u8) -> Self {
+            return PositionActor { x, mutator: None };
+        }
+
+        fn set_x(&mut self, x: u8) {
+            self.x = x;
+            if let Some(mutator) = &self.mutator {
+                mutator.borrow_mut().mutate(0);
+            }
+        }
+    }
+
+    impl Actor<PositionActorType> for PositionActor {
+        fn get_state_mask_size(&self) -> u8 {
+            return 1;
+        }
+        fn get_typed_copy(&self) -> PositionActorType {
+            return PositionActorType(Rc::new(RefCell::new(PositionActor::new(self.x))));
+        }
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<PositionActor>();
+        }
+        fn write(&self, out_bytes: &mut Vec<u8>) {
+            out_bytes.push(self.x);
+        }
+        fn write_partial(&self, state_mask: &StateMask, out_bytes: &mut Vec<u8>) {
+            if state_mask.get_bit(0) == Some(true) {
+                out_bytes.push(self.x);
+            }
+        }
+        fn read_full(&mut self, reader: &mut PacketReader, _: u16) {
+            self.x = reader.read_u8();
+        }
+        fn read_partial(&mut self, state_mask: &StateMask, reader: &mut PacketReader, _: u16) {
+            if state_mask.get_bit(0) == Some(true) {
+                self.x = reader.read_u8();
+            }
+        }
+        fn set_mutator(&mut self, mutator: &Rc<RefCell<dyn ActorMutator>>) {
+            self.mutator = Some(mutator.clone());
+        }
+        fn is_interpolated(&self) -> bool {
+            return false;
+        }
+        fn is_predicted(&self) -> bool {
+            return true;
+        }
+    }
+
+    #[derive(Clone)]
+    struct PositionActorType(Rc<RefCell<PositionActor>>);
+
+    impl PositionActorType {
+        fn x(&self) -> u8 {
+            return self.0.borrow().x;
+        }
+    }
+
+    impl ActorType for PositionActorType {
+        fn read_full(&mut self, reader: &mut PacketReader, packet_index: u16) {
+            self.0.borrow_mut().read_full(reader, packet_index);
+        }
+        fn read_partial(
+            &mut self,
+            state_mask: &StateMask,
+            reader: &mut PacketReader,
+            packet_index: u16,
+        ) {
+            self.0
+                .borrow_mut()
+                .read_partial(state_mask, reader, packet_index);
+        }
+        fn inner_ref(&self) -> Rc<RefCell<dyn Actor<PositionActorType>>> {
+            return self.0.clone();
+        }
+        fn equals(&self, other: &PositionActorType) -> bool {
+            return self.x() == other.x();
+        }
+        fn equals_prediction(&self, other: &PositionActorType) -> bool {
+            return self.x() == other.x();
+        }
+        fn set_to_interpolation(&mut self, _: &PositionActorType, new: &PositionActorType, _: f32) {
+            self.mirror(new);
+        }
+        fn set_to_extrapolation(&mut self, _: &PositionActorType, new: &PositionActorType, _: f32) {
+            self.mirror(new);
+        }
+        fn mirror(&mut self, other: &PositionActorType) {
+            let x = other.x();
+            self.0.borrow_mut().x = x;
+        }
+        fn is_interpolated(&self) -> bool {
+            return false;
+        }
+        fn is_predicted(&self) -> bool {
+            return true;
+        }
+    }
+
+    struct PositionActorBuilder;
+
+    impl ActorBuilder<PositionActorType> for PositionActorBuilder {
+        fn build(&self, reader: &mut PacketReader) -> PositionActorType {
+            return PositionActorType(Rc::new(RefCell::new(PositionActor::new(reader.read_u8()))));
+        }
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<PositionActor>();
+        }
+    }
+
+    // Runs the handshake between a Server & a Client sharing a MockLink,
+    // returning the User that the Client connected as
+    fn connect_over_link<U: ActorType>(
+        server: &mut NaiaServer<TestEventType, U>,
+        client: &mut NaiaClient<TestEventType, U>,
+    ) -> UserKey {
+        let mut connected_user = None;
+        let mut client_connected = false;
+        for _ in 0..50 {
+            while let Some(result) = client.receive() {
+                if let Ok(ClientEvent::Connection(_)) = result {
+                    client_connected = true;
+                }
+            }
+            while let Some(result) = server.try_receive() {
+                if let Ok(ServerEvent::Connection(user_key, _)) = result {
+                    connected_user = Some(user_key);
+                }
+            }
+            server.send_all_updates().now_or_never().unwrap();
+            if client_connected && connected_user.is_some() {
+                break;
+            }
+        }
+        assert!(client_connected);
+        return connected_user.unwrap();
+    }
+
+    // Lets a connected Server & Client exchange packets for the given number
+    // of rounds, returning every event the Client received
+    fn exchange_over_link<U: ActorType>(
+        server: &mut NaiaServer<TestEventType, U>,
+        client: &mut NaiaClient<TestEventType, U>,
+        rounds: usize,
+    ) -> Vec<ClientEvent<TestEventType>> {
+        let mut client_events = Vec::new();
+        for _ in 0..rounds {
+            while server.try_receive().is_some() {}
+            server.send_all_updates().now_or_never().unwrap();
+            client.ring_tick();
+            while let Some(result) = client.receive() {
+                if let Ok(event) = result {
+                    client_events.push(event);
+                }
+            }
+        }
+        return client_events;
+    }
+
+    // A Server & Client over a MockLink at the given port, both with every
+    // PositionActor in scope & both Events registered, ready to be connected
+    fn new_position_link(
+        port: u16,
+    ) -> (
+        NaiaServer<TestEventType, PositionActorType>,
+        NaiaClient<TestEventType, PositionActorType>,
+    ) {
+        let link = MockLink::new(format!("127.0.0.1:{}", port).parse().unwrap());
+        // the Client holds back received Data packets until its next tick
+        let shared_config = SharedConfig::new(TEST_TICK_INTERVAL, None, None);
+
+        let mut server_manifest = Manifest::<TestEventType, PositionActorType>::new();
+        server_manifest.register_event(Box::new(ChatEventBuilder));
+        server_manifest.register_event(Box::new(MoveEventBuilder));
+        server_manifest.register_actor(Box::new(PositionActorBuilder));
+        let mut server = NaiaServer::new_mock(&link, server_manifest, None, shared_config.clone());
+        server.on_scope_actor(Rc::new(Box::new(|_, _, _, _| true)));
+
+        let mut client_manifest = Manifest::<TestEventType, PositionActorType>::new();
+        client_manifest.register_event(Box::new(ChatEventBuilder));
+        client_manifest.register_event(Box::new(MoveEventBuilder));
+        client_manifest.register_actor(Box::new(PositionActorBuilder));
+        // the Client's heartbeats ack the Actors it's sent, so that they
+        // can then be updated
+        let client_config = ClientConfig {
+            send_handshake_interval: Duration::from_millis(0),
+            heartbeat_interval: Duration::from_millis(0),
+            ..Default::default()
+        };
+        let client = NaiaClient::new_mock(
+            &link,
+            client_manifest,
+            Some(client_config),
+            shared_config,
+            None,
+        );
+        return (server, client);
+    }
+
+    #[test]
+    fn relayed_event_is_not_sent_back_to_its_sender() {
+        let mut manifest = Manifest::<TestEventType, TestActorType>::new();
+        manifest.register_event(Box::new(ChatEventBuilder));
+        let mut_handler = MutHandler::new();
+        let mut users = DenseSlotMap::<UserKey, ()>::with_key();
+        let mut client_connections = HashMap::new();
+        for port in 14191..14194 {
+            let address: SocketAddr = format!("127.0.0.1:{}", port).parse().unwrap();
+            client_connections.insert(
+                users.insert(()),
+                ClientConnection::<TestEventType, TestActorType>::new(
+                    address,
+                    Some(&mut_handler),
+                    &ConnectionConfig::default(),
+                    None,
+                ),
+            );
+        }
+        let sender_key = users.keys().next().unwrap();
+
+        NaiaServer::queue_shared_event_except(
+            &mut client_connections,
+            Some(&sender_key),
+            &ChatEvent,
+        );
+
+        for (user_key, connection) in client_connections.iter_mut() {
+            let packet = connection.get_outgoing_packet(0, &manifest);
+            assert!(packet.is_some() == (*user_key != sender_key));
+        }
+    }
+
+    #[test]
+    fn excluding_a_disconnected_user_broadcasts_to_everyone() {
+        let mut manifest = Manifest::<TestEventType, TestActorType>::new();
+        manifest.register_event(Box::new(ChatEventBuilder));
+        let mut_handler = MutHandler::new();
+        let mut users = DenseSlotMap::<UserKey, ()>::with_key();
+        let mut client_connections = HashMap::new();
+        let address: SocketAddr = "127.0.0.1:14191".parse().unwrap();
+        client_connections.insert(
+            users.insert(()),
+            ClientConnection::<TestEventType, TestActorType>::new(
+                address,
+                Some(&mut_handler),
+                &ConnectionConfig::default(),
+                None,
+            ),
+        );
+        let disconnected_key = users.insert(());
+
+        NaiaServer::queue_shared_event_except(
+            &mut client_connections,
+            Some(&disconnected_key),
+            &ChatEvent,
+        );
+
+        for (_, connection) in client_connections.iter_mut() {
+            assert!(connection.get_outgoing_packet(0, &manifest).is_some());
+        }
+    }
+
+    #[test]
+    fn withheld_pongs_make_rtt_unknown_while_heartbeats_keep_the_connection() {
+        let mut_handler = MutHandler::new();
+        let connection_config = ConnectionConfig {
+            ping_interval: Duration::from_millis(10),
+            pong_timeout_intervals: 3,
+            ..ConnectionConfig::default()
+        };
+        let mut connection = ClientConnection::<TestEventType, TestActorType>::new(
+            "127.0.0.1:14191".parse().unwrap(),
+            Some(&mut_handler),
+            &connection_config,
+            None,
+        );
+
+        let ping = connection.get_ping_payload();
+        connection.process_pong(&PingManager::process_ping(&ping).unwrap());
+        assert!(connection.get_rtt().is_some());
+
+        // the Client's heartbeats still arrive, but its pongs are lost
+        for _ in 0..4 {
+            connection.get_ping_payload();
+            connection.mark_heard();
+        }
+
+        assert!(connection.get_rtt().is_none());
+        assert!(!connection.should_drop(None));
+    }
+
+    #[test]
+    fn event_backlog_is_drained_within_send_budget() {
+        let mut manifest = Manifest::<TestEventType, TestActorType>::new();
+        manifest.register_event(Box::new(ChatEventBuilder));
+        let mut_handler = MutHandler::new();
+        let mut connection = ClientConnection::<TestEventType, TestActorType>::new(
+            "127.0.0.1:14191".parse().unwrap(),
+            Some(&mut_handler),
+            &ConnectionConfig::default(),
+            Some(1000),
+        );
+
+        // several packets worth of Events
+        for _ in 0..300 {
+            connection.queue_event(&ChatEvent);
+        }
+
+        // ticks 300ms apart, each refilling 300 bytes of the budget, so a
+        // tick which sends nothing means the backlog has been drained
+        let mut now = Instant::now();
+        let mut bytes_per_tick = Vec::new();
+        loop {
+            let mut bytes = 0;
+            while let Some(packet) = connection.get_outgoing_packet_at(0, &manifest, &now) {
+                bytes += packet.len();
+            }
+            if bytes == 0 {
+                break;
+            }
+            bytes_per_tick.push(bytes);
+            now.add_millis(300);
+        }
+
+        assert!(bytes_per_tick.len() > 1);
+        // a tick overspends its budget by less than a packet
+        let max_packet_len = MTU_SIZE + StandardHeader::bytes_number();
+        assert!(bytes_per_tick[0] < 1000 + max_packet_len);
+        for bytes in &bytes_per_tick[1..] {
+            assert!(*bytes < 300 + max_packet_len);
+        }
+    }
+
+    #[test]
+    fn events_queued_within_the_coalescing_window_share_a_packet() {
+        let mut manifest = Manifest::<TestEventType, TestActorType>::new();
+        manifest.register_event(Box::new(ChatEventBuilder));
+        let mut_handler = MutHandler::new();
+        let connection_config = ConnectionConfig {
+            coalesce_delay: Some(Duration::from_millis(20)),
+            ..ConnectionConfig::default()
+        };
+        let mut connection = ClientConnection::<TestEventType, TestActorType>::new(
+            "127.0.0.1:14191".parse().unwrap(),
+            Some(&mut_handler),
+            &connection_config,
+            None,
+        );
+
+        let mut now = Instant::now();
+        connection.queue_event(&ChatEvent);
+        assert!(connection
+            .get_outgoing_packet_at(0, &manifest, &now)
+            .is_none());
+        now.add_millis(10);
+        connection.queue_event(&ChatEvent);
+        assert!(connection
+            .get_outgoing_packet_at(0, &manifest, &now)
+            .is_none());
+
+        // the window has closed
+        now.add_millis(10);
+        assert!(connection
+            .get_outgoing_packet_at(0, &manifest, &now)
+            .is_some());
+        assert!(connection
+            .get_outgoing_packet_at(0, &manifest, &now)
+            .is_none());
+
+        // a tick sends held data without waiting out the window
+        connection.queue_event(&ChatEvent);
+        assert!(connection
+            .get_outgoing_packet_at(0, &manifest, &now)
+            .is_none());
+        connection.flush_coalesced();
+        assert!(connection
+            .get_outgoing_packet_at(0, &manifest, &now)
+            .is_some());
+    }
+
+    fn new_test_server(
+        tick_interval: Duration,
+        server_config: Option<ServerConfig>,
+    ) -> NaiaServer<TestEventType, TestActorType> {
+        let address: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        return NaiaServer::new(
+            ServerAddresses::new(address, address, address),
+            Manifest::new(),
+            server_config,
+            SharedConfig::new(tick_interval, None, None),
+        )
+        .now_or_never()
+        .unwrap();
+    }
+
+    #[test]
+    fn try_receive_returns_none_when_idle() {
+        let mut server = new_test_server(Duration::from_secs(3600), None);
+
+        assert!(server.try_receive().is_none());
+        assert!(server.try_receive().is_none());
+    }
+
+    #[test]
+    fn try_receive_returns_ready_event() {
+        let mut server = new_test_server(TEST_TICK_INTERVAL, None);
+        server.tick_timer.ring_manual();
+
+        match server.try_receive() {
+            Some(Ok(ServerEvent::Tick(_))) => {}
+            _ => panic!("expected a Tick event"),
+        }
+    }
+
+    // a connected Client, controlling a Pawn
+    fn connect_test_user(
+        server: &mut NaiaServer<TestEventType, TestActorType>,
+        address: SocketAddr,
+        pawn_key: &ActorKey,
+    ) -> (UserKey, [u8; 16]) {
+        let user_key = server.users.insert(User::new(address, Timestamp::now()));
+        server.address_to_user_key_map.insert(address, user_key);
+        let mut connection = ClientConnection::new(
+            address,
+            Some(&server.mut_handler),
+            &server.connection_config,
+            None,
+        );
+        let session_token = server.new_session_token(user_key);
+        connection.set_session_token(session_token);
+        connection.add_pawn(pawn_key);
+        server.client_connections.insert(user_key, connection);
+        return (user_key, session_token);
+    }
+
+    #[test]
+    fn client_reconnecting_within_grace_period_keeps_its_pawn() {
+        let server_config = ServerConfig {
+            disconnect_policy: DisconnectPolicy::Timeout(Duration::from_millis(1)),
+            heartbeat_interval: Duration::from_micros(500),
+            reconnect_grace_period: Some(Duration::from_secs(3600)),
+            ..ServerConfig::default()
+        };
+        let mut server = new_test_server(Duration::from_secs(3600), Some(server_config));
+        let mut actor_keys = DenseSlotMap::<ActorKey, ()>::with_key();
+        let pawn_key = actor_keys.insert(());
+        let old_address: SocketAddr = "127.0.0.1:14191".parse().unwrap();
+        let (user_key, session_token) = connect_test_user(&mut server, old_address, &pawn_key);
+
+        // the Client times out, but is held onto for the grace period
+        let mut timed_out = Instant::now();
+        timed_out.add_millis(10);
+        let grace_period = server.reconnect_grace_period;
+        let connection = server.client_connections.get_mut(&user_key).unwrap();
+        assert!(!connection.should_drop_at(grace_period, &timed_out));
+
+        // and then reconnects from a new address
+        let new_address: SocketAddr = "127.0.0.1:14192".parse().unwrap();
+        assert!(server.reconnect_user(&[0; 16], new_address).is_none());
+        assert!(server.reconnect_user(&session_token, new_address) == Some(user_key));
+
+        // the token is replaced, so that replaying it from elsewhere can't
+        // move the connection again, though the Client may still retry
+        let replay_address: SocketAddr = "127.0.0.1:14193".parse().unwrap();
+        assert!(server
+            .reconnect_user(&session_token, replay_address)
+            .is_none());
+        assert!(server.reconnect_user(&session_token, new_address) == Some(user_key));
+        let new_session_token = *server
+            .client_connections
+            .get(&user_key)
+            .unwrap()
+            .get_session_token();
+        assert!(new_session_token != session_token);
+
+        assert!(!server.address_to_user_key_map.contains_key(&old_address));
+        assert!(server.address_to_user_key_map.get(&new_address) == Some(&user_key));
+        assert!(server.users.get(user_key).unwrap().address == new_address);
+        let connection = server.client_connections.get_mut(&user_key).unwrap();
+        assert!(connection.get_address() == new_address);
+        assert!(connection.has_pawn(&pawn_key));
+        assert!(!connection.should_drop(grace_period));
+    }
+
+    #[test]
+    fn client_reconnects_through_a_challenge_after_losing_the_server() {
+        let link = MockLink::new("127.0.0.1:14197".parse().unwrap());
+        let shared_config = SharedConfig::new(TEST_TICK_INTERVAL, None, None);
+        let server_config = ServerConfig {
+            reconnect_grace_period: Some(Duration::from_secs(3600)),
+            ..ServerConfig::default()
+        };
+        let mut server = NaiaServer::new_mock(
+            &link,
+            Manifest::<TestEventType, TestActorType>::new(),
+            Some(server_config),
+            shared_config.clone(),
+        );
+        let client_config = ClientConfig {
+            send_handshake_interval: Duration::from_millis(0),
+            disconnect_policy: DisconnectPolicy::Timeout(Duration::from_millis(20)),
+            heartbeat_interval: Duration::from_millis(5),
+            reconnect_grace_period: Some(Duration::from_secs(3600)),
+            ..Default::default()
+        };
+        let mut client = NaiaClient::new_mock(
+            &link,
+            Manifest::<TestEventType, TestActorType>::new(),
+            Some(client_config),
+            shared_config,
+            None,
+        );
+
+        let user_key = connect_over_link(&mut server, &mut client);
+        let old_session_token = *server
+            .client_connections
+            .get(&user_key)
+            .unwrap()
+            .get_session_token();
+
+        // the link goes down for longer than the Client's timeout, and
+        // whatever was still on its way to the Client is lost
+        sleep(Duration::from_millis(21));
+        while link.receive_at_client().is_some() {}
+
+        // so the Client tries to reconnect
+        let mut reconnect_sent = false;
+        for _ in 0..50 {
+            while client.receive().is_some() {}
+            while let Some(packet) = link.receive_at_server() {
+                if StandardHeader::read(&packet).unwrap().0.packet_type() == PacketType::Reconnect {
+                    reconnect_sent = true;
+                }
+            }
+            if reconnect_sent {
+                break;
+            }
+        }
+        assert!(reconnect_sent);
+
+        // once the link is back, the Client is challenged before reconnecting
+        let mut reconnected = false;
+        for _ in 0..50 {
+            while let Some(result) = client.receive() {
+                if let Ok(ClientEvent::Reconnection) = result {
+                    reconnected = true;
+                }
+            }
+            while server.try_receive().is_some() {}
+            server.send_all_updates().now_or_never().unwrap();
+            if reconnected {
+                break;
+            }
+        }
+
+        assert!(reconnected);
+        assert!(client.server_epoch() == Some(server.epoch()));
+        let connection = server.client_connections.get(&user_key).unwrap();
+        assert!(*connection.get_session_token() != old_session_token);
+        assert!(server.session_user(&old_session_token, &link.client_address()) == Some(user_key));
+    }
+
+    #[test]
+    fn client_not_reconnecting_is_dropped_after_grace_period() {
+        let server_config = ServerConfig {
+            disconnect_policy: DisconnectPolicy::Timeout(Duration::from_millis(1)),
+            heartbeat_interval: Duration::from_micros(500),
+            reconnect_grace_period: Some(Duration::from_millis(10)),
+            ..ServerConfig::default()
+        };
+        let mut server = new_test_server(Duration::from_secs(3600), Some(server_config));
+        let mut actor_keys = DenseSlotMap::<ActorKey, ()>::with_key();
+        let pawn_key = actor_keys.insert(());
+        let address: SocketAddr = "127.0.0.1:14191".parse().unwrap();
+        let (user_key, _) = connect_test_user(&mut server, address, &pawn_key);
+
+        let mut timed_out = Instant::now();
+        timed_out.add_millis(5);
+        let grace_period = server.reconnect_grace_period;
+        let connection = server.client_connections.get_mut(&user_key).unwrap();
+        assert!(!connection.should_drop_at(grace_period, &timed_out));
+
+        let mut within_grace_period = timed_out.clone();
+        within_grace_period.add_millis(9);
+        assert!(!connection.should_drop_at(grace_period, &within_grace_period));
+
+        let mut after_grace_period = timed_out.clone();
+        after_grace_period.add_millis(10);
+        assert!(connection.should_drop_at(grace_period, &after_grace_period));
+    }
+
+    #[test]
+    fn client_count_follows_connects_and_disconnects() {
+        let mut server = new_test_server(Duration::from_secs(3600), None);
+        let mut actor_keys = DenseSlotMap::<ActorKey, ()>::with_key();
+        let pawn_key = actor_keys.insert(());
+        assert!(server.client_count() == 0);
+
+        let address_a: SocketAddr = "127.0.0.1:14191".parse().unwrap();
+        let address_b: SocketAddr = "127.0.0.1:14192".parse().unwrap();
+        let (user_a, _) = connect_test_user(&mut server, address_a, &pawn_key);
+        connect_test_user(&mut server, address_b, &pawn_key);
+        assert!(server.client_count() == 2);
+        assert!(server.client_info(&address_b).unwrap().address == address_b);
+
+        server.outstanding_disconnects.push_back(user_a);
+        match server.try_receive() {
+            Some(Ok(ServerEvent::Disconnection(user_key, _))) => assert!(user_key == user_a),
+            _ => panic!("expected a Disconnection event"),
+        }
+        assert!(server.client_count() == 1);
+        assert!(server.client_info(&address_a).is_none());
+        let unknown_address: SocketAddr = "127.0.0.1:14193".parse().unwrap();
+        assert!(server.client_info(&unknown_address).is_none());
+    }
+
+    #[test]
+    fn raw_message_for_a_disconnected_user_is_refused() {
+        let mut server = new_test_server(Duration::from_secs(3600), None);
+        let mut actor_keys = DenseSlotMap::<ActorKey, ()>::with_key();
+        let pawn_key = actor_keys.insert(());
+        let address: SocketAddr = "127.0.0.1:14191".parse().unwrap();
+        let (user_key, _) = connect_test_user(&mut server, address, &pawn_key);
+        assert!(server.queue_raw(&user_key, 1, &[1, 2, 3]).is_ok());
+
+        server.outstanding_disconnects.push_back(user_key);
+        assert!(server.try_receive().is_some());
+        match server.queue_raw(&user_key, 1, &[1, 2, 3]) {
+            Err(NaiaServerError::Wrapped(error)) => assert!(matches!(
+                error.downcast_ref::<RawMessageError>(),
+                Some(RawMessageError::NotConnected)
+            )),
+            _ => panic!("the raw message should be refused"),
+        }
+    }
+
+    #[test]
+    fn client_capacity_sizes_the_per_client_maps() {
+        let link = MockLink::new("127.0.0.1:14191".parse().unwrap());
+        let shared_config = SharedConfig::new(TEST_TICK_INTERVAL, None, None);
+        let server_config = ServerConfig {
+            client_capacity: 16,
+            ..ServerConfig::default()
+        };
+        let server = NaiaServer::new_mock(
+            &link,
+            Manifest::<TestEventType, TestActorType>::new(),
+            Some(server_config),
+            shared_config,
+        );
+
+        assert!(server.users.capacity() >= 16);
+        assert!(server.client_connections.capacity() >= 16);
+        assert!(server.address_to_user_key_map.capacity() >= 16);
+    }
+
+    #[test]
+    fn handshake_and_event_exchange_run_in_memory() {
+        let link = MockLink::new("127.0.0.1:14191".parse().unwrap());
+        // the Client holds back received Data packets until its next tick
+        let shared_config = SharedConfig::new(TEST_TICK_INTERVAL, None, None);
+
+        let mut server_manifest = Manifest::<TestEventType, TestActorType>::new();
+        server_manifest.register_event(Box::new(ChatEventBuilder));
+        let mut server = NaiaServer::new_mock(&link, server_manifest, None, shared_config.clone());
+
+        let mut client_manifest = Manifest::<TestEventType, TestActorType>::new();
+        client_manifest.register_event(Box::new(ChatEventBuilder));
+        // the Client only moves on to the next handshake step when it next
+        // sends a handshake message
+        let client_config = ClientConfig {
+            send_handshake_interval: Duration::from_millis(0),
+            ..Default::default()
+        };
+        let mut client = NaiaClient::new_mock(
+            &link,
+            client_manifest,
+            Some(client_config),
+            shared_config,
+            None,
+        );
+
+        let mut connected_user = None;
+        let mut client_connected = false;
+        let mut server_received_event = false;
+        let mut client_received_event = false;
+        for _ in 0..20 {
+            while let Some(result) = client.receive() {
+                match result {
+                    Ok(ClientEvent::Connection(_)) => {
+                        client_connected = true;
+                        client.send_event(&ChatEvent).unwrap();
+                    }
+                    Ok(ClientEvent::Event(_)) => client_received_event = true,
+                    _ => {}
+                }
+            }
+            while let Some(result) = server.try_receive() {
+                match result {
+                    Ok(ServerEvent::Connection(user_key, _)) => connected_user = Some(user_key),
+                    Ok(ServerEvent::Event(user_key, _)) => {
+                        assert!(Some(user_key) == connected_user);
+                        server_received_event = true;
+                        server.queue_event(&user_key, &ChatEvent);
+                    }
+                    _ => {}
+                }
+            }
+            server.send_all_updates().now_or_never().unwrap();
+            client.ring_tick();
+        }
+
+        assert!(connected_user.is_some());
+        assert!(client_connected);
+        assert!(client.server_epoch() == Some(server.epoch()));
+        assert!(server_received_event);
+        assert!(client_received_event);
+    }
+
+    #[test]
+    fn assigned_pawn_is_available_to_the_client_interpolated_and_predicted() {
+        let (mut server, mut client) = new_position_link(14198);
+        let user_key = connect_over_link(&mut server, &mut client);
+
+        let room_key = server.create_room();
+        let actor_key = server.register_actor(PositionActorType(Rc::new(RefCell::new(
+            PositionActor::new(7),
+        ))));
+        server.room_add_user(&room_key, &user_key);
+        server.room_add_actor(&room_key, &actor_key);
+        server.assign_pawn(&user_key, &actor_key);
+
+        let mut pawn_key = None;
+        for event in exchange_over_link(&mut server, &mut client, 20) {
+            if let ClientEvent::AssignPawn(key) = event {
+                pawn_key = Some(key);
+            }
+        }
+
+        let pawn_key = pawn_key.unwrap();
+        assert!(client.get_pawn(&pawn_key).unwrap().x() == 7);
+        assert!(client.get_pawn_predicted(&pawn_key).unwrap().x() == 7);
+        assert!(client
+            .get_pawn_predicted(&pawn_key.wrapping_add(1))
+            .is_none());
+    }
+
+    #[test]
+    fn actor_update_callback_is_given_each_update_as_it_is_received() {
+        let (mut server, mut client) = new_position_link(14199);
+        let updates = Rc::new(RefCell::new(Vec::new()));
+        let recorded_updates = updates.clone();
+        client.on_actor_update(Box::new(move |key, actor: &PositionActorType| {
+            recorded_updates.borrow_mut().push((key, actor.x()));
+        }));
+        let user_key = connect_over_link(&mut server, &mut client);
+
+        let room_key = server.create_room();
+        let position = Rc::new(RefCell::new(PositionActor::new(7)));
+        let actor_key = server.register_actor(PositionActorType(position.clone()));
+        server.room_add_user(&room_key, &user_key);
+        server.room_add_actor(&room_key, &actor_key);
+
+        let mut local_key = None;
+        for event in exchange_over_link(&mut server, &mut client, 20) {
+            if let ClientEvent::CreateActor(key, _, _) = event {
+                local_key = Some(key);
+            }
+        }
+        let local_key = local_key.unwrap();
+        // creating the Actor is not an update
+        assert!(updates.borrow().is_empty());
+
+        position.borrow_mut().set_x(9);
+        let mut update_events = 0;
+        for event in exchange_over_link(&mut server, &mut client, 20) {
+            if let ClientEvent::UpdateActor(key, _) = event {
+                assert!(key == local_key);
+                update_events += 1;
+            }
+        }
+
+        assert!(update_events == 1);
+        assert!(*updates.borrow() == vec![(local_key, 9)]);
+    }
+
+    #[test]
+    fn past_actor_state_is_kept_for_the_history_length() {
+        let link = MockLink::new("127.0.0.1:14201".parse().unwrap());
+        let mut manifest = Manifest::<TestEventType, PositionActorType>::new();
+        manifest.register_actor(Box::new(PositionActorBuilder));
+        let server_config = ServerConfig {
+            actor_history_length: 3,
+            ..Default::default()
+        };
+        let mut server = NaiaServer::new_mock(
+            &link,
+            manifest,
+            Some(server_config),
+            SharedConfig::new(TEST_TICK_INTERVAL, None, None),
+        );
+        let position = Rc::new(RefCell::new(PositionActor::new(0)));
+        let actor_key = server.register_actor(PositionActorType(position.clone()));
+
+        // the Actor moves to the number of each tick, once that tick begins
+        let mut ticks = Vec::new();
+        for _ in 0..6 {
+            server.tick_timer.ring_manual();
+            match server.try_receive() {
+                Some(Ok(ServerEvent::Tick(tick))) => {
+                    position.borrow_mut().set_x(tick as u8);
+                    ticks.push(tick);
+                }
+                _ => panic!("expected a Tick event"),
+            }
+        }
+
+        // each tick's state is the one it began with, left by the tick before
+        for index in 3..6 {
+            let past_state = server
+                .actor_state_at_tick(&actor_key, ticks[index])
+                .unwrap();
+            assert!(past_state.x() == ticks[index - 1] as u8);
+        }
+        assert!(position.borrow().x == ticks[5] as u8);
+        // older ticks are dropped once the history is full
+        assert!(server.actor_state_at_tick(&actor_key, ticks[2]).is_none());
+    }
+
+    #[test]
+    fn disconnecting_client_is_dropped_by_the_server_within_a_tick() {
+        let (mut server, mut client) = new_position_link(14202);
+        let user_key = connect_over_link(&mut server, &mut client);
+
+        client.disconnect().unwrap();
+        assert!(!client.has_connection());
+        assert!(!client.is_connected());
+
+        // the disconnection timeout is 10 seconds, so only the Disconnect
+        // packet can drop the User this soon
+        let mut ticks_before_disconnection = 0;
+        let mut disconnected_user = None;
+        while disconnected_user.is_none() && ticks_before_disconnection <= 1 {
+            match server.try_receive() {
+                Some(Ok(ServerEvent::Disconnection(user_key, _))) => {
+                    disconnected_user = Some(user_key)
+                }
+                Some(Ok(ServerEvent::Tick(_))) => ticks_before_disconnection += 1,
+                Some(_) => {}
+                None => server.tick_timer.ring_manual(),
+            }
+        }
+
+        assert!(disconnected_user == Some(user_key));
+        assert!(server.get_users_count() == 0);
+    }
+
+    #[test]
+    fn kicked_client_receives_the_reason_and_drops_its_connection() {
+        let (mut server, mut client) = new_position_link(14203);
+        let user_key = connect_over_link(&mut server, &mut client);
+
+        let reason = TestEventType(TypeId::of::<ChatEvent>());
+        server
+            .disconnect_client(&user_key, Some(reason))
+            .now_or_never()
+            .unwrap();
+
+        let mut disconnected_user = None;
+        while let Some(result) = server.try_receive() {
+            if let Ok(ServerEvent::Disconnection(user_key, _)) = result {
+                disconnected_user = Some(user_key);
+            }
+        }
+        assert!(disconnected_user == Some(user_key));
+
+        let mut received_reason = None;
+        while let Some(result) = client.receive() {
+            if let Ok(ClientEvent::Disconnection(reason)) = result {
+                received_reason = Some(reason);
+                break;
+            }
+        }
+        let received_reason = received_reason.expect("the Client was not disconnected");
+        assert!(received_reason.unwrap().get_type_id() == TypeId::of::<ChatEvent>());
+        assert!(!client.has_connection());
+        assert!(!client.is_connected());
+    }
+
+    #[test]
+    fn single_poll_returns_every_received_event_in_order() {
+        let (mut server, mut client) = new_position_link(14204);
+        let user_key = connect_over_link(&mut server, &mut client);
+
+        let queued_types = vec![
+            TypeId::of::<ChatEvent>(),
+            TypeId::of::<MoveEvent>(),
+            TypeId::of::<MoveEvent>(),
+            TypeId::of::<ChatEvent>(),
+        ];
+        server.queue_event(&user_key, &ChatEvent);
+        server.queue_event(&user_key, &MoveEvent);
+        server.queue_event(&user_key, &MoveEvent);
+        server.queue_event(&user_key, &ChatEvent);
+
+        // the Events share a packet, which the Client releases at one of its
+        // ticks, so that a single poll() returns all of them
+        let mut events = Vec::new();
+        let mut received_types = Vec::new();
+        for _ in 0..50 {
+            while server.try_receive().is_some() {}
+            server.send_all_updates().now_or_never().unwrap();
+            client.poll(&mut events);
+            for result in events.iter() {
+                if let Ok(ClientEvent::Event(event)) = result {
+                    received_types.push(event.get_type_id());
+                }
+            }
+            if !received_types.is_empty() {
+                break;
+            }
+            client.ring_tick();
+        }
+
+        assert!(received_types == queued_types);
+    }
+
+    #[test]
+    fn client_connection_state_follows_the_handshake() {
+        let (mut server, mut client) = new_position_link(14205);
+        let states = Rc::new(RefCell::new(Vec::new()));
+        let recorded_states = states.clone();
+        client.on_state_change(Box::new(move |state| {
+            recorded_states.borrow_mut().push(state);
+        }));
+        assert!(client.connection_state() == ClientConnectionState::AwaitingChallengeResponse);
+        assert!(!client.is_connected());
+
+        connect_over_link(&mut server, &mut client);
+
+        assert!(
+            *states.borrow()
+                == vec![
+                    ClientConnectionState::AwaitingConnectResponse,
+                    ClientConnectionState::Connected,
+                ]
+        );
+        assert!(client.connection_state() == ClientConnectionState::Connected);
+        assert!(client.is_connected());
+    }
+
+    #[test]
+    fn oversized_raw_message_is_refused_without_blocking_the_channel() {
+        let link = MockLink::new("127.0.0.1:14194".parse().unwrap());
+        let shared_config = SharedConfig::new(TEST_TICK_INTERVAL, None, None);
+        let mut server = NaiaServer::new_mock(
+            &link,
+            Manifest::<TestEventType, TestActorType>::new(),
+            None,
+            shared_config.clone(),
+        );
+        let client_config = ClientConfig {
+            send_handshake_interval: Duration::from_millis(0),
+            ..Default::default()
+        };
+        let mut client = NaiaClient::new_mock(
+            &link,
+            Manifest::<TestEventType, TestActorType>::new(),
+            Some(client_config),
+            shared_config,
+            None,
+        );
+
+        let mut received_raw = Vec::new();
+        for _ in 0..20 {
+            while let Some(result) = client.receive() {
+                if let Ok(ClientEvent::Raw(channel, data)) = result {
+                    received_raw.push((channel, data));
+                }
+            }
+            while let Some(result) = server.try_receive() {
+                if let Ok(ServerEvent::Connection(user_key, _)) = result {
+                    assert!(server.queue_raw(&user_key, 1, &vec![0; 1 << 16]).is_err());
+                    assert!(server.queue_raw(&user_key, 2, &[1, 2, 3]).is_ok());
+                }
+            }
+            server.send_all_updates().now_or_never().unwrap();
+            client.ring_tick();
+        }
+
+        assert!(received_raw == vec![(2, vec![1, 2, 3])]);
+    }
+
+    // Flips every bit of a payload, under the given id
+    struct InvertingCompressor(u8);
+
+    impl Compressor for InvertingCompressor {
+        fn id(&self) -> u8 {
+            return self.0;
+        }
+        fn compress(&self, payload: &[u8]) -> Vec<u8> {
+            return payload.iter().map(|byte| !byte).collect();
+        }
+        fn decompress(&self, payload: &[u8]) -> Option<Vec<u8>> {
+            return Some(payload.iter().map(|byte| !byte).collect());
+        }
+    }
+
+    #[test]
+    fn compression_is_only_agreed_with_the_same_compressor() {
+        for (port, client_compressor_id, agreed) in [(14195, 1, true), (14196, 2, false)].iter() {
+            let link = MockLink::new(format!("127.0.0.1:{}", port).parse().unwrap());
+            let server_compressor: Rc<dyn Compressor> = Rc::new(InvertingCompressor(1));
+            let client_compressor: Rc<dyn Compressor> =
+                Rc::new(InvertingCompressor(*client_compressor_id));
+
+            let mut server_manifest = Manifest::<TestEventType, TestActorType>::new();
+            server_manifest.register_event(Box::new(ChatEventBuilder));
+            let mut server = NaiaServer::new_mock(
+                &link,
+                server_manifest,
+                None,
+                SharedConfig::new(TEST_TICK_INTERVAL, None, Some(server_compressor)),
+            );
+            let mut client_manifest = Manifest::<TestEventType, TestActorType>::new();
+            client_manifest.register_event(Box::new(ChatEventBuilder));
+            let client_config = ClientConfig {
+                send_handshake_interval: Duration::from_millis(0),
+                ..Default::default()
+            };
+            let mut client = NaiaClient::new_mock(
+                &link,
+                client_manifest,
+                Some(client_config),
+                SharedConfig::new(TEST_TICK_INTERVAL, None, Some(client_compressor)),
+                None,
+            );
+
+            let mut client_received_event = false;
+            for _ in 0..20 {
+                while let Some(result) = client.receive() {
+                    if let Ok(ClientEvent::Event(_)) = result {
+                        client_received_event = true;
+                    }
+                }
+                while let Some(result) = server.try_receive() {
+                    if let Ok(ServerEvent::Connection(user_key, _)) = result {
+                        server.queue_event(&user_key, &ChatEvent);
+                    }
+                }
+                server.send_all_updates().now_or_never().unwrap();
+                client.ring_tick();
+            }
+
+            let connection = server.client_connections.values().next().unwrap();
+            assert!(connection.has_compressor() == *agreed);
+            assert!(client_received_event);
+        }
+    }
+
+    #[test]
+    fn sync_server_completes_a_handshake_over_loopback() {
+        // find a free port to listen on
+        let ip_address = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let port = UdpSocket::bind((ip_address, 0))
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let server_address = SocketAddr::new(ip_address, port);
+        let shared_config = SharedConfig::new(TEST_TICK_INTERVAL, None, None);
+
+        let mut server_manifest = Manifest::<TestEventType, TestActorType>::new();
+        server_manifest.register_event(Box::new(ChatEventBuilder));
+        let mut server =
+            NaiaServer::new_sync(server_address, server_manifest, None, shared_config.clone());
+
+        let mut client_manifest = Manifest::<TestEventType, TestActorType>::new();
+        client_manifest.register_event(Box::new(ChatEventBuilder));
+        let client_config = ClientConfig {
+            send_handshake_interval: Duration::from_millis(0),
+            ..Default::default()
+        };
+        let mut client = NaiaClient::new(
+            server_address,
+            client_manifest,
+            Some(client_config),
+            shared_config,
+            None,
+        );
+
+        // no runtime is driving either side, only these calls
+        let mut server_connected = false;
+        let mut client_connected = false;
+        for _ in 0..500 {
+            while let Some(result) = client.receive() {
+                if let Ok(ClientEvent::Connection(_)) = result {
+                    client_connected = true;
+                }
+            }
+            while let Some(result) = server.try_receive() {
+                if let Ok(ServerEvent::Connection(_, _)) = result {
+                    server_connected = true;
+                }
+            }
+            server.try_send_all_updates();
+            if server_connected && client_connected {
+                break;
+            }
+        }
+
+        assert!(server_connected);
+        assert!(client_connected);
+    }
+
+    #[test]
+    fn connection_event_carries_the_auth_event_evaluated() {
+        // the auth event is only read when there is an auth function to
+        // evaluate it
+        for has_auth_func in [true, false].iter() {
+            let link = MockLink::new("127.0.0.1:14191".parse().unwrap());
+            let shared_config = SharedConfig::new(TEST_TICK_INTERVAL, None, None);
+
+            let mut server_manifest = Manifest::<TestEventType, TestActorType>::new();
+            server_manifest.register_event(Box::new(ChatEventBuilder));
+            server_manifest.register_event(Box::new(MoveEventBuilder));
+            let mut server =
+                NaiaServer::new_mock(&link, server_manifest, None, shared_config.clone());
+            if *has_auth_func {
+                server.on_auth(Rc::new(Box::new(|_, _| Ok(()))));
+            }
+
+            let mut client_manifest = Manifest::<TestEventType, TestActorType>::new();
+            client_manifest.register_event(Box::new(ChatEventBuilder));
+            client_manifest.register_event(Box::new(MoveEventBuilder));
+            let client_config = ClientConfig {
+                send_handshake_interval: Duration::from_millis(0),
+                ..Default::default()
+            };
+            let mut client = NaiaClient::new_mock(
+                &link,
+                client_manifest,
+                Some(client_config),
+                shared_config,
+                Some(TestEventType(TypeId::of::<MoveEvent>())),
+            );
+
+            let mut auth_event = None;
+            for _ in 0..20 {
+                while client.receive().is_some() {}
+                while let Some(result) = server.try_receive() {
+                    if let Ok(ServerEvent::Connection(_, event)) = result {
+                        auth_event = Some(event);
+                    }
+                }
+            }
+
+            let auth_event = auth_event.expect("the Client never connected");
+            if *has_auth_func {
+                assert!(auth_event.unwrap().get_type_id() == TypeId::of::<MoveEvent>());
+            } else {
+                assert!(auth_event.is_none());
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "max_actor_updates_per_tick must be at least 1")]
+    fn zero_actor_updates_per_tick_is_rejected() {
+        let link = MockLink::new("127.0.0.1:14200".parse().unwrap());
+        let server_config = ServerConfig {
+            max_actor_updates_per_tick: Some(0),
+            ..Default::default()
+        };
+        NaiaServer::new_mock(
+            &link,
+            Manifest::<TestEventType, TestActorType>::new(),
+            Some(server_config),
+            SharedConfig::new(TEST_TICK_INTERVAL, None, None),
+        );
+    }
+
+    #[test]
+    fn client_beyond_max_clients_is_rejected() {
+        // two Clients are already connected, so a third only fits if the
+        // limit is higher than two
+        for (max_clients, expect_connection) in [(3, true), (2, false)] {
+            let link = MockLink::new("127.0.0.1:14191".parse().unwrap());
+            let shared_config = SharedConfig::new(TEST_TICK_INTERVAL, None, None);
+
+            let mut server_manifest = Manifest::<TestEventType, TestActorType>::new();
+            server_manifest.register_event(Box::new(ChatEventBuilder));
+            let server_config = ServerConfig {
+                max_clients: Some(max_clients),
+                ..ServerConfig::default()
+            };
+            let mut server = NaiaServer::new_mock(
+                &link,
+                server_manifest,
+                Some(server_config),
+                shared_config.clone(),
+            );
+            server.set_server_full_reason(Some(TestEventType(TypeId::of::<ChatEvent>())));
+            let mut actor_keys = DenseSlotMap::<ActorKey, ()>::with_key();
+            let pawn_key = actor_keys.insert(());
+            connect_test_user(&mut server, "127.0.0.1:14192".parse().unwrap(), &pawn_key);
+            connect_test_user(&mut server, "127.0.0.1:14193".parse().unwrap(), &pawn_key);
+
+            let mut client_manifest = Manifest::<TestEventType, TestActorType>::new();
+            client_manifest.register_event(Box::new(ChatEventBuilder));
+            let client_config = ClientConfig {
+                send_handshake_interval: Duration::from_millis(0),
+                ..Default::default()
+            };
+            let mut client = NaiaClient::new_mock(
+                &link,
+                client_manifest,
+                Some(client_config),
+                shared_config,
+                None,
+            );
+
+            let mut client_connected = false;
+            let mut rejection_reason = None;
+            for _ in 0..20 {
+                while let Some(result) = client.receive() {
+                    match result {
+                        Ok(ClientEvent::Connection(_)) => client_connected = true,
+                        Ok(ClientEvent::RejectConnection(reason)) => rejection_reason = reason,
+                        _ => {}
+                    }
+                }
+                while server.try_receive().is_some() {}
+            }
+
+            assert!(client_connected == expect_connection);
+            if expect_connection {
+                assert!(server.client_count() == 3);
+            } else {
+                assert!(server.client_count() == 2);
+                let reason = rejection_reason.unwrap();
+                assert!(reason.get_type_id() == TypeId::of::<ChatEvent>());
+            }
+        }
+    }
+
+    #[test]
+    fn retrying_auth_after_rejection_connects() {
+        let link = MockLink::new("127.0.0.1:14191".parse().unwrap());
+        let shared_config = SharedConfig::new(TEST_TICK_INTERVAL, None, None);
+
+        let mut server_manifest = Manifest::<TestEventType, TestActorType>::new();
+        server_manifest.register_event(Box::new(ChatEventBuilder));
+        server_manifest.register_event(Box::new(MoveEventBuilder));
+        let mut server = NaiaServer::new_mock(&link, server_manifest, None, shared_config.clone());
+        // only a ChatEvent holds valid credentials
+        server.on_auth(Rc::new(Box::new(|_, auth_event| {
+            if auth_event.get_type_id() == TypeId::of::<ChatEvent>() {
+                return Ok(());
+            }
+            return Err(None);
+        })));
+
+        let mut client_manifest = Manifest::<TestEventType, TestActorType>::new();
+        client_manifest.register_event(Box::new(ChatEventBuilder));
+        client_manifest.register_event(Box::new(MoveEventBuilder));
+        let client_config = ClientConfig {
+            send_handshake_interval: Duration::from_millis(0),
+            ..Default::default()
+        };
+        let mut client = NaiaClient::new_mock(
+            &link,
+            client_manifest,
+            Some(client_config),
+            shared_config,
+            Some(TestEventType(TypeId::of::<MoveEvent>())),
+        );
+
+        // new credentials can't be supplied in the middle of a handshake
+        assert!(client
+            .retry_auth(TestEventType(TypeId::of::<ChatEvent>()))
+            .is_err());
+
+        let mut rejected = false;
+        let mut connected_user = None;
+        for _ in 0..40 {
+            while let Some(result) = client.receive() {
+                match result {
+                    Ok(ClientEvent::RejectConnection(_)) => {
+                        rejected = true;
+                        // retried within the same second as the first attempt, so
+                        // only the credentials tell the two requests apart
+                        assert!(client
+                            .retry_auth(TestEventType(TypeId::of::<ChatEvent>()))
+                            .is_ok());
+                    }
+                    Ok(ClientEvent::ConnectionTimeout) => panic!("the retry was not answered"),
+                    _ => {}
+                }
+            }
+            while let Some(result) = server.try_receive() {
+                if let Ok(ServerEvent::Connection(user_key, _)) = result {
+                    connected_user = Some(user_key);
+                }
+            }
+            server.send_all_updates().now_or_never().unwrap();
+            client.ring_tick();
+        }
+
+        assert!(rejected);
+        assert!(connected_user.is_some());
+        assert!(client.is_connected());
+        assert!(server.client_count() == 1);
+    }
+
+    #[test]
+    fn lost_rejection_is_sent_again() {
+        let link = MockLink::new("127.0.0.1:14191".parse().unwrap());
+        let shared_config = SharedConfig::new(TEST_TICK_INTERVAL, None, None);
+
+        let server_config = ServerConfig {
+            max_clients: Some(0),
+            ..ServerConfig::default()
+        };
+        let mut server = NaiaServer::new_mock(
+            &link,
+            Manifest::<TestEventType, TestActorType>::new(),
+            Some(server_config),
+            shared_config.clone(),
+        );
+
+        let client_config = ClientConfig {
+            send_handshake_interval: Duration::from_millis(0),
+            ..Default::default()
+        };
+        let mut client = NaiaClient::new_mock(
+            &link,
+            Manifest::<TestEventType, TestActorType>::new(),
+            Some(client_config),
+            shared_config,
+            None,
+        );
+
+        let mut rejections_dropped = 0;
+        let mut client_rejected = false;
+        for _ in 0..40 {
+            while let Some(result) = client.receive() {
+                if let Ok(ClientEvent::RejectConnection(_)) = result {
+                    client_rejected = true;
+                }
+            }
+            while server.try_receive().is_some() {}
+
+            // lose the first rejection on its way to the Client
+            let mut delivered = Vec::new();
+            while let Some(packet) = link.receive_at_client() {
+                let (header, _) = StandardHeader::read(&packet).unwrap();
+                if header.packet_type() == PacketType::ServerRejectConnect
+                    && rejections_dropped == 0
+                {
+                    rejections_dropped += 1;
+                    continue;
+                }
+                delivered.push(packet);
+            }
+            for packet in delivered {
+                link.send_to_client(packet);
+            }
+        }
+
+        assert!(rejections_dropped == 1);
+        assert!(client_rejected);
+    }
+
+    #[test]
+    fn periodic_event_is_queued_every_interval() {
+        let mut server = new_test_server(Duration::from_secs(3600), None);
+        let mut actor_keys = DenseSlotMap::<ActorKey, ()>::with_key();
+        let pawn_key = actor_keys.insert(());
+        let address: SocketAddr = "127.0.0.1:14191".parse().unwrap();
+        let (user_key, _) = connect_test_user(&mut server, address, &pawn_key);
+
+        let queued_ticks = Rc::new(RefCell::new(Vec::new()));
+        let queued_ticks_ref = queued_ticks.clone();
+        server.set_periodic_event(
+            3,
+            Rc::new(Box::new(move |tick| {
+                queued_ticks_ref.borrow_mut().push(tick);
+                return Box::new(ChatEvent);
+            })),
+        );
+
+        for _ in 0..10 {
+            server.tick_manager.increment_tick();
+            server.queue_periodic_event();
+        }
+
+        assert!(*queued_ticks.borrow() == vec![3, 6, 9]);
+        let mut manifest = Manifest::<TestEventType, TestActorType>::new();
+        manifest.register_event(Box::new(ChatEventBuilder));
+        let connection = server.client_connections.get_mut(&user_key).unwrap();
+        assert!(connection.get_outgoing_packet(0, &manifest).is_some());
+    }
+
+    #[test]
+    fn commands_for_an_actor_are_only_accepted_from_its_authority() {
+        let mut manifest = Manifest::<TestEventType, TestActorType>::new();
+        manifest.register_event(Box::new(ChatEventBuilder));
+        let mut server = new_test_server(Duration::from_secs(3600), None);
+        let actor_key = server.register_actor(TestActorType(Rc::new(RefCell::new(TestActor))));
+        let actor = server
+            .global_actor_store
+            .get(actor_key)
+            .unwrap()
+            .inner_ref();
+        let (owner, _) =
+            connect_test_user(&mut server, "127.0.0.1:14191".parse().unwrap(), &actor_key);
+        let (other, _) =
+            connect_test_user(&mut server, "127.0.0.1:14192".parse().unwrap(), &actor_key);
+        server.set_actor_authority(&actor_key, Some(owner));
+        assert!(server.get_actor_authority(&actor_key) == Some(owner));
+
+        // both Clients send a Command for the Actor, which is the first Actor
+        // in each of their scopes
+        let naia_id = manifest.get_event_naia_id(&TypeId::of::<ChatEvent>());
+        let mut command_bytes = vec![ManagerType::Command as u8, 1, 0, 0];
+        command_bytes.write_u16::<BigEndian>(naia_id).unwrap();
+        command_bytes.push(0);
+        for user_key in [owner, other] {
+            let connection = server.client_connections.get_mut(&user_key).unwrap();
+            connection.add_actor(&actor_key, &actor, None, None);
+            connection.process_incoming_data(0, 0, &manifest, &command_bytes);
+        }
+
+        let mut commanding_users = Vec::new();
+        while let Some(Ok(ServerEvent::Command(user_key, key, _))) = server.try_receive() {
+            assert!(key == actor_key);
+            commanding_users.push(user_key);
+        }
+        assert!(commanding_users == vec![owner]);
+    }
+
+    #[test]
+    fn filtered_events_do_not_surface() {
+        let mut manifest = Manifest::<TestEventType, TestActorType>::new();
+        manifest.register_event(Box::new(ChatEventBuilder));
+        manifest.register_event(Box::new(MoveEventBuilder));
+        let mut server = new_test_server(Duration::from_secs(3600), None);
+        let mut actor_keys = DenseSlotMap::<ActorKey, ()>::with_key();
+        let pawn_key = actor_keys.insert(());
+        let address: SocketAddr = "127.0.0.1:14191".parse().unwrap();
+        let (user_key, _) = connect_test_user(&mut server, address, &pawn_key);
+
+        // a Client sends a MoveEvent between two ChatEvents
+        let mut_handler = MutHandler::new();
+        let mut client = ClientConnection::<TestEventType, TestActorType>::new(
+            address,
+            Some(&mut_handler),
+            &ConnectionConfig::default(),
+            None,
+        );
+        client.queue_event(&ChatEvent);
+        client.queue_event(&MoveEvent);
+        client.queue_event(&ChatEvent);
+        let packet = client.get_outgoing_packet(0, &manifest).unwrap();
+        let (_, payload) = StandardHeader::read(&packet).unwrap();
+        let connection = server.client_connections.get_mut(&user_key).unwrap();
+        connection.process_incoming_data(0, 0, &manifest, &payload);
+
+        server.set_event_filter(Rc::new(Box::new(|_, event| {
+            return event.get_type_id() != TypeId::of::<MoveEvent>();
+        })));
+
+        let mut received_types = Vec::new();
+        while let Some(Ok(ServerEvent::Event(_, event))) = server.try_receive() {
+            received_types.push(event.get_type_id());
+        }
+        assert!(received_types == vec![TypeId::of::<ChatEvent>(), TypeId::of::<ChatEvent>()]);
+    }
+}
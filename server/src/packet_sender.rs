@@ -0,0 +1,33 @@
+use std::error::Error;
+#[cfg(any(test, feature = "sync"))]
+use std::net::UdpSocket;
+
+use naia_server_socket::{MessageSender, Packet};
+
+/// Sends the packets the Server writes, either through the channel of its
+/// socket, or, for a Server created with new_sync(), straight into a UDP
+/// socket. Sends into a UDP socket complete without awaiting, so a Server
+/// polled from a synchronous game loop never leaves a send half-finished
+#[derive(Debug)]
+pub enum PacketSender {
+    /// Sends packets through a socket's channel, to be sent once the socket
+    /// is next polled
+    Channel(MessageSender),
+    /// Writes packets immediately into a non-blocking UDP socket
+    #[cfg(any(test, feature = "sync"))]
+    Udp(UdpSocket),
+}
+
+impl PacketSender {
+    /// Sends a Packet to a Client
+    pub async fn send(&mut self, packet: Packet) -> Result<(), Box<dyn Error + Send + Sync>> {
+        match self {
+            PacketSender::Channel(sender) => return sender.send(packet).await,
+            #[cfg(any(test, feature = "sync"))]
+            PacketSender::Udp(socket) => match socket.send_to(packet.payload(), packet.address()) {
+                Ok(_) => return Ok(()),
+                Err(error) => return Err(Box::new(error)),
+            },
+        }
+    }
+}
@@ -1,12 +1,30 @@
 use std::{default::Default, time::Duration};
 
+use naia_shared::{DisconnectPolicy, OverflowPolicy, MTU_SIZE};
+
 /// Contains Config properties which will be used by a Server or Client
 #[derive(Clone, Debug)]
 pub struct ServerConfig {
     /// The duration between the resend of certain connection handshake messages
     pub send_handshake_interval: Duration,
+    /// The maximum age of the timestamp in a Client's connect request. Older
+    /// requests, or requests reusing a timestamp already seen from the same
+    /// address, are refused to guard against replayed handshakes. Client
+    /// clocks are expected to be roughly in sync with the Server's.
+    pub handshake_timestamp_window: Duration,
+    /// The maximum number of challenge requests answered per second from a
+    /// single IP address. Excess requests are dropped silently, so that the
+    /// Server can't be flooded with handshakes. This should allow for a
+    /// Client resending its request every `send_handshake_interval`.
+    pub challenge_requests_per_second: u16,
+    /// How long to wait for communication from a remote host before
+    /// initiating a disconnect, either as a duration or as a number of missed
+    /// heartbeats
+    pub disconnect_policy: DisconnectPolicy,
     /// The duration to wait for communication from a remote host before
-    /// initiating a disconnect
+    /// initiating a disconnect. Only used while `disconnect_policy` is left at
+    /// its default
+    #[deprecated(note = "use disconnect_policy instead")]
     pub disconnection_timeout_duration: Duration,
     /// The duration to wait before sending a heartbeat message to a remote
     /// host, if the host has not already sent another message within that time.
@@ -17,16 +35,131 @@ pub struct ServerConfig {
     /// Number of samples to measure RTT & Jitter by. A higher number will
     /// smooth out RTT measurements, but at the cost of responsiveness.
     pub rtt_sample_size: u16,
+    /// The largest datagram, in bytes, that will be sent to the remote host.
+    /// Larger packets are split into fragments and reassembled by the remote
+    /// host.
+    pub max_packet_size: usize,
+    /// The duration to wait for the remaining fragments of a packet before
+    /// dropping the fragments which have been received
+    pub fragment_timeout_duration: Duration,
+    /// The maximum number of times a guaranteed Event will be retransmitted
+    /// after being dropped, before giving up on it. If None, guaranteed Events
+    /// will be retransmitted until they are delivered.
+    pub max_retransmits: Option<u16>,
+    /// Number of most recently sent packets over which to measure packet
+    /// loss
+    pub packet_loss_window_size: u16,
+    /// The maximum number of Events written into a single packet, leaving
+    /// room for Actor updates when many Events are queued. Events beyond the
+    /// cap wait for the next packet. If None, Events are written until the
+    /// packet is full.
+    pub max_events_per_packet: Option<u16>,
+    /// The largest payload, in bytes, that Events, Commands & Actor messages
+    /// are packed into before the remainder waits for the next packet. Raise
+    /// it for larger datagrams on a LAN, or lower it for mobile networks.
+    pub max_payload_size: usize,
+    /// Number of ping intervals to wait for a pong from the remote host, after
+    /// which RTT is considered unknown until pongs arrive again. This is
+    /// independent of the disconnection timeout, which heartbeats also reset.
+    pub pong_timeout_intervals: u16,
+    /// The longest duration that outgoing Events, Commands & Actor messages
+    /// are held back for, so that data queued in quick succession is sent in
+    /// fewer, fuller packets at the cost of some latency. Held data is always
+    /// sent at the next tick. If None, data is sent as soon as it is queued.
+    pub coalesce_delay: Option<Duration>,
+    /// The number of Events that the queues of outgoing, incoming & in-flight
+    /// Events have room for from the start, so that a busy connection doesn't
+    /// reallocate them as they grow
+    pub event_capacity: usize,
+    /// Whether to append a checksum to every packet sent over the connection,
+    /// so that packets corrupted in transit are dropped instead of processed.
+    /// Checksums are only used if both the Client & Server enable this, as
+    /// agreed on during the handshake
+    pub integrity_check: bool,
+    /// The maximum number of Events waiting to be sent to the remote host,
+    /// including those queued up again for retransmission. Events queued beyond
+    /// it are handled according to the `overflow_policy`. If None, the queue
+    /// grows without limit.
+    pub max_outgoing_queue_len: Option<usize>,
+    /// What happens to an Event queued while the outgoing queue is already
+    /// `max_outgoing_queue_len` long
+    pub overflow_policy: OverflowPolicy,
+    /// The maximum number of bytes of Data packets sent to each Client per
+    /// second, so that a Client on a slow link does not build up a large
+    /// backlog in the network. Once a Client's budget is spent, its remaining
+    /// Events & Actor updates wait for later calls to send_all_updates(). If
+    /// None, Data packets are sent as fast as they are produced.
+    pub max_send_bytes_per_second: Option<u32>,
+    /// How long the Server keeps the connection of a Client which has timed
+    /// out, so that the Client can reconnect with its session token and keep
+    /// its Actors & Pawns, even from a new address. If None, Clients are
+    /// disconnected as soon as they time out.
+    pub reconnect_grace_period: Option<Duration>,
+    /// The number of past ticks for which the Server keeps a copy of every
+    /// Actor's state, available through actor_state_at_tick(), for example
+    /// to rewind Actors by a Client's RTT for hit detection. If 0, no history
+    /// is kept.
+    pub actor_history_length: usize,
+    /// The number of connected Clients that the Server has room for from the
+    /// start, so that its per-Client maps don't reallocate as Clients join
+    pub client_capacity: usize,
+    /// The tick the Server starts counting from. A Server restarted within
+    /// the same epoch should start from the tick it stopped at, so that
+    /// Clients see ticks continue to increase
+    pub start_tick: u16,
+    /// Identifies this run of the Server to Clients, which treat a change of
+    /// epoch as a restart of the Server, rather than a continuation of the
+    /// ticks they have seen. Set it along with `start_tick` to carry a
+    /// session across restarts. If None, a random epoch is chosen, so every
+    /// restart is detected
+    pub epoch: Option<u32>,
+    /// The maximum number of Clients connected at once. Connect requests from
+    /// new Clients beyond this are rejected, with the reason set through
+    /// NaiaServer::set_server_full_reason(). Clients waiting to reconnect
+    /// within the grace period hold on to their place. If None, any number of
+    /// Clients may connect.
+    pub max_clients: Option<usize>,
+    /// The maximum number of Actor updates queued for each Client per call to
+    /// send_all_updates(), to keep replication of large worlds within budget.
+    /// Changed Actors accrue priority while they wait, weighted by
+    /// Actor::update_priority(), and those with the highest priority are
+    /// updated first. Creates, Deletes & Pawns are not limited. If None, every
+    /// changed Actor is updated each time. Must not be Some(0).
+    pub max_actor_updates_per_tick: Option<usize>,
 }
 
 impl Default for ServerConfig {
+    #[allow(deprecated)]
     fn default() -> Self {
         Self {
+            disconnect_policy: DisconnectPolicy::default(),
             disconnection_timeout_duration: Duration::from_secs(10),
             heartbeat_interval: Duration::from_secs(4),
             send_handshake_interval: Duration::from_secs(1),
+            handshake_timestamp_window: Duration::from_secs(60),
+            challenge_requests_per_second: 5,
             ping_interval: Duration::from_secs(1),
             rtt_sample_size: 20,
+            max_packet_size: 508,
+            fragment_timeout_duration: Duration::from_secs(2),
+            max_retransmits: None,
+            packet_loss_window_size: 100,
+            max_events_per_packet: None,
+            max_payload_size: MTU_SIZE,
+            pong_timeout_intervals: 5,
+            coalesce_delay: None,
+            event_capacity: 0,
+            integrity_check: false,
+            max_outgoing_queue_len: None,
+            overflow_policy: OverflowPolicy::DropOldest,
+            max_send_bytes_per_second: None,
+            reconnect_grace_period: None,
+            actor_history_length: 0,
+            client_capacity: 0,
+            start_tick: 0,
+            epoch: None,
+            max_clients: None,
+            max_actor_updates_per_tick: None,
         }
     }
 }
@@ -7,8 +7,10 @@ use super::{
 /// a Tick event
 pub enum ServerEvent<T> {
     /// Occurs when a new Client has successfully established a connection with
-    /// the Server
-    Connection(UserKey),
+    /// the Server. Carries the auth event the Client connected with, if an
+    /// auth function was set with NaiaServer::on_auth(), for example to load
+    /// the player's account
+    Connection(UserKey, Option<T>),
     /// Occurs when the Server has lost connection to a Client, usually as the
     /// result of a timeout
     Disconnection(UserKey, User),
@@ -16,7 +18,11 @@ pub enum ServerEvent<T> {
     Event(UserKey, T),
     /// An Command emitted to the Server from a Client
     Command(UserKey, ActorKey, T),
+    /// A raw message emitted to the Server from a Client, containing the
+    /// channel it was sent on & its bytes
+    Raw(UserKey, u8, Vec<u8>),
     /// A Tick Event, the duration between Tick events is defined in the Config
-    /// object passed to the Server on initialization
-    Tick,
+    /// object passed to the Server on initialization. Contains the Server's
+    /// new current tick, which wraps around past 65535
+    Tick(u16),
 }
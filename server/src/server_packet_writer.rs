@@ -1,6 +1,6 @@
 use byteorder::WriteBytesExt;
 
-use naia_shared::{ActorType, Event, EventPacketWriter, EventType, ManagerType, Manifest};
+use naia_shared::{EventPacketWriter, ManagerType, MTU_SIZE};
 
 /// Handles writing of Event & Actor data into an outgoing packet
 pub struct ServerPacketWriter {
@@ -12,11 +12,17 @@ pub struct ServerPacketWriter {
 }
 
 impl ServerPacketWriter {
-    /// Construct a new instance of `PacketReader`, the given `buffer` will be
-    /// used to read information from.
+    /// Construct a new instance of `ServerPacketWriter`, which packs Events &
+    /// Actor messages into a payload of at most `MTU_SIZE` bytes
     pub fn new() -> ServerPacketWriter {
+        return ServerPacketWriter::with_capacity(MTU_SIZE);
+    }
+
+    /// Construct a new instance of `ServerPacketWriter`, which packs Events &
+    /// Actor messages into a payload of at most `max_payload_size` bytes
+    pub fn with_capacity(max_payload_size: usize) -> ServerPacketWriter {
         ServerPacketWriter {
-            event_writer: EventPacketWriter::new(),
+            event_writer: EventPacketWriter::with_capacity(max_payload_size),
             actor_working_bytes: Vec::<u8>::new(),
             actor_message_count: 0,
         }
@@ -47,19 +53,31 @@ impl ServerPacketWriter {
         out_bytes.into_boxed_slice()
     }
 
+    /// Get the largest payload, in bytes, that the writer packs data into
+    pub fn max_payload_size(&self) -> usize {
+        return self.event_writer.max_payload_size();
+    }
+
     /// Get the number of bytes which is ready to be written into an outgoing
     /// packet
     pub fn bytes_number(&self) -> usize {
         return self.event_writer.bytes_number() + self.actor_working_bytes.len();
     }
 
-    /// Writes an Event into the Writer's internal buffer, which will eventually
-    /// be put into the outgoing packet
-    pub fn write_event<T: EventType, U: ActorType>(
-        &mut self,
-        manifest: &Manifest<T, U>,
-        event: &Box<dyn Event<T>>,
-    ) -> bool {
-        return self.event_writer.write_event(manifest, event);
+    /// Gets the writer for the Events & raw messages of the outgoing packet
+    pub(crate) fn event_writer_mut(&mut self) -> &mut EventPacketWriter {
+        return &mut self.event_writer;
+    }
+
+    /// Writes a raw message into the Writer's internal buffer, which will
+    /// eventually be put into the outgoing packet
+    pub fn write_raw(&mut self, channel: u8, data: &[u8]) -> bool {
+        return self.event_writer.write_raw(channel, data);
+    }
+}
+
+impl Default for ServerPacketWriter {
+    fn default() -> Self {
+        Self::new()
     }
 }
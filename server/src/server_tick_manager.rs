@@ -10,11 +10,12 @@ pub struct ServerTickManager {
 }
 
 impl ServerTickManager {
-    /// Create a new HostTickManager with a given tick interval duration
-    pub fn new(tick_interval: Duration) -> Self {
+    /// Create a new HostTickManager with a given tick interval duration,
+    /// counting from the given tick
+    pub fn new(tick_interval: Duration, start_tick: u16) -> Self {
         ServerTickManager {
             tick_interval,
-            current_tick: 0,
+            current_tick: start_tick,
         }
     }
 
@@ -29,3 +30,29 @@ impl HostTickManager for ServerTickManager {
         self.current_tick
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use naia_shared::HostTickManager;
+
+    use super::ServerTickManager;
+
+    #[test]
+    fn consecutive_ticks_increment_and_wrap() {
+        let mut tick_manager = ServerTickManager::new(Duration::from_millis(50), 0);
+        tick_manager.increment_tick();
+        assert!(tick_manager.get_tick() == 1);
+        tick_manager.increment_tick();
+        assert!(tick_manager.get_tick() == 2);
+
+        tick_manager.current_tick = 65534;
+        tick_manager.increment_tick();
+        assert!(tick_manager.get_tick() == 65535);
+        tick_manager.increment_tick();
+        assert!(tick_manager.get_tick() == 0);
+        tick_manager.increment_tick();
+        assert!(tick_manager.get_tick() == 1);
+    }
+}
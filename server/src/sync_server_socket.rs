@@ -0,0 +1,94 @@
+use std::{
+    io::ErrorKind,
+    net::{SocketAddr, UdpSocket},
+};
+
+use async_trait::async_trait;
+use futures_channel::mpsc;
+
+use naia_server_socket::{
+    LinkConditionerConfig, MessageSender, NaiaServerSocketError, Packet, ServerSocketTrait,
+};
+
+use super::packet_sender::PacketSender;
+
+// The most packets the Server can send between two calls to receive()
+const SEND_BUFFER_SIZE: usize = 1024;
+// The largest datagram which can be received
+const RECEIVE_BUFFER_SIZE: usize = 1472;
+
+/// A Server socket which exchanges packets over a non-blocking UDP socket,
+/// so that the Server can be polled from a synchronous game loop without an
+/// async runtime. The Server writes packets straight into the UDP socket
+/// through packet_sender(). Packets sent through get_sender() are held until
+/// the next call to receive()
+#[derive(Debug)]
+pub struct SyncServerSocket {
+    socket: UdpSocket,
+    sender: mpsc::Sender<Packet>,
+    outgoing: mpsc::Receiver<Packet>,
+}
+
+impl SyncServerSocket {
+    /// Binds a new SyncServerSocket to the given address
+    pub fn listen(address: SocketAddr) -> Self {
+        let socket = UdpSocket::bind(address).expect("couldn't bind to address");
+        socket
+            .set_nonblocking(true)
+            .expect("couldn't set socket to non-blocking");
+        let (sender, outgoing) = mpsc::channel(SEND_BUFFER_SIZE);
+        SyncServerSocket {
+            socket,
+            sender,
+            outgoing,
+        }
+    }
+
+    /// Gets a PacketSender which writes packets straight into the UDP socket,
+    /// without awaiting
+    pub fn packet_sender(&self) -> PacketSender {
+        let socket = self.socket.try_clone().expect("couldn't clone socket");
+        return PacketSender::Udp(socket);
+    }
+}
+
+#[async_trait]
+impl ServerSocketTrait for SyncServerSocket {
+    async fn receive(&mut self) -> Result<Packet, NaiaServerSocketError> {
+        // pass along everything the Server has sent since the last call
+        while let Ok(packet) = self.outgoing.try_recv() {
+            if let Err(error) = self.socket.send_to(packet.payload(), packet.address()) {
+                return Err(NaiaServerSocketError::Wrapped(Box::new(error)));
+            }
+        }
+
+        let mut buffer = [0; RECEIVE_BUFFER_SIZE];
+        match self.socket.recv_from(&mut buffer) {
+            Ok((length, address)) => {
+                return Ok(Packet::new(address, buffer[..length].to_vec()));
+            }
+            Err(error) if error.kind() == ErrorKind::WouldBlock => {
+                // nothing arrives until the next call, so let the tick
+                // timer win the race instead
+                return futures_util::future::pending().await;
+            }
+            Err(error) => {
+                return Err(NaiaServerSocketError::Wrapped(Box::new(error)));
+            }
+        }
+    }
+
+    fn get_sender(&mut self) -> MessageSender {
+        return MessageSender::new(self.sender.clone());
+    }
+
+    // Packets are sent straight out of the socket, so there is nowhere to
+    // condition them
+    fn with_link_conditioner(
+        self: Box<Self>,
+        _: &LinkConditionerConfig,
+    ) -> Box<dyn ServerSocketTrait> {
+        warn!("the link conditioner is not supported by the synchronous Server socket");
+        return self;
+    }
+}
@@ -0,0 +1,224 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    net::SocketAddr,
+    time::{Duration, SystemTime},
+};
+
+use naia_shared::{Timestamp, TimestampExt};
+
+/// The Server's answer to a connect request, kept so that it can be given
+/// again if the Client resends the request because the answer was lost
+#[derive(Clone, Debug)]
+pub enum ConnectDecision {
+    /// A connection was established for the request
+    Accepted,
+    /// The request was turned away, with the given ServerRejectConnect
+    /// payload
+    Rejected(Box<[u8]>),
+}
+
+/// How a connect request should be handled
+#[derive(Debug)]
+pub enum ConnectRequestStatus {
+    /// The request's timestamp is too old, so the request is ignored
+    Stale,
+    /// The request has not been answered before, and should be evaluated
+    New,
+    /// The same request has been answered already, with the given decision
+    Decided(ConnectDecision),
+}
+
+// The most answers remembered for a single address. Beyond this, the
+// oldest is forgotten, so that one address sending endless distinct
+// requests can't use up memory
+const MAX_DECISIONS_PER_ADDRESS: usize = 8;
+
+// a connect request's payload, the answer given to it, and when
+type Decision = (Box<[u8]>, ConnectDecision, SystemTime);
+
+/// Guards against replayed connect requests, by refusing timestamps which are
+/// too old, and by remembering the answer given to each of the latest
+/// requests from a given address. Requests are told apart by their whole
+/// payload, which holds the full timestamp, its digest, and any auth event,
+/// so that a Client retrying with new credentials is evaluated again
+#[derive(Debug)]
+pub struct TimestampValidator {
+    window: Duration,
+    decisions: HashMap<SocketAddr, VecDeque<Decision>>,
+}
+
+impl TimestampValidator {
+    pub fn new(window: Duration) -> Self {
+        TimestampValidator {
+            window,
+            decisions: HashMap::new(),
+        }
+    }
+
+    /// Returns how a connect request with the given timestamp and payload
+    /// should be handled, when received from the given address
+    pub fn validate(
+        &mut self,
+        address: &SocketAddr,
+        timestamp: &Timestamp,
+        request: &[u8],
+    ) -> ConnectRequestStatus {
+        let now = SystemTime::now();
+        let window = self.window;
+        self.decisions.retain(|_, decisions| {
+            decisions.retain(
+                |(_, _, decided_time)| match now.duration_since(*decided_time) {
+                    Ok(age) => age <= window,
+                    Err(_) => true,
+                },
+            );
+            return !decisions.is_empty();
+        });
+
+        if timestamp.is_older_than(window) {
+            return ConnectRequestStatus::Stale;
+        }
+
+        if let Some(decisions) = self.decisions.get(address) {
+            for (decided_request, decision, _) in decisions {
+                if decided_request.as_ref() == request {
+                    return ConnectRequestStatus::Decided(decision.clone());
+                }
+            }
+        }
+        return ConnectRequestStatus::New;
+    }
+
+    /// Records the answer given to a connect request from the given address
+    pub fn decide(&mut self, address: &SocketAddr, request: &[u8], decision: ConnectDecision) {
+        let decisions = self.decisions.entry(*address).or_default();
+        decisions.retain(|(decided_request, _, _)| decided_request.as_ref() != request);
+        if decisions.len() >= MAX_DECISIONS_PER_ADDRESS {
+            decisions.pop_front();
+        }
+        decisions.push_back((Box::from(request), decision, SystemTime::now()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{IpAddr, Ipv4Addr, SocketAddr},
+        time::Duration,
+    };
+
+    use naia_shared::{PacketReader, Timestamp, TimestampExt};
+
+    use super::{
+        ConnectDecision, ConnectRequestStatus, TimestampValidator, MAX_DECISIONS_PER_ADDRESS,
+    };
+
+    fn address(port: u16) -> SocketAddr {
+        return SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port);
+    }
+
+    fn request(timestamp: &Timestamp, auth: u8) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        timestamp.write(&mut bytes);
+        // stands in for the digest & auth event
+        bytes.push(auth);
+        return bytes;
+    }
+
+    #[test]
+    fn repeated_request_is_given_the_same_answer() {
+        let mut validator = TimestampValidator::new(Duration::from_secs(30));
+        let timestamp = Timestamp::now();
+        let request = request(&timestamp, 0);
+
+        assert!(matches!(
+            validator.validate(&address(1000), &timestamp, &request),
+            ConnectRequestStatus::New
+        ));
+        validator.decide(
+            &address(1000),
+            &request,
+            ConnectDecision::Rejected(Box::new([7])),
+        );
+        match validator.validate(&address(1000), &timestamp, &request) {
+            ConnectRequestStatus::Decided(ConnectDecision::Rejected(payload)) => {
+                assert!(payload.as_ref() == [7]);
+            }
+            _ => panic!("the rejection should be given again"),
+        }
+        // another client may connect within the same second
+        assert!(matches!(
+            validator.validate(&address(1001), &timestamp, &request),
+            ConnectRequestStatus::New
+        ));
+    }
+
+    #[test]
+    fn retry_with_new_credentials_is_evaluated_again() {
+        let mut validator = TimestampValidator::new(Duration::from_secs(30));
+        let timestamp = Timestamp::now();
+
+        validator.decide(
+            &address(1000),
+            &request(&timestamp, 0),
+            ConnectDecision::Rejected(Box::new([])),
+        );
+        assert!(matches!(
+            validator.validate(&address(1000), &timestamp, &request(&timestamp, 1)),
+            ConnectRequestStatus::New
+        ));
+    }
+
+    #[test]
+    fn old_timestamp_is_refused() {
+        let mut validator = TimestampValidator::new(Duration::from_secs(30));
+        let old_secs = Timestamp::now().since_epoch().as_secs() - 60;
+        let old_millis = old_secs * 1000;
+
+        for clock in [old_secs, old_millis].iter() {
+            let old_bytes = clock.to_be_bytes();
+            let old_timestamp = Timestamp::read(&mut PacketReader::new(&old_bytes));
+            assert!(matches!(
+                validator.validate(&address(1000), &old_timestamp, &old_bytes),
+                ConnectRequestStatus::Stale
+            ));
+        }
+    }
+
+    #[test]
+    fn flood_from_one_address_keeps_only_the_latest_answers() {
+        let mut validator = TimestampValidator::new(Duration::from_secs(30));
+        let timestamp = Timestamp::now();
+
+        for auth in 0..=255 {
+            validator.decide(
+                &address(1000),
+                &request(&timestamp, auth),
+                ConnectDecision::Accepted,
+            );
+        }
+        validator.decide(
+            &address(1001),
+            &request(&timestamp, 0),
+            ConnectDecision::Accepted,
+        );
+        assert!(
+            validator.decisions.get(&address(1000)).unwrap().len() == MAX_DECISIONS_PER_ADDRESS
+        );
+
+        // the earliest answers have been forgotten, but not the latest, nor
+        // those given to other addresses
+        assert!(matches!(
+            validator.validate(&address(1000), &timestamp, &request(&timestamp, 0)),
+            ConnectRequestStatus::New
+        ));
+        assert!(matches!(
+            validator.validate(&address(1000), &timestamp, &request(&timestamp, 255)),
+            ConnectRequestStatus::Decided(ConnectDecision::Accepted)
+        ));
+        assert!(matches!(
+            validator.validate(&address(1001), &timestamp, &request(&timestamp, 0)),
+            ConnectRequestStatus::Decided(ConnectDecision::Accepted)
+        ));
+    }
+}
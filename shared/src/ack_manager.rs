@@ -1,15 +1,16 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 
 use super::{
     sequence_buffer::{SequenceBuffer, SequenceNumber},
     standard_header::StandardHeader,
-    wrapping_number::sequence_greater_than,
+    wrapping_number::{sequence_greater_than, sequence_less_than},
 };
 
 use super::{
     actors::actor_notifiable::ActorNotifiable,
     events::{event_manager::EventManager, event_type::EventType},
     packet_type::PacketType,
+    raw_manager::RawManager,
 };
 
 const REDUNDANT_PACKET_ACKS_SIZE: u16 = 32;
@@ -29,30 +30,63 @@ pub struct AckManager {
     // However, we can only reasonably ack up to `REDUNDANT_PACKET_ACKS_SIZE + 1` packets on each
     // message we send so this should be that large.
     received_packets: SequenceBuffer<ReceivedPacket>,
+    // Whether each of the most recently sent packets was delivered (true) or dropped (false), used
+    // to measure packet loss.
+    packet_loss_window: VecDeque<bool>,
+    packet_loss_window_size: usize,
 }
 
 impl AckManager {
-    /// Create a new AckManager
-    pub fn new() -> Self {
+    /// Create a new AckManager, which measures packet loss over the given
+    /// number of most recently sent packets
+    pub fn new(packet_loss_window_size: u16) -> Self {
         AckManager {
             sequence_number: 0,
             remote_ack_sequence_num: u16::max_value(),
             sent_packets: HashMap::with_capacity(DEFAULT_SEND_PACKETS_SIZE),
             received_packets: SequenceBuffer::with_capacity(REDUNDANT_PACKET_ACKS_SIZE + 1),
+            packet_loss_window: VecDeque::with_capacity(packet_loss_window_size as usize),
+            packet_loss_window_size: packet_loss_window_size as usize,
         }
     }
 
+    /// Gets the fraction (between 0.0 and 1.0) of recently sent packets which
+    /// have been dropped
+    pub fn packet_loss(&self) -> f32 {
+        if self.packet_loss_window.is_empty() {
+            return 0.0;
+        }
+        let dropped_count = self
+            .packet_loss_window
+            .iter()
+            .filter(|delivered| !**delivered)
+            .count();
+        return dropped_count as f32 / self.packet_loss_window.len() as f32;
+    }
+
     /// Get the index of the next outgoing packet
     pub fn get_local_packet_index(&self) -> SequenceNumber {
         self.sequence_number
     }
 
+    /// Returns whether an incoming packet with the given index has already
+    /// been received, or is too old to tell whether it has been
+    pub fn is_duplicate(&self, packet_index: SequenceNumber) -> bool {
+        let oldest_tracked = self
+            .received_packets
+            .sequence_num()
+            .wrapping_sub(REDUNDANT_PACKET_ACKS_SIZE + 1);
+        return self.received_packets.exists(packet_index)
+            || sequence_less_than(packet_index, oldest_tracked);
+    }
+
     /// Process an incoming packet, handle notifications of delivered / dropped
     /// packets
     pub fn process_incoming<T: EventType>(
         &mut self,
         header: &StandardHeader,
         event_manager: &mut EventManager<T>,
+        raw_manager: &mut RawManager,
         actor_notifiable: &mut Option<&mut dyn ActorNotifiable>,
     ) {
         let remote_seq_num = header.local_packet_index();
@@ -71,10 +105,16 @@ impl AckManager {
         // the current `remote_ack_seq` was (clearly) received so we should remove it
         if let Some(sent_packet) = self.sent_packets.get(&remote_ack_seq) {
             if sent_packet.packet_type == PacketType::Data {
-                self.notify_packet_delivered(remote_ack_seq, event_manager, actor_notifiable);
+                self.notify_packet_delivered(
+                    remote_ack_seq,
+                    event_manager,
+                    raw_manager,
+                    actor_notifiable,
+                );
             }
 
             self.sent_packets.remove(&remote_ack_seq);
+            self.record_packet_loss(true);
         }
 
         // The `remote_ack_field` is going to include whether or not the past 32 packets
@@ -85,15 +125,27 @@ impl AckManager {
             if let Some(sent_packet) = self.sent_packets.get(&ack_sequence) {
                 if remote_ack_field & 1 == 1 {
                     if sent_packet.packet_type == PacketType::Data {
-                        self.notify_packet_delivered(ack_sequence, event_manager, actor_notifiable);
+                        self.notify_packet_delivered(
+                            ack_sequence,
+                            event_manager,
+                            raw_manager,
+                            actor_notifiable,
+                        );
                     }
 
                     self.sent_packets.remove(&ack_sequence);
+                    self.record_packet_loss(true);
                 } else {
                     if sent_packet.packet_type == PacketType::Data {
-                        self.notify_packet_dropped(ack_sequence, event_manager, actor_notifiable);
+                        self.notify_packet_dropped(
+                            ack_sequence,
+                            event_manager,
+                            raw_manager,
+                            actor_notifiable,
+                        );
                     }
                     self.sent_packets.remove(&ack_sequence);
+                    self.record_packet_loss(false);
                 }
             }
 
@@ -117,13 +169,25 @@ impl AckManager {
         self.sequence_number = self.sequence_number.wrapping_add(1);
     }
 
+    fn record_packet_loss(&mut self, delivered: bool) {
+        if self.packet_loss_window_size == 0 {
+            return;
+        }
+        if self.packet_loss_window.len() >= self.packet_loss_window_size {
+            self.packet_loss_window.pop_front();
+        }
+        self.packet_loss_window.push_back(delivered);
+    }
+
     fn notify_packet_delivered<T: EventType>(
         &self,
         packet_sequence_number: u16,
         event_manager: &mut EventManager<T>,
+        raw_manager: &mut RawManager,
         actor_notifiable: &mut Option<&mut dyn ActorNotifiable>,
     ) {
         event_manager.notify_packet_delivered(packet_sequence_number);
+        raw_manager.notify_packet_delivered(packet_sequence_number);
         if let Some(notifiable) = actor_notifiable {
             notifiable.notify_packet_delivered(packet_sequence_number);
         }
@@ -133,9 +197,11 @@ impl AckManager {
         &self,
         packet_sequence_number: u16,
         event_manager: &mut EventManager<T>,
+        raw_manager: &mut RawManager,
         actor_notifiable: &mut Option<&mut dyn ActorNotifiable>,
     ) {
         event_manager.notify_packet_dropped(packet_sequence_number);
+        raw_manager.notify_packet_dropped(packet_sequence_number);
         if let Some(notifiable) = actor_notifiable {
             notifiable.notify_packet_dropped(packet_sequence_number);
         }
@@ -172,3 +238,31 @@ pub struct SentPacket {
 
 #[derive(Clone, Debug, Default)]
 pub struct ReceivedPacket;
+
+#[cfg(test)]
+mod tests {
+    use super::AckManager;
+
+    #[test]
+    fn packet_loss_over_window() {
+        let mut ack_manager = AckManager::new(10);
+        for index in 0..10 {
+            ack_manager.record_packet_loss(index % 3 != 0 || index == 9);
+        }
+
+        assert!((ack_manager.packet_loss() - 0.3).abs() < 0.0001);
+    }
+
+    #[test]
+    fn packet_loss_window_slides() {
+        let mut ack_manager = AckManager::new(10);
+        for _ in 0..10 {
+            ack_manager.record_packet_loss(false);
+        }
+        for _ in 0..10 {
+            ack_manager.record_packet_loss(true);
+        }
+
+        assert!(ack_manager.packet_loss() == 0.0);
+    }
+}
@@ -5,7 +5,10 @@ use std::{
     rc::Rc,
 };
 
-use super::{actor_mutator::ActorMutator, actor_type::ActorType, state_mask::StateMask};
+use super::{
+    actor_mutator::ActorMutator, actor_type::ActorType, interp_lerp::FixedFraction,
+    state_mask::StateMask,
+};
 
 use crate::PacketReader;
 
@@ -45,6 +48,12 @@ pub trait Actor<T: ActorType> {
     fn is_interpolated(&self) -> bool;
     /// Returns whether or not the Actor has any predicted properties
     fn is_predicted(&self) -> bool;
+    /// Gets the weight by which the Actor's accumulated update priority is
+    /// multiplied, when the Server is limited to updating only some Actors
+    /// each tick. Actors with a higher weight are updated more often
+    fn update_priority(&self) -> f32 {
+        return 1.0;
+    }
 }
 
 //TODO: do we really need another trait here?
@@ -58,6 +67,12 @@ pub trait ActorEq<T: ActorType, Impl = Self>: Actor<T> {
     /// Sets the current Actor to an interpolated state between two other
     /// Actors of the same type
     fn set_to_interpolation(&mut self, old: &Impl, new: &Impl, fraction: f32);
+    /// Sets the current Actor to an interpolated state between two other
+    /// Actors of the same type, using fixed-point math so that the result is
+    /// the same on every platform
+    fn set_to_interpolation_fixed(&mut self, old: &Impl, new: &Impl, fraction: FixedFraction) {
+        self.set_to_interpolation(old, new, fraction.to_f32());
+    }
     /// Sets the current Actor to the state of another Actor of the same type
     fn mirror(&mut self, other: &Impl);
 }
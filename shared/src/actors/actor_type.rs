@@ -1,6 +1,6 @@
 use std::{cell::RefCell, rc::Rc};
 
-use super::{actor::Actor, state_mask::StateMask};
+use super::{actor::Actor, interp_lerp::FixedFraction, state_mask::StateMask};
 
 use crate::PacketReader;
 
@@ -26,6 +26,17 @@ pub trait ActorType<Impl = Self>: Clone {
     /// Sets the current Actor to an interpolated state between two other
     /// Actors of the same type
     fn set_to_interpolation(&mut self, old: &Impl, new: &Impl, fraction: f32);
+    /// Sets the current Actor to an interpolated state between two other
+    /// Actors of the same type, using fixed-point math so that the result is
+    /// the same on every platform. A fraction above FixedFraction::ONE
+    /// extrapolates past `new`
+    fn set_to_interpolation_fixed(&mut self, old: &Impl, new: &Impl, fraction: FixedFraction) {
+        self.set_to_interpolation(old, new, fraction.to_f32());
+    }
+    /// Sets the current Actor to a state projected past `new`, continuing
+    /// along the trajectory from `old` to `new`. A `fraction` of 1.0 is
+    /// `new`, and 2.0 is as far past `new` as `new` is from `old`
+    fn set_to_extrapolation(&mut self, old: &Impl, new: &Impl, fraction: f32);
     /// Sets the current Actor to an interpolated state between itself and
     /// another Actor of the same type
     fn mirror(&mut self, other: &Impl);
@@ -0,0 +1,6 @@
+/// Set in the flags of an Actor creation message when an Event attached to
+/// the creation follows the Actor's payload
+pub const SPAWN_EVENT_FLAG: u8 = 1;
+/// Set in the flags of an Actor creation message when the token of the
+/// Client's prediction of the spawn follows, after any spawn Event
+pub const PREDICTION_TOKEN_FLAG: u8 = 2;
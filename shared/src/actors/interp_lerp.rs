@@ -1,8 +1,56 @@
-use std::ops::{Add, Sub};
+use std::{
+    convert::TryFrom,
+    ops::{Add, Sub},
+    time::Duration,
+};
 
 pub trait InterpLerpable: Sized + Sub + Add + Clone + Copy + PartialEq {
     fn to_f32(&self) -> f32;
     fn from_f32(input: f32) -> Self;
+    fn to_fixed(&self) -> i64;
+    fn from_fixed(input: i64) -> Self;
+}
+
+const FIXED_FRACTION_BITS: u32 = 16;
+
+/// An interpolation fraction in Q16.16 fixed-point, so that interpolating
+/// with it gives bit-identical results on every platform. Values above ONE
+/// extrapolate past the newer state
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FixedFraction(i64);
+
+impl FixedFraction {
+    /// A fraction of 0, at the older state
+    pub const ZERO: FixedFraction = FixedFraction(0);
+    /// A fraction of 1, at the newer state
+    pub const ONE: FixedFraction = FixedFraction(1 << FIXED_FRACTION_BITS);
+
+    /// Converts a floating-point fraction, rounding it to the nearest
+    /// fixed-point value
+    pub fn from_f32(fraction: f32) -> Self {
+        // scaling by a power of two is exact, so only the rounding loses
+        // precision, and it does so identically everywhere. Fractions beyond
+        // the range of i64 saturate
+        return FixedFraction((fraction * FixedFraction::ONE.0 as f32).round() as i64);
+    }
+
+    /// Gets the fraction of `total` which `elapsed` makes up, computed with
+    /// integer math only. Fractions beyond the range of i64 saturate
+    pub fn from_durations(elapsed: Duration, total: Duration) -> Self {
+        let total_nanos = total.as_nanos();
+        if total_nanos == 0 {
+            return FixedFraction::ONE;
+        }
+        // a Duration holds under 2^94 nanoseconds, so the shift can't overflow
+        let fixed = (elapsed.as_nanos() << FIXED_FRACTION_BITS) / total_nanos;
+        return FixedFraction(i64::try_from(fixed).unwrap_or(i64::MAX));
+    }
+
+    /// Converts the fraction to floating-point. This is exact for fractions
+    /// up to 256
+    pub fn to_f32(&self) -> f32 {
+        return self.0 as f32 / FixedFraction::ONE.0 as f32;
+    }
 }
 
 /// Returns an interpolation from one value to another by a specified amount
@@ -20,6 +68,41 @@ pub fn interp_lerp<T: InterpLerpable>(old_value: &T, new_value: &T, fraction: f3
     output
 }
 
+/// Returns an interpolation from one value to another by a fixed-point
+/// amount, using integer math only so that the result is the same on every
+/// platform
+pub fn interp_lerp_fixed<T: InterpLerpable>(
+    old_value: &T,
+    new_value: &T,
+    fraction: FixedFraction,
+) -> T {
+    if fraction == FixedFraction::ZERO || PartialEq::eq(old_value, new_value) {
+        return *old_value;
+    }
+    if fraction == FixedFraction::ONE {
+        return *new_value;
+    }
+    // widened, so that neither the difference nor the product can wrap, and
+    // saturated back into the range of i64
+    let old_fixed = i128::from(old_value.to_fixed());
+    let new_fixed = i128::from(new_value.to_fixed());
+    let output_fixed = ((new_fixed - old_fixed).saturating_mul(i128::from(fraction.0))
+        >> FIXED_FRACTION_BITS)
+        .saturating_add(old_fixed);
+    let output: T = T::from_fixed(saturate_fixed(output_fixed));
+    output
+}
+
+// Clamps a widened fixed-point value into the range of i64
+fn saturate_fixed(input: i128) -> i64 {
+    return input.clamp(i128::from(i64::MIN), i128::from(i64::MAX)) as i64;
+}
+
+// Rounds a fixed-point value to the nearest whole number
+fn round_fixed(input: i64) -> i64 {
+    return input.saturating_add(1 << (FIXED_FRACTION_BITS - 1)) >> FIXED_FRACTION_BITS;
+}
+
 ///TODO: for https://github.com/naia-rs/naia/issues/15, add more implementations here
 impl InterpLerpable for u8 {
     fn to_f32(&self) -> f32 {
@@ -29,6 +112,14 @@ impl InterpLerpable for u8 {
     fn from_f32(input: f32) -> Self {
         input as Self
     }
+
+    fn to_fixed(&self) -> i64 {
+        i64::from(*self) << FIXED_FRACTION_BITS
+    }
+
+    fn from_fixed(input: i64) -> Self {
+        round_fixed(input).clamp(i64::from(Self::MIN), i64::from(Self::MAX)) as Self
+    }
 }
 impl InterpLerpable for u16 {
     fn to_f32(&self) -> f32 {
@@ -38,6 +129,14 @@ impl InterpLerpable for u16 {
     fn from_f32(input: f32) -> Self {
         input as Self
     }
+
+    fn to_fixed(&self) -> i64 {
+        i64::from(*self) << FIXED_FRACTION_BITS
+    }
+
+    fn from_fixed(input: i64) -> Self {
+        round_fixed(input).clamp(i64::from(Self::MIN), i64::from(Self::MAX)) as Self
+    }
 }
 impl InterpLerpable for i8 {
     fn to_f32(&self) -> f32 {
@@ -47,6 +146,14 @@ impl InterpLerpable for i8 {
     fn from_f32(input: f32) -> Self {
         input as Self
     }
+
+    fn to_fixed(&self) -> i64 {
+        i64::from(*self) << FIXED_FRACTION_BITS
+    }
+
+    fn from_fixed(input: i64) -> Self {
+        round_fixed(input).clamp(i64::from(Self::MIN), i64::from(Self::MAX)) as Self
+    }
 }
 impl InterpLerpable for i16 {
     fn to_f32(&self) -> f32 {
@@ -56,4 +163,93 @@ impl InterpLerpable for i16 {
     fn from_f32(input: f32) -> Self {
         input as Self
     }
+
+    fn to_fixed(&self) -> i64 {
+        i64::from(*self) << FIXED_FRACTION_BITS
+    }
+
+    fn from_fixed(input: i64) -> Self {
+        round_fixed(input).clamp(i64::from(Self::MIN), i64::from(Self::MAX)) as Self
+    }
+}
+impl InterpLerpable for f32 {
+    fn to_f32(&self) -> f32 {
+        *self
+    }
+
+    fn from_f32(input: f32) -> Self {
+        input
+    }
+
+    fn to_fixed(&self) -> i64 {
+        (*self * FixedFraction::ONE.0 as f32) as i64
+    }
+
+    fn from_fixed(input: i64) -> Self {
+        input as f32 / FixedFraction::ONE.0 as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{interp_lerp, interp_lerp_fixed, FixedFraction};
+
+    #[test]
+    fn position_is_halfway_at_midpoint() {
+        let old_position: (f32, f32) = (0.0, 10.0);
+        let new_position: (f32, f32) = (20.0, 30.0);
+
+        let x = interp_lerp(&old_position.0, &new_position.0, 0.5);
+        let y = interp_lerp(&old_position.1, &new_position.1, 0.5);
+
+        assert!(x == 10.0);
+        assert!(y == 20.0);
+    }
+
+    #[test]
+    fn endpoints_are_exact() {
+        assert!(interp_lerp(&4u8, &16u8, 0.0) == 4);
+        assert!(interp_lerp(&4u8, &16u8, 1.0) == 16);
+        assert!(interp_lerp(&-8i16, &8i16, 0.5) == 0);
+    }
+
+    #[test]
+    fn fixed_point_results_are_bit_identical() {
+        let fraction =
+            FixedFraction::from_durations(Duration::from_millis(10), Duration::from_millis(30));
+        assert!(fraction == FixedFraction::from_f32(21845.0 / 65536.0));
+
+        // expected bits are spelled out, so that any platform which computes
+        // a different result fails
+        let x = interp_lerp_fixed(&0.0f32, &10.0f32, fraction);
+        assert!(x.to_bits() == 0x4055_5480);
+        let y = interp_lerp_fixed(&-7.25f32, &3.5f32, FixedFraction::from_f32(0.7));
+        assert!(y.to_bits() == 0x3e8c_c800);
+        assert!(interp_lerp_fixed(&-8i16, &8i16, FixedFraction::from_f32(0.5)) == 0);
+        assert!(interp_lerp_fixed(&4u8, &16u8, fraction) == 8);
+
+        // extrapolating past the newer value
+        let z = interp_lerp_fixed(&0.0f32, &10.0f32, FixedFraction::from_f32(1.5));
+        assert!(z == 15.0);
+    }
+
+    #[test]
+    fn fixed_point_saturates_rather_than_wrapping() {
+        let elapsed = Duration::from_secs(u64::MAX);
+        let fraction = FixedFraction::from_durations(elapsed, Duration::from_nanos(1));
+        assert!(fraction == FixedFraction(i64::MAX));
+
+        // extrapolating past the range of the type
+        let past_max = FixedFraction::from_f32(3.0);
+        assert!(interp_lerp_fixed(&250u8, &255u8, past_max) == u8::MAX);
+        assert!(interp_lerp_fixed(&0i16, &i16::MAX, past_max) == i16::MAX);
+        assert!(interp_lerp_fixed(&0i16, &i16::MIN, past_max) == i16::MIN);
+        assert!(interp_lerp_fixed(&0u8, &255u8, fraction) == u8::MAX);
+
+        // the difference between the extremes of f32 is beyond i64
+        let middle = interp_lerp_fixed(&f32::MIN, &f32::MAX, FixedFraction::from_f32(0.5));
+        assert!(middle.abs() < 1.0);
+    }
 }
@@ -3,6 +3,7 @@ pub(crate) mod actor_builder;
 pub(crate) mod actor_mutator;
 pub(crate) mod actor_notifiable;
 pub(crate) mod actor_type;
+pub(crate) mod create_flags;
 pub(crate) mod interp_lerp;
 pub(crate) mod local_actor_key;
 pub(crate) mod property;
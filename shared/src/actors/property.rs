@@ -1,8 +1,12 @@
 use std::{cell::RefCell, rc::Rc};
 
+use log::warn;
 use nanoserde::{DeBin, SerBin};
 
-use crate::{wrapping_number::sequence_greater_than, PacketReader};
+use crate::{
+    packet_reader_ext::PacketReaderExt, wrapping_number::sequence_greater_than, BitReader,
+    BitWriter, PacketReader,
+};
 
 use super::actor_mutator::ActorMutator;
 
@@ -65,7 +69,18 @@ impl<T: Clone + DeBin + SerBin + PartialEq> Property<T> {
     /// Given a cursor into incoming packet data, updates the Property with the
     /// synced value, but only if data is newer than the last data received
     pub fn read(&mut self, reader: &mut PacketReader, packet_index: u16) {
-        let length = reader.read_u8();
+        let length = match reader.try_read_u8() {
+            Some(length) => length,
+            None => {
+                warn!("discarding truncated property data");
+                return;
+            }
+        };
+        if usize::from(length) > reader.remaining() {
+            warn!("discarding property data with a length past the end of the packet");
+            reader.skip_remaining();
+            return;
+        }
 
         let buffer = reader.get_buffer();
         let cursor = reader.get_cursor();
@@ -74,10 +89,108 @@ impl<T: Clone + DeBin + SerBin + PartialEq> Property<T> {
         let end: usize = start + (length as usize);
 
         if sequence_greater_than(packet_index, self.last_recv_index) {
-            self.last_recv_index = packet_index;
-            self.inner = DeBin::deserialize_bin(&buffer[start..end]).unwrap();
+            match DeBin::deserialize_bin(&buffer[start..end]) {
+                Ok(inner) => {
+                    self.last_recv_index = packet_index;
+                    self.inner = inner;
+                }
+                Err(_) => {
+                    warn!("discarding property data which could not be deserialized");
+                }
+            }
         }
 
         cursor.set_position(end as u64);
     }
 }
+
+impl Property<bool> {
+    /// Writes contained value into a BitWriter as a single bit
+    pub fn write_bit(&self, writer: &mut BitWriter) {
+        writer.write_bit(self.inner);
+    }
+
+    /// Given a BitReader into incoming packet data, updates the Property with
+    /// the synced bit, but only if data is newer than the last data received
+    pub fn read_bit(&mut self, reader: &mut BitReader, packet_index: u16) {
+        let bit = match reader.read_bit() {
+            Some(bit) => bit,
+            None => {
+                warn!("discarding truncated property data");
+                return;
+            }
+        };
+
+        if sequence_greater_than(packet_index, self.last_recv_index) {
+            self.last_recv_index = packet_index;
+            self.inner = bit;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{PacketReader, Property, StateMask};
+
+    fn test_properties() -> Vec<Property<u32>> {
+        return (0..5)
+            .map(|index| Property::new(1000000 + index as u32, index))
+            .collect();
+    }
+
+    // writes the Properties marked in the StateMask, as a derived Actor's
+    // write_partial() does
+    fn write_partial(properties: &Vec<Property<u32>>, state_mask: &StateMask) -> Vec<u8> {
+        let mut out_bytes = Vec::new();
+        for (index, property) in properties.iter().enumerate() {
+            if let Some(true) = state_mask.get_bit(index as u8) {
+                property.write(&mut out_bytes);
+            }
+        }
+        return out_bytes;
+    }
+
+    #[test]
+    fn partial_write_is_smaller_than_full_write() {
+        let properties = test_properties();
+
+        let mut full_bytes = Vec::new();
+        for property in properties.iter() {
+            property.write(&mut full_bytes);
+        }
+
+        let mut state_mask = StateMask::new(1);
+        state_mask.set_bit(3, true);
+        let mut partial_bytes = Vec::new();
+        state_mask.write(&mut partial_bytes);
+        partial_bytes.append(&mut write_partial(&properties, &state_mask));
+
+        assert!(partial_bytes.len() * 3 < full_bytes.len());
+    }
+
+    #[test]
+    fn partial_read_only_applies_masked_properties() {
+        let mut sender = test_properties();
+        sender[3].set(42);
+
+        let mut state_mask = StateMask::new(1);
+        state_mask.set_bit(3, true);
+        let mut bytes = Vec::new();
+        state_mask.write(&mut bytes);
+        bytes.append(&mut write_partial(&sender, &state_mask));
+
+        let mut receiver = test_properties();
+        let mut reader = PacketReader::new(&bytes);
+        let read_mask = StateMask::read(&mut reader);
+        for (index, property) in receiver.iter_mut().enumerate() {
+            if let Some(true) = read_mask.get_bit(index as u8) {
+                property.read(&mut reader, 1);
+            }
+        }
+
+        assert!(*receiver[3].get() == 42);
+        for index in [0, 1, 2, 4].iter() {
+            assert!(receiver[*index].equals(&sender[*index]));
+        }
+    }
+}
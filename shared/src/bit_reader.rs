@@ -0,0 +1,50 @@
+use crate::{packet_reader_ext::PacketReaderExt, PacketReader};
+
+/// Reads back bits packed by a BitWriter from incoming packet data. Bytes are
+/// only taken from the PacketReader as they are needed, so once the packed
+/// values have been read, the PacketReader is left at the following byte
+pub struct BitReader<'r, 'b> {
+    reader: &'r mut PacketReader<'b>,
+    scratch: u8,
+    scratch_bits: u8,
+}
+
+impl<'r, 'b> BitReader<'r, 'b> {
+    /// Create a new BitReader, reading from the current position of a
+    /// PacketReader
+    pub fn new(reader: &'r mut PacketReader<'b>) -> BitReader<'r, 'b> {
+        BitReader {
+            reader,
+            scratch: 0,
+            scratch_bits: 0,
+        }
+    }
+
+    /// Reads a single bit, or returns None if there are no bytes left
+    pub fn read_bit(&mut self) -> Option<bool> {
+        if self.scratch_bits == 0 {
+            match self.reader.try_read_u8() {
+                Some(byte) => {
+                    self.scratch = byte;
+                    self.scratch_bits = 8;
+                }
+                None => return None,
+            }
+        }
+        self.scratch_bits -= 1;
+        return Some(self.scratch & (1 << self.scratch_bits) != 0);
+    }
+
+    /// Reads a value written with BitWriter::write_bits() using the same
+    /// number of bits, or returns None if there are not enough bytes left
+    pub fn read_bits(&mut self, bits: u8) -> Option<u32> {
+        let mut value: u32 = 0;
+        for _ in 0..bits.min(32) {
+            match self.read_bit() {
+                Some(bit) => value = (value << 1) | (bit as u32),
+                None => return None,
+            }
+        }
+        return Some(value);
+    }
+}
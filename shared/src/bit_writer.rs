@@ -0,0 +1,110 @@
+/// Packs bools into single bits, and integers into as few bits as they need,
+/// so that boolean-heavy state can be written in a fraction of the bytes it
+/// would take to write each value separately. Bits are written most
+/// significant first, and read back in the same order by a BitReader
+#[derive(Debug)]
+pub struct BitWriter {
+    bytes: Vec<u8>,
+    scratch: u8,
+    scratch_bits: u8,
+}
+
+impl BitWriter {
+    /// Create a new, empty BitWriter
+    pub fn new() -> BitWriter {
+        BitWriter {
+            bytes: Vec::new(),
+            scratch: 0,
+            scratch_bits: 0,
+        }
+    }
+
+    /// Writes a single bit
+    pub fn write_bit(&mut self, bit: bool) {
+        self.scratch <<= 1;
+        if bit {
+            self.scratch |= 1;
+        }
+        self.scratch_bits += 1;
+        if self.scratch_bits == 8 {
+            self.bytes.push(self.scratch);
+            self.scratch = 0;
+            self.scratch_bits = 0;
+        }
+    }
+
+    /// Writes the lowest `bits` bits of a value, so a value known to be less
+    /// than 2^bits takes up only that many bits. At most 32 bits can be
+    /// written at once
+    pub fn write_bits(&mut self, value: u32, bits: u8) {
+        for index in (0..bits.min(32)).rev() {
+            self.write_bit(value & (1 << index) != 0);
+        }
+    }
+
+    /// Gets the number of bytes the written bits take up, once the last
+    /// partially filled byte is padded
+    pub fn byte_count(&self) -> usize {
+        if self.scratch_bits == 0 {
+            return self.bytes.len();
+        }
+        return self.bytes.len() + 1;
+    }
+
+    /// Pads the last partially filled byte with zeroes, and appends all
+    /// written bytes to an outgoing byte stream
+    pub fn finish(mut self, out_bytes: &mut Vec<u8>) {
+        if self.scratch_bits > 0 {
+            self.bytes.push(self.scratch << (8 - self.scratch_bits));
+        }
+        out_bytes.append(&mut self.bytes);
+    }
+}
+
+impl Default for BitWriter {
+    fn default() -> Self {
+        return BitWriter::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BitReader, PacketReader};
+
+    use super::BitWriter;
+
+    #[test]
+    fn bools_and_small_ints_round_trip() {
+        let flags = [true, false, false, true, true, false, true, false];
+        let small_ints: [(u32, u8); 3] = [(5, 3), (0, 2), (1000, 10)];
+
+        let mut writer = BitWriter::new();
+        for flag in flags.iter() {
+            writer.write_bit(*flag);
+        }
+        assert!(writer.byte_count() == 1);
+        for (value, bits) in small_ints.iter() {
+            writer.write_bits(*value, *bits);
+        }
+        let mut bytes = Vec::new();
+        writer.finish(&mut bytes);
+        bytes.push(77);
+
+        // 8 + 15 bits, padded out to 3 bytes
+        assert!(bytes.len() == 3 + 1);
+
+        let mut reader = PacketReader::new(&bytes);
+        {
+            let mut bit_reader = BitReader::new(&mut reader);
+            for flag in flags.iter() {
+                assert!(bit_reader.read_bit() == Some(*flag));
+            }
+            for (value, bits) in small_ints.iter() {
+                assert!(bit_reader.read_bits(*bits) == Some(*value));
+            }
+        }
+
+        // reading resumes at the byte after the packed bits
+        assert!(reader.get_cursor().position() == 3);
+    }
+}
@@ -0,0 +1,21 @@
+use std::fmt::{Debug, Formatter, Result};
+
+/// Compresses the payloads of outgoing Data packets, and decompresses them
+/// on the remote host. Compression is only used for a connection when the
+/// Client and the Server are configured with Compressors of the same id.
+pub trait Compressor {
+    /// Identifies the compression algorithm & its settings, so that a Client
+    /// and a Server only compress when they would decompress alike
+    fn id(&self) -> u8;
+    /// Compresses an outgoing packet payload
+    fn compress(&self, payload: &[u8]) -> Vec<u8>;
+    /// Restores a payload which was compressed by the remote host, or returns
+    /// None if it is not a valid compressed payload
+    fn decompress(&self, payload: &[u8]) -> Option<Vec<u8>>;
+}
+
+impl Debug for dyn Compressor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.write_str("Compressor")
+    }
+}
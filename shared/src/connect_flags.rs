@@ -0,0 +1,9 @@
+/// Set in the flags of a connect request when the Client is able to decompress
+/// Data packets, in which case the flags are followed by the id of its
+/// Compressor. Set in the flags of a connect response when the Server has a
+/// Compressor of the same id, and will compress Data packets
+pub const COMPRESSION_FLAG: u8 = 1;
+/// Set in the flags of a connect request when the Client wants packets to
+/// carry a checksum, and in the flags of a connect response when the Server
+/// has agreed to it
+pub const INTEGRITY_CHECK_FLAG: u8 = 2;
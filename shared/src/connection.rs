@@ -1,50 +1,170 @@
-use std::{net::SocketAddr, rc::Rc};
+use std::{net::SocketAddr, rc::Rc, time::Duration};
 
-use crate::{wrapping_diff, Timer};
+use log::warn;
+
+use crate::{wrapping_diff, Instant, Timer};
 
 use super::{
     ack_manager::AckManager,
-    actors::{actor_notifiable::ActorNotifiable, actor_type::ActorType},
+    actors::{
+        actor_notifiable::ActorNotifiable, actor_type::ActorType, local_actor_key::LocalActorKey,
+    },
+    compressor::Compressor,
     connection_config::ConnectionConfig,
-    events::{event::Event, event_manager::EventManager, event_type::EventType},
+    disconnect_policy::DisconnectPolicy,
+    events::{
+        event::Event,
+        event_id::EventId,
+        event_manager::EventManager,
+        event_packet_writer::{EventFit, EventPacketWriter},
+        event_type::EventType,
+    },
+    fragment_manager::{FragmentError, FragmentManager},
     manifest::Manifest,
     packet_type::PacketType,
+    raw_manager::{RawManager, RawMessageError},
     sequence_buffer::SequenceNumber,
     standard_header::StandardHeader,
+    traffic_stats::TrafficStats,
     PacketReader,
 };
 
+// The most Events set aside for being too large for the space left in a
+// packet, before giving up on filling it
+const MAX_UNFIT_EVENTS: usize = 8;
+
 /// Represents a connection to a remote host, and provides functionality to
 /// manage the connection and the communications to it
 #[derive(Debug)]
 pub struct Connection<T: EventType> {
     address: SocketAddr,
     heartbeat_timer: Timer,
-    timeout_timer: Timer,
+    // when the timeout policy's period of silence started
+    timeout_started: Instant,
+    last_heard: Instant,
+    disconnect_policy: DisconnectPolicy,
+    heartbeat_interval: Duration,
+    heartbeat_interval_started: Instant,
+    heard_this_interval: bool,
+    missed_heartbeats: u8,
     ack_manager: AckManager,
     event_manager: EventManager<T>,
+    raw_manager: RawManager,
+    fragment_manager: FragmentManager,
+    compressor: Option<Rc<dyn Compressor>>,
+    integrity_check: bool,
+    max_payload_size: usize,
+    coalesce_delay: Option<Duration>,
+    coalesce_started: Option<Instant>,
+    coalesce_flushed: bool,
     last_received_tick: u16,
+    traffic_stats: TrafficStats,
 }
 
 impl<T: EventType> Connection<T> {
     /// Create a new Connection, given the appropriate underlying managers
     pub fn new(address: SocketAddr, config: &ConnectionConfig) -> Self {
+        let mut event_manager = EventManager::with_capacity(
+            config.max_retransmits,
+            config.max_events_per_packet,
+            config.event_capacity,
+        );
+        event_manager.set_overflow_policy(config.max_outgoing_queue_len, config.overflow_policy);
+        let disconnect_policy = config.effective_disconnect_policy();
         return Connection {
             address,
             heartbeat_timer: Timer::new(config.heartbeat_interval),
-            timeout_timer: Timer::new(config.disconnection_timeout_duration),
-            ack_manager: AckManager::new(),
-            event_manager: EventManager::new(),
+            timeout_started: Instant::now(),
+            last_heard: Instant::now(),
+            disconnect_policy,
+            heartbeat_interval: config.heartbeat_interval,
+            heartbeat_interval_started: Instant::now(),
+            heard_this_interval: false,
+            missed_heartbeats: 0,
+            max_payload_size: config.max_payload_size,
+            ack_manager: AckManager::new(config.packet_loss_window_size),
+            event_manager,
+            raw_manager: RawManager::new(),
+            fragment_manager: FragmentManager::new(
+                config.max_packet_size,
+                config.fragment_timeout_duration,
+            ),
+            compressor: None,
+            integrity_check: false,
+            coalesce_delay: config.coalesce_delay,
+            coalesce_started: None,
+            coalesce_flushed: false,
             last_received_tick: 0,
+            traffic_stats: TrafficStats::default(),
         };
     }
 
+    /// Applies a new ConnectionConfig to an established Connection. The
+    /// heartbeat interval, disconnection timeout, coalescing delay, and Event
+    /// retransmit & per-packet limits take effect immediately, and both timers
+    /// restart.
+    /// Packet size, fragment and packet loss settings are fixed when the
+    /// Connection is created.
+    pub fn set_config(&mut self, config: &ConnectionConfig) {
+        self.heartbeat_timer = Timer::new(config.heartbeat_interval);
+        self.disconnect_policy = config.effective_disconnect_policy();
+        self.timeout_started = Instant::now();
+        self.heartbeat_interval = config.heartbeat_interval;
+        self.heartbeat_interval_started = Instant::now();
+        self.heard_this_interval = false;
+        self.missed_heartbeats = 0;
+        self.max_payload_size = config.max_payload_size;
+        self.coalesce_delay = config.coalesce_delay;
+        self.event_manager
+            .set_limits(config.max_retransmits, config.max_events_per_packet);
+        self.event_manager
+            .set_overflow_policy(config.max_outgoing_queue_len, config.overflow_policy);
+    }
+
     /// Record that a message has been sent (to prevent needing to send a
     /// heartbeat)
     pub fn mark_sent(&mut self) {
         return self.heartbeat_timer.reset();
     }
 
+    /// Returns whether pending outgoing data should be held back for now, so
+    /// that it can be sent together with data queued shortly after. The
+    /// coalescing window opens the first time this is called with data
+    /// pending, and lasts for the configured `coalesce_delay`
+    pub fn is_coalescing(&mut self) -> bool {
+        return self.is_coalescing_at(&Instant::now());
+    }
+
+    /// Same as is_coalescing(), at the given moment
+    pub fn is_coalescing_at(&mut self, now: &Instant) -> bool {
+        let coalesce_delay = match self.coalesce_delay {
+            Some(coalesce_delay) => coalesce_delay,
+            None => return false,
+        };
+        if self.coalesce_flushed {
+            return false;
+        }
+        let mut coalesce_end = self
+            .coalesce_started
+            .get_or_insert_with(|| now.clone())
+            .clone();
+        coalesce_end.add_millis(coalesce_delay.as_millis().min(u128::from(u32::MAX)) as u32);
+        return *now < coalesce_end;
+    }
+
+    /// Ends the current coalescing window early, so that held outgoing data
+    /// is sent right away. Called on every tick
+    pub fn flush_coalesced(&mut self) {
+        self.coalesce_flushed = true;
+    }
+
+    /// Resets the coalescing window once all held outgoing data has been
+    /// written, so that the next data to be queued is held back again
+    pub fn reset_coalescing(&mut self) {
+        self.coalesce_started = None;
+        self.coalesce_flushed = false;
+    }
+
     /// Returns whether a heartbeat message should be sent
     pub fn should_send_heartbeat(&self) -> bool {
         return self.heartbeat_timer.ringing();
@@ -53,28 +173,116 @@ impl<T: EventType> Connection<T> {
     /// Record that a message has been received from a remote host (to prevent
     /// disconnecting from the remote host)
     pub fn mark_heard(&mut self) {
-        return self.timeout_timer.reset();
+        self.mark_heard_at(Instant::now());
+    }
+
+    fn mark_heard_at(&mut self, now: Instant) {
+        self.count_missed_heartbeats(&now);
+        self.heard_this_interval = true;
+        self.missed_heartbeats = 0;
+        self.last_heard = now.clone();
+        self.timeout_started = now;
+    }
+
+    /// Gets the time at which a message was last received from the remote
+    /// host, or at which the Connection was created if none has been yet
+    pub fn last_heard(&self) -> Instant {
+        return self.last_heard.clone();
     }
 
     /// Returns whether this connection should be dropped as a result of a
-    /// timeout
-    pub fn should_drop(&self) -> bool {
-        return self.timeout_timer.ringing();
+    /// timeout, or of too many consecutive missed heartbeats
+    pub fn should_drop(&mut self) -> bool {
+        return self.should_drop_at(&Instant::now());
+    }
+
+    /// Same as should_drop(), at the given moment
+    pub fn should_drop_at(&mut self, now: &Instant) -> bool {
+        match self.disconnect_policy {
+            DisconnectPolicy::Timeout(timeout) => {
+                let mut deadline = self.timeout_started.clone();
+                deadline.add_millis(timeout.as_millis().min(u128::from(u32::MAX)) as u32);
+                return *now > deadline;
+            }
+            DisconnectPolicy::MissedHeartbeats(max_missed_heartbeats) => {
+                self.count_missed_heartbeats(now);
+                return self.missed_heartbeats >= max_missed_heartbeats;
+            }
+        }
+    }
+
+    // Ends every heartbeat interval which has fully elapsed since the current
+    // one started
+    fn count_missed_heartbeats(&mut self, now: &Instant) {
+        let interval_millis = self.heartbeat_interval.as_millis().max(1);
+        let interval_millis = interval_millis.min(u128::from(u32::MAX)) as u32;
+        let mut elapsed_intervals = 0;
+        loop {
+            let mut interval_end = self.heartbeat_interval_started.clone();
+            interval_end.add_millis(interval_millis);
+            if interval_end > *now {
+                break;
+            }
+            elapsed_intervals += 1;
+            // any more than this and every heartbeat has been missed anyway
+            if elapsed_intervals > u128::from(u8::MAX) {
+                self.heartbeat_interval_started = now.clone();
+                break;
+            }
+            self.heartbeat_interval_started = interval_end;
+        }
+        if elapsed_intervals == 0 {
+            return;
+        }
+        self.end_heartbeat_intervals(elapsed_intervals);
+    }
+
+    // Counts the given number of consecutive heartbeat intervals as missed,
+    // except for the first if a packet was received during it
+    fn end_heartbeat_intervals(&mut self, count: u128) {
+        let mut missed = count;
+        if self.heard_this_interval {
+            self.heard_this_interval = false;
+            missed -= 1;
+        }
+        let missed = missed.min(u128::from(u8::MAX)) as u8;
+        self.missed_heartbeats = self.missed_heartbeats.saturating_add(missed);
     }
 
     /// Process an incoming packet, pulling out the packet index number to keep
     /// track of the current RTT, and sending the packet to the AckManager to
-    /// handle packet notification events
+    /// handle packet notification events. The size of the payload which
+    /// followed the header is recorded in the Connection's TrafficStats
     pub fn process_incoming_header(
         &mut self,
         header: &StandardHeader,
+        payload_size: usize,
         actor_notifiable: &mut Option<&mut dyn ActorNotifiable>,
     ) {
+        let mut packet_size = StandardHeader::bytes_number() + payload_size;
+        if header.is_checksummed() {
+            packet_size += StandardHeader::checksum_bytes_number();
+        }
+        self.traffic_stats
+            .record_received(header.packet_type(), packet_size);
+
         if wrapping_diff(self.last_received_tick, header.host_tick()) > 0 {
             self.last_received_tick = header.host_tick();
         }
-        self.ack_manager
-            .process_incoming(&header, &mut self.event_manager, actor_notifiable);
+        self.ack_manager.process_incoming(
+            &header,
+            &mut self.event_manager,
+            &mut self.raw_manager,
+            actor_notifiable,
+        );
+    }
+
+    /// Returns whether an incoming packet with the given index has already been
+    /// received, or is too old to tell. Such packets should be discarded
+    /// before their data is processed, so that their contents are not
+    /// applied twice
+    pub fn is_duplicate(&self, packet_index: SequenceNumber) -> bool {
+        return self.ack_manager.is_duplicate(packet_index);
     }
 
     /// Given a packet payload, start tracking the packet via it's index, attach
@@ -87,9 +295,15 @@ impl<T: EventType> Connection<T> {
         packet_type: PacketType,
         payload: &[u8],
     ) -> Box<[u8]> {
-        // Add header onto message!
-        let mut header_bytes = Vec::new();
+        // Compress Data packets, if compression was agreed on
+        let mut compressed_payload = None;
+        if packet_type == PacketType::Data {
+            if let Some(compressor) = &self.compressor {
+                compressed_payload = Some(compressor.compress(payload));
+            }
+        }
 
+        // Add header onto message!
         let local_packet_index = self.ack_manager.get_local_packet_index();
         let last_remote_packet_index = self.ack_manager.get_last_remote_packet_index();
         let bit_field = self.ack_manager.get_ack_bitfield();
@@ -101,8 +315,9 @@ impl<T: EventType> Connection<T> {
             bit_field,
             host_tick,
             last_received_tick,
-        );
-        header.write(&mut header_bytes);
+        )
+        .with_compressed(compressed_payload.is_some())
+        .with_checksummed(self.integrity_check);
 
         // Ack stuff //
         self.ack_manager
@@ -110,9 +325,105 @@ impl<T: EventType> Connection<T> {
         self.ack_manager.increment_local_packet_index();
         ///////////////
 
-        [header_bytes.as_slice(), &payload]
-            .concat()
-            .into_boxed_slice()
+        let payload: &[u8] = match &compressed_payload {
+            Some(compressed_payload) => compressed_payload,
+            None => payload,
+        };
+        let packet = header.write_packet(payload);
+        self.traffic_stats.record_sent(packet_type, packet.len());
+        return packet;
+    }
+
+    /// Gets the totals of the bytes sent & received through the Connection,
+    /// by packet type
+    pub fn traffic_stats(&self) -> TrafficStats {
+        return self.traffic_stats;
+    }
+
+    /// Sets the Compressor used for outgoing Data packets, and for incoming
+    /// packets which the remote host has compressed
+    pub fn set_compressor(&mut self, compressor: Option<Rc<dyn Compressor>>) {
+        self.compressor = compressor;
+    }
+
+    /// Returns whether outgoing Data packets are compressed
+    pub fn has_compressor(&self) -> bool {
+        return self.compressor.is_some();
+    }
+
+    /// Sets whether outgoing packets carry a checksum, for when both hosts
+    /// have agreed on it
+    pub fn set_integrity_check(&mut self, integrity_check: bool) {
+        self.integrity_check = integrity_check;
+    }
+
+    /// Returns whether outgoing packets carry a checksum
+    pub fn has_integrity_check(&self) -> bool {
+        return self.integrity_check;
+    }
+
+    /// Returns whether an incoming packet with the given header may be
+    /// processed. Once both hosts have agreed on checksums, a packet without
+    /// one may have been corrupted or forged, so it is refused. Handshake
+    /// messages are sent outside of the Connection, and are never checksummed
+    pub fn accepts_header(&self, header: &StandardHeader) -> bool {
+        match header.packet_type() {
+            PacketType::ClientChallengeRequest
+            | PacketType::ServerChallengeResponse
+            | PacketType::ClientConnectRequest
+            | PacketType::ServerRejectConnect
+            | PacketType::Reconnect
+            | PacketType::ServerReconnectChallenge => return true,
+            _ => return !self.integrity_check || header.is_checksummed(),
+        }
+    }
+
+    /// Decompresses the payload of an incoming packet if its header indicates
+    /// it has been compressed. Returns None if the payload was compressed but
+    /// this Connection has no Compressor to restore it with, or if it could
+    /// not be decompressed
+    pub fn decompress_payload(
+        &self,
+        header: &StandardHeader,
+        payload: Box<[u8]>,
+    ) -> Option<Box<[u8]>> {
+        if !header.is_compressed() {
+            return Some(payload);
+        }
+        match &self.compressor {
+            Some(compressor) => match compressor.decompress(&payload) {
+                Some(payload) => return Some(payload.into_boxed_slice()),
+                None => {
+                    warn!("discarding packet which could not be decompressed");
+                    return None;
+                }
+            },
+            None => {
+                warn!("received compressed packet, but no compressor has been agreed on");
+                return None;
+            }
+        }
+    }
+
+    /// Split an outgoing packet into datagrams which fit within the maximum
+    /// packet size, or refuse it if it would need too many
+    pub fn fragment_outgoing_packet(
+        &mut self,
+        packet: Box<[u8]>,
+    ) -> Result<Vec<Box<[u8]>>, FragmentError> {
+        return self.fragment_manager.fragment_outgoing_packet(packet);
+    }
+
+    /// Process an incoming fragment, returning the reassembled packet once all
+    /// of its fragments have been received
+    pub fn process_incoming_fragment(&mut self, payload: &[u8]) -> Option<Box<[u8]>> {
+        return self.fragment_manager.process_incoming_fragment(payload);
+    }
+
+    /// Gets the fraction (between 0.0 and 1.0) of recently sent packets which
+    /// have been dropped
+    pub fn packet_loss(&self) -> f32 {
+        return self.ack_manager.packet_loss();
     }
 
     /// Get the next outgoing packet's index
@@ -120,27 +431,111 @@ impl<T: EventType> Connection<T> {
         return self.ack_manager.get_local_packet_index();
     }
 
-    /// Queue up an event to be sent to the remote host
-    pub fn queue_event(&mut self, event: &impl Event<T>) {
+    /// Queue up an event to be sent to the remote host, returning the id its
+    /// delivery will be reported with, if it is guaranteed. Returns None if
+    /// the outgoing queue is full & refuses the event
+    pub fn queue_event(&mut self, event: &impl Event<T>) -> Option<EventId> {
         return self.event_manager.queue_outgoing_event(event);
     }
 
+    /// Queue up an already boxed event to be sent to the remote host, which
+    /// may be shared with other Connections
+    pub fn queue_shared_event(&mut self, event: &Rc<Box<dyn Event<T>>>) -> Option<EventId> {
+        return self.event_manager.queue_outgoing_shared_event(event);
+    }
+
+    /// Queue up an event to be sent to the remote host, addressed to the
+    /// Actor with the given key
+    pub fn queue_actor_event(
+        &mut self,
+        target: LocalActorKey,
+        event: &impl Event<T>,
+    ) -> Option<EventId> {
+        return self.event_manager.queue_outgoing_actor_event(target, event);
+    }
+
+    /// Drop the events addressed to an Actor which has gone out of scope for
+    /// the remote host, rather than retransmitting them
+    pub fn forget_actor_events(&mut self, target: LocalActorKey) {
+        self.event_manager.forget_actor_events(target);
+    }
+
+    /// Get the id of the next guaranteed event which the remote host has
+    /// received
+    pub fn get_delivered_event(&mut self) -> Option<EventId> {
+        return self.event_manager.pop_delivered_event();
+    }
+
     /// Returns whether there are events to be sent to the remote host
     pub fn has_outgoing_events(&self) -> bool {
         return self.event_manager.has_outgoing_events();
     }
 
-    /// Pop the next outgoing event from the queue
-    pub fn pop_outgoing_event(&mut self, next_packet_index: u16) -> Option<Rc<Box<dyn Event<T>>>> {
+    /// Pop the next outgoing event from the queue, along with its order index
+    /// if it is a ReliableOrdered event, and the Actor it is addressed to, if
+    /// any
+    pub fn pop_outgoing_event(
+        &mut self,
+        next_packet_index: u16,
+    ) -> Option<(Option<u16>, Option<LocalActorKey>, Rc<Box<dyn Event<T>>>)> {
         return self.event_manager.pop_outgoing_event(next_packet_index);
     }
 
-    /// If for some reason the next outgoing event could not be written into a
-    /// message and sent, place it back into the front of the queue
-    pub fn unpop_outgoing_event(&mut self, next_packet_index: u16, event: &Rc<Box<dyn Event<T>>>) {
-        return self
-            .event_manager
-            .unpop_outgoing_event(next_packet_index, event);
+    /// Returns whether the ReliableOrdered event with the given order index
+    /// is addressed to an Actor which has gone, and should be written as a
+    /// tombstone
+    pub fn is_tombstone(&self, order_index: u16) -> bool {
+        return self.event_manager.is_tombstone(order_index);
+    }
+
+    /// If for some reason an outgoing event could not be written into a
+    /// message and sent, place it back into the front of the queue. Several
+    /// events are placed back in the reverse of the order they were popped in
+    pub fn unpop_outgoing_event(
+        &mut self,
+        next_packet_index: u16,
+        order_index: Option<u16>,
+        target: Option<LocalActorKey>,
+        event: &Rc<Box<dyn Event<T>>>,
+    ) {
+        return self.event_manager.unpop_outgoing_event(
+            next_packet_index,
+            order_index,
+            target,
+            event,
+        );
+    }
+
+    /// Writes as many outgoing events as fit into the given writer, in queue
+    /// order. Events too large for the space left are set aside, so that
+    /// smaller events queued behind them can still fill the packet, and are
+    /// placed back at the front of the queue afterwards
+    pub fn write_outgoing_events<U: ActorType>(
+        &mut self,
+        next_packet_index: u16,
+        manifest: &Manifest<T, U>,
+        writer: &mut EventPacketWriter,
+    ) {
+        let mut unfit_events = Vec::new();
+        while let Some((order_index, target, popped_event)) =
+            self.pop_outgoing_event(next_packet_index)
+        {
+            let fit = match order_index {
+                Some(order_index) if self.is_tombstone(order_index) => {
+                    writer.write_tombstone(order_index)
+                }
+                _ => writer.write_event(manifest, order_index, target, &popped_event),
+            };
+            if let EventFit::NoFit(_) = fit {
+                unfit_events.push((order_index, target, popped_event));
+                if writer.is_full_of_events() || unfit_events.len() >= MAX_UNFIT_EVENTS {
+                    break;
+                }
+            }
+        }
+        for (order_index, target, unfit_event) in unfit_events.iter().rev() {
+            self.unpop_outgoing_event(next_packet_index, *order_index, *target, unfit_event);
+        }
     }
 
     /// Given an incoming packet which has been identified as an event, send the
@@ -153,18 +548,561 @@ impl<T: EventType> Connection<T> {
         return self.event_manager.process_data(reader, manifest);
     }
 
-    /// Get the most recent event that has been received from a remote host
-    pub fn get_incoming_event(&mut self) -> Option<T> {
+    /// Get the most recent event that has been received from a remote host,
+    /// along with the Actor it is addressed to, if any
+    pub fn get_incoming_event(&mut self) -> Option<(Option<LocalActorKey>, T)> {
         return self.event_manager.pop_incoming_event();
     }
 
+    /// Queues up a raw message to be sent to the remote host on the given
+    /// channel, or refuses it if it is too large to fit into a packet
+    pub fn queue_raw(&mut self, channel: u8, data: &[u8]) -> Result<(), RawMessageError> {
+        return self
+            .raw_manager
+            .queue_outgoing_message(channel, data, self.max_payload_size);
+    }
+
+    /// Returns whether there are raw messages to be sent to the remote host
+    pub fn has_outgoing_raw(&self) -> bool {
+        return self.raw_manager.has_outgoing_messages();
+    }
+
+    /// Pops the next outgoing raw message from the queue
+    pub fn pop_outgoing_raw(&mut self, next_packet_index: u16) -> Option<(u8, Box<[u8]>)> {
+        return self
+            .raw_manager
+            .pop_outgoing_message(next_packet_index, self.max_payload_size);
+    }
+
+    /// If the last popped raw message from the queue somehow wasn't able to be
+    /// written into a packet, put the message back into the front of the queue
+    pub fn unpop_outgoing_raw(&mut self, next_packet_index: u16, channel: u8, data: Box<[u8]>) {
+        self.raw_manager
+            .unpop_outgoing_message(next_packet_index, channel, data);
+    }
+
+    /// Given an incoming packet which has been identified as raw data, send
+    /// the data to the RawManager for processing
+    pub fn process_raw_data(&mut self, reader: &mut PacketReader) {
+        self.raw_manager.process_data(reader);
+    }
+
+    /// Get the next raw message that has been received from the remote host,
+    /// along with its channel
+    pub fn get_incoming_raw(&mut self) -> Option<(u8, Box<[u8]>)> {
+        return self.raw_manager.pop_incoming_message();
+    }
+
+    /// Get the largest payload, in bytes, that outgoing data should be packed
+    /// into
+    pub fn get_max_payload_size(&self) -> usize {
+        return self.max_payload_size;
+    }
+
     /// Get the address of the remote host
     pub fn get_address(&self) -> SocketAddr {
         return self.address;
     }
 
+    /// Set the address of the remote host, for when it has reconnected from a
+    /// new one
+    pub fn set_address(&mut self, address: SocketAddr) {
+        self.address = address;
+    }
+
     /// Get the latest received tick from the remote host
     pub fn get_last_received_tick(&self) -> u16 {
         return self.last_received_tick;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{any::TypeId, net::SocketAddr, rc::Rc, time::Duration};
+
+    use super::{Connection, MAX_UNFIT_EVENTS};
+    use crate::{
+        connection_config::ConnectionConfig, ActorNotifiable, Compressor, DeliveryGuarantee,
+        DisconnectPolicy, Event, EventBuilder, EventPacketWriter, EventType, Instant, Manifest,
+        NoActorType, PacketReader, PacketType, StandardHeader,
+    };
+
+    #[derive(Clone)]
+    struct TestEventType;
+
+    impl EventType for TestEventType {
+        fn write(&self, _: &mut Vec<u8>) {}
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<TestEventType>();
+        }
+    }
+
+    // Carries a payload of the given number of bytes
+    #[derive(Clone)]
+    struct TestEvent(usize);
+
+    impl Event<TestEventType> for TestEvent {
+        fn guarantee_delivery(&self) -> DeliveryGuarantee {
+            return DeliveryGuarantee::Reliable;
+        }
+        fn write(&self, buffer: &mut Vec<u8>) {
+            buffer.resize(buffer.len() + self.0, 0);
+        }
+        fn get_typed_copy(&self) -> TestEventType {
+            return TestEventType;
+        }
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<TestEvent>();
+        }
+    }
+
+    struct TestEventBuilder;
+
+    impl EventBuilder<TestEventType> for TestEventBuilder {
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<TestEvent>();
+        }
+        fn build(&self, _: &mut PacketReader) -> TestEventType {
+            return TestEventType;
+        }
+    }
+
+    // Stands in for an actor manager, recording the packets it's notified of
+    #[derive(Default)]
+    struct RecordingNotifiable {
+        delivered: Vec<u16>,
+        dropped: Vec<u16>,
+    }
+
+    impl ActorNotifiable for RecordingNotifiable {
+        fn notify_packet_delivered(&mut self, packet_index: u16) {
+            self.delivered.push(packet_index);
+        }
+        fn notify_packet_dropped(&mut self, packet_index: u16) {
+            self.dropped.push(packet_index);
+        }
+    }
+
+    struct NoopCompressor;
+
+    impl Compressor for NoopCompressor {
+        fn id(&self) -> u8 {
+            return 0;
+        }
+        fn compress(&self, payload: &[u8]) -> Vec<u8> {
+            return payload.to_vec();
+        }
+        fn decompress(&self, payload: &[u8]) -> Option<Vec<u8>> {
+            return Some(payload.to_vec());
+        }
+    }
+
+    // Encodes runs of repeated bytes as (count, byte) pairs
+    struct RleCompressor;
+
+    impl Compressor for RleCompressor {
+        fn id(&self) -> u8 {
+            return 1;
+        }
+        fn compress(&self, payload: &[u8]) -> Vec<u8> {
+            let mut output = Vec::new();
+            for byte in payload {
+                let run_length = output.len();
+                if run_length >= 2
+                    && output[run_length - 1] == *byte
+                    && output[run_length - 2] < 255
+                {
+                    output[run_length - 2] += 1;
+                } else {
+                    output.push(1);
+                    output.push(*byte);
+                }
+            }
+            return output;
+        }
+        fn decompress(&self, payload: &[u8]) -> Option<Vec<u8>> {
+            let mut output = Vec::new();
+            for pair in payload.chunks(2) {
+                if pair.len() < 2 {
+                    return None;
+                }
+                for _ in 0..pair[0] {
+                    output.push(pair[1]);
+                }
+            }
+            return Some(output);
+        }
+    }
+
+    fn new_connection(compressor: Option<Rc<dyn Compressor>>) -> Connection<TestEventType> {
+        let address: SocketAddr = "127.0.0.1:14191".parse().unwrap();
+        let mut connection =
+            Connection::<TestEventType>::new(address, &ConnectionConfig::default());
+        connection.set_compressor(compressor);
+        return connection;
+    }
+
+    #[test]
+    fn compressed_actor_update_round_trips() {
+        // an Actor update message, followed by a run of unchanged zeroed bytes
+        let mut actor_update: Vec<u8> = vec![2, 1, 2, 0, 3, 1];
+        actor_update.extend_from_slice(&[0; 32]);
+
+        let compressors: Vec<Rc<dyn Compressor>> =
+            vec![Rc::new(NoopCompressor), Rc::new(RleCompressor)];
+        for compressor in compressors {
+            let mut sender = new_connection(Some(compressor.clone()));
+            let receiver = new_connection(Some(compressor));
+
+            let packet = sender.process_outgoing_header(0, 0, PacketType::Data, &actor_update);
+            let (header, payload) = StandardHeader::read(&packet).unwrap();
+            assert!(header.is_compressed());
+
+            let payload = receiver.decompress_payload(&header, payload).unwrap();
+            assert!(payload.as_ref() == actor_update.as_slice());
+        }
+    }
+
+    #[test]
+    fn bit_flipped_payload_is_rejected_when_checksummed() {
+        let mut connection = new_connection(None);
+        let packet = connection.process_outgoing_header(0, 0, PacketType::Data, &[1, 2, 3]);
+        let (header, _) = StandardHeader::read(&packet).unwrap();
+        assert!(!header.is_checksummed());
+
+        // without a checksum, corruption goes unnoticed
+        let mut corrupted = packet.to_vec();
+        *corrupted.last_mut().unwrap() ^= 0b0000_0100;
+        assert!(StandardHeader::read(&corrupted).is_some());
+
+        connection.set_integrity_check(true);
+        let packet = connection.process_outgoing_header(0, 0, PacketType::Data, &[1, 2, 3]);
+        let (header, payload) = StandardHeader::read(&packet).unwrap();
+        assert!(header.is_checksummed());
+        assert!(payload.as_ref() == [1, 2, 3]);
+
+        let mut corrupted = packet.to_vec();
+        *corrupted.last_mut().unwrap() ^= 0b0000_0100;
+        assert!(StandardHeader::read(&corrupted).is_none());
+    }
+
+    #[test]
+    fn unchecksummed_packet_is_refused_once_checksums_are_agreed() {
+        let mut sender = new_connection(None);
+        let mut receiver = new_connection(None);
+        let packet = sender.process_outgoing_header(0, 0, PacketType::Data, &[1, 2, 3]);
+        let (header, _) = StandardHeader::read(&packet).unwrap();
+        assert!(receiver.accepts_header(&header));
+
+        // stripping the checksum off doesn't get a packet past the receiver
+        receiver.set_integrity_check(true);
+        assert!(!receiver.accepts_header(&header));
+
+        sender.set_integrity_check(true);
+        let packet = sender.process_outgoing_header(0, 0, PacketType::Data, &[1, 2, 3]);
+        let (header, _) = StandardHeader::read(&packet).unwrap();
+        assert!(receiver.accepts_header(&header));
+
+        // a resent connect request is still answered
+        let packet = crate::utils::write_connectionless_payload(
+            PacketType::ClientConnectRequest,
+            &[1, 2, 3],
+        );
+        let (header, _) = StandardHeader::read(&packet).unwrap();
+        assert!(receiver.accepts_header(&header));
+    }
+
+    #[test]
+    fn only_data_packets_are_compressed() {
+        let mut connection = new_connection(Some(Rc::new(RleCompressor)));
+        let packet = connection.process_outgoing_header(0, 0, PacketType::Heartbeat, &[0, 0, 0]);
+        let (header, payload) = StandardHeader::read(&packet).unwrap();
+        assert!(!header.is_compressed());
+        assert!(payload.as_ref() == [0, 0, 0]);
+
+        // a compressed packet can't be read without a Compressor
+        let mut connection = new_connection(Some(Rc::new(RleCompressor)));
+        let packet = connection.process_outgoing_header(0, 0, PacketType::Data, &[0, 0, 0]);
+        let (header, payload) = StandardHeader::read(&packet).unwrap();
+        assert!(new_connection(None)
+            .decompress_payload(&header, payload)
+            .is_none());
+    }
+
+    #[test]
+    fn payload_which_fails_to_decompress_is_dropped() {
+        let mut sender = new_connection(Some(Rc::new(RleCompressor)));
+        let receiver = new_connection(Some(Rc::new(RleCompressor)));
+        let packet = sender.process_outgoing_header(0, 0, PacketType::Data, &[0, 0, 0]);
+        let (header, payload) = StandardHeader::read(&packet).unwrap();
+
+        // a run with no byte to repeat
+        let mut truncated = payload.to_vec();
+        truncated.pop();
+        assert!(receiver
+            .decompress_payload(&header, truncated.into_boxed_slice())
+            .is_none());
+    }
+
+    #[test]
+    fn next_packet_index_advances_after_send() {
+        let address: SocketAddr = "127.0.0.1:14191".parse().unwrap();
+        let mut connection =
+            Connection::<TestEventType>::new(address, &ConnectionConfig::default());
+
+        let first_index = connection.get_next_packet_index();
+        connection.process_outgoing_header(0, 0, PacketType::Data, &[]);
+        assert!(connection.get_next_packet_index() == first_index.wrapping_add(1));
+        connection.process_outgoing_header(0, 0, PacketType::Heartbeat, &[]);
+        assert!(connection.get_next_packet_index() == first_index.wrapping_add(2));
+    }
+
+    #[test]
+    fn data_packet_size_is_counted_in_traffic_stats() {
+        let mut sender = new_connection(None);
+        let mut receiver = new_connection(None);
+
+        let packet = sender.process_outgoing_header(0, 0, PacketType::Data, &[1, 2, 3, 4, 5]);
+        let packet_size = (StandardHeader::bytes_number() + 5) as u64;
+        assert!(packet.len() as u64 == packet_size);
+        assert!(sender.traffic_stats().data.bytes_sent == packet_size);
+        assert!(sender.traffic_stats().heartbeat.bytes_sent == 0);
+
+        let (header, payload) = StandardHeader::read(&packet).unwrap();
+        receiver.process_incoming_header(&header, payload.len(), &mut None);
+        assert!(receiver.traffic_stats().data.bytes_received == packet_size);
+
+        // other packet types are counted separately
+        sender.process_outgoing_header(0, 0, PacketType::Pong, &[]);
+        assert!(sender.traffic_stats().data.bytes_sent == packet_size);
+        assert!(sender.traffic_stats().ping.bytes_sent == StandardHeader::bytes_number() as u64);
+        assert!(
+            sender.traffic_stats().bytes_sent()
+                == packet_size + StandardHeader::bytes_number() as u64
+        );
+    }
+
+    #[test]
+    fn heartbeat_ack_triggers_retransmit_of_dropped_packet() {
+        let mut sender = new_connection(None);
+        let mut receiver = new_connection(None);
+
+        // a Data packet carrying a reliable Event is lost on the way
+        sender.queue_event(&TestEvent(0));
+        let packet_index = sender.get_next_packet_index();
+        assert!(sender.pop_outgoing_event(packet_index).is_some());
+        sender.process_outgoing_header(0, 0, PacketType::Data, &[]);
+        assert!(!sender.has_outgoing_events());
+
+        // with no more data to send, only heartbeats are exchanged
+        let heartbeat = sender.process_outgoing_header(0, 0, PacketType::Heartbeat, &[]);
+        let (header, _) = StandardHeader::read(&heartbeat).unwrap();
+        receiver.process_incoming_header(&header, 0, &mut None);
+
+        let heartbeat = receiver.process_outgoing_header(0, 0, PacketType::Heartbeat, &[]);
+        let (header, _) = StandardHeader::read(&heartbeat).unwrap();
+        sender.process_incoming_header(&header, 0, &mut None);
+
+        // the heartbeat's ack bitfield reveals the drop, requeueing the Event
+        assert!(sender.has_outgoing_events());
+    }
+
+    #[test]
+    fn delivered_packet_notifies_events_and_actors() {
+        let mut sender = new_connection(None);
+        let mut receiver = new_connection(None);
+
+        let event_id = sender.queue_event(&TestEvent(0)).unwrap();
+        let packet_index = sender.get_next_packet_index();
+        assert!(sender.pop_outgoing_event(packet_index).is_some());
+        let packet = sender.process_outgoing_header(0, 0, PacketType::Data, &[]);
+        let (header, _) = StandardHeader::read(&packet).unwrap();
+        receiver.process_incoming_header(&header, 0, &mut None);
+
+        let heartbeat = receiver.process_outgoing_header(0, 0, PacketType::Heartbeat, &[]);
+        let (header, _) = StandardHeader::read(&heartbeat).unwrap();
+        let mut actor_manager = RecordingNotifiable::default();
+        sender.process_incoming_header(&header, 0, &mut Some(&mut actor_manager));
+
+        // one ack reaches both the Event & Actor side of the connection
+        assert!(sender.get_delivered_event() == Some(event_id));
+        assert!(actor_manager.delivered == vec![packet_index]);
+        assert!(actor_manager.dropped.is_empty());
+    }
+
+    #[test]
+    fn connections_with_different_timeouts_drop_at_different_times() {
+        let mut config = ConnectionConfig::default();
+        config.disconnect_policy = DisconnectPolicy::Timeout(Duration::from_millis(20));
+        let address: SocketAddr = "127.0.0.1:14191".parse().unwrap();
+        let mut player = Connection::<TestEventType>::new(address, &config);
+        let mut admin = Connection::<TestEventType>::new(address, &config);
+
+        config.disconnect_policy = DisconnectPolicy::Timeout(Duration::from_secs(10));
+        admin.set_config(&config);
+        player.mark_heard();
+
+        let mut later = Instant::now();
+        later.add_millis(50);
+        assert!(player.should_drop_at(&later));
+        assert!(!admin.should_drop_at(&later));
+    }
+
+    #[test]
+    fn connection_drops_after_missed_heartbeats() {
+        let config = ConnectionConfig {
+            // long enough that no interval ends by the clock during the test
+            heartbeat_interval: Duration::from_secs(3600),
+            disconnect_policy: DisconnectPolicy::MissedHeartbeats(3),
+            ..ConnectionConfig::default()
+        };
+        let address: SocketAddr = "127.0.0.1:14191".parse().unwrap();
+        let mut connection = Connection::<TestEventType>::new(address, &config);
+
+        connection.end_heartbeat_intervals(2);
+        assert!(!connection.should_drop());
+
+        // an interval in which a packet was received is not missed, and
+        // restarts the count
+        connection.mark_heard();
+        connection.end_heartbeat_intervals(1);
+        assert!(connection.missed_heartbeats == 0);
+        connection.end_heartbeat_intervals(2);
+        assert!(!connection.should_drop());
+
+        // the third consecutive missed heartbeat
+        connection.end_heartbeat_intervals(1);
+        assert!(connection.should_drop());
+    }
+
+    #[test]
+    fn heartbeats_are_missed_by_the_given_moment() {
+        let config = ConnectionConfig {
+            heartbeat_interval: Duration::from_millis(100),
+            disconnect_policy: DisconnectPolicy::MissedHeartbeats(3),
+            ..ConnectionConfig::default()
+        };
+        let address: SocketAddr = "127.0.0.1:14191".parse().unwrap();
+        let mut silent = Connection::<TestEventType>::new(address, &config);
+        let mut heard = Connection::<TestEventType>::new(address, &config);
+        let created = Instant::now();
+
+        let mut now = created.clone();
+        now.add_millis(150);
+        heard.mark_heard_at(now.clone());
+
+        now.add_millis(100);
+        assert!(!silent.should_drop_at(&now));
+        now.add_millis(100);
+        assert!(silent.should_drop_at(&now));
+
+        // the interval in which a packet was received is not missed
+        assert!(!heard.should_drop_at(&now));
+        now.add_millis(200);
+        assert!(heard.should_drop_at(&now));
+    }
+
+    #[test]
+    fn last_heard_moves_only_when_a_message_arrives() {
+        let mut connection = new_connection(None);
+        let created = connection.last_heard();
+
+        // sending to the remote host is not hearing from it
+        connection.process_outgoing_header(0, 0, PacketType::Heartbeat, &[]);
+        connection.mark_sent();
+        assert!(connection.last_heard() == created);
+
+        let mut heard = created.clone();
+        heard.add_millis(20);
+        connection.mark_heard_at(heard.clone());
+        assert!(connection.last_heard() == heard);
+    }
+
+    #[test]
+    fn duplicated_data_packet_surfaces_event_once() {
+        let mut manifest = Manifest::<TestEventType, NoActorType>::new();
+        manifest.register_event(Box::new(TestEventBuilder));
+        let mut sender = new_connection(None);
+        let mut receiver = new_connection(None);
+
+        // one unordered Event with naia_id 0 and an empty payload
+        let event_bytes: Vec<u8> = vec![1, 0, 0, 0, 0, 0];
+        let packet = sender.process_outgoing_header(0, 0, PacketType::Data, &event_bytes);
+
+        // the same packet arrives twice
+        for _ in 0..2 {
+            let (header, payload) = StandardHeader::read(&packet).unwrap();
+            if receiver.is_duplicate(header.local_packet_index()) {
+                continue;
+            }
+            receiver.process_incoming_header(&header, payload.len(), &mut None);
+            receiver.process_event_data(&mut PacketReader::new(&payload), &manifest);
+        }
+
+        assert!(receiver.get_incoming_event().is_some());
+        assert!(receiver.get_incoming_event().is_none());
+    }
+
+    #[test]
+    fn packet_older_than_ack_window_is_duplicate() {
+        let mut receiver = new_connection(None);
+        let header = StandardHeader::new(PacketType::Heartbeat, 100, 0, 0, 0, 0);
+        receiver.process_incoming_header(&header, 0, &mut None);
+
+        assert!(receiver.is_duplicate(100));
+        assert!(!receiver.is_duplicate(99));
+        assert!(!receiver.is_duplicate(101));
+        assert!(receiver.is_duplicate(50));
+    }
+
+    // the payload sizes of the Events left in the outgoing queue, in order
+    fn queued_payload_sizes(connection: &mut Connection<TestEventType>) -> Vec<usize> {
+        let mut sizes = Vec::new();
+        while let Some((_, _, event)) = connection.pop_outgoing_event(0) {
+            let mut payload = Vec::new();
+            event.write(&mut payload);
+            sizes.push(payload.len());
+        }
+        return sizes;
+    }
+
+    #[test]
+    fn small_event_is_written_past_one_too_large_to_fit() {
+        let mut manifest = Manifest::<TestEventType, NoActorType>::new();
+        manifest.register_event(Box::new(TestEventBuilder));
+        let mut connection = new_connection(None);
+        // an Event takes 5 bytes of header ahead of its payload
+        connection.queue_event(&TestEvent(5));
+        connection.queue_event(&TestEvent(20));
+        connection.queue_event(&TestEvent(0));
+
+        // room for the manager header & 15 bytes of Events
+        let mut writer = EventPacketWriter::with_capacity(18);
+        connection.write_outgoing_events(0, &manifest, &mut writer);
+
+        assert!(writer.bytes_number() == 15);
+        assert!(writer.is_full_of_events());
+        assert!(queued_payload_sizes(&mut connection) == vec![20]);
+    }
+
+    #[test]
+    fn writing_events_gives_up_after_too_many_do_not_fit() {
+        let mut manifest = Manifest::<TestEventType, NoActorType>::new();
+        manifest.register_event(Box::new(TestEventBuilder));
+        let mut connection = new_connection(None);
+        for _ in 0..MAX_UNFIT_EVENTS + 2 {
+            connection.queue_event(&TestEvent(20));
+        }
+        connection.queue_event(&TestEvent(0));
+
+        // room for the manager header & 10 bytes of Events, so that the
+        // small Event would fit, were it reached
+        let mut writer = EventPacketWriter::with_capacity(13);
+        connection.write_outgoing_events(0, &manifest, &mut writer);
+
+        assert!(!writer.has_bytes());
+        let mut expected_sizes = vec![20; MAX_UNFIT_EVENTS + 2];
+        expected_sizes.push(0);
+        assert!(queued_payload_sizes(&mut connection) == expected_sizes);
+    }
+}
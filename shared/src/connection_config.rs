@@ -1,10 +1,30 @@
 use std::{default::Default, time::Duration};
 
-/// Contains Config properties which will be used by a Server or Client
+use super::{
+    connection_config_builder::ConnectionConfigError,
+    disconnect_policy::DisconnectPolicy,
+    events::event_packet_writer::{MIN_PAYLOAD_SIZE, MTU_SIZE},
+    fragment_header::FragmentHeader,
+    overflow_policy::OverflowPolicy,
+};
+
+// a single missed heartbeat is too few, as an idle remote host only sends one
+// every heartbeat interval
+const MIN_MISSED_HEARTBEATS: u8 = 2;
+
+/// Contains Config properties which will be used by a Server or Client.
+/// Construct one with a ConnectionConfigBuilder, which validates the
+/// combination of properties
 #[derive(Clone, Debug)]
 pub struct ConnectionConfig {
+    /// How long to wait for communication from a remote host before
+    /// initiating a disconnect, either as a duration or as a number of missed
+    /// heartbeats
+    pub disconnect_policy: DisconnectPolicy,
     /// The duration to wait for communication from a remote host before
-    /// initiating a disconnect
+    /// initiating a disconnect. Only used while `disconnect_policy` is left at
+    /// its default
+    #[deprecated(note = "use disconnect_policy instead")]
     pub disconnection_timeout_duration: Duration,
     /// The duration to wait before sending a heartbeat message to a remote
     /// host, if the host has not already sent another message within that time.
@@ -15,32 +35,125 @@ pub struct ConnectionConfig {
     /// Number of samples to measure RTT & Jitter by. A higher number will
     /// smooth out RTT measurements, but at the cost of responsiveness.
     pub rtt_sample_size: u16,
+    /// The largest datagram, in bytes, that will be sent to the remote host.
+    /// Larger packets are split into fragments and reassembled by the remote
+    /// host.
+    pub max_packet_size: usize,
+    /// The duration to wait for the remaining fragments of a packet before
+    /// dropping the fragments which have been received
+    pub fragment_timeout_duration: Duration,
+    /// The maximum number of times a guaranteed Event will be retransmitted
+    /// after being dropped, before giving up on it. If None, guaranteed Events
+    /// will be retransmitted until they are delivered.
+    pub max_retransmits: Option<u16>,
+    /// Number of most recently sent packets over which to measure packet
+    /// loss
+    pub packet_loss_window_size: u16,
+    /// The maximum number of Events written into a single packet, leaving
+    /// room for Actor updates when many Events are queued. Events beyond the
+    /// cap wait for the next packet. If None, Events are written until the
+    /// packet is full.
+    pub max_events_per_packet: Option<u16>,
+    /// The largest payload, in bytes, that Events, Commands & Actor messages
+    /// are packed into before the remainder waits for the next packet. Raise
+    /// it for larger datagrams on a LAN, or lower it for mobile networks.
+    pub max_payload_size: usize,
+    /// Number of ping intervals to wait for a pong from the remote host, after
+    /// which RTT is considered unknown until pongs arrive again. This is
+    /// independent of the disconnection timeout, which heartbeats also reset.
+    pub pong_timeout_intervals: u16,
+    /// The longest duration that outgoing Events, Commands & Actor messages
+    /// are held back for, so that data queued in quick succession is sent in
+    /// fewer, fuller packets at the cost of some latency. Held data is always
+    /// sent at the next tick. If None, data is sent as soon as it is queued.
+    pub coalesce_delay: Option<Duration>,
+    /// The number of Events that the queues of outgoing, incoming & in-flight
+    /// Events have room for from the start, so that a busy connection doesn't
+    /// reallocate them as they grow
+    pub event_capacity: usize,
+    /// Whether to append a checksum to every packet sent over the connection,
+    /// so that packets corrupted in transit are dropped instead of processed.
+    /// Checksums are only used if both the Client & Server enable this, as
+    /// agreed on during the handshake
+    pub integrity_check: bool,
+    /// The maximum number of Events waiting to be sent to the remote host,
+    /// including those queued up again for retransmission. Events queued beyond
+    /// it are handled according to the `overflow_policy`. If None, the queue
+    /// grows without limit.
+    pub max_outgoing_queue_len: Option<usize>,
+    /// What happens to an Event queued while the outgoing queue is already
+    /// `max_outgoing_queue_len` long
+    pub overflow_policy: OverflowPolicy,
 }
 
 impl ConnectionConfig {
-    /// Creates a new ConnectionConfig, used to initialize a Connection
-    pub fn new(
-        disconnection_timeout_duration: Duration,
-        heartbeat_interval: Duration,
-        ping_interval: Duration,
-        rtt_sample_size: u16,
-    ) -> Self {
-        ConnectionConfig {
-            disconnection_timeout_duration,
-            heartbeat_interval,
-            ping_interval,
-            rtt_sample_size,
+    /// Gets the DisconnectPolicy in effect, taking the deprecated
+    /// `disconnection_timeout_duration` into account
+    #[allow(deprecated)]
+    pub fn effective_disconnect_policy(&self) -> DisconnectPolicy {
+        return self
+            .disconnect_policy
+            .or_disconnection_timeout(self.disconnection_timeout_duration);
+    }
+
+    /// Checks that the combination of properties is valid, as is done when a
+    /// ConnectionConfigBuilder builds a ConnectionConfig
+    pub fn validate(&self) -> Result<(), ConnectionConfigError> {
+        match self.effective_disconnect_policy() {
+            DisconnectPolicy::Timeout(disconnection_timeout_duration) => {
+                if self.heartbeat_interval >= disconnection_timeout_duration {
+                    return Err(ConnectionConfigError::HeartbeatIntervalTooLong {
+                        heartbeat_interval: self.heartbeat_interval,
+                        disconnection_timeout_duration,
+                    });
+                }
+            }
+            DisconnectPolicy::MissedHeartbeats(missed_heartbeats) => {
+                if missed_heartbeats < MIN_MISSED_HEARTBEATS {
+                    return Err(ConnectionConfigError::TooFewMissedHeartbeats {
+                        missed_heartbeats,
+                        min_missed_heartbeats: MIN_MISSED_HEARTBEATS,
+                    });
+                }
+            }
+        }
+        if self.max_packet_size <= FragmentHeader::bytes_number() {
+            return Err(ConnectionConfigError::MaxPacketSizeTooSmall {
+                max_packet_size: self.max_packet_size,
+                min_packet_size: FragmentHeader::bytes_number() + 1,
+            });
+        }
+        if self.max_payload_size < MIN_PAYLOAD_SIZE {
+            return Err(ConnectionConfigError::MaxPayloadSizeTooSmall {
+                max_payload_size: self.max_payload_size,
+                min_payload_size: MIN_PAYLOAD_SIZE,
+            });
         }
+        return Ok(());
     }
 }
 
 impl Default for ConnectionConfig {
+    #[allow(deprecated)]
     fn default() -> Self {
         Self {
+            disconnect_policy: DisconnectPolicy::default(),
             disconnection_timeout_duration: Duration::from_secs(10),
             heartbeat_interval: Duration::from_secs(4),
             ping_interval: Duration::from_secs(1),
             rtt_sample_size: 20,
+            max_packet_size: 508,
+            fragment_timeout_duration: Duration::from_secs(2),
+            max_retransmits: None,
+            packet_loss_window_size: 100,
+            max_events_per_packet: None,
+            max_payload_size: MTU_SIZE,
+            pong_timeout_intervals: 5,
+            coalesce_delay: None,
+            event_capacity: 0,
+            integrity_check: false,
+            max_outgoing_queue_len: None,
+            overflow_policy: OverflowPolicy::DropOldest,
         }
     }
 }
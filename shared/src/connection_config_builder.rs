@@ -0,0 +1,338 @@
+use std::{error::Error, fmt, time::Duration};
+
+use super::{
+    connection_config::ConnectionConfig, disconnect_policy::DisconnectPolicy,
+    overflow_policy::OverflowPolicy,
+};
+
+/// Builds a ConnectionConfig one named property at a time, validating the
+/// resulting combination of properties
+#[derive(Clone, Debug)]
+pub struct ConnectionConfigBuilder {
+    config: ConnectionConfig,
+}
+
+impl ConnectionConfigBuilder {
+    /// Creates a new ConnectionConfigBuilder, starting from the default
+    /// ConnectionConfig
+    pub fn new() -> Self {
+        ConnectionConfigBuilder {
+            config: ConnectionConfig::default(),
+        }
+    }
+
+    /// Sets the duration to wait for communication from a remote host before
+    /// initiating a disconnect
+    #[allow(deprecated)]
+    pub fn disconnection_timeout(mut self, duration: Duration) -> Self {
+        self.config.disconnect_policy = DisconnectPolicy::Timeout(duration);
+        self.config.disconnection_timeout_duration = duration;
+        return self;
+    }
+
+    /// Sets the number of consecutive heartbeat intervals to wait without
+    /// hearing from a remote host before initiating a disconnect
+    pub fn missed_heartbeats(mut self, count: u8) -> Self {
+        self.config.disconnect_policy = DisconnectPolicy::MissedHeartbeats(count);
+        return self;
+    }
+
+    /// Sets how long to wait for communication from a remote host before
+    /// initiating a disconnect
+    pub fn disconnect_policy(mut self, disconnect_policy: DisconnectPolicy) -> Self {
+        self.config.disconnect_policy = disconnect_policy;
+        return self;
+    }
+
+    /// Sets the duration to wait before sending a heartbeat message to a
+    /// remote host, if the host has not already sent another message within
+    /// that time
+    pub fn heartbeat_interval(mut self, duration: Duration) -> Self {
+        self.config.heartbeat_interval = duration;
+        return self;
+    }
+
+    /// Sets the duration to wait before sending a ping message to the remote
+    /// host, in order to estimate RTT time
+    pub fn ping_interval(mut self, duration: Duration) -> Self {
+        self.config.ping_interval = duration;
+        return self;
+    }
+
+    /// Sets the number of samples to measure RTT & Jitter by
+    pub fn rtt_sample_size(mut self, rtt_sample_size: u16) -> Self {
+        self.config.rtt_sample_size = rtt_sample_size;
+        return self;
+    }
+
+    /// Sets the largest datagram, in bytes, that will be sent to the remote
+    /// host. Larger packets are split into fragments.
+    pub fn max_packet_size(mut self, max_packet_size: usize) -> Self {
+        self.config.max_packet_size = max_packet_size;
+        return self;
+    }
+
+    /// Sets the duration to wait for the remaining fragments of a packet
+    /// before dropping the fragments which have been received
+    pub fn fragment_timeout(mut self, duration: Duration) -> Self {
+        self.config.fragment_timeout_duration = duration;
+        return self;
+    }
+
+    /// Sets the maximum number of times a guaranteed Event will be
+    /// retransmitted after being dropped
+    pub fn max_retransmits(mut self, max_retransmits: u16) -> Self {
+        self.config.max_retransmits = Some(max_retransmits);
+        return self;
+    }
+
+    /// Sets the number of most recently sent packets over which to measure
+    /// packet loss
+    pub fn packet_loss_window_size(mut self, packet_loss_window_size: u16) -> Self {
+        self.config.packet_loss_window_size = packet_loss_window_size;
+        return self;
+    }
+
+    /// Sets the maximum number of Events written into a single packet
+    pub fn max_events_per_packet(mut self, max_events_per_packet: u16) -> Self {
+        self.config.max_events_per_packet = Some(max_events_per_packet);
+        return self;
+    }
+
+    /// Sets the largest payload, in bytes, which outgoing data is packed into
+    pub fn max_payload_size(mut self, max_payload_size: usize) -> Self {
+        self.config.max_payload_size = max_payload_size;
+        return self;
+    }
+
+    /// Sets the number of ping intervals without a pong after which RTT is
+    /// considered unknown
+    pub fn pong_timeout_intervals(mut self, pong_timeout_intervals: u16) -> Self {
+        self.config.pong_timeout_intervals = pong_timeout_intervals;
+        return self;
+    }
+
+    /// Sets the longest duration that outgoing data is held back for, to be
+    /// sent in fewer, fuller packets
+    pub fn coalesce_delay(mut self, coalesce_delay: Duration) -> Self {
+        self.config.coalesce_delay = Some(coalesce_delay);
+        return self;
+    }
+
+    /// Sets the number of Events that the Event queues have room for from the
+    /// start
+    pub fn event_capacity(mut self, event_capacity: usize) -> Self {
+        self.config.event_capacity = event_capacity;
+        return self;
+    }
+
+    /// Sets whether packets carry a checksum, which is verified on receipt
+    pub fn integrity_check(mut self, integrity_check: bool) -> Self {
+        self.config.integrity_check = integrity_check;
+        return self;
+    }
+
+    /// Sets the maximum number of Events waiting to be sent, and what happens
+    /// to Events queued beyond it
+    pub fn outgoing_queue_limit(
+        mut self,
+        max_outgoing_queue_len: usize,
+        overflow_policy: OverflowPolicy,
+    ) -> Self {
+        self.config.max_outgoing_queue_len = Some(max_outgoing_queue_len);
+        self.config.overflow_policy = overflow_policy;
+        return self;
+    }
+
+    /// Validates the configured properties and returns the resulting
+    /// ConnectionConfig
+    pub fn build(self) -> Result<ConnectionConfig, ConnectionConfigError> {
+        self.config.validate()?;
+        return Ok(self.config);
+    }
+}
+
+impl Default for ConnectionConfigBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An error which occurs when building an invalid ConnectionConfig
+#[derive(Debug)]
+pub enum ConnectionConfigError {
+    /// The heartbeat interval must be shorter than the disconnection timeout,
+    /// otherwise an idle connection would time out before a heartbeat is sent
+    HeartbeatIntervalTooLong {
+        /// The configured heartbeat interval
+        heartbeat_interval: Duration,
+        /// The configured disconnection timeout duration
+        disconnection_timeout_duration: Duration,
+    },
+    /// A Connection dropped after missed heartbeats must allow for at least
+    /// two, as an idle remote host only sends one every heartbeat interval
+    TooFewMissedHeartbeats {
+        /// The configured number of missed heartbeats
+        missed_heartbeats: u8,
+        /// The fewest missed heartbeats which are allowed
+        min_missed_heartbeats: u8,
+    },
+    /// The maximum packet size must leave room for a fragment header and at
+    /// least one byte of the packet being fragmented
+    MaxPacketSizeTooSmall {
+        /// The configured maximum packet size
+        max_packet_size: usize,
+        /// The smallest maximum packet size which is allowed
+        min_packet_size: usize,
+    },
+    /// The maximum payload size must leave room for at least one Event
+    MaxPayloadSizeTooSmall {
+        /// The configured maximum payload size
+        max_payload_size: usize,
+        /// The smallest maximum payload size which is allowed
+        min_payload_size: usize,
+    },
+}
+
+impl fmt::Display for ConnectionConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            ConnectionConfigError::HeartbeatIntervalTooLong {
+                heartbeat_interval,
+                disconnection_timeout_duration,
+            } => write!(
+                f,
+                "Naia Connection Config Error: heartbeat interval ({:?}) must be shorter than disconnection timeout duration ({:?})",
+                heartbeat_interval, disconnection_timeout_duration
+            ),
+            ConnectionConfigError::TooFewMissedHeartbeats {
+                missed_heartbeats,
+                min_missed_heartbeats,
+            } => write!(
+                f,
+                "Naia Connection Config Error: missed heartbeats ({}) must be at least {}",
+                missed_heartbeats, min_missed_heartbeats
+            ),
+            ConnectionConfigError::MaxPacketSizeTooSmall {
+                max_packet_size,
+                min_packet_size,
+            } => write!(
+                f,
+                "Naia Connection Config Error: max packet size ({}) must be at least {}",
+                max_packet_size, min_packet_size
+            ),
+            ConnectionConfigError::MaxPayloadSizeTooSmall {
+                max_payload_size,
+                min_payload_size,
+            } => write!(
+                f,
+                "Naia Connection Config Error: max payload size ({}) must be at least {}",
+                max_payload_size, min_payload_size
+            ),
+        }
+    }
+}
+
+impl Error for ConnectionConfigError {}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{ConnectionConfig, ConnectionConfigBuilder, DisconnectPolicy, MIN_PAYLOAD_SIZE};
+
+    #[test]
+    fn build_valid_config() {
+        let config = ConnectionConfigBuilder::new()
+            .disconnection_timeout(Duration::from_secs(5))
+            .heartbeat_interval(Duration::from_secs(2))
+            .ping_interval(Duration::from_millis(500))
+            .rtt_sample_size(10)
+            .build()
+            .unwrap();
+
+        assert!(config.disconnect_policy == DisconnectPolicy::Timeout(Duration::from_secs(5)));
+        assert!(config.heartbeat_interval == Duration::from_secs(2));
+        assert!(config.ping_interval == Duration::from_millis(500));
+        assert!(config.rtt_sample_size == 10);
+    }
+
+    #[test]
+    fn build_rejects_heartbeat_longer_than_timeout() {
+        let result = ConnectionConfigBuilder::new()
+            .disconnection_timeout(Duration::from_secs(2))
+            .heartbeat_interval(Duration::from_secs(5))
+            .build();
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn build_rejects_a_single_missed_heartbeat() {
+        for missed_heartbeats in [0, 1].iter() {
+            let result = ConnectionConfigBuilder::new()
+                .heartbeat_interval(Duration::from_secs(2))
+                .missed_heartbeats(*missed_heartbeats)
+                .build();
+            assert!(result.is_err());
+        }
+
+        let config = ConnectionConfigBuilder::new()
+            .heartbeat_interval(Duration::from_secs(2))
+            .missed_heartbeats(3)
+            .build()
+            .unwrap();
+        assert!(config.disconnect_policy == DisconnectPolicy::MissedHeartbeats(3));
+    }
+
+    #[test]
+    fn config_built_by_hand_is_validated_the_same() {
+        let config = ConnectionConfig {
+            disconnect_policy: DisconnectPolicy::MissedHeartbeats(0),
+            ..ConnectionConfig::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn deprecated_timeout_applies_while_the_policy_is_left_at_its_default() {
+        let mut config = ConnectionConfig {
+            disconnection_timeout_duration: Duration::from_secs(5),
+            ..ConnectionConfig::default()
+        };
+        assert!(
+            config.effective_disconnect_policy()
+                == DisconnectPolicy::Timeout(Duration::from_secs(5))
+        );
+
+        config.disconnect_policy = DisconnectPolicy::MissedHeartbeats(3);
+        assert!(config.effective_disconnect_policy() == DisconnectPolicy::MissedHeartbeats(3));
+    }
+
+    #[test]
+    fn build_rejects_a_packet_size_with_no_room_for_a_fragment() {
+        let result = ConnectionConfigBuilder::new().max_packet_size(5).build();
+        assert!(result.is_err());
+
+        let config = ConnectionConfigBuilder::new()
+            .max_packet_size(6)
+            .build()
+            .unwrap();
+        assert!(config.max_packet_size == 6);
+    }
+
+    #[test]
+    fn build_rejects_a_payload_size_with_no_room_for_an_event() {
+        let result = ConnectionConfigBuilder::new()
+            .max_payload_size(MIN_PAYLOAD_SIZE - 1)
+            .build();
+        assert!(result.is_err());
+
+        let config = ConnectionConfigBuilder::new()
+            .max_payload_size(MIN_PAYLOAD_SIZE)
+            .build()
+            .unwrap();
+        assert!(config.max_payload_size == MIN_PAYLOAD_SIZE);
+    }
+}
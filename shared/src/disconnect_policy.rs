@@ -0,0 +1,33 @@
+use std::time::Duration;
+
+/// Determines how long a Connection waits without hearing from the remote
+/// host before it is dropped
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DisconnectPolicy {
+    /// Drop the Connection once nothing has been heard from the remote host
+    /// for the given duration
+    Timeout(Duration),
+    /// Drop the Connection once the given number of consecutive heartbeat
+    /// intervals pass without receiving a single packet from the remote host.
+    /// Intervals are counted with the local heartbeat interval, so the count
+    /// follows any change to it
+    MissedHeartbeats(u8),
+}
+
+impl DisconnectPolicy {
+    /// Resolves the policy against the deprecated
+    /// `disconnection_timeout_duration` of a config. While the policy is left
+    /// at its default, a timeout changed from its own default is used instead
+    pub fn or_disconnection_timeout(self, disconnection_timeout_duration: Duration) -> Self {
+        if self == DisconnectPolicy::default() {
+            return DisconnectPolicy::Timeout(disconnection_timeout_duration);
+        }
+        return self;
+    }
+}
+
+impl Default for DisconnectPolicy {
+    fn default() -> Self {
+        DisconnectPolicy::Timeout(Duration::from_secs(10))
+    }
+}
@@ -0,0 +1,21 @@
+/// Describes how an Event is to be delivered to the remote host
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DeliveryGuarantee {
+    /// The Event is sent once, and is never tracked or re-transmitted if the
+    /// packet it was written into is dropped
+    Unreliable,
+    /// The Event is tracked until delivery is confirmed, and is re-transmitted
+    /// whenever the packet it was written into is dropped
+    Reliable,
+    /// The Event is delivered as with Reliable, and is additionally handed to
+    /// the remote application only after all ReliableOrdered Events sent
+    /// before it
+    ReliableOrdered,
+}
+
+impl DeliveryGuarantee {
+    /// Returns whether the Event must be re-transmitted if dropped
+    pub fn is_reliable(&self) -> bool {
+        return *self != DeliveryGuarantee::Unreliable;
+    }
+}
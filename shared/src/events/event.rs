@@ -3,20 +3,26 @@ use std::{
     fmt::{Debug, Formatter, Result},
 };
 
-use super::event_type::EventType;
+use super::{delivery_guarantee::DeliveryGuarantee, event_type::EventType};
 
 /// An Event is a struct of data that can be sent and recreated on the connected
 /// remote host
 pub trait Event<T: EventType>: EventClone<T> {
     /// Whether the Event is guaranteed for eventual delivery to the remote
-    /// host.
-    fn is_guaranteed(&self) -> bool;
+    /// host, or is sent only once without any re-transmission
+    fn guarantee_delivery(&self) -> DeliveryGuarantee;
     /// Writes the current Event into an outgoing packet's byte stream
     fn write(&self, out_bytes: &mut Vec<u8>);
     /// Gets a copy of the Event, encapsulated within an EventType enum
     fn get_typed_copy(&self) -> T;
     /// Gets the TypeId of the Event
     fn get_type_id(&self) -> TypeId;
+    /// Gets the priority of the Event. Queued Events with a higher priority
+    /// are sent before those with a lower one, and Events of the same
+    /// priority are sent in the order they were queued
+    fn priority(&self) -> u8 {
+        return 0;
+    }
 }
 
 /// A Boxed Event must be able to clone itself
@@ -0,0 +1,3 @@
+/// Identifies an outgoing Event within the connection it was sent on, so
+/// that the application can be told once the Event has been delivered
+pub type EventId = u32;
@@ -1,16 +1,24 @@
 use std::{
-    collections::{HashMap, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
     rc::Rc,
     vec::Vec,
 };
 
+use log::warn;
+
 use crate::{
-    actors::actor_type::ActorType,
+    actors::{actor_type::ActorType, local_actor_key::LocalActorKey},
     events::{
+        delivery_guarantee::DeliveryGuarantee,
         event::{Event, EventClone},
+        event_id::EventId,
+        event_packet_writer::{ORDERED_EVENT_FLAG, TARGETED_EVENT_FLAG, TOMBSTONE_EVENT_FLAG},
         event_type::EventType,
     },
     manifest::Manifest,
+    overflow_policy::OverflowPolicy,
+    packet_reader_ext::PacketReaderExt,
+    wrapping_number::sequence_greater_than,
     PacketReader,
 };
 
@@ -18,36 +26,209 @@ use crate::{
 /// that guaranteed Events can be re-transmitted to the remote host
 #[derive(Debug)]
 pub struct EventManager<T: EventType> {
-    queued_outgoing_events: VecDeque<Rc<Box<dyn Event<T>>>>,
-    queued_incoming_events: VecDeque<T>,
-    sent_events: HashMap<u16, Vec<Rc<Box<dyn Event<T>>>>>,
+    // Events are stored alongside the number of times they have been
+    // retransmitted, their order index if they are ReliableOrdered, the
+    // Actor they are addressed to, if any, and their id if they are
+    // guaranteed, to report their delivery
+    queued_outgoing_events: VecDeque<(
+        u16,
+        Option<u16>,
+        Option<LocalActorKey>,
+        Rc<Box<dyn Event<T>>>,
+        Option<EventId>,
+    )>,
+    queued_incoming_events: VecDeque<(Option<LocalActorKey>, T)>,
+    sent_events: HashMap<
+        u16,
+        Vec<(
+            u16,
+            Option<u16>,
+            Option<LocalActorKey>,
+            Rc<Box<dyn Event<T>>>,
+            Option<EventId>,
+        )>,
+    >,
+    next_event_id: EventId,
+    delivered_events: VecDeque<EventId>,
+    max_retransmits: Option<u16>,
+    max_events_per_packet: Option<u16>,
+    max_outgoing_queue_len: Option<usize>,
+    overflow_policy: OverflowPolicy,
+    // packet index currently being written, and how many Events went into it
+    packet_event_count: (u16, u16),
+    next_outgoing_order_index: u16,
+    next_incoming_order_index: u16,
+    // None for the order slot of a tombstone, which holds no Event
+    early_incoming_events: HashMap<u16, Option<(Option<LocalActorKey>, T)>>,
+    // order indices of ReliableOrdered Events whose target has gone, which
+    // are sent as tombstones that only fill in their order slot
+    tombstones: HashSet<u16>,
 }
 
 impl<T: EventType> EventManager<T> {
-    /// Creates a new EventManager
-    pub fn new() -> Self {
+    /// Creates a new EventManager, which will retransmit a dropped guaranteed
+    /// Event at most `max_retransmits` times, or indefinitely if None.
+    /// ReliableOrdered Events are always retransmitted until delivered, as
+    /// every later ReliableOrdered Event waits on them. At most
+    /// `max_events_per_packet` Events are popped for any one packet, if set.
+    pub fn new(max_retransmits: Option<u16>, max_events_per_packet: Option<u16>) -> Self {
+        return EventManager::with_capacity(max_retransmits, max_events_per_packet, 0);
+    }
+
+    /// Creates a new EventManager in the same way as `new`, with queues that
+    /// have room for `capacity` outgoing, incoming & in-flight Events before
+    /// they need to grow
+    pub fn with_capacity(
+        max_retransmits: Option<u16>,
+        max_events_per_packet: Option<u16>,
+        capacity: usize,
+    ) -> Self {
         EventManager {
-            queued_outgoing_events: VecDeque::new(),
-            queued_incoming_events: VecDeque::new(),
-            sent_events: HashMap::new(),
+            queued_outgoing_events: VecDeque::with_capacity(capacity),
+            queued_incoming_events: VecDeque::with_capacity(capacity),
+            sent_events: HashMap::with_capacity(capacity),
+            next_event_id: 0,
+            delivered_events: VecDeque::new(),
+            max_retransmits,
+            max_events_per_packet,
+            max_outgoing_queue_len: None,
+            overflow_policy: OverflowPolicy::DropOldest,
+            packet_event_count: (0, 0),
+            next_outgoing_order_index: 0,
+            next_incoming_order_index: 0,
+            early_incoming_events: HashMap::new(),
+            tombstones: HashSet::new(),
         }
     }
 
+    /// Replaces the retransmit & per-packet limits. Events already queued or
+    /// in flight are subject to the new limits from now on
+    pub fn set_limits(&mut self, max_retransmits: Option<u16>, max_events_per_packet: Option<u16>) {
+        self.max_retransmits = max_retransmits;
+        self.max_events_per_packet = max_events_per_packet;
+    }
+
+    /// Limits the outgoing queue to `max_outgoing_queue_len` Events, with
+    /// Events queued beyond it handled according to `overflow_policy`. If
+    /// None, the queue grows without limit
+    pub fn set_overflow_policy(
+        &mut self,
+        max_outgoing_queue_len: Option<usize>,
+        overflow_policy: OverflowPolicy,
+    ) {
+        self.max_outgoing_queue_len = max_outgoing_queue_len;
+        self.overflow_policy = overflow_policy;
+    }
+
     /// Occurs when a packet has been notified as delivered. Stops tracking the
-    /// status of Events in that packet.
+    /// status of Events in that packet, and records the guaranteed Events in
+    /// it as delivered. Any other copies of those Events, still in flight in
+    /// other packets or queued for retransmission, are forgotten, so that a
+    /// delivered Event is neither reported nor sent again. Tombstones are
+    /// forgotten in the same way, but are not reported
     pub fn notify_packet_delivered(&mut self, packet_index: u16) {
-        self.sent_events.remove(&packet_index);
+        if let Some(delivered_events_list) = self.sent_events.remove(&packet_index) {
+            for (_, order_index, _, _, event_id) in delivered_events_list.into_iter() {
+                if let Some(event_id) = event_id {
+                    let was_tombstone = match order_index {
+                        Some(order_index) => self.tombstones.remove(&order_index),
+                        None => false,
+                    };
+                    if !was_tombstone {
+                        self.delivered_events.push_back(event_id);
+                    }
+                    self.forget_event(event_id);
+                }
+            }
+        }
+    }
+
+    // Removes every remaining copy of the guaranteed Event with the given id
+    fn forget_event(&mut self, event_id: EventId) {
+        for sent_events_list in self.sent_events.values_mut() {
+            sent_events_list.retain(|(_, _, _, _, sent_id)| *sent_id != Some(event_id));
+        }
+        self.sent_events
+            .retain(|_, sent_events_list| !sent_events_list.is_empty());
+        self.queued_outgoing_events
+            .retain(|(_, _, _, _, queued_id)| *queued_id != Some(event_id));
+    }
+
+    /// Occurs when the Actor with the given key goes out of scope for the
+    /// remote host. Drops the Events addressed to it which are queued, or in
+    /// flight & awaiting retransmission, as the remote host would have no Actor
+    /// to route them to, and which might reach another Actor given the same
+    /// key. Dropped Events are never reported as delivered. Every later
+    /// ReliableOrdered Event waits on a ReliableOrdered Event, so these are
+    /// instead turned into tombstones, which fill in their order slot without
+    /// being handed to the application
+    pub fn forget_actor_events(&mut self, target: LocalActorKey) {
+        let tombstones = &mut self.tombstones;
+        let mut keep = |order_index: &Option<u16>, event_target: &mut Option<LocalActorKey>| {
+            if *event_target != Some(target) {
+                return true;
+            }
+            if let Some(order_index) = order_index {
+                tombstones.insert(*order_index);
+                *event_target = None;
+                return true;
+            }
+            return false;
+        };
+        for sent_events_list in self.sent_events.values_mut() {
+            sent_events_list
+                .retain_mut(|(_, order_index, event_target, _, _)| keep(order_index, event_target));
+        }
+        self.sent_events
+            .retain(|_, sent_events_list| !sent_events_list.is_empty());
+        self.queued_outgoing_events
+            .retain_mut(|(_, order_index, event_target, _, _)| keep(order_index, event_target));
+    }
+
+    /// Returns whether the ReliableOrdered Event with the given order index
+    /// has been turned into a tombstone, and should be written as one
+    pub fn is_tombstone(&self, order_index: u16) -> bool {
+        return self.tombstones.contains(&order_index);
+    }
+
+    /// Get the id of the next guaranteed Event which has been delivered to
+    /// the remote host, in the order their packets were acknowledged
+    pub fn pop_delivered_event(&mut self) -> Option<EventId> {
+        return self.delivered_events.pop_front();
     }
 
     /// Occurs when a packet has been notified as having been dropped. Queues up
-    /// any guaranteed Events that were lost in the packet for retransmission.
+    /// any guaranteed Events that were lost in the packet for retransmission,
+    /// unless they have already been retransmitted the maximum number of
+    /// times, or are not ReliableOrdered & the outgoing queue is full
     pub fn notify_packet_dropped(&mut self, packet_index: u16) {
-        if let Some(dropped_events_list) = self.sent_events.get(&packet_index) {
-            for dropped_event in dropped_events_list.into_iter() {
-                self.queued_outgoing_events.push_back(dropped_event.clone());
+        if let Some(dropped_events_list) = self.sent_events.remove(&packet_index) {
+            for (retransmits, order_index, target, dropped_event, event_id) in
+                dropped_events_list.into_iter()
+            {
+                if let Some(max_retransmits) = self.max_retransmits {
+                    if order_index.is_none() && retransmits >= max_retransmits {
+                        warn!(
+                            "dropping guaranteed event after {} retransmits",
+                            retransmits
+                        );
+                        continue;
+                    }
+                }
+                // ReliableOrdered Events are always requeued, as every later
+                // one waits on them
+                if order_index.is_none() && self.is_outgoing_queue_full() {
+                    warn!("outgoing event queue is full, dropping a retransmitted event");
+                    continue;
+                }
+                self.queued_outgoing_events.push_back((
+                    retransmits + 1,
+                    order_index,
+                    target,
+                    dropped_event,
+                    event_id,
+                ));
             }
-
-            self.sent_events.remove(&packet_index);
         }
     }
 
@@ -57,49 +238,223 @@ impl<T: EventType> EventManager<T> {
         return self.queued_outgoing_events.len() != 0;
     }
 
-    /// Gets the next queued Event to be transmitted
-    pub fn pop_outgoing_event(&mut self, packet_index: u16) -> Option<Rc<Box<dyn Event<T>>>> {
-        match self.queued_outgoing_events.pop_front() {
-            Some(event) => {
+    /// Gets the next queued Event to be transmitted, along with its order
+    /// index if it is a ReliableOrdered Event, and the Actor it is addressed
+    /// to, if any. The earliest queued Event of the highest priority is sent
+    /// first. Returns None once the packet has reached the maximum number of
+    /// Events per packet
+    pub fn pop_outgoing_event(
+        &mut self,
+        packet_index: u16,
+    ) -> Option<(Option<u16>, Option<LocalActorKey>, Rc<Box<dyn Event<T>>>)> {
+        if self.packet_event_count.0 != packet_index {
+            self.packet_event_count = (packet_index, 0);
+        }
+        if let Some(max_events_per_packet) = self.max_events_per_packet {
+            if self.packet_event_count.1 >= max_events_per_packet {
+                return None;
+            }
+        }
+
+        match self.remove_highest_priority_event() {
+            Some((retransmits, order_index, target, event, event_id)) => {
+                self.packet_event_count.1 += 1;
+
                 //place in transmission record if this is a gauranteed event
-                if Event::is_guaranteed(event.as_ref().as_ref()) {
+                if Event::guarantee_delivery(event.as_ref().as_ref()).is_reliable() {
                     if !self.sent_events.contains_key(&packet_index) {
-                        let sent_events_list: Vec<Rc<Box<dyn Event<T>>>> = Vec::new();
+                        let sent_events_list: Vec<(
+                            u16,
+                            Option<u16>,
+                            Option<LocalActorKey>,
+                            Rc<Box<dyn Event<T>>>,
+                            Option<EventId>,
+                        )> = Vec::new();
                         self.sent_events.insert(packet_index, sent_events_list);
                     }
 
                     if let Some(sent_events_list) = self.sent_events.get_mut(&packet_index) {
-                        sent_events_list.push(event.clone());
+                        sent_events_list.push((
+                            retransmits,
+                            order_index,
+                            target,
+                            event.clone(),
+                            event_id,
+                        ));
                     }
                 }
 
-                Some(event)
+                Some((order_index, target, event))
             }
             None => None,
         }
     }
 
-    /// If  the last popped Event from the queue somehow wasn't able to be
-    /// written into a packet, put the Event back into the front of the queue
-    pub fn unpop_outgoing_event(&mut self, packet_index: u16, event: &Rc<Box<dyn Event<T>>>) {
+    fn remove_highest_priority_event(
+        &mut self,
+    ) -> Option<(
+        u16,
+        Option<u16>,
+        Option<LocalActorKey>,
+        Rc<Box<dyn Event<T>>>,
+        Option<EventId>,
+    )> {
+        let mut highest: Option<(usize, u8)> = None;
+        for (index, (_, _, _, event, _)) in self.queued_outgoing_events.iter().enumerate() {
+            let priority = Event::priority(event.as_ref().as_ref());
+            match highest {
+                Some((_, highest_priority)) if highest_priority >= priority => {}
+                _ => highest = Some((index, priority)),
+            }
+        }
+        match highest {
+            Some((index, _)) => return self.queued_outgoing_events.remove(index),
+            None => return None,
+        }
+    }
+
+    /// If a popped Event from the queue somehow wasn't able to be written into
+    /// a packet, put the Event back into the front of the queue. Events
+    /// popped for the same packet after it may have been written, so several
+    /// Events being put back should be put back in the reverse of the order
+    /// they were popped in, to keep their place in the queue
+    pub fn unpop_outgoing_event(
+        &mut self,
+        packet_index: u16,
+        order_index: Option<u16>,
+        target: Option<LocalActorKey>,
+        event: &Rc<Box<dyn Event<T>>>,
+    ) {
         let cloned_event = event.clone();
+        let mut retransmits = 0;
+        let mut event_id = None;
 
-        if Event::is_guaranteed(event.as_ref().as_ref()) {
+        if self.packet_event_count.0 == packet_index && self.packet_event_count.1 > 0 {
+            self.packet_event_count.1 -= 1;
+        }
+
+        if Event::guarantee_delivery(event.as_ref().as_ref()).is_reliable() {
             if let Some(sent_events_list) = self.sent_events.get_mut(&packet_index) {
-                sent_events_list.pop();
+                if let Some(position) = sent_events_list.iter().rposition(
+                    |(_, sent_order_index, sent_target, sent_event, _)| {
+                        *sent_order_index == order_index
+                            && *sent_target == target
+                            && Rc::ptr_eq(sent_event, event)
+                    },
+                ) {
+                    let (sent_retransmits, _, _, _, sent_event_id) =
+                        sent_events_list.remove(position);
+                    retransmits = sent_retransmits;
+                    event_id = sent_event_id;
+                }
                 if sent_events_list.len() == 0 {
                     self.sent_events.remove(&packet_index);
                 }
             }
         }
 
-        self.queued_outgoing_events.push_front(cloned_event);
+        self.queued_outgoing_events.push_front((
+            retransmits,
+            order_index,
+            target,
+            cloned_event,
+            event_id,
+        ));
+    }
+
+    /// Queues an Event to be transmitted to the remote host, returning the
+    /// id that its delivery will be reported with, if it is guaranteed. Ids
+    /// are handed out in order, starting from 0. Returns None, without
+    /// queueing the Event, if the queue is full & the OverflowPolicy did not
+    /// make room for it
+    pub fn queue_outgoing_event(&mut self, event: &impl Event<T>) -> Option<EventId> {
+        let clone = Rc::new(EventClone::clone_box(event));
+        return self.queue_outgoing_shared_event(&clone);
+    }
+
+    /// Queues an already boxed Event to be transmitted to the remote host.
+    /// Used to share a single copy of an Event between many EventManagers
+    pub fn queue_outgoing_shared_event(
+        &mut self,
+        event: &Rc<Box<dyn Event<T>>>,
+    ) -> Option<EventId> {
+        return self.queue_outgoing_targeted_event(None, event);
     }
 
-    /// Queues an Event to be transmitted to the remote host
-    pub fn queue_outgoing_event(&mut self, event: &impl Event<T>) {
+    /// Queues an Event to be transmitted to the remote host, addressed to the
+    /// Actor with the given key, so that the remote host can route it
+    pub fn queue_outgoing_actor_event(
+        &mut self,
+        target: LocalActorKey,
+        event: &impl Event<T>,
+    ) -> Option<EventId> {
         let clone = Rc::new(EventClone::clone_box(event));
-        self.queued_outgoing_events.push_back(clone);
+        return self.queue_outgoing_targeted_event(Some(target), &clone);
+    }
+
+    fn queue_outgoing_targeted_event(
+        &mut self,
+        target: Option<LocalActorKey>,
+        event: &Rc<Box<dyn Event<T>>>,
+    ) -> Option<EventId> {
+        if self.is_outgoing_queue_full() {
+            let made_room = match self.overflow_policy {
+                OverflowPolicy::DropOldest => self.drop_oldest_event(),
+                OverflowPolicy::DropNewest => false,
+                OverflowPolicy::Error => return None,
+            };
+            if !made_room {
+                // the dropped Event is given no id, as its delivery will
+                // never be reported
+                warn!("outgoing event queue is full, dropping the newest event");
+                return None;
+            }
+        }
+
+        let guarantee = Event::guarantee_delivery(event.as_ref().as_ref());
+        let mut order_index = None;
+        if guarantee == DeliveryGuarantee::ReliableOrdered {
+            order_index = Some(self.next_outgoing_order_index);
+            self.next_outgoing_order_index = self.next_outgoing_order_index.wrapping_add(1);
+        }
+        let event_id = self.next_event_id;
+        self.next_event_id = self.next_event_id.wrapping_add(1);
+        // only guaranteed Events are tracked until they are delivered
+        let tracked_id = if guarantee.is_reliable() {
+            Some(event_id)
+        } else {
+            None
+        };
+        self.queued_outgoing_events
+            .push_back((0, order_index, target, event.clone(), tracked_id));
+        return Some(event_id);
+    }
+
+    fn is_outgoing_queue_full(&self) -> bool {
+        match self.max_outgoing_queue_len {
+            Some(max_outgoing_queue_len) => {
+                return self.queued_outgoing_events.len() >= max_outgoing_queue_len;
+            }
+            None => return false,
+        }
+    }
+
+    // Drops the oldest queued Event which is not ReliableOrdered, along with
+    // any copies of it in flight, returning whether one was found
+    fn drop_oldest_event(&mut self) -> bool {
+        let index = match self
+            .queued_outgoing_events
+            .iter()
+            .position(|(_, order_index, _, _, _)| order_index.is_none())
+        {
+            Some(index) => index,
+            None => return false,
+        };
+        warn!("outgoing event queue is full, dropping the oldest event");
+        if let Some((_, _, _, _, Some(event_id))) = self.queued_outgoing_events.remove(index) {
+            self.forget_event(event_id);
+        }
+        return true;
     }
 
     /// Returns whether any Events have been received that must be handed to the
@@ -108,28 +463,863 @@ impl<T: EventType> EventManager<T> {
         return self.queued_incoming_events.len() != 0;
     }
 
-    /// Get the most recently received Event
-    pub fn pop_incoming_event(&mut self) -> Option<T> {
+    /// Get the most recently received Event, along with the Actor it is
+    /// addressed to, if any
+    pub fn pop_incoming_event(&mut self) -> Option<(Option<LocalActorKey>, T)> {
         return self.queued_incoming_events.pop_front();
     }
 
     /// Given incoming packet data, read transmitted Events and store them to be
-    /// returned to the application
+    /// returned to the application. Events with a naia_id which is not in the
+    /// Manifest, usually the result of mismatched Client & Server versions,
+    /// are skipped with a warning. If the data is truncated, or declares a
+    /// payload longer than the rest of the packet, the remainder of the
+    /// packet is discarded with a warning
     pub fn process_data<U: ActorType>(
         &mut self,
         reader: &mut PacketReader,
         manifest: &Manifest<T, U>,
     ) {
-        let event_count = reader.read_u8();
+        let event_count = match reader.try_read_u8() {
+            Some(event_count) => event_count,
+            None => {
+                warn!("discarding truncated event data");
+                return;
+            }
+        };
         for _x in 0..event_count {
-            let naia_id: u16 = reader.read_u16();
+            let (naia_id, order_index, target, payload_length) =
+                match EventManager::<T>::read_event_header(reader) {
+                    Some(EventHeader::Event(header)) => header,
+                    Some(EventHeader::Tombstone(order_index)) => {
+                        self.receive_event(Some(order_index), None);
+                        continue;
+                    }
+                    None => {
+                        warn!("discarding truncated event data");
+                        reader.skip_remaining();
+                        return;
+                    }
+                };
+            if usize::from(payload_length) > reader.remaining() {
+                warn!(
+                    "discarding event data with a payload length of {} past the end of the packet",
+                    payload_length
+                );
+                reader.skip_remaining();
+                return;
+            }
 
-            match manifest.create_event(naia_id, reader) {
+            // the Event is read from its own payload only, so that it cannot
+            // read into the following Events
+            let payload_start = reader.get_cursor().position() as usize;
+            let payload_end = payload_start + usize::from(payload_length);
+            let mut payload_reader =
+                PacketReader::new(&reader.get_buffer()[payload_start..payload_end]);
+
+            match manifest.create_event(naia_id, &mut payload_reader) {
                 Some(new_event) => {
-                    self.queued_incoming_events.push_back(new_event);
+                    self.receive_event(order_index, Some((target, new_event)));
+                }
+                None => {
+                    warn!("skipping received event with unknown naia_id: {}", naia_id);
+                }
+            }
+
+            reader.get_cursor().set_position(payload_end as u64);
+        }
+    }
+
+    // Reads an Event's naia_id, order index, target & payload length, or
+    // a tombstone's order index, or None if the packet ends first
+    fn read_event_header(reader: &mut PacketReader) -> Option<EventHeader> {
+        let naia_id: u16 = reader.try_read_u16()?;
+        let flags = reader.try_read_u8()?;
+        let mut order_index = None;
+        if flags & ORDERED_EVENT_FLAG != 0 {
+            order_index = Some(reader.try_read_u16()?);
+        }
+        if flags & TOMBSTONE_EVENT_FLAG != 0 {
+            return Some(EventHeader::Tombstone(order_index?));
+        }
+        let mut target = None;
+        if flags & TARGETED_EVENT_FLAG != 0 {
+            target = Some(reader.try_read_u16()?);
+        }
+        let payload_length: u16 = reader.try_read_u16()?;
+        return Some(EventHeader::Event((
+            naia_id,
+            order_index,
+            target,
+            payload_length,
+        )));
+    }
+
+    // ReliableOrdered Events which arrive early are held back until every
+    // Event before them has been received, and duplicates are discarded. A
+    // tombstone, with no Event, only fills in its order slot
+    fn receive_event(
+        &mut self,
+        order_index: Option<u16>,
+        event: Option<(Option<LocalActorKey>, T)>,
+    ) {
+        match order_index {
+            None => {
+                if let Some(event) = event {
+                    self.queued_incoming_events.push_back(event);
+                }
+            }
+            Some(index) => {
+                if index == self.next_incoming_order_index {
+                    if let Some(event) = event {
+                        self.queued_incoming_events.push_back(event);
+                    }
+                    self.next_incoming_order_index = self.next_incoming_order_index.wrapping_add(1);
+
+                    while let Some(early_event) = self
+                        .early_incoming_events
+                        .remove(&self.next_incoming_order_index)
+                    {
+                        if let Some(early_event) = early_event {
+                            self.queued_incoming_events.push_back(early_event);
+                        }
+                        self.next_incoming_order_index =
+                            self.next_incoming_order_index.wrapping_add(1);
+                    }
+                } else if sequence_greater_than(index, self.next_incoming_order_index) {
+                    self.early_incoming_events.insert(index, event);
+                }
+            }
+        }
+    }
+}
+
+// The header of an Event within a packet, or the order index of a tombstone
+enum EventHeader {
+    Event((u16, Option<u16>, Option<LocalActorKey>, u16)),
+    Tombstone(u16),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{any::TypeId, rc::Rc};
+
+    use crate::{
+        DeliveryGuarantee, Event, EventBuilder, EventFit, EventManager, EventPacketWriter,
+        EventType, Manifest, NoActorType, OverflowPolicy, PacketReader,
+    };
+
+    #[derive(Clone)]
+    struct TestEvent {
+        guarantee: DeliveryGuarantee,
+        index: u8,
+    }
+
+    impl TestEvent {
+        fn with_index(&self, index: u8) -> Self {
+            let mut output = self.clone();
+            output.index = index;
+            return output;
+        }
+    }
+
+    #[derive(Clone)]
+    enum TestEventType {
+        TestEvent(TestEvent),
+    }
+
+    impl EventType for TestEventType {
+        fn write(&self, buffer: &mut Vec<u8>) {
+            match self {
+                TestEventType::TestEvent(event) => event.write(buffer),
+            }
+        }
+
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<TestEvent>();
+        }
+    }
+
+    impl Event<TestEventType> for TestEvent {
+        fn guarantee_delivery(&self) -> DeliveryGuarantee {
+            return self.guarantee;
+        }
+
+        fn write(&self, buffer: &mut Vec<u8>) {
+            buffer.push(self.index);
+        }
+
+        fn get_typed_copy(&self) -> TestEventType {
+            return TestEventType::TestEvent(self.clone());
+        }
+
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<TestEvent>();
+        }
+    }
+
+    // Sent ahead of any TestEvent
+    #[derive(Clone)]
+    struct UrgentEvent(TestEvent);
+
+    impl Event<TestEventType> for UrgentEvent {
+        fn guarantee_delivery(&self) -> DeliveryGuarantee {
+            return self.0.guarantee;
+        }
+
+        fn write(&self, buffer: &mut Vec<u8>) {
+            buffer.push(self.0.index);
+        }
+
+        fn get_typed_copy(&self) -> TestEventType {
+            return TestEventType::TestEvent(self.0.clone());
+        }
+
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<TestEvent>();
+        }
+
+        fn priority(&self) -> u8 {
+            return 1;
+        }
+    }
+
+    struct TestEventBuilder;
+
+    impl EventBuilder<TestEventType> for TestEventBuilder {
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<TestEvent>();
+        }
+
+        fn build(&self, reader: &mut PacketReader) -> TestEventType {
+            return TestEventType::TestEvent(TestEvent {
+                guarantee: DeliveryGuarantee::Unreliable,
+                index: reader.read_u8(),
+            });
+        }
+    }
+
+    #[test]
+    fn shared_event_is_queued_for_every_manager() {
+        let shared_event: Rc<Box<dyn Event<TestEventType>>> = Rc::new(Box::new(TestEvent {
+            guarantee: DeliveryGuarantee::Reliable,
+            index: 0,
+        }));
+
+        let mut managers: Vec<EventManager<TestEventType>> =
+            (0..3).map(|_| EventManager::new(None, None)).collect();
+        for manager in managers.iter_mut() {
+            manager.queue_outgoing_shared_event(&shared_event);
+        }
+
+        for manager in managers.iter_mut() {
+            assert!(manager.pop_outgoing_event(0).is_some());
+            assert!(manager.has_outgoing_events() == false);
+        }
+    }
+
+    #[test]
+    fn queueing_within_capacity_does_not_reallocate() {
+        let mut manager = EventManager::<TestEventType>::with_capacity(None, None, 64);
+        let outgoing_capacity = manager.queued_outgoing_events.capacity();
+        let sent_capacity = manager.sent_events.capacity();
+        assert!(outgoing_capacity >= 64);
+        assert!(sent_capacity >= 64);
+
+        let event = TestEvent {
+            guarantee: DeliveryGuarantee::Reliable,
+            index: 0,
+        };
+        for _ in 0..64 {
+            manager.queue_outgoing_event(&event);
+        }
+        for packet_index in 0..64 {
+            assert!(manager.pop_outgoing_event(packet_index).is_some());
+        }
+
+        assert!(manager.queued_outgoing_events.capacity() == outgoing_capacity);
+        assert!(manager.sent_events.capacity() == sent_capacity);
+    }
+
+    #[test]
+    fn reliable_event_is_reported_delivered_once_acked() {
+        let mut manager = EventManager::<TestEventType>::new(None, None);
+        manager.queue_outgoing_event(&TestEvent {
+            guarantee: DeliveryGuarantee::Unreliable,
+            index: 0,
+        });
+        let reliable_id = manager
+            .queue_outgoing_event(&TestEvent {
+                guarantee: DeliveryGuarantee::Reliable,
+                index: 1,
+            })
+            .unwrap();
+
+        // first sent in a packet which is dropped, then retransmitted
+        assert!(manager.pop_outgoing_event(0).is_some());
+        assert!(manager.pop_outgoing_event(0).is_some());
+        manager.notify_packet_dropped(0);
+        assert!(manager.pop_outgoing_event(1).is_some());
+        assert!(manager.pop_delivered_event().is_none());
+
+        manager.notify_packet_delivered(1);
+        assert!(manager.pop_delivered_event() == Some(reliable_id));
+        assert!(manager.pop_delivered_event().is_none());
+    }
+
+    #[test]
+    fn event_delivered_in_a_later_packet_is_not_resent() {
+        let mut manager = EventManager::<TestEventType>::new(None, None);
+        let event_id = manager
+            .queue_outgoing_event(&TestEvent {
+                guarantee: DeliveryGuarantee::Reliable,
+                index: 0,
+            })
+            .unwrap();
+
+        // the Event rides packet 1, and is then sent again in packet 2 while
+        // packet 1 is still in flight
+        assert!(manager.pop_outgoing_event(1).is_some());
+        let resent = manager.sent_events.get(&1).unwrap()[0].clone();
+        manager.queued_outgoing_events.push_back(resent);
+        assert!(manager.pop_outgoing_event(2).is_some());
+
+        manager.notify_packet_delivered(2);
+        manager.notify_packet_dropped(1);
+
+        assert!(!manager.has_outgoing_events());
+        assert!(manager.pop_delivered_event() == Some(event_id));
+        assert!(manager.pop_delivered_event().is_none());
+    }
+
+    #[test]
+    fn dropped_unreliable_event_is_not_requeued() {
+        let mut manager = EventManager::<TestEventType>::new(None, None);
+        manager.queue_outgoing_event(&TestEvent {
+            guarantee: DeliveryGuarantee::Unreliable,
+            index: 0,
+        });
+
+        assert!(manager.pop_outgoing_event(0).is_some());
+        manager.notify_packet_dropped(0);
+
+        assert!(manager.has_outgoing_events() == false);
+    }
+
+    #[test]
+    fn dropped_reliable_event_is_requeued() {
+        let mut manager = EventManager::<TestEventType>::new(None, None);
+        manager.queue_outgoing_event(&TestEvent {
+            guarantee: DeliveryGuarantee::Unreliable,
+            index: 0,
+        });
+        manager.queue_outgoing_event(&TestEvent {
+            guarantee: DeliveryGuarantee::Reliable,
+            index: 0,
+        });
+
+        assert!(manager.pop_outgoing_event(0).is_some());
+        assert!(manager.pop_outgoing_event(0).is_some());
+        manager.notify_packet_dropped(0);
+
+        let requeued = manager.pop_outgoing_event(1).unwrap();
+        assert!(requeued.2.guarantee_delivery() == DeliveryGuarantee::Reliable);
+        assert!(manager.has_outgoing_events() == false);
+    }
+
+    #[test]
+    fn reliable_event_stops_requeueing_after_max_retransmits() {
+        let mut manager = EventManager::<TestEventType>::new(Some(2), None);
+        manager.queue_outgoing_event(&TestEvent {
+            guarantee: DeliveryGuarantee::Reliable,
+            index: 0,
+        });
+
+        // initial transmission, then 2 retransmissions
+        for packet_index in 0..3 {
+            assert!(manager.pop_outgoing_event(packet_index).is_some());
+            manager.notify_packet_dropped(packet_index);
+        }
+
+        assert!(manager.has_outgoing_events() == false);
+    }
+
+    #[test]
+    fn reliable_ordered_events_are_received_in_order() {
+        let mut manager = EventManager::<TestEventType>::new(None, None);
+        let event = TestEvent {
+            guarantee: DeliveryGuarantee::ReliableOrdered,
+            index: 0,
+        };
+        let received_order = |manager: &mut EventManager<TestEventType>| {
+            let mut output = Vec::new();
+            while let Some((_, TestEventType::TestEvent(event))) = manager.pop_incoming_event() {
+                output.push(event.index);
+            }
+            return output;
+        };
+
+        manager.receive_event(
+            Some(0),
+            Some((None, TestEventType::TestEvent(event.with_index(1)))),
+        );
+        manager.receive_event(
+            Some(2),
+            Some((None, TestEventType::TestEvent(event.with_index(3)))),
+        );
+        assert!(received_order(&mut manager) == vec![1]);
+
+        manager.receive_event(
+            Some(1),
+            Some((None, TestEventType::TestEvent(event.with_index(2)))),
+        );
+        assert!(received_order(&mut manager) == vec![2, 3]);
+
+        // duplicates of already received events are discarded
+        manager.receive_event(
+            Some(1),
+            Some((None, TestEventType::TestEvent(event.with_index(2)))),
+        );
+        assert!(received_order(&mut manager).is_empty());
+    }
+
+    #[test]
+    fn reliable_ordered_events_are_assigned_order_indices() {
+        let mut manager = EventManager::<TestEventType>::new(Some(0), None);
+        for guarantee in [
+            DeliveryGuarantee::ReliableOrdered,
+            DeliveryGuarantee::Unreliable,
+            DeliveryGuarantee::ReliableOrdered,
+        ]
+        .iter()
+        {
+            manager.queue_outgoing_event(&TestEvent {
+                guarantee: *guarantee,
+                index: 0,
+            });
+        }
+
+        assert!(manager.pop_outgoing_event(0).unwrap().0 == Some(0));
+        assert!(manager.pop_outgoing_event(0).unwrap().0.is_none());
+        assert!(manager.pop_outgoing_event(0).unwrap().0 == Some(1));
+
+        // ordered events are retransmitted regardless of max_retransmits
+        manager.notify_packet_dropped(0);
+        assert!(manager.pop_outgoing_event(1).unwrap().0 == Some(0));
+        assert!(manager.pop_outgoing_event(1).unwrap().0 == Some(1));
+    }
+
+    #[test]
+    fn events_beyond_per_packet_cap_wait_for_next_packet() {
+        let mut manager = EventManager::<TestEventType>::new(None, Some(10));
+        for _ in 0..100 {
+            manager.queue_outgoing_event(&TestEvent {
+                guarantee: DeliveryGuarantee::Unreliable,
+                index: 0,
+            });
+        }
+
+        let mut packet_index: u16 = 0;
+        while manager.has_outgoing_events() {
+            let mut packet_event_count = 0;
+            while manager.pop_outgoing_event(packet_index).is_some() {
+                packet_event_count += 1;
+            }
+            assert!(packet_event_count <= 10);
+            packet_index += 1;
+        }
+
+        assert!(packet_index >= 10);
+    }
+
+    // Pops every queued Event into the given packet, returning their indices
+    fn pop_all_indices(manager: &mut EventManager<TestEventType>, packet_index: u16) -> Vec<u8> {
+        let mut indices = Vec::new();
+        while let Some((_, _, popped)) = manager.pop_outgoing_event(packet_index) {
+            let mut buffer = Vec::new();
+            popped.write(&mut buffer);
+            indices.push(buffer[0]);
+        }
+        return indices;
+    }
+
+    #[test]
+    fn full_queue_drops_its_oldest_event_and_stops_tracking_it() {
+        let mut manager = EventManager::<TestEventType>::new(None, None);
+        manager.set_overflow_policy(Some(2), OverflowPolicy::DropOldest);
+        let event = TestEvent {
+            guarantee: DeliveryGuarantee::Reliable,
+            index: 0,
+        };
+
+        // the oldest Event is in flight in packet 0, and also queued to be
+        // sent again
+        manager.queue_outgoing_event(&event);
+        assert!(manager.pop_outgoing_event(0).is_some());
+        let resent = manager.sent_events.get(&0).unwrap()[0].clone();
+        manager.queued_outgoing_events.push_back(resent);
+        manager.queue_outgoing_event(&event.with_index(1));
+
+        assert!(manager.queue_outgoing_event(&event.with_index(2)).is_some());
+        assert!(manager.sent_events.is_empty());
+
+        // so losing packet 0 doesn't bring it back
+        manager.notify_packet_dropped(0);
+        assert!(pop_all_indices(&mut manager, 1) == vec![1, 2]);
+    }
+
+    #[test]
+    fn full_queue_drops_the_newest_event() {
+        let mut manager = EventManager::<TestEventType>::new(None, None);
+        manager.set_overflow_policy(Some(2), OverflowPolicy::DropNewest);
+        let event = TestEvent {
+            guarantee: DeliveryGuarantee::Reliable,
+            index: 0,
+        };
+        assert!(manager.queue_outgoing_event(&event).is_some());
+        assert!(manager.queue_outgoing_event(&event.with_index(1)).is_some());
+        // the dropped Event is given no id, as it will never be delivered
+        assert!(manager.queue_outgoing_event(&event.with_index(2)).is_none());
+
+        assert!(pop_all_indices(&mut manager, 0) == vec![0, 1]);
+    }
+
+    #[test]
+    fn full_queue_of_ordered_events_gives_no_id_to_the_dropped_event() {
+        let mut manager = EventManager::<TestEventType>::new(None, None);
+        manager.set_overflow_policy(Some(2), OverflowPolicy::DropOldest);
+        let event = TestEvent {
+            guarantee: DeliveryGuarantee::ReliableOrdered,
+            index: 0,
+        };
+        assert!(manager.queue_outgoing_event(&event).is_some());
+        assert!(manager.queue_outgoing_event(&event.with_index(1)).is_some());
+        assert!(manager.queue_outgoing_event(&event.with_index(2)).is_none());
+
+        assert!(pop_all_indices(&mut manager, 0) == vec![0, 1]);
+    }
+
+    #[test]
+    fn retransmits_are_held_to_the_queue_limit() {
+        let mut manager = EventManager::<TestEventType>::new(None, None);
+        manager.set_overflow_policy(Some(2), OverflowPolicy::Error);
+        let event = TestEvent {
+            guarantee: DeliveryGuarantee::Reliable,
+            index: 0,
+        };
+        let ordered_event = TestEvent {
+            guarantee: DeliveryGuarantee::ReliableOrdered,
+            index: 1,
+        };
+        manager.queue_outgoing_event(&event);
+        manager.queue_outgoing_event(&ordered_event);
+        assert!(pop_all_indices(&mut manager, 0) == vec![0, 1]);
+        manager.queue_outgoing_event(&event.with_index(2));
+        manager.queue_outgoing_event(&event.with_index(3));
+
+        // the lost Reliable Event is dropped, but the lost ReliableOrdered
+        // Event is always sent again, as later ones wait on it
+        manager.notify_packet_dropped(0);
+        assert!(pop_all_indices(&mut manager, 1) == vec![2, 3, 1]);
+    }
+
+    #[test]
+    fn full_queue_refuses_events_until_there_is_room() {
+        let mut manager = EventManager::<TestEventType>::new(None, None);
+        manager.set_overflow_policy(Some(2), OverflowPolicy::Error);
+        let event = TestEvent {
+            guarantee: DeliveryGuarantee::Reliable,
+            index: 0,
+        };
+        assert!(manager.queue_outgoing_event(&event).is_some());
+        assert!(manager.queue_outgoing_event(&event.with_index(1)).is_some());
+        assert!(manager.queue_outgoing_event(&event.with_index(2)).is_none());
+        assert!(pop_all_indices(&mut manager, 0) == vec![0, 1]);
+
+        // the refused Event didn't use up an id
+        assert!(manager.queue_outgoing_event(&event.with_index(3)) == Some(2));
+        assert!(pop_all_indices(&mut manager, 1) == vec![3]);
+    }
+
+    #[test]
+    fn higher_priority_event_is_popped_before_earlier_ones() {
+        let mut manager = EventManager::<TestEventType>::new(None, None);
+        let event = TestEvent {
+            guarantee: DeliveryGuarantee::Reliable,
+            index: 0,
+        };
+        for index in 0..3 {
+            manager.queue_outgoing_event(&event.with_index(index));
+        }
+        manager.queue_outgoing_event(&UrgentEvent(event.with_index(9)));
+
+        let pop_index = |manager: &mut EventManager<TestEventType>, packet_index: u16| {
+            let (_, _, popped) = manager.pop_outgoing_event(packet_index).unwrap();
+            let mut buffer = Vec::new();
+            popped.write(&mut buffer);
+            return buffer[0];
+        };
+        assert!(pop_index(&mut manager, 0) == 9);
+        assert!(pop_index(&mut manager, 1) == 0);
+
+        // a dropped urgent event is still resent first
+        manager.notify_packet_dropped(0);
+        assert!(pop_index(&mut manager, 2) == 9);
+        assert!(pop_index(&mut manager, 2) == 1);
+        assert!(pop_index(&mut manager, 2) == 2);
+        assert!(manager.has_outgoing_events() == false);
+    }
+
+    #[test]
+    fn unknown_event_is_skipped_and_following_events_are_read() {
+        let mut manifest = Manifest::<TestEventType, NoActorType>::new();
+        manifest.register_event(Box::new(TestEventBuilder));
+
+        let bytes: Vec<u8> = vec![
+            2, // event count
+            0, 7, 0, 0, 3, 9, 9, 9, // naia_id 7 is not in the Manifest
+            0, 0, 0, 0, 1, 42, // naia_id 0 is TestEvent, with an index of 42
+        ];
+        let mut reader = PacketReader::new(&bytes);
+
+        let mut manager = EventManager::<TestEventType>::new(None, None);
+        manager.process_data(&mut reader, &manifest);
+
+        match manager.pop_incoming_event() {
+            Some((None, TestEventType::TestEvent(event))) => assert!(event.index == 42),
+            _ => panic!("expected the known event to be read"),
+        }
+        assert!(manager.has_incoming_events() == false);
+        assert!(reader.has_more() == false);
+    }
+
+    #[test]
+    fn events_stop_being_packed_at_the_configured_payload_size() {
+        let mut manifest = Manifest::<TestEventType, NoActorType>::new();
+        manifest.register_event(Box::new(TestEventBuilder));
+        let event: Box<dyn Event<TestEventType>> = Box::new(TestEvent {
+            guarantee: DeliveryGuarantee::Unreliable,
+            index: 0,
+        });
+
+        // each Event takes 6 bytes, after the 2 byte manager header
+        let mut writer = EventPacketWriter::with_capacity(16);
+        let mut written = 0;
+        while writer.write_event(&manifest, None, None, &event) == EventFit::Fit {
+            written += 1;
+        }
+        assert!(written == 2);
+        let mut bytes = Vec::new();
+        writer.get_bytes(&mut bytes);
+        assert!(bytes.len() < 16);
+
+        let mut default_writer = EventPacketWriter::new();
+        for _ in 0..10 {
+            assert!(default_writer.write_event(&manifest, None, None, &event) == EventFit::Fit);
+        }
+    }
+
+    #[test]
+    fn small_event_is_packed_after_a_large_one_does_not_fit() {
+        let mut manifest = Manifest::<TestEventType, NoActorType>::new();
+        manifest.register_event(Box::new(TestEventBuilder));
+        let mut manager = EventManager::<TestEventType>::new(None, None);
+        let event = TestEvent {
+            guarantee: DeliveryGuarantee::Reliable,
+            index: 0,
+        };
+        manager.queue_outgoing_event(&event.with_index(1));
+        // 2 bytes larger than the others, for its target
+        let large_id = manager.queue_outgoing_actor_event(513, &event.with_index(2));
+        manager.queue_outgoing_event(&event.with_index(3));
+
+        // room for the manager header & 7 more bytes after the first Event
+        let mut writer = EventPacketWriter::with_capacity(16);
+        let mut unfit_events = Vec::new();
+        while let Some((order_index, target, popped_event)) = manager.pop_outgoing_event(0) {
+            match writer.write_event(&manifest, order_index, target, &popped_event) {
+                EventFit::Fit => {}
+                EventFit::NoFit(bytes_needed) => {
+                    assert!(bytes_needed == 1);
+                    unfit_events.push((order_index, target, popped_event));
                 }
-                _ => {}
             }
         }
+        for (order_index, target, unfit_event) in unfit_events.iter().rev() {
+            manager.unpop_outgoing_event(0, *order_index, *target, unfit_event);
+        }
+        assert!(writer.is_full_of_events());
+
+        let mut bytes = Vec::new();
+        writer.get_bytes(&mut bytes);
+        let mut receiver = EventManager::<TestEventType>::new(None, None);
+        let mut reader = PacketReader::new(&bytes[1..]);
+        receiver.process_data(&mut reader, &manifest);
+        for index in [1, 3] {
+            match receiver.pop_incoming_event() {
+                Some((None, TestEventType::TestEvent(event))) => assert!(event.index == index),
+                _ => panic!("expected the small events to be packed"),
+            }
+        }
+        assert!(receiver.pop_incoming_event().is_none());
+
+        // the large Event waits for the next packet, & is only reported
+        // delivered once that packet is
+        manager.notify_packet_delivered(0);
+        while let Some(event_id) = manager.pop_delivered_event() {
+            assert!(Some(event_id) != large_id);
+        }
+        match manager.pop_outgoing_event(1) {
+            Some((None, Some(513), _)) => {}
+            _ => panic!("expected the large event to be sent next"),
+        }
+        manager.notify_packet_delivered(1);
+        assert!(manager.pop_delivered_event() == large_id);
+    }
+
+    #[test]
+    fn actor_event_round_trips_with_its_target() {
+        let mut manifest = Manifest::<TestEventType, NoActorType>::new();
+        manifest.register_event(Box::new(TestEventBuilder));
+        let mut sender = EventManager::<TestEventType>::new(None, None);
+        let mut receiver = EventManager::<TestEventType>::new(None, None);
+        let event = TestEvent {
+            guarantee: DeliveryGuarantee::ReliableOrdered,
+            index: 7,
+        };
+        sender.queue_outgoing_actor_event(513, &event);
+        sender.queue_outgoing_event(&event.with_index(8));
+
+        let mut writer = EventPacketWriter::new();
+        while let Some((order_index, target, event)) = sender.pop_outgoing_event(0) {
+            assert!(writer.write_event(&manifest, order_index, target, &event) == EventFit::Fit);
+        }
+        let mut bytes = Vec::new();
+        writer.get_bytes(&mut bytes);
+
+        // skip the manager type
+        let mut reader = PacketReader::new(&bytes[1..]);
+        receiver.process_data(&mut reader, &manifest);
+
+        match receiver.pop_incoming_event() {
+            Some((Some(513), TestEventType::TestEvent(event))) => assert!(event.index == 7),
+            _ => panic!("expected the event addressed to actor 513"),
+        }
+        match receiver.pop_incoming_event() {
+            Some((None, TestEventType::TestEvent(event))) => assert!(event.index == 8),
+            _ => panic!("expected the untargeted event"),
+        }
+    }
+
+    #[test]
+    fn forgetting_an_actor_purges_its_pending_reliable_events() {
+        let mut manager = EventManager::<TestEventType>::new(None, None);
+        let event = TestEvent {
+            guarantee: DeliveryGuarantee::Reliable,
+            index: 0,
+        };
+        manager.queue_outgoing_actor_event(513, &event);
+        assert!(manager.pop_outgoing_event(0).is_some());
+        manager.queue_outgoing_actor_event(513, &event.with_index(1));
+        manager.queue_outgoing_actor_event(514, &event.with_index(2));
+        manager.queue_outgoing_event(&event.with_index(3));
+
+        // the actor despawns while one of its events is in flight
+        manager.forget_actor_events(513);
+        manager.notify_packet_dropped(0);
+
+        let mut remaining = Vec::new();
+        while let Some((_, target, _)) = manager.pop_outgoing_event(1) {
+            remaining.push(target);
+        }
+        assert!(remaining == vec![Some(514), None]);
+    }
+
+    #[test]
+    fn ordered_event_for_a_forgotten_actor_is_not_delivered_to_its_key_reuser() {
+        let mut manifest = Manifest::<TestEventType, NoActorType>::new();
+        manifest.register_event(Box::new(TestEventBuilder));
+        let mut sender = EventManager::<TestEventType>::new(None, None);
+        let mut receiver = EventManager::<TestEventType>::new(None, None);
+        let event = TestEvent {
+            guarantee: DeliveryGuarantee::ReliableOrdered,
+            index: 0,
+        };
+
+        // an Event for actor 513 is lost in flight, as the actor despawns
+        let old_id = sender.queue_outgoing_actor_event(513, &event).unwrap();
+        assert!(sender.pop_outgoing_event(0).is_some());
+        sender.forget_actor_events(513);
+        sender.notify_packet_dropped(0);
+
+        // a new actor is given the same key
+        sender.queue_outgoing_actor_event(513, &event.with_index(1));
+        sender.queue_outgoing_event(&event.with_index(2));
+
+        let mut writer = EventPacketWriter::new();
+        while let Some((order_index, target, event)) = sender.pop_outgoing_event(1) {
+            let fit = match order_index {
+                Some(order_index) if sender.is_tombstone(order_index) => {
+                    writer.write_tombstone(order_index)
+                }
+                _ => writer.write_event(&manifest, order_index, target, &event),
+            };
+            assert!(fit == EventFit::Fit);
+        }
+        let mut bytes = Vec::new();
+        writer.get_bytes(&mut bytes);
+        let mut reader = PacketReader::new(&bytes[1..]);
+        receiver.process_data(&mut reader, &manifest);
+
+        match receiver.pop_incoming_event() {
+            Some((Some(513), TestEventType::TestEvent(event))) => assert!(event.index == 1),
+            _ => panic!("expected only the new actor's event"),
+        }
+        match receiver.pop_incoming_event() {
+            Some((None, TestEventType::TestEvent(event))) => assert!(event.index == 2),
+            _ => panic!("expected the later ordered event not to wait on the tombstone"),
+        }
+        assert!(receiver.pop_incoming_event().is_none());
+
+        // the tombstone is not reported as a delivered Event
+        sender.notify_packet_delivered(1);
+        let mut delivered = Vec::new();
+        while let Some(event_id) = sender.pop_delivered_event() {
+            delivered.push(event_id);
+        }
+        assert!(delivered == vec![old_id + 1, old_id + 2]);
+    }
+
+    #[test]
+    fn malformed_event_data_is_discarded_without_panicking() {
+        let mut manifest = Manifest::<TestEventType, NoActorType>::new();
+        manifest.register_event(Box::new(TestEventBuilder));
+
+        // the second event declares a payload far past the end of the packet
+        let overlong_bytes: Vec<u8> = vec![
+            2, // event count
+            0, 0, 0, 0, 1, 42, // naia_id 0 is TestEvent, with an index of 42
+            0, 0, 0, 255, 255, 1,
+        ];
+        let mut reader = PacketReader::new(&overlong_bytes);
+        let mut manager = EventManager::<TestEventType>::new(None, None);
+        manager.process_data(&mut reader, &manifest);
+
+        match manager.pop_incoming_event() {
+            Some((None, TestEventType::TestEvent(event))) => assert!(event.index == 42),
+            _ => panic!("expected the well-formed event to be read"),
+        }
+        assert!(manager.has_incoming_events() == false);
+        assert!(reader.has_more() == false);
+
+        // the packet ends partway through an event's header
+        let truncated_bytes: Vec<u8> = vec![3, 0, 0, 0, 0, 1, 42, 0];
+        let mut reader = PacketReader::new(&truncated_bytes);
+        let mut manager = EventManager::<TestEventType>::new(None, None);
+        manager.process_data(&mut reader, &manifest);
+
+        assert!(manager.pop_incoming_event().is_some());
+        assert!(manager.has_incoming_events() == false);
+        assert!(reader.has_more() == false);
     }
 }
@@ -1,35 +1,80 @@
 use byteorder::{BigEndian, WriteBytesExt};
 
 use crate::{
-    actors::actor_type::ActorType,
+    actors::{actor_type::ActorType, local_actor_key::LocalActorKey},
     events::{event::Event, event_type::EventType},
     manager_type::ManagerType,
     manifest::Manifest,
+    raw_manager::RawManager,
     standard_header::StandardHeader,
 };
 
-/// The maximum of bytes that can be used for the payload of a given packet. (See #38 of http://ithare.com/64-network-dos-and-donts-for-game-engines-part-v-udp/)
-pub const MTU_SIZE: usize = 508 - StandardHeader::bytes_number();
+/// The maximum of bytes that can be used for the payload of a given packet,
+/// leaving room for the header and its checksum. (See #38 of http://ithare.com/64-network-dos-and-donts-for-game-engines-part-v-udp/)
+pub const MTU_SIZE: usize =
+    508 - StandardHeader::bytes_number() - StandardHeader::checksum_bytes_number();
 
-/// Handles writing of Event & Actor data into an outgoing packet
+// Bits of the flags byte in each Event's header, each followed by a u16 when set
+pub(crate) const ORDERED_EVENT_FLAG: u8 = 1;
+pub(crate) const TARGETED_EVENT_FLAG: u8 = 2;
+// Marks the order slot of a ReliableOrdered Event which holds no Event, as
+// its target has gone. Only the naia id, flags & order index are written
+pub(crate) const TOMBSTONE_EVENT_FLAG: u8 = 4;
+
+// The size of an Event with an empty payload: its naia id, flags & payload
+// length
+const MIN_EVENT_SIZE: usize = 5;
+
+/// The smallest payload which has room for an Event with an empty payload,
+/// after the manager type & Event count
+pub const MIN_PAYLOAD_SIZE: usize = 2 + MIN_EVENT_SIZE;
+
+/// Whether an Event could be written into the space left in a packet
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EventFit {
+    /// The Event was written into the packet
+    Fit,
+    /// The Event was not written, as it needed this many more bytes than
+    /// were left in the packet. A smaller Event may still fit
+    NoFit(usize),
+}
+
+/// Handles writing of Event & raw message data into an outgoing packet
 pub struct EventPacketWriter {
     event_working_bytes: Vec<u8>,
     event_count: u8,
+    raw_working_bytes: Vec<u8>,
+    raw_count: u8,
+    max_payload_size: usize,
 }
 
 impl EventPacketWriter {
     /// Construct a new instance of `EventPacketWriter`, the given `buffer` will
     /// be used to read information from.
     pub fn new() -> EventPacketWriter {
+        return EventPacketWriter::with_capacity(MTU_SIZE);
+    }
+
+    /// Construct a new instance of `EventPacketWriter`, which packs Events
+    /// into a payload of at most `max_payload_size` bytes
+    pub fn with_capacity(max_payload_size: usize) -> EventPacketWriter {
         EventPacketWriter {
             event_working_bytes: Vec::<u8>::new(),
             event_count: 0,
+            raw_working_bytes: Vec::<u8>::new(),
+            raw_count: 0,
+            max_payload_size,
         }
     }
 
+    /// Get the largest payload, in bytes, that Events are packed into
+    pub fn max_payload_size(&self) -> usize {
+        return self.max_payload_size;
+    }
+
     /// Returns whether the writer has bytes to write into the outgoing packet
     pub fn has_bytes(&self) -> bool {
-        return self.event_count != 0;
+        return self.event_count != 0 || self.raw_count != 0;
     }
 
     /// Gets the bytes to write into an outgoing packet
@@ -41,21 +86,47 @@ impl EventPacketWriter {
             out_bytes.append(&mut self.event_working_bytes); // write event payload
             self.event_count = 0;
         }
+        if self.raw_count != 0 {
+            out_bytes.write_u8(ManagerType::Raw as u8).unwrap(); // write manager type
+            out_bytes.write_u8(self.raw_count).unwrap(); // write number of raw messages
+            out_bytes.append(&mut self.raw_working_bytes); // write raw payload
+            self.raw_count = 0;
+        }
     }
 
     /// Get the number of bytes which is ready to be written into an outgoing
     /// packet
     pub fn bytes_number(&self) -> usize {
-        return self.event_working_bytes.len();
+        return self.event_working_bytes.len() + self.raw_working_bytes.len();
+    }
+
+    /// Get the number of bytes left for the next Event written, including its
+    /// header
+    pub fn remaining_event_bytes(&self) -> usize {
+        // the payload must stay under the maximum, along with the manager
+        // header written ahead of the Events: 2 bytes for the manager type &
+        // Event count, and 1 byte of slack
+        let used_bytes = self.bytes_number() + 2 + 1;
+        return self.max_payload_size.saturating_sub(used_bytes);
+    }
+
+    /// Returns whether there is too little space left for any further Event
+    /// to be written, however small
+    pub fn is_full_of_events(&self) -> bool {
+        return self.remaining_event_bytes() < MIN_EVENT_SIZE;
     }
 
     /// Writes an Event into the Writer's internal buffer, which will eventually
-    /// be put into the outgoing packet
+    /// be put into the outgoing packet, along with its order index if it is
+    /// ReliableOrdered, and the Actor it is addressed to, if any. If the Event
+    /// does not fit, reports how many bytes it was short by
     pub fn write_event<T: EventType, U: ActorType>(
         &mut self,
         manifest: &Manifest<T, U>,
+        order_index: Option<u16>,
+        target: Option<LocalActorKey>,
         event: &Box<dyn Event<T>>,
-    ) -> bool {
+    ) -> EventFit {
         //Write event payload
         let mut event_payload_bytes = Vec::<u8>::new();
         event.as_ref().write(&mut event_payload_bytes);
@@ -66,15 +137,76 @@ impl EventPacketWriter {
         let type_id = event.as_ref().get_type_id();
         let naia_id = manifest.get_event_naia_id(&type_id); // get naia id
         event_total_bytes.write_u16::<BigEndian>(naia_id).unwrap(); // write naia id
+        let mut flags = 0;
+        if order_index.is_some() {
+            flags |= ORDERED_EVENT_FLAG;
+        }
+        if target.is_some() {
+            flags |= TARGETED_EVENT_FLAG;
+        }
+        event_total_bytes.write_u8(flags).unwrap(); // write flags
+        if let Some(index) = order_index {
+            event_total_bytes.write_u16::<BigEndian>(index).unwrap(); // write order index
+        }
+        if let Some(key) = target {
+            event_total_bytes.write_u16::<BigEndian>(key).unwrap(); // write target actor key
+        }
+        event_total_bytes
+            .write_u16::<BigEndian>(event_payload_bytes.len() as u16)
+            .unwrap(); // write payload length
         event_total_bytes.append(&mut event_payload_bytes); // write payload
 
-        let mut hypothetical_next_payload_size = self.bytes_number() + event_total_bytes.len();
-        if self.event_count == 0 {
-            hypothetical_next_payload_size += 2;
-        }
-        if hypothetical_next_payload_size < MTU_SIZE {
+        let remaining_bytes = self.remaining_event_bytes();
+        if event_total_bytes.len() <= remaining_bytes {
             self.event_count += 1;
             self.event_working_bytes.append(&mut event_total_bytes);
+            return EventFit::Fit;
+        } else {
+            return EventFit::NoFit(event_total_bytes.len() - remaining_bytes);
+        }
+    }
+
+    /// Writes a tombstone in place of the ReliableOrdered Event with the
+    /// given order index, so that the remote host fills in its order slot
+    /// without handing anything to the application
+    pub fn write_tombstone(&mut self, order_index: u16) -> EventFit {
+        let mut tombstone_bytes = Vec::<u8>::new();
+        tombstone_bytes.write_u16::<BigEndian>(0).unwrap(); // write naia id
+        tombstone_bytes
+            .write_u8(ORDERED_EVENT_FLAG | TOMBSTONE_EVENT_FLAG)
+            .unwrap(); // write flags
+        tombstone_bytes.write_u16::<BigEndian>(order_index).unwrap(); // write order index
+
+        let remaining_bytes = self.remaining_event_bytes();
+        if tombstone_bytes.len() <= remaining_bytes {
+            self.event_count += 1;
+            self.event_working_bytes.append(&mut tombstone_bytes);
+            return EventFit::Fit;
+        } else {
+            return EventFit::NoFit(tombstone_bytes.len() - remaining_bytes);
+        }
+    }
+
+    /// Writes a raw message into the Writer's internal buffer, which will
+    /// eventually be put into the outgoing packet, along with its channel
+    pub fn write_raw(&mut self, channel: u8, data: &[u8]) -> bool {
+        if self.raw_count == u8::MAX {
+            return false;
+        }
+
+        let mut raw_total_bytes = Vec::<u8>::new();
+        RawManager::write_message(channel, data, &mut raw_total_bytes);
+
+        let mut hypothetical_next_payload_size = self.bytes_number() + raw_total_bytes.len();
+        if self.event_count != 0 {
+            hypothetical_next_payload_size += 2;
+        }
+        if self.raw_count == 0 {
+            hypothetical_next_payload_size += 2;
+        }
+        if hypothetical_next_payload_size < self.max_payload_size {
+            self.raw_count += 1;
+            self.raw_working_bytes.append(&mut raw_total_bytes);
             return true;
         } else {
             return false;
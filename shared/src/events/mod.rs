@@ -1,5 +1,7 @@
+pub(crate) mod delivery_guarantee;
 pub(crate) mod event;
 pub(crate) mod event_builder;
+pub(crate) mod event_id;
 pub(crate) mod event_manager;
 pub(crate) mod event_packet_writer;
 pub(crate) mod event_type;
@@ -0,0 +1,85 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+use std::io::Read;
+
+use crate::packet_type::PacketType;
+
+#[derive(Copy, Clone, Debug)]
+/// This header is prepended to each piece of a packet that was too large to be
+/// sent as a single datagram, so that the remote host can reassemble it.
+pub struct FragmentHeader {
+    // This identifies which packet the fragment belongs to
+    message_id: u16,
+    // This is the position of the fragment within the packet
+    fragment_index: u8,
+    // This is the total number of fragments the packet was split into
+    fragment_total: u8,
+}
+
+impl FragmentHeader {
+    /// Creates a new FragmentHeader
+    pub fn new(message_id: u16, fragment_index: u8, fragment_total: u8) -> FragmentHeader {
+        FragmentHeader {
+            message_id,
+            fragment_index,
+            fragment_total,
+        }
+    }
+
+    /// Returns the number of bytes in the header, including the leading
+    /// packet type byte
+    pub const fn bytes_number() -> usize {
+        return 5;
+    }
+
+    /// Returns the id of the packet the fragment belongs to
+    pub fn message_id(&self) -> u16 {
+        self.message_id
+    }
+
+    /// Returns the position of the fragment within the packet
+    pub fn fragment_index(&self) -> u8 {
+        self.fragment_index
+    }
+
+    /// Returns the total number of fragments the packet was split into
+    pub fn fragment_total(&self) -> u8 {
+        self.fragment_total
+    }
+
+    /// Writes the header to an outgoing byte buffer
+    pub fn write(&self, buffer: &mut Vec<u8>) {
+        buffer.write_u8(PacketType::Fragment as u8).unwrap();
+        buffer.write_u16::<BigEndian>(self.message_id).unwrap();
+        buffer.write_u8(self.fragment_index).unwrap();
+        buffer.write_u8(self.fragment_total).unwrap();
+    }
+
+    /// Reads the header from an incoming byte slice, returning None if the
+    /// slice is not a valid fragment
+    pub fn read(mut msg: &[u8]) -> Option<(Self, Box<[u8]>)> {
+        if msg.len() < FragmentHeader::bytes_number() {
+            return None;
+        }
+
+        let p_type: PacketType = msg.read_u8().unwrap().into();
+        if p_type != PacketType::Fragment {
+            return None;
+        }
+        let message_id = msg.read_u16::<BigEndian>().unwrap();
+        let fragment_index = msg.read_u8().unwrap();
+        let fragment_total = msg.read_u8().unwrap();
+
+        let mut buffer = Vec::new();
+        msg.read_to_end(&mut buffer).unwrap();
+
+        return Some((
+            FragmentHeader {
+                message_id,
+                fragment_index,
+                fragment_total,
+            },
+            buffer.into_boxed_slice(),
+        ));
+    }
+}
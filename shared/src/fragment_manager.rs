@@ -0,0 +1,315 @@
+use std::{collections::HashMap, error::Error, fmt, time::Duration};
+
+use naia_socket_shared::Instant;
+
+use super::{fragment_header::FragmentHeader, packet_type::PacketType};
+
+// The most packets which may be reassembled at once. A fragment of another
+// packet beyond this replaces the packet which has waited longest, so that a
+// remote host sending endless incomplete packets can't use up memory
+const MAX_INCOMING_PACKETS: usize = 8;
+
+/// Splits outgoing packets which exceed the maximum packet size into
+/// fragments, and reassembles incoming fragments into whole packets
+#[derive(Debug)]
+pub struct FragmentManager {
+    max_packet_size: usize,
+    fragment_timeout_duration: Duration,
+    next_message_id: u16,
+    incoming_fragments: HashMap<u16, IncomingFragments>,
+}
+
+#[derive(Debug)]
+struct IncomingFragments {
+    first_received: Instant,
+    fragments: Vec<Option<Box<[u8]>>>,
+    received_count: usize,
+}
+
+impl FragmentManager {
+    /// Create a new FragmentManager, which will split packets larger than
+    /// `max_packet_size` bytes, and drop incomplete incoming packets after
+    /// `fragment_timeout_duration`
+    pub fn new(max_packet_size: usize, fragment_timeout_duration: Duration) -> Self {
+        if max_packet_size <= FragmentHeader::bytes_number() {
+            panic!(
+                "max_packet_size must be larger than the fragment header size of {} bytes",
+                FragmentHeader::bytes_number()
+            );
+        }
+
+        FragmentManager {
+            max_packet_size,
+            fragment_timeout_duration,
+            next_message_id: 0,
+            incoming_fragments: HashMap::new(),
+        }
+    }
+
+    /// Returns whether the given incoming payload is a fragment of a larger
+    /// packet
+    pub fn is_fragment(payload: &[u8]) -> bool {
+        return match payload.first() {
+            Some(byte) => PacketType::from(*byte) == PacketType::Fragment,
+            None => false,
+        };
+    }
+
+    /// Split an outgoing packet into datagrams no larger than the maximum
+    /// packet size. A packet which already fits is returned unchanged. A
+    /// packet which would need more fragments than a fragment header can
+    /// count is refused, and nothing is sent
+    pub fn fragment_outgoing_packet(
+        &mut self,
+        packet: Box<[u8]>,
+    ) -> Result<Vec<Box<[u8]>>, FragmentError> {
+        if packet.len() <= self.max_packet_size {
+            return Ok(vec![packet]);
+        }
+
+        let chunk_size = self.max_packet_size - FragmentHeader::bytes_number();
+        let fragment_total = packet.len().div_ceil(chunk_size);
+        if fragment_total > u8::MAX as usize {
+            return Err(FragmentError::PacketTooLarge {
+                packet_size: packet.len(),
+                max_size: chunk_size * u8::MAX as usize,
+            });
+        }
+
+        let message_id = self.next_message_id;
+        self.next_message_id = self.next_message_id.wrapping_add(1);
+
+        let mut output = Vec::with_capacity(fragment_total);
+        for (fragment_index, chunk) in packet.chunks(chunk_size).enumerate() {
+            let mut fragment_bytes = Vec::with_capacity(self.max_packet_size);
+            FragmentHeader::new(message_id, fragment_index as u8, fragment_total as u8)
+                .write(&mut fragment_bytes);
+            fragment_bytes.extend_from_slice(chunk);
+            output.push(fragment_bytes.into_boxed_slice());
+        }
+
+        return Ok(output);
+    }
+
+    /// Process an incoming fragment, returning the reassembled packet once
+    /// all of its fragments have been received. Fragments may arrive in any
+    /// order, and incomplete packets are dropped once they time out.
+    pub fn process_incoming_fragment(&mut self, payload: &[u8]) -> Option<Box<[u8]>> {
+        return self.process_incoming_fragment_at(payload, &Instant::now());
+    }
+
+    /// Same as process_incoming_fragment(), at the given moment, against
+    /// which incomplete packets are timed out
+    pub fn process_incoming_fragment_at(
+        &mut self,
+        payload: &[u8],
+        now: &Instant,
+    ) -> Option<Box<[u8]>> {
+        let timeout_millis = self
+            .fragment_timeout_duration
+            .as_millis()
+            .min(u128::from(u32::MAX)) as u32;
+        self.incoming_fragments.retain(|_, incoming| {
+            let mut deadline = incoming.first_received.clone();
+            deadline.add_millis(timeout_millis);
+            return deadline >= *now;
+        });
+
+        let (header, chunk) = FragmentHeader::read(payload)?;
+        let fragment_total = header.fragment_total() as usize;
+        let fragment_index = header.fragment_index() as usize;
+        if fragment_index >= fragment_total {
+            return None;
+        }
+
+        if !self.incoming_fragments.contains_key(&header.message_id())
+            && self.incoming_fragments.len() >= MAX_INCOMING_PACKETS
+        {
+            let oldest_message_id = self
+                .incoming_fragments
+                .iter()
+                .min_by_key(|(_, incoming)| incoming.first_received.clone())
+                .map(|(message_id, _)| *message_id);
+            if let Some(oldest_message_id) = oldest_message_id {
+                self.incoming_fragments.remove(&oldest_message_id);
+            }
+        }
+
+        let incoming = self
+            .incoming_fragments
+            .entry(header.message_id())
+            .or_insert_with(|| IncomingFragments::new(fragment_total, now));
+        if incoming.fragments.len() != fragment_total {
+            // message id has been reused for a different packet
+            *incoming = IncomingFragments::new(fragment_total, now);
+        }
+
+        if incoming.fragments[fragment_index].is_none() {
+            incoming.fragments[fragment_index] = Some(chunk);
+            incoming.received_count += 1;
+        }
+
+        if incoming.received_count < fragment_total {
+            return None;
+        }
+
+        let incoming = self
+            .incoming_fragments
+            .remove(&header.message_id())
+            .unwrap();
+        let mut packet = Vec::new();
+        for fragment in incoming.fragments {
+            packet.extend_from_slice(&fragment.unwrap());
+        }
+        return Some(packet.into_boxed_slice());
+    }
+}
+
+/// An error which occurs when an outgoing packet can't be fragmented
+#[derive(Debug)]
+pub enum FragmentError {
+    /// The packet needs more fragments than a fragment header can count
+    PacketTooLarge {
+        /// The size of the packet, in bytes
+        packet_size: usize,
+        /// The largest packet which can be fragmented, in bytes
+        max_size: usize,
+    },
+}
+
+impl fmt::Display for FragmentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            FragmentError::PacketTooLarge {
+                packet_size,
+                max_size,
+            } => write!(
+                f,
+                "Naia Fragment Error: packet of {} bytes is larger than the {} bytes which can be fragmented",
+                packet_size, max_size
+            ),
+        }
+    }
+}
+
+impl Error for FragmentError {}
+
+impl IncomingFragments {
+    fn new(fragment_total: usize, now: &Instant) -> Self {
+        IncomingFragments {
+            first_received: now.clone(),
+            fragments: vec![None; fragment_total],
+            received_count: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use naia_socket_shared::Instant;
+
+    use super::MAX_INCOMING_PACKETS;
+    use crate::FragmentManager;
+
+    fn test_packet(size: usize) -> Box<[u8]> {
+        return (0..size)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<u8>>()
+            .into_boxed_slice();
+    }
+
+    #[test]
+    fn small_packet_is_not_fragmented() {
+        let mut sender = FragmentManager::new(100, Duration::from_secs(1));
+        let packet = test_packet(100);
+
+        let datagrams = sender.fragment_outgoing_packet(packet.clone()).unwrap();
+
+        assert!(datagrams.len() == 1);
+        assert!(datagrams[0] == packet);
+        assert!(!FragmentManager::is_fragment(&datagrams[0]));
+    }
+
+    #[test]
+    fn large_packet_round_trips_out_of_order() {
+        let mut sender = FragmentManager::new(100, Duration::from_secs(1));
+        let mut receiver = FragmentManager::new(100, Duration::from_secs(1));
+        let packet = test_packet(5000);
+
+        let mut datagrams = sender.fragment_outgoing_packet(packet.clone()).unwrap();
+        assert!(datagrams.len() > 1);
+        for datagram in datagrams.iter() {
+            assert!(datagram.len() <= 100);
+            assert!(FragmentManager::is_fragment(datagram));
+        }
+
+        datagrams.reverse();
+        let last_datagram = datagrams.pop().unwrap();
+        for datagram in datagrams.iter() {
+            assert!(receiver.process_incoming_fragment(datagram).is_none());
+        }
+        // duplicates should not complete the packet early
+        assert!(receiver.process_incoming_fragment(&datagrams[0]).is_none());
+
+        let reassembled = receiver.process_incoming_fragment(&last_datagram);
+        assert!(reassembled == Some(packet));
+    }
+
+    #[test]
+    fn incomplete_packet_is_dropped_after_timeout() {
+        let mut sender = FragmentManager::new(100, Duration::from_secs(1));
+        let mut receiver = FragmentManager::new(100, Duration::from_millis(20));
+        let packet = test_packet(5000);
+
+        let mut datagrams = sender.fragment_outgoing_packet(packet).unwrap();
+        let last_datagram = datagrams.pop().unwrap();
+        let first_received = Instant::now();
+        for datagram in datagrams.iter() {
+            receiver.process_incoming_fragment_at(datagram, &first_received);
+        }
+
+        let mut timed_out = first_received.clone();
+        timed_out.add_millis(21);
+        assert!(receiver
+            .process_incoming_fragment_at(&last_datagram, &timed_out)
+            .is_none());
+    }
+
+    #[test]
+    fn packet_needing_too_many_fragments_is_refused() {
+        let mut sender = FragmentManager::new(10, Duration::from_secs(1));
+
+        // 5 bytes of each fragment are left after its header
+        assert!(sender
+            .fragment_outgoing_packet(test_packet(5 * 255))
+            .is_ok());
+        assert!(sender
+            .fragment_outgoing_packet(test_packet(5 * 255 + 1))
+            .is_err());
+    }
+
+    #[test]
+    fn reassembly_of_too_many_packets_drops_the_oldest() {
+        let mut sender = FragmentManager::new(100, Duration::from_secs(1));
+        let mut receiver = FragmentManager::new(100, Duration::from_secs(1));
+
+        let mut last_datagrams = Vec::new();
+        for _ in 0..(MAX_INCOMING_PACKETS + 1) {
+            let mut datagrams = sender.fragment_outgoing_packet(test_packet(150)).unwrap();
+            assert!(datagrams.len() == 2);
+            last_datagrams.push(datagrams.pop().unwrap());
+            receiver.process_incoming_fragment(&datagrams[0]);
+        }
+        assert!(receiver.incoming_fragments.len() == MAX_INCOMING_PACKETS);
+
+        // the first packet was dropped to make room for the last
+        assert!(receiver
+            .process_incoming_fragment(&last_datagrams[0])
+            .is_none());
+        assert!(receiver
+            .process_incoming_fragment(&last_datagrams[MAX_INCOMING_PACKETS])
+            .is_some());
+    }
+}
@@ -0,0 +1,58 @@
+use std::net::{IpAddr, Ipv6Addr, SocketAddr, UdpSocket};
+
+// A public IPv6 address, used only to choose a route. Nothing is sent to it.
+const PUBLIC_IPV6_ADDRESS: Ipv6Addr = Ipv6Addr::new(0x2001, 0x4860, 0x4860, 0, 0, 0, 0, 0x8888);
+
+/// Helper method to find the local IPv6 address of the interface used to
+/// reach the internet, if possible. On dual-stack hosts, where
+/// `find_my_ip_address()` may return either family, this always returns an
+/// IPv6 address.
+pub fn find_my_ipv6_address() -> Option<IpAddr> {
+    return find_my_ip_address_towards(IpAddr::V6(PUBLIC_IPV6_ADDRESS));
+}
+
+/// Helper method to find the local IP address of the interface which would
+/// be used to reach the given remote address, if possible. The returned
+/// address is of the same family as the remote address, so this also picks
+/// between IPv4 & IPv6 on dual-stack hosts.
+pub fn find_my_ip_address_towards(remote_address: IpAddr) -> Option<IpAddr> {
+    let bind_address: SocketAddr = match remote_address {
+        IpAddr::V4(_) => "0.0.0.0:0".parse().unwrap(),
+        IpAddr::V6(_) => "[::]:0".parse().unwrap(),
+    };
+    // connecting a UDP socket only selects a route, no packet is sent
+    let socket = UdpSocket::bind(bind_address).ok()?;
+    socket.connect(SocketAddr::new(remote_address, 80)).ok()?;
+    let local_address = socket.local_addr().ok()?.ip();
+    if local_address.is_unspecified() {
+        return None;
+    }
+    return Some(local_address);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, UdpSocket};
+
+    use super::{find_my_ip_address_towards, find_my_ipv6_address};
+
+    #[test]
+    fn loopback_is_reached_through_loopback() {
+        let ipv4_loopback = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        assert!(find_my_ip_address_towards(ipv4_loopback) == Some(ipv4_loopback));
+
+        // only on hosts with an IPv6 loopback interface
+        if UdpSocket::bind("[::1]:0").is_ok() {
+            let ipv6_loopback = IpAddr::V6(Ipv6Addr::LOCALHOST);
+            assert!(find_my_ip_address_towards(ipv6_loopback) == Some(ipv6_loopback));
+        }
+    }
+
+    #[test]
+    fn ipv6_address_is_never_ipv4() {
+        // None on hosts without an IPv6 route
+        if let Some(address) = find_my_ipv6_address() {
+            assert!(address.is_ipv6());
+        }
+    }
+}
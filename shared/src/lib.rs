@@ -12,24 +12,46 @@
 
 mod ack_manager;
 mod actors;
+mod bit_reader;
+mod bit_writer;
+mod compressor;
+mod connect_flags;
 mod connection;
 mod connection_config;
+mod connection_config_builder;
+mod disconnect_policy;
 mod events;
+mod fragment_header;
+mod fragment_manager;
 mod host_tick_manager;
 mod host_type;
+mod ip_address;
 mod manager_type;
 mod manifest;
+#[cfg(feature = "testing")]
+mod mock_link;
+mod overflow_policy;
+mod packet_reader_ext;
+mod packet_serde;
 mod packet_type;
+mod ping_manager;
+mod protocol_version;
+mod raw_manager;
 mod sequence_buffer;
 mod shared_config;
 mod standard_header;
+#[cfg(any(test, feature = "testing"))]
+mod test_support;
+mod timestamp_ext;
+mod traffic_stats;
 mod wrapping_number;
 
 /// Commonly used utility methods to be used by naia-server & naia-client
 pub mod utils;
 
 pub use naia_socket_shared::{
-    find_my_ip_address, Instant, LinkConditionerConfig, PacketReader, Random, Timer, Timestamp,
+    find_my_ip_address, link_condition_logic, Instant, LinkConditionerConfig, PacketReader, Random,
+    Ref, TimeQueue, Timer, Timestamp,
 };
 
 pub use ack_manager::AckManager;
@@ -39,26 +61,50 @@ pub use actors::{
     actor_mutator::ActorMutator,
     actor_notifiable::ActorNotifiable,
     actor_type::ActorType,
-    interp_lerp::interp_lerp,
+    create_flags::{PREDICTION_TOKEN_FLAG, SPAWN_EVENT_FLAG},
+    interp_lerp::{interp_lerp, interp_lerp_fixed, FixedFraction},
     local_actor_key::LocalActorKey,
     property::Property,
     state_mask::StateMask,
 };
+pub use bit_reader::BitReader;
+pub use bit_writer::BitWriter;
+pub use compressor::Compressor;
+pub use connect_flags::{COMPRESSION_FLAG, INTEGRITY_CHECK_FLAG};
 pub use connection::Connection;
 pub use connection_config::ConnectionConfig;
+pub use connection_config_builder::{ConnectionConfigBuilder, ConnectionConfigError};
+pub use disconnect_policy::DisconnectPolicy;
 pub use events::{
+    delivery_guarantee::DeliveryGuarantee,
     event::{Event, EventClone},
     event_builder::EventBuilder,
+    event_id::EventId,
     event_manager::EventManager,
-    event_packet_writer::{EventPacketWriter, MTU_SIZE},
+    event_packet_writer::{EventFit, EventPacketWriter, MIN_PAYLOAD_SIZE, MTU_SIZE},
     event_type::EventType,
 };
+pub use fragment_header::FragmentHeader;
+pub use fragment_manager::{FragmentError, FragmentManager};
 pub use host_tick_manager::HostTickManager;
 pub use host_type::HostType;
+pub use ip_address::{find_my_ip_address_towards, find_my_ipv6_address};
 pub use manager_type::ManagerType;
-pub use manifest::Manifest;
+pub use manifest::{Manifest, ManifestError};
+#[cfg(feature = "testing")]
+pub use mock_link::MockLink;
+pub use overflow_policy::OverflowPolicy;
+pub use packet_reader_ext::PacketReaderExt;
+pub use packet_serde::PacketSerde;
 pub use packet_type::PacketType;
+pub use ping_manager::PingManager;
+pub use protocol_version::PROTOCOL_VERSION;
+pub use raw_manager::{RawManager, RawMessageError};
 pub use sequence_buffer::{SequenceBuffer, SequenceIterator, SequenceNumber};
-pub use shared_config::SharedConfig;
+pub use shared_config::{SharedConfig, SharedConfigError};
 pub use standard_header::StandardHeader;
+#[cfg(any(test, feature = "testing"))]
+pub use test_support::NoActorType;
+pub use timestamp_ext::TimestampExt;
+pub use traffic_stats::{PacketTraffic, TrafficStats};
 pub use wrapping_number::{sequence_greater_than, sequence_less_than, wrapping_diff};
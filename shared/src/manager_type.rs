@@ -1,5 +1,5 @@
-/// Every data packet transmitted has data specific to either the Event or
-/// Actor managers. This value is written to differentiate those parts of the
+/// Every data packet transmitted has data specific to the Event, Actor,
+/// Command or Raw managers. This value is written to differentiate those parts of the
 /// payload.
 #[derive(Copy, Clone, Debug, PartialEq)]
 #[repr(u8)]
@@ -10,6 +10,8 @@ pub enum ManagerType {
     Actor = 2,
     /// An CommandManager
     Command = 3,
+    /// A RawManager
+    Raw = 4,
     /// Unknown Manager
     Unknown = 255,
 }
@@ -20,6 +22,7 @@ impl From<u8> for ManagerType {
             1 => return ManagerType::Event,
             2 => return ManagerType::Actor,
             3 => return ManagerType::Command,
+            4 => return ManagerType::Raw,
             _ => return ManagerType::Unknown,
         };
     }
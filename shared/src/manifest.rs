@@ -1,4 +1,4 @@
-use std::{any::TypeId, collections::HashMap};
+use std::{any::TypeId, collections::HashMap, error::Error, fmt};
 
 use crate::{
     actors::{actor_builder::ActorBuilder, actor_type::ActorType},
@@ -111,4 +111,162 @@ impl<T: EventType, U: ActorType> Manifest<T, U> {
         self.register_actor(actor_builder);
         self.register_event(event_builder);
     }
+
+    /// Checks that no Event or Actor type has been registered more than once.
+    /// NaiaIds are assigned in order of registration, so a type registered
+    /// twice is left with a NaiaId which is never written, and shifts the
+    /// NaiaIds of every type registered after it. Call this at startup, on
+    /// both the Client & Server.
+    pub fn validate(&self) -> Result<(), ManifestError> {
+        let event_type_ids = (0..self.event_naia_id_count)
+            .map(|naia_id| self.event_builder_map.get(&naia_id).unwrap().get_type_id());
+        if let Some(naia_ids) = find_duplicate(event_type_ids) {
+            return Err(ManifestError::DuplicateEventType { naia_ids });
+        }
+
+        let actor_type_ids = (0..self.actor_naia_id_count)
+            .map(|naia_id| self.actor_builder_map.get(&naia_id).unwrap().get_type_id());
+        if let Some(naia_ids) = find_duplicate(actor_type_ids) {
+            return Err(ManifestError::DuplicateActorType { naia_ids });
+        }
+
+        return Ok(());
+    }
+}
+
+// Given TypeIds in order of NaiaId, returns the NaiaIds of the first TypeId
+// which appears more than once
+fn find_duplicate(type_ids: impl Iterator<Item = TypeId>) -> Option<Vec<u16>> {
+    let mut naia_ids_by_type: HashMap<TypeId, Vec<u16>> = HashMap::new();
+    let mut type_order: Vec<TypeId> = Vec::new();
+    for (naia_id, type_id) in type_ids.enumerate() {
+        let naia_ids = naia_ids_by_type.entry(type_id).or_insert_with(|| {
+            type_order.push(type_id);
+            Vec::new()
+        });
+        naia_ids.push(naia_id as u16);
+    }
+    for type_id in type_order {
+        let naia_ids = naia_ids_by_type.remove(&type_id).unwrap();
+        if naia_ids.len() > 1 {
+            return Some(naia_ids);
+        }
+    }
+    return None;
+}
+
+/// An error which occurs when a Manifest has been registered incorrectly
+#[derive(Debug)]
+pub enum ManifestError {
+    /// An Event type has been registered more than once
+    DuplicateEventType {
+        /// The NaiaIds the Event type was registered under
+        naia_ids: Vec<u16>,
+    },
+    /// An Actor type has been registered more than once
+    DuplicateActorType {
+        /// The NaiaIds the Actor type was registered under
+        naia_ids: Vec<u16>,
+    },
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            ManifestError::DuplicateEventType { naia_ids } => write!(
+                f,
+                "Naia Manifest Error: the same Event type is registered under NaiaIds {:?}",
+                naia_ids
+            ),
+            ManifestError::DuplicateActorType { naia_ids } => write!(
+                f,
+                "Naia Manifest Error: the same Actor type is registered under NaiaIds {:?}",
+                naia_ids
+            ),
+        }
+    }
+}
+
+impl Error for ManifestError {}
+
+#[cfg(test)]
+mod tests {
+    use std::any::TypeId;
+
+    use crate::{DeliveryGuarantee, Event, EventBuilder, EventType, NoActorType, PacketReader};
+
+    use super::{Manifest, ManifestError};
+
+    #[derive(Clone)]
+    struct ChatEvent;
+
+    #[derive(Clone)]
+    struct MoveEvent;
+
+    #[derive(Clone)]
+    enum TestEventType {
+        ChatEvent(ChatEvent),
+        MoveEvent(MoveEvent),
+    }
+
+    impl EventType for TestEventType {
+        fn write(&self, _: &mut Vec<u8>) {}
+        fn get_type_id(&self) -> TypeId {
+            match self {
+                TestEventType::ChatEvent(_) => return TypeId::of::<ChatEvent>(),
+                TestEventType::MoveEvent(_) => return TypeId::of::<MoveEvent>(),
+            }
+        }
+    }
+
+    impl Event<TestEventType> for ChatEvent {
+        fn guarantee_delivery(&self) -> DeliveryGuarantee {
+            return DeliveryGuarantee::Reliable;
+        }
+        fn write(&self, _: &mut Vec<u8>) {}
+        fn get_typed_copy(&self) -> TestEventType {
+            return TestEventType::ChatEvent(self.clone());
+        }
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<ChatEvent>();
+        }
+    }
+
+    struct ChatEventBuilder;
+
+    impl EventBuilder<TestEventType> for ChatEventBuilder {
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<ChatEvent>();
+        }
+        fn build(&self, _: &mut PacketReader) -> TestEventType {
+            return TestEventType::ChatEvent(ChatEvent);
+        }
+    }
+
+    struct MoveEventBuilder;
+
+    impl EventBuilder<TestEventType> for MoveEventBuilder {
+        fn get_type_id(&self) -> TypeId {
+            return TypeId::of::<MoveEvent>();
+        }
+        fn build(&self, _: &mut PacketReader) -> TestEventType {
+            return TestEventType::MoveEvent(MoveEvent);
+        }
+    }
+
+    #[test]
+    fn event_registered_twice_is_reported() {
+        let mut manifest = Manifest::<TestEventType, NoActorType>::new();
+        manifest.register_event(Box::new(ChatEventBuilder));
+        manifest.register_event(Box::new(MoveEventBuilder));
+        assert!(manifest.validate().is_ok());
+
+        manifest.register_event(Box::new(ChatEventBuilder));
+        match manifest.validate() {
+            Err(ManifestError::DuplicateEventType { naia_ids }) => {
+                assert!(naia_ids == vec![0, 2])
+            }
+            _ => panic!("expected a DuplicateEventType error"),
+        }
+    }
 }
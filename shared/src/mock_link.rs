@@ -0,0 +1,53 @@
+use std::{
+    collections::VecDeque,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+/// An in-memory link between one Client & a Server, made up of a queue of
+/// packets in each direction. A mock Client socket & a mock Server socket
+/// created from clones of the same MockLink exchange packets through it
+/// without touching the network, so that tests run deterministically
+#[derive(Clone, Debug)]
+pub struct MockLink {
+    client_address: SocketAddr,
+    to_server: Arc<Mutex<VecDeque<Box<[u8]>>>>,
+    to_client: Arc<Mutex<VecDeque<Box<[u8]>>>>,
+}
+
+impl MockLink {
+    /// Creates a new MockLink, with the address the Server will see packets
+    /// from the Client arrive from
+    pub fn new(client_address: SocketAddr) -> Self {
+        MockLink {
+            client_address,
+            to_server: Arc::new(Mutex::new(VecDeque::new())),
+            to_client: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+
+    /// Gets the address the Server sees the Client at
+    pub fn client_address(&self) -> SocketAddr {
+        return self.client_address;
+    }
+
+    /// Queues a packet sent by the Client, to be received by the Server
+    pub fn send_to_server(&self, payload: Box<[u8]>) {
+        self.to_server.lock().unwrap().push_back(payload);
+    }
+
+    /// Queues a packet sent by the Server, to be received by the Client
+    pub fn send_to_client(&self, payload: Box<[u8]>) {
+        self.to_client.lock().unwrap().push_back(payload);
+    }
+
+    /// Gets the next packet the Client has sent to the Server, if any
+    pub fn receive_at_server(&self) -> Option<Box<[u8]>> {
+        return self.to_server.lock().unwrap().pop_front();
+    }
+
+    /// Gets the next packet the Server has sent to the Client, if any
+    pub fn receive_at_client(&self) -> Option<Box<[u8]>> {
+        return self.to_client.lock().unwrap().pop_front();
+    }
+}
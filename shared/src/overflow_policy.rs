@@ -0,0 +1,17 @@
+/// Determines what happens to an Event queued while the outgoing Event queue
+/// of a Connection is already at its maximum length, for example because the
+/// application queues Events faster than a slow link can carry them
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OverflowPolicy {
+    /// Make room by dropping the oldest queued Event which is not
+    /// ReliableOrdered, as every later ReliableOrdered Event waits on those.
+    /// If only ReliableOrdered Events are queued, the new Event is dropped,
+    /// as with DropNewest
+    DropOldest,
+    /// Drop the new Event, keeping the queue as it is. The Event is reported
+    /// to the caller as not queued, and is given no id
+    DropNewest,
+    /// Refuse the new Event, reporting it to the caller so that it can be
+    /// queued again later
+    Error,
+}
@@ -0,0 +1,90 @@
+use std::io::Read;
+
+use byteorder::{BigEndian, ReadBytesExt};
+
+use naia_socket_shared::PacketReader;
+
+/// Adds bounds-checked reads to PacketReaders, so that a truncated or
+/// malformed packet can be rejected instead of causing a panic
+pub trait PacketReaderExt {
+    /// Returns the number of bytes which have not yet been read
+    fn remaining(&mut self) -> usize;
+    /// Reads a single byte, or returns None if there are no bytes left
+    fn try_read_u8(&mut self) -> Option<u8>;
+    /// Reads a u16, or returns None without reading anything if there are
+    /// fewer than two bytes left
+    fn try_read_u16(&mut self) -> Option<u16>;
+    /// Reads the given number of bytes, or returns None without reading
+    /// anything if there are fewer than that left
+    fn try_read_bytes(&mut self, count: usize) -> Option<Box<[u8]>>;
+    /// Moves past every remaining byte, so that nothing more is read from a
+    /// packet found to be malformed
+    fn skip_remaining(&mut self);
+}
+
+impl<'s> PacketReaderExt for PacketReader<'s> {
+    fn remaining(&mut self) -> usize {
+        let length = self.get_buffer().len();
+        let position = self.get_cursor().position() as usize;
+        return length.saturating_sub(position);
+    }
+
+    fn try_read_u8(&mut self) -> Option<u8> {
+        return self.get_cursor().read_u8().ok();
+    }
+
+    fn try_read_u16(&mut self) -> Option<u16> {
+        // a failed read may still consume the last byte
+        if self.remaining() < 2 {
+            return None;
+        }
+        return self.get_cursor().read_u16::<BigEndian>().ok();
+    }
+
+    fn try_read_bytes(&mut self, count: usize) -> Option<Box<[u8]>> {
+        if self.remaining() < count {
+            return None;
+        }
+        let mut bytes = vec![0; count];
+        self.get_cursor().read_exact(&mut bytes).ok()?;
+        return Some(bytes.into_boxed_slice());
+    }
+
+    fn skip_remaining(&mut self) {
+        let length = self.get_buffer().len();
+        self.get_cursor().set_position(length as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use naia_socket_shared::PacketReader;
+
+    use super::PacketReaderExt;
+
+    #[test]
+    fn reads_past_the_end_return_none() {
+        let bytes: Vec<u8> = vec![1, 2, 3];
+        let mut reader = PacketReader::new(&bytes);
+
+        assert!(reader.remaining() == 3);
+        assert!(reader.try_read_u16() == Some(258));
+        assert!(reader.remaining() == 1);
+        assert!(reader.try_read_u16().is_none());
+        assert!(reader.try_read_u8().is_some());
+        assert!(reader.try_read_u8().is_none());
+        assert!(reader.remaining() == 0);
+    }
+
+    #[test]
+    fn reading_more_bytes_than_remain_reads_nothing() {
+        let bytes: Vec<u8> = vec![1, 2, 3];
+        let mut reader = PacketReader::new(&bytes);
+
+        assert!(reader.try_read_bytes(4).is_none());
+        assert!(reader.remaining() == 3);
+        assert!(reader.try_read_bytes(2) == Some(vec![1, 2].into_boxed_slice()));
+        assert!(reader.try_read_bytes(2).is_none());
+        assert!(reader.try_read_u8() == Some(3));
+    }
+}
@@ -0,0 +1,158 @@
+use std::convert::TryInto;
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::{packet_reader_ext::PacketReaderExt, PacketReader};
+
+/// A value with a fixed byte layout, which can be written to an outgoing
+/// byte stream and read back from incoming packet data. Structs made up of
+/// PacketSerde fields can derive this with `#[derive(PacketSerde)]` from
+/// naia-derive, which writes & reads fields in the order they are declared
+pub trait PacketSerde: Sized {
+    /// Writes the value into an outgoing byte stream
+    fn write(&self, buffer: &mut Vec<u8>);
+    /// Reads a value from incoming packet data, or returns None if the data
+    /// is truncated or malformed
+    fn read(reader: &mut PacketReader) -> Option<Self>;
+}
+
+macro_rules! impl_packet_serde_for_number {
+    ($type:ty, $size:expr, $write:ident, $read:ident) => {
+        impl PacketSerde for $type {
+            fn write(&self, buffer: &mut Vec<u8>) {
+                buffer.$write::<BigEndian>(*self).unwrap();
+            }
+
+            fn read(reader: &mut PacketReader) -> Option<Self> {
+                if reader.remaining() < $size {
+                    return None;
+                }
+                return reader.get_cursor().$read::<BigEndian>().ok();
+            }
+        }
+    };
+}
+
+impl_packet_serde_for_number!(u16, 2, write_u16, read_u16);
+impl_packet_serde_for_number!(u32, 4, write_u32, read_u32);
+impl_packet_serde_for_number!(u64, 8, write_u64, read_u64);
+impl_packet_serde_for_number!(i16, 2, write_i16, read_i16);
+impl_packet_serde_for_number!(i32, 4, write_i32, read_i32);
+impl_packet_serde_for_number!(i64, 8, write_i64, read_i64);
+impl_packet_serde_for_number!(f32, 4, write_f32, read_f32);
+impl_packet_serde_for_number!(f64, 8, write_f64, read_f64);
+
+impl PacketSerde for u8 {
+    fn write(&self, buffer: &mut Vec<u8>) {
+        buffer.push(*self);
+    }
+
+    fn read(reader: &mut PacketReader) -> Option<Self> {
+        return reader.try_read_u8();
+    }
+}
+
+impl PacketSerde for i8 {
+    fn write(&self, buffer: &mut Vec<u8>) {
+        buffer.push(*self as u8);
+    }
+
+    fn read(reader: &mut PacketReader) -> Option<Self> {
+        if let Some(byte) = reader.try_read_u8() {
+            return Some(byte as i8);
+        }
+        return None;
+    }
+}
+
+impl PacketSerde for bool {
+    fn write(&self, buffer: &mut Vec<u8>) {
+        buffer.push(*self as u8);
+    }
+
+    fn read(reader: &mut PacketReader) -> Option<Self> {
+        match reader.try_read_u8() {
+            Some(0) => return Some(false),
+            Some(1) => return Some(true),
+            _ => return None,
+        }
+    }
+}
+
+// Options are written as a presence byte, followed by the value if present
+impl<T: PacketSerde> PacketSerde for Option<T> {
+    fn write(&self, buffer: &mut Vec<u8>) {
+        match self {
+            Some(value) => {
+                buffer.push(1);
+                value.write(buffer);
+            }
+            None => buffer.push(0),
+        }
+    }
+
+    fn read(reader: &mut PacketReader) -> Option<Self> {
+        match reader.try_read_u8() {
+            Some(0) => return Some(None),
+            Some(1) => {
+                if let Some(value) = T::read(reader) {
+                    return Some(Some(value));
+                }
+                return None;
+            }
+            _ => return None,
+        }
+    }
+}
+
+// Fixed arrays have a known length, so only their elements are written
+impl<T: PacketSerde, const N: usize> PacketSerde for [T; N] {
+    fn write(&self, buffer: &mut Vec<u8>) {
+        for value in self.iter() {
+            value.write(buffer);
+        }
+    }
+
+    fn read(reader: &mut PacketReader) -> Option<Self> {
+        let mut values = Vec::with_capacity(N);
+        for _ in 0..N {
+            match T::read(reader) {
+                Some(value) => values.push(value),
+                None => return None,
+            }
+        }
+        return values.try_into().ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use naia_socket_shared::PacketReader;
+
+    use super::PacketSerde;
+
+    #[test]
+    fn values_round_trip() {
+        let mut buffer = Vec::new();
+        (-3_i16).write(&mut buffer);
+        Some(2.5_f32).write(&mut buffer);
+        let absent: Option<u8> = None;
+        absent.write(&mut buffer);
+        [true, false, true].write(&mut buffer);
+        assert!(buffer.len() == 2 + 5 + 1 + 3);
+
+        let mut reader = PacketReader::new(&buffer);
+        assert!(i16::read(&mut reader) == Some(-3));
+        assert!(Option::<f32>::read(&mut reader) == Some(Some(2.5)));
+        assert!(Option::<u8>::read(&mut reader) == Some(None));
+        assert!(<[bool; 3]>::read(&mut reader) == Some([true, false, true]));
+        assert!(u8::read(&mut reader).is_none());
+    }
+
+    #[test]
+    fn truncated_data_is_rejected() {
+        let buffer: Vec<u8> = vec![1, 0, 0];
+        let mut reader = PacketReader::new(&buffer);
+        assert!(Option::<u32>::read(&mut reader).is_none());
+    }
+}
@@ -22,6 +22,28 @@ pub enum PacketType {
     /// A Pong message, used to calculate RTT. Must be the response to all Ping
     /// messages
     Pong = 8,
+    /// A message sent by a host to indicate that it is closing the connection,
+    /// so that the remote host need not wait for a timeout
+    Disconnect = 9,
+    /// A piece of a larger packet which exceeded the maximum packet size, to
+    /// be reassembled by the remote host
+    Fragment = 10,
+    /// The Server's response to the Client's final handshake message,
+    /// indicating that the connection has been refused
+    ServerRejectConnect = 11,
+    /// A message sent by a Client which has stopped hearing from the Server,
+    /// carrying the session token it was issued on connecting, so that the
+    /// Server can restore its connection, possibly from a new address. Once
+    /// challenged, the Client sends it again along with the challenge digest
+    Reconnect = 12,
+    /// The Server's response to a Reconnect message, carrying a digest which
+    /// the Client must send back to prove it can receive at its new address
+    ServerReconnectChallenge = 13,
+    /// The Server's response to a Reconnect message carrying a valid digest,
+    /// indicating that the connection has been restored. Carries the session
+    /// token to present if the connection times out again, as each one is
+    /// only good for a single reconnect
+    ServerReconnectResponse = 14,
     /// An unknown packet type
     Unknown = 255,
 }
@@ -37,6 +59,12 @@ impl From<u8> for PacketType {
             6 => return PacketType::ServerConnectResponse,
             7 => return PacketType::Ping,
             8 => return PacketType::Pong,
+            9 => return PacketType::Disconnect,
+            10 => return PacketType::Fragment,
+            11 => return PacketType::ServerRejectConnect,
+            12 => return PacketType::Reconnect,
+            13 => return PacketType::ServerReconnectChallenge,
+            14 => return PacketType::ServerReconnectResponse,
             _ => return PacketType::Unknown,
         };
     }
@@ -0,0 +1,233 @@
+use std::time::Duration;
+
+use byteorder::{BigEndian, WriteBytesExt};
+use log::warn;
+
+use crate::{
+    sequence_buffer::{SequenceBuffer, SequenceNumber},
+    Instant, PacketReader, PacketReaderExt, Timer,
+};
+
+#[derive(Clone, Debug)]
+struct SentPing {
+    time_sent: Instant,
+}
+
+/// Sends Ping messages to the remote host at a regular interval, and measures
+/// the Round Trip Time from the Pong messages it responds with
+#[derive(Debug)]
+pub struct PingManager {
+    ping_timer: Timer,
+    sent_pings: SequenceBuffer<SentPing>,
+    ping_index: SequenceNumber,
+    samples: f32,
+    max_samples: f32,
+    rtt_average: f32,
+    rtt_variance: f32,
+    rtt_deviation: f32,
+    pong_timeout_intervals: u16,
+    // pings sent since a pong last arrived, one per ping interval
+    unanswered_pings: u16,
+    warned_pong_timeout: bool,
+}
+
+impl PingManager {
+    /// Create a new PingManager, which pings every `ping_interval`, averages
+    /// RTT over `rtt_sample_size` samples, and considers RTT unknown if no
+    /// pong arrives within `pong_timeout_intervals` ping intervals
+    pub fn new(ping_interval: Duration, rtt_sample_size: u16, pong_timeout_intervals: u16) -> Self {
+        PingManager {
+            ping_index: 0,
+            ping_timer: Timer::new(ping_interval),
+            sent_pings: SequenceBuffer::with_capacity(rtt_sample_size),
+            samples: 0.0,
+            max_samples: f32::from(rtt_sample_size),
+            rtt_average: 0.0,
+            rtt_variance: 0.0,
+            rtt_deviation: 0.0,
+            pong_timeout_intervals,
+            unanswered_pings: 0,
+            warned_pong_timeout: false,
+        }
+    }
+
+    /// Returns whether a ping message should be sent
+    pub fn should_send_ping(&self) -> bool {
+        self.ping_timer.ringing()
+    }
+
+    /// Get an outgoing ping payload
+    pub fn get_ping_payload(&mut self) -> Box<[u8]> {
+        self.ping_timer.reset();
+        self.unanswered_pings = self.unanswered_pings.saturating_add(1);
+
+        if self.pong_timed_out() && !self.warned_pong_timeout {
+            warn!("no pong received within the pong timeout, RTT is unknown");
+            self.warned_pong_timeout = true;
+        }
+
+        self.sent_pings.insert(
+            self.ping_index,
+            SentPing {
+                time_sent: Instant::now(),
+            },
+        );
+
+        let mut out_bytes = Vec::<u8>::new();
+        out_bytes.write_u16::<BigEndian>(self.ping_index).unwrap(); // write index
+
+        // increment ping index
+        self.ping_index = self.ping_index.wrapping_add(1);
+
+        out_bytes.into_boxed_slice()
+    }
+
+    /// Process an incoming ping payload, returning the payload of the Pong to
+    /// respond with, or None if the Ping is too short to hold its index
+    pub fn process_ping(ping_payload: &[u8]) -> Option<Box<[u8]>> {
+        // read incoming ping index
+        let mut reader = PacketReader::new(&ping_payload);
+        let ping_index = match reader.try_read_u16() {
+            Some(ping_index) => ping_index,
+            None => {
+                warn!("discarding truncated ping");
+                return None;
+            }
+        };
+
+        // write pong payload
+        let mut out_bytes = Vec::<u8>::new();
+        out_bytes.write_u16::<BigEndian>(ping_index).unwrap(); // write index
+        Some(out_bytes.into_boxed_slice())
+    }
+
+    /// Process an incoming pong payload
+    pub fn process_pong(&mut self, pong_payload: &[u8]) {
+        let mut reader = PacketReader::new(&pong_payload);
+        // a truncated Pong can't be matched to its Ping
+        let ping_index = match reader.try_read_u16() {
+            Some(ping_index) => ping_index,
+            None => return,
+        };
+
+        match self.sent_pings.remove(ping_index) {
+            None => {}
+            Some(ping) => {
+                self.unanswered_pings = 0;
+                self.warned_pong_timeout = false;
+                self.process_new_rtt(&ping.time_sent.elapsed().as_secs_f32() * 1000.0);
+            }
+        }
+    }
+
+    fn process_new_rtt(&mut self, elapsed_millis: f32) {
+        if self.samples == 0.0 {
+            self.rtt_average = elapsed_millis;
+            self.samples = 1.0;
+            return;
+        } else {
+            if self.samples < self.max_samples {
+                self.samples += 1.0;
+            }
+
+            self.rtt_average =
+                ((self.rtt_average * self.samples) + elapsed_millis) / (self.samples + 1.0);
+
+            let new_variance = (elapsed_millis - self.rtt_average).powi(2);
+            self.rtt_variance =
+                ((self.rtt_variance * self.samples) + new_variance) / (self.samples + 1.0);
+
+            self.rtt_deviation = self.rtt_variance.sqrt();
+        }
+    }
+
+    /// Gets the current calculated average Round Trip Time to the remote host,
+    /// in milliseconds, or None if no pong has been received yet or within
+    /// the pong timeout
+    pub fn get_rtt(&self) -> Option<f32> {
+        if self.samples == 0.0 || self.pong_timed_out() {
+            return None;
+        }
+        return Some(self.rtt_average);
+    }
+
+    /// Gets the most recently calculated average Round Trip Time, in
+    /// milliseconds, even if no pong has been received within the pong
+    /// timeout
+    pub fn get_last_rtt(&self) -> f32 {
+        return self.rtt_average;
+    }
+
+    // The ping sent most recently is not yet overdue, so the pong timeout
+    // has passed once more pings than it has intervals go unanswered
+    fn pong_timed_out(&self) -> bool {
+        return self.unanswered_pings > self.pong_timeout_intervals;
+    }
+
+    /// Gets the current calculated standard deviation of Jitter to the remote
+    /// host, in milliseconds
+    pub fn get_jitter(&self) -> f32 {
+        return self.rtt_deviation;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::PingManager;
+
+    #[test]
+    fn rtt_updates_with_new_samples() {
+        let mut ping_manager = PingManager::new(Duration::from_secs(1), 20, 5);
+        assert!(ping_manager.get_rtt().is_none());
+        assert!(ping_manager.get_jitter() == 0.0);
+
+        ping_manager.process_new_rtt(100.0);
+        assert!(ping_manager.get_rtt() == Some(100.0));
+
+        ping_manager.process_new_rtt(200.0);
+        let rtt = ping_manager.get_rtt().unwrap();
+        assert!(rtt > 100.0);
+        assert!(rtt < 200.0);
+        assert!(ping_manager.get_jitter() > 0.0);
+    }
+
+    #[test]
+    fn rtt_is_unknown_once_pongs_stop_arriving() {
+        let mut ping_manager = PingManager::new(Duration::from_millis(10), 20, 3);
+
+        let ping = ping_manager.get_ping_payload();
+        ping_manager.process_pong(&PingManager::process_ping(&ping).unwrap());
+        assert!(ping_manager.get_rtt().is_some());
+
+        // pings keep going out, one per interval, but no pongs come back
+        for _ in 0..3 {
+            ping_manager.get_ping_payload();
+        }
+        assert!(ping_manager.get_rtt().is_some());
+        ping_manager.get_ping_payload();
+        assert!(ping_manager.get_rtt().is_none());
+
+        let ping = ping_manager.get_ping_payload();
+        ping_manager.process_pong(&PingManager::process_ping(&ping).unwrap());
+        assert!(ping_manager.get_rtt().is_some());
+    }
+
+    #[test]
+    fn truncated_pong_is_ignored() {
+        let mut ping_manager = PingManager::new(Duration::from_secs(1), 20, 3);
+        ping_manager.get_ping_payload();
+
+        ping_manager.process_pong(&[0]);
+        ping_manager.process_pong(&[]);
+        assert!(ping_manager.get_rtt().is_none());
+    }
+
+    #[test]
+    fn truncated_ping_gets_no_pong() {
+        assert!(PingManager::process_ping(&[0]).is_none());
+        assert!(PingManager::process_ping(&[]).is_none());
+        assert!(PingManager::process_ping(&[0, 7]) == Some(vec![0, 7].into_boxed_slice()));
+    }
+}
@@ -0,0 +1,5 @@
+/// The version of the wire format spoken by this build of naia. It is
+/// exchanged in the handshake, so that a Client & Server which would misparse
+/// each other's packets refuse to connect instead. Must be incremented with
+/// every change to the wire format
+pub const PROTOCOL_VERSION: u16 = 2;
@@ -0,0 +1,295 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    error::Error,
+    fmt,
+};
+
+use byteorder::{BigEndian, WriteBytesExt};
+use log::warn;
+
+use crate::{packet_reader_ext::PacketReaderExt, PacketReader};
+
+// The bytes written alongside a raw message into an otherwise empty payload:
+// the manager type & message count, then the message's channel & length, plus
+// the byte EventPacketWriter keeps below the maximum payload size
+const RAW_MESSAGE_OVERHEAD: usize = 6;
+
+/// Handles incoming/outgoing raw messages, which carry application-defined
+/// bytes on a numbered channel instead of an Event. Raw messages are sent in
+/// Data packets, and are retransmitted until the packet they were sent in is
+/// acknowledged by the remote host
+#[derive(Debug)]
+pub struct RawManager {
+    queued_outgoing_messages: VecDeque<(u8, Box<[u8]>)>,
+    queued_incoming_messages: VecDeque<(u8, Box<[u8]>)>,
+    sent_messages: HashMap<u16, Vec<(u8, Box<[u8]>)>>,
+}
+
+impl RawManager {
+    /// Creates a new RawManager
+    pub fn new() -> Self {
+        RawManager {
+            queued_outgoing_messages: VecDeque::new(),
+            queued_incoming_messages: VecDeque::new(),
+            sent_messages: HashMap::new(),
+        }
+    }
+
+    /// Occurs when a packet has been notified as delivered. Stops tracking
+    /// the raw messages sent in it
+    pub fn notify_packet_delivered(&mut self, packet_index: u16) {
+        self.sent_messages.remove(&packet_index);
+    }
+
+    /// Occurs when a packet has been notified as having been dropped. Queues
+    /// up the raw messages that were lost in the packet for retransmission,
+    /// ahead of any which have not been sent yet
+    pub fn notify_packet_dropped(&mut self, packet_index: u16) {
+        if let Some(dropped_messages) = self.sent_messages.remove(&packet_index) {
+            for message in dropped_messages.into_iter().rev() {
+                self.queued_outgoing_messages.push_front(message);
+            }
+        }
+    }
+
+    /// Returns whether the Manager has queued raw messages that can be
+    /// transmitted to the remote host
+    pub fn has_outgoing_messages(&self) -> bool {
+        return !self.queued_outgoing_messages.is_empty();
+    }
+
+    /// Returns the largest raw message, in bytes, which fits into a payload
+    /// of the given size
+    pub fn max_message_size(max_payload_size: usize) -> usize {
+        return max_payload_size
+            .saturating_sub(RAW_MESSAGE_OVERHEAD)
+            .min(usize::from(u16::MAX));
+    }
+
+    /// Queues up a raw message to be sent to the remote host on the given
+    /// channel, or refuses it if it could never fit into a payload of the
+    /// given size
+    pub fn queue_outgoing_message(
+        &mut self,
+        channel: u8,
+        data: &[u8],
+        max_payload_size: usize,
+    ) -> Result<(), RawMessageError> {
+        let max_size = RawManager::max_message_size(max_payload_size);
+        if data.len() > max_size {
+            return Err(RawMessageError::TooLarge {
+                size: data.len(),
+                max_size,
+            });
+        }
+        self.queued_outgoing_messages
+            .push_back((channel, data.into()));
+        return Ok(());
+    }
+
+    /// Pops the next outgoing raw message from the queue, recording it as
+    /// sent in the packet with the given index. Messages which no longer fit
+    /// into a payload of the given size, as it has been lowered since they
+    /// were queued, are discarded rather than left to block the queue
+    pub fn pop_outgoing_message(
+        &mut self,
+        packet_index: u16,
+        max_payload_size: usize,
+    ) -> Option<(u8, Box<[u8]>)> {
+        let max_size = RawManager::max_message_size(max_payload_size);
+        while let Some((channel, data)) = self.queued_outgoing_messages.pop_front() {
+            if data.len() > max_size {
+                warn!(
+                    "discarding raw message of {} bytes, larger than the maximum of {}",
+                    data.len(),
+                    max_size
+                );
+                continue;
+            }
+            self.sent_messages
+                .entry(packet_index)
+                .or_default()
+                .push((channel, data.clone()));
+            return Some((channel, data));
+        }
+        return None;
+    }
+
+    /// Places a raw message back into the front of the queue, for when it
+    /// did not fit into the packet with the given index
+    pub fn unpop_outgoing_message(&mut self, packet_index: u16, channel: u8, data: Box<[u8]>) {
+        if let Some(sent_messages) = self.sent_messages.get_mut(&packet_index) {
+            sent_messages.pop();
+            if sent_messages.is_empty() {
+                self.sent_messages.remove(&packet_index);
+            }
+        }
+        self.queued_outgoing_messages.push_front((channel, data));
+    }
+
+    /// Writes a raw message into an outgoing byte stream, as its channel,
+    /// length & data
+    pub fn write_message(channel: u8, data: &[u8], out_bytes: &mut Vec<u8>) {
+        out_bytes.write_u8(channel).unwrap(); // write channel
+        out_bytes.write_u16::<BigEndian>(data.len() as u16).unwrap(); // write data length
+        out_bytes.extend_from_slice(data); // write data
+    }
+
+    /// Given incoming packet data, read raw messages into the incoming queue
+    pub fn process_data(&mut self, reader: &mut PacketReader) {
+        let message_count = match reader.try_read_u8() {
+            Some(message_count) => message_count,
+            None => {
+                warn!("discarding truncated raw data");
+                return;
+            }
+        };
+        for _ in 0..message_count {
+            let (channel, length) = match (reader.try_read_u8(), reader.try_read_u16()) {
+                (Some(channel), Some(length)) => (channel, usize::from(length)),
+                _ => {
+                    warn!("discarding truncated raw data");
+                    reader.skip_remaining();
+                    return;
+                }
+            };
+            if length > reader.remaining() {
+                warn!("discarding raw data with a length past the end of the packet");
+                reader.skip_remaining();
+                return;
+            }
+
+            let start = reader.get_cursor().position() as usize;
+            let end = start + length;
+            let data: Box<[u8]> = reader.get_buffer()[start..end].into();
+            reader.get_cursor().set_position(end as u64);
+
+            self.queued_incoming_messages.push_back((channel, data));
+        }
+    }
+
+    /// Get the next raw message that has been received from the remote host,
+    /// along with its channel
+    pub fn pop_incoming_message(&mut self) -> Option<(u8, Box<[u8]>)> {
+        return self.queued_incoming_messages.pop_front();
+    }
+}
+
+impl Default for RawManager {
+    fn default() -> Self {
+        return RawManager::new();
+    }
+}
+
+/// An error which occurs when queueing a raw message
+#[derive(Debug)]
+pub enum RawMessageError {
+    /// The raw message is larger than any packet it could be sent in
+    TooLarge {
+        /// The size of the raw message, in bytes
+        size: usize,
+        /// The largest raw message which can be sent, in bytes
+        max_size: usize,
+    },
+    /// There is no connection for the raw message to be sent on
+    NotConnected,
+}
+
+impl fmt::Display for RawMessageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            RawMessageError::TooLarge { size, max_size } => write!(
+                f,
+                "Naia Raw Message Error: raw message of {} bytes is larger than the maximum of {}",
+                size, max_size
+            ),
+            RawMessageError::NotConnected => write!(
+                f,
+                "Naia Raw Message Error: there is no connection to send the raw message on"
+            ),
+        }
+    }
+}
+
+impl Error for RawMessageError {}
+
+#[cfg(test)]
+mod tests {
+    use naia_socket_shared::PacketReader;
+
+    use super::RawManager;
+    use crate::{EventPacketWriter, ManagerType, MTU_SIZE};
+
+    #[test]
+    fn raw_message_round_trips_and_is_resent_when_dropped() {
+        let mut sender = RawManager::new();
+        sender
+            .queue_outgoing_message(7, &[1, 2, 3], MTU_SIZE)
+            .unwrap();
+
+        let (channel, data) = sender.pop_outgoing_message(0, MTU_SIZE).unwrap();
+        let mut writer = EventPacketWriter::new();
+        assert!(writer.write_raw(channel, &data));
+        let mut out_bytes = Vec::new();
+        writer.get_bytes(&mut out_bytes);
+
+        let mut receiver = RawManager::new();
+        let mut reader = PacketReader::new(&out_bytes);
+        assert!(ManagerType::from(reader.read_u8()) == ManagerType::Raw);
+        receiver.process_data(&mut reader);
+        let (channel, data) = receiver.pop_incoming_message().unwrap();
+        assert!(channel == 7);
+        assert!(*data == [1, 2, 3]);
+
+        // the packet is lost, so the message is queued up again
+        assert!(!sender.has_outgoing_messages());
+        sender.notify_packet_dropped(0);
+        let (channel, data) = sender.pop_outgoing_message(1, MTU_SIZE).unwrap();
+        assert!(channel == 7);
+        assert!(*data == [1, 2, 3]);
+
+        // once delivered, it is not sent a third time
+        sender.notify_packet_delivered(1);
+        sender.notify_packet_dropped(1);
+        assert!(!sender.has_outgoing_messages());
+    }
+
+    #[test]
+    fn largest_raw_message_fits_an_empty_packet() {
+        let max_size = RawManager::max_message_size(MTU_SIZE);
+        let mut sender = RawManager::new();
+        assert!(sender
+            .queue_outgoing_message(0, &vec![0; max_size + 1], MTU_SIZE)
+            .is_err());
+        sender
+            .queue_outgoing_message(0, &vec![0; max_size], MTU_SIZE)
+            .unwrap();
+
+        let (channel, data) = sender.pop_outgoing_message(0, MTU_SIZE).unwrap();
+        let mut writer = EventPacketWriter::new();
+        assert!(writer.write_raw(channel, &data));
+    }
+
+    #[test]
+    fn raw_message_past_the_length_field_is_refused() {
+        let max_payload_size = 1 << 20;
+        let mut sender = RawManager::new();
+        assert!(RawManager::max_message_size(max_payload_size) == usize::from(u16::MAX));
+        assert!(sender
+            .queue_outgoing_message(0, &vec![0; 1 << 16], max_payload_size)
+            .is_err());
+    }
+
+    #[test]
+    fn raw_message_too_large_for_a_lowered_payload_size_is_discarded() {
+        let mut sender = RawManager::new();
+        sender
+            .queue_outgoing_message(0, &[0; 100], MTU_SIZE)
+            .unwrap();
+        sender.queue_outgoing_message(1, &[1], MTU_SIZE).unwrap();
+
+        let (channel, _) = sender.pop_outgoing_message(0, 50).unwrap();
+        assert!(channel == 1);
+        assert!(!sender.has_outgoing_messages());
+    }
+}
@@ -1,5 +1,8 @@
-use naia_socket_shared::LinkConditionerConfig;
-use std::{default::Default, time::Duration};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use naia_socket_shared::{LinkConditionerConfig, PacketReader};
+use std::{default::Default, error::Error, fmt, rc::Rc, time::Duration};
+
+use crate::compressor::Compressor;
 
 /// Contains Config properties which will be shared by Server and Client
 #[derive(Clone, Debug)]
@@ -8,6 +11,11 @@ pub struct SharedConfig {
     pub tick_interval: Duration,
     /// Configuration used to simulate network conditions
     pub link_condition_config: Option<LinkConditionerConfig>,
+    /// Used to compress the payloads of Data packets. Compression is only
+    /// applied to a connection if both the Client and the Server have a
+    /// Compressor with the same id, which is agreed on during the connection
+    /// handshake
+    pub compressor: Option<Rc<dyn Compressor>>,
 }
 
 impl SharedConfig {
@@ -15,11 +23,45 @@ impl SharedConfig {
     pub fn new(
         tick_interval: Duration,
         link_condition_config: Option<LinkConditionerConfig>,
+        compressor: Option<Rc<dyn Compressor>>,
     ) -> Self {
         SharedConfig {
             tick_interval,
             link_condition_config,
+            compressor,
+        }
+    }
+
+    /// Writes the tick interval into a handshake payload, so that the remote
+    /// host can check it against its own. The interval is sent with
+    /// microsecond precision.
+    pub fn write_tick_interval(&self, buffer: &mut Vec<u8>) {
+        buffer
+            .write_u32::<BigEndian>(self.tick_interval.as_micros() as u32)
+            .unwrap();
+    }
+
+    /// Reads a tick interval written by write_tick_interval()
+    pub fn read_tick_interval(reader: &mut PacketReader) -> Duration {
+        let micros = reader.get_cursor().read_u32::<BigEndian>().unwrap();
+        return Duration::from_micros(micros.into());
+    }
+
+    /// Checks that the tick interval of the remote host matches this one.
+    /// Tick synchronization between Client and Server assumes both tick at
+    /// the same rate, and will drift if they do not
+    pub fn validate_tick_interval(
+        &self,
+        remote_tick_interval: Duration,
+    ) -> Result<(), SharedConfigError> {
+        let local_micros = self.tick_interval.as_micros();
+        if local_micros != remote_tick_interval.as_micros() {
+            return Err(SharedConfigError::TickIntervalMismatch {
+                local_tick_interval: Duration::from_micros(local_micros as u64),
+                remote_tick_interval,
+            });
         }
+        return Ok(());
     }
 }
 
@@ -28,6 +70,86 @@ impl Default for SharedConfig {
         Self {
             tick_interval: Duration::from_secs(1),
             link_condition_config: None,
+            compressor: None,
+        }
+    }
+}
+
+/// An error which occurs when the SharedConfig of the Client and the Server
+/// disagree
+#[derive(Debug)]
+pub enum SharedConfigError {
+    /// The Client and the Server are configured with different tick
+    /// intervals
+    TickIntervalMismatch {
+        /// The tick interval configured locally
+        local_tick_interval: Duration,
+        /// The tick interval configured on the remote host
+        remote_tick_interval: Duration,
+    },
+}
+
+impl fmt::Display for SharedConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            SharedConfigError::TickIntervalMismatch {
+                local_tick_interval,
+                remote_tick_interval,
+            } => write!(
+                f,
+                "Naia Shared Config Error: local tick interval ({:?}) does not match remote tick interval ({:?})",
+                local_tick_interval, remote_tick_interval
+            ),
+        }
+    }
+}
+
+impl Error for SharedConfigError {}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use naia_socket_shared::PacketReader;
+
+    use super::{SharedConfig, SharedConfigError};
+
+    fn config_with_tick_interval(tick_interval: Duration) -> SharedConfig {
+        return SharedConfig::new(tick_interval, None, None);
+    }
+
+    #[test]
+    fn mismatched_tick_interval_is_reported() {
+        let server_config = config_with_tick_interval(Duration::from_millis(50));
+        let client_config = config_with_tick_interval(Duration::from_millis(16));
+
+        let mut payload = Vec::new();
+        server_config.write_tick_interval(&mut payload);
+        let remote_tick_interval =
+            SharedConfig::read_tick_interval(&mut PacketReader::new(&payload));
+
+        match client_config.validate_tick_interval(remote_tick_interval) {
+            Err(SharedConfigError::TickIntervalMismatch {
+                local_tick_interval,
+                remote_tick_interval,
+            }) => {
+                assert!(local_tick_interval == Duration::from_millis(16));
+                assert!(remote_tick_interval == Duration::from_millis(50));
+            }
+            Ok(()) => panic!("mismatched tick intervals should be reported"),
         }
     }
+
+    #[test]
+    fn matching_tick_interval_survives_the_wire() {
+        // not a whole number of microseconds
+        let config = config_with_tick_interval(Duration::from_nanos(16_666_667));
+
+        let mut payload = Vec::new();
+        config.write_tick_interval(&mut payload);
+        let remote_tick_interval =
+            SharedConfig::read_tick_interval(&mut PacketReader::new(&payload));
+
+        assert!(config.validate_tick_interval(remote_tick_interval).is_ok());
+    }
 }
@@ -1,13 +1,19 @@
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 
-use std::io::Read;
-
 use crate::packet_type::PacketType;
 
+// Bits of the flags byte of the header
+const COMPRESSED_FLAG: u8 = 1;
+const CHECKSUMMED_FLAG: u8 = 2;
+
 #[derive(Copy, Clone, Debug)]
 /// This header provides reliability information.
 pub struct StandardHeader {
     p_type: PacketType,
+    // Whether the payload following the header has been compressed
+    compressed: bool,
+    // Whether a checksum of the packet follows the header
+    checksummed: bool,
     // This is the sequence number so that we can know where in the sequence of packages this
     // packet belongs.
     local_packet_index: u16,
@@ -38,6 +44,8 @@ impl StandardHeader {
     ) -> StandardHeader {
         StandardHeader {
             p_type,
+            compressed: false,
+            checksummed: false,
             local_packet_index,
             last_remote_packet_index,
             ack_field: bit_field,
@@ -46,9 +54,27 @@ impl StandardHeader {
         }
     }
 
-    /// Returns the number of bytes in the header
+    /// Sets whether the payload following the header has been compressed
+    pub fn with_compressed(mut self, compressed: bool) -> StandardHeader {
+        self.compressed = compressed;
+        return self;
+    }
+
+    /// Sets whether a checksum of the packet is written after the header
+    pub fn with_checksummed(mut self, checksummed: bool) -> StandardHeader {
+        self.checksummed = checksummed;
+        return self;
+    }
+
+    /// Returns the number of bytes in the header, not counting the checksum
     pub const fn bytes_number() -> usize {
-        return 13;
+        return 14;
+    }
+
+    /// Returns the number of bytes of the checksum which follows the header of
+    /// checksummed packets
+    pub const fn checksum_bytes_number() -> usize {
+        return 4;
     }
 
     /// Returns the packet type indicated by the header
@@ -56,6 +82,17 @@ impl StandardHeader {
         self.p_type
     }
 
+    /// Returns whether the payload following the header has been compressed
+    pub fn is_compressed(&self) -> bool {
+        self.compressed
+    }
+
+    /// Returns whether the packet carries a checksum, which has been verified
+    /// if the header was read from an incoming packet
+    pub fn is_checksummed(&self) -> bool {
+        self.checksummed
+    }
+
     /// Returns the sequence number from this packet.
     pub fn local_packet_index(&self) -> u16 {
         self.local_packet_index
@@ -84,6 +121,14 @@ impl StandardHeader {
     /// Writes the header to an outgoing byte buffer
     pub fn write(&self, buffer: &mut Vec<u8>) {
         buffer.write_u8(self.p_type as u8).unwrap();
+        let mut flags = 0;
+        if self.compressed {
+            flags |= COMPRESSED_FLAG;
+        }
+        if self.checksummed {
+            flags |= CHECKSUMMED_FLAG;
+        }
+        buffer.write_u8(flags).unwrap();
         buffer
             .write_u16::<BigEndian>(self.local_packet_index)
             .unwrap();
@@ -97,28 +142,61 @@ impl StandardHeader {
             .unwrap();
     }
 
-    /// Reads the header from an incoming byte slice
-    pub fn read(mut msg: &[u8]) -> (Self, Box<[u8]>) {
-        let p_type: PacketType = msg.read_u8().unwrap().into();
-        let seq = msg.read_u16::<BigEndian>().unwrap();
-        let ack_seq = msg.read_u16::<BigEndian>().unwrap();
-        let ack_field = msg.read_u32::<BigEndian>().unwrap();
-        let host_tick = msg.read_u16::<BigEndian>().unwrap();
-        let last_received_tick = msg.read_u16::<BigEndian>().unwrap();
-
+    /// Writes the header, followed by the checksum if the header calls for
+    /// one, and then the payload, returning the bytes of the whole packet
+    pub fn write_packet(&self, payload: &[u8]) -> Box<[u8]> {
         let mut buffer = Vec::new();
-        msg.read_to_end(&mut buffer).unwrap();
+        self.write(&mut buffer);
+        if self.checksummed {
+            let checksum = StandardHeader::checksum(&buffer, payload);
+            buffer.write_u32::<BigEndian>(checksum).unwrap();
+        }
+        buffer.extend_from_slice(payload);
+        return buffer.into_boxed_slice();
+    }
 
-        (
+    /// Reads the header from an incoming byte slice, returning it along with
+    /// the payload which follows. Returns None if the slice is too short to
+    /// hold a header, or if the packet carries a checksum which does not
+    /// match its contents, in which case it has been corrupted in transit
+    pub fn read(mut msg: &[u8]) -> Option<(Self, Box<[u8]>)> {
+        let header_bytes = msg.get(..StandardHeader::bytes_number())?;
+        let p_type: PacketType = msg.read_u8().ok()?.into();
+        let flags = msg.read_u8().ok()?;
+        let seq = msg.read_u16::<BigEndian>().ok()?;
+        let ack_seq = msg.read_u16::<BigEndian>().ok()?;
+        let ack_field = msg.read_u32::<BigEndian>().ok()?;
+        let host_tick = msg.read_u16::<BigEndian>().ok()?;
+        let last_received_tick = msg.read_u16::<BigEndian>().ok()?;
+
+        let checksummed = flags & CHECKSUMMED_FLAG != 0;
+        if checksummed {
+            let checksum = msg.read_u32::<BigEndian>().ok()?;
+            if checksum != StandardHeader::checksum(header_bytes, msg) {
+                return None;
+            }
+        }
+
+        return Some((
             StandardHeader {
                 p_type,
+                compressed: flags & COMPRESSED_FLAG != 0,
+                checksummed,
                 local_packet_index: seq,
                 last_remote_packet_index: ack_seq,
                 ack_field,
                 host_tick,
                 last_received_tick,
             },
-            buffer.into_boxed_slice(),
-        )
+            msg.into(),
+        ));
+    }
+
+    // CRC32 of the header & payload of a packet
+    fn checksum(header_bytes: &[u8], payload: &[u8]) -> u32 {
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(header_bytes);
+        hasher.update(payload);
+        return hasher.finalize();
     }
 }
@@ -0,0 +1,31 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{Actor, ActorType, PacketReader, StateMask};
+
+/// An ActorType for tests in which no Actor is ever registered, sent or
+/// received, so that a Manifest can be made for tests of Events alone
+#[derive(Clone, Debug)]
+pub struct NoActorType;
+
+impl ActorType for NoActorType {
+    fn read_full(&mut self, _: &mut PacketReader, _: u16) {}
+    fn read_partial(&mut self, _: &StateMask, _: &mut PacketReader, _: u16) {}
+    fn inner_ref(&self) -> Rc<RefCell<dyn Actor<NoActorType>>> {
+        unreachable!("no Actor exists to be referenced");
+    }
+    fn equals(&self, _: &NoActorType) -> bool {
+        return true;
+    }
+    fn equals_prediction(&self, _: &NoActorType) -> bool {
+        return true;
+    }
+    fn set_to_interpolation(&mut self, _: &NoActorType, _: &NoActorType, _: f32) {}
+    fn set_to_extrapolation(&mut self, _: &NoActorType, _: &NoActorType, _: f32) {}
+    fn mirror(&mut self, _: &NoActorType) {}
+    fn is_interpolated(&self) -> bool {
+        return false;
+    }
+    fn is_predicted(&self) -> bool {
+        return false;
+    }
+}
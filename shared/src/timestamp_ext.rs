@@ -0,0 +1,113 @@
+use std::{cmp::Ordering, time::Duration};
+
+use naia_socket_shared::Timestamp;
+
+use byteorder::{BigEndian, ReadBytesExt};
+
+// Wasm Timestamps hold milliseconds since the Unix epoch, and native ones
+// hold seconds. A Server may be sent Timestamps from either kind of Client, so
+// the resolution is told apart by size: a clock value this large would be
+// over three thousand years away if it were in seconds
+const MILLIS_THRESHOLD: u64 = 100_000_000_000;
+
+fn clock_duration(clock: u64) -> Duration {
+    if clock >= MILLIS_THRESHOLD {
+        return Duration::from_millis(clock);
+    }
+    return Duration::from_secs(clock);
+}
+
+/// Adds ordering and aging to Timestamps, for replay protection and for
+/// expiring stored handshake Timestamps. The clock value written to the wire
+/// has a platform-specific resolution, which is normalised so that Timestamps
+/// created on different platforms can be compared.
+pub trait TimestampExt {
+    /// Returns the time between the Unix epoch and the Timestamp's creation
+    fn since_epoch(&self) -> Duration;
+    /// Returns the time elapsed since the Timestamp was created, or zero if
+    /// it is from the future
+    fn elapsed(&self) -> Duration;
+    /// Returns whether the Timestamp was created more than the given duration
+    /// ago
+    fn is_older_than(&self, duration: Duration) -> bool;
+    /// Compares two Timestamps, where a Timestamp created earlier is Less
+    /// than one created later
+    fn compare(&self, other: &Timestamp) -> Ordering;
+}
+
+impl TimestampExt for Timestamp {
+    fn since_epoch(&self) -> Duration {
+        return clock_duration(clock_value(self));
+    }
+
+    fn elapsed(&self) -> Duration {
+        let now = Timestamp::now().since_epoch();
+        return now.checked_sub(self.since_epoch()).unwrap_or_default();
+    }
+
+    fn is_older_than(&self, duration: Duration) -> bool {
+        return self.elapsed() > duration;
+    }
+
+    fn compare(&self, other: &Timestamp) -> Ordering {
+        return self.since_epoch().cmp(&other.since_epoch());
+    }
+}
+
+// Timestamp keeps its clock value private, so read it back from the wire
+// format
+fn clock_value(timestamp: &Timestamp) -> u64 {
+    let mut bytes = Vec::new();
+    timestamp.write(&mut bytes);
+    return bytes.as_slice().read_u64::<BigEndian>().unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cmp::Ordering, time::Duration};
+
+    use naia_socket_shared::{PacketReader, Timestamp};
+
+    use super::{clock_value, TimestampExt};
+
+    fn timestamp_from_clock(clock: u64) -> Timestamp {
+        let bytes = clock.to_be_bytes();
+        return Timestamp::read(&mut PacketReader::new(&bytes));
+    }
+
+    #[test]
+    fn earlier_timestamp_is_less_than_later() {
+        let later = Timestamp::now();
+        let earlier = timestamp_from_clock(clock_value(&later) - 1);
+
+        assert!(earlier.compare(&later) == Ordering::Less);
+        assert!(later.compare(&earlier) == Ordering::Greater);
+        assert!(later.compare(&later) == Ordering::Equal);
+    }
+
+    #[test]
+    fn old_timestamp_expires() {
+        let now = Timestamp::now();
+        let old = timestamp_from_clock(clock_value(&now) - 60);
+        let future = timestamp_from_clock(clock_value(&now) + 60);
+
+        assert!(old.elapsed() >= Duration::from_secs(60));
+        assert!(old.is_older_than(Duration::from_secs(30)));
+        assert!(!now.is_older_than(Duration::from_secs(30)));
+        assert!(future.elapsed() == Duration::from_secs(0));
+    }
+
+    #[test]
+    fn millisecond_timestamps_are_normalised() {
+        let now = Timestamp::now();
+        let now_millis = now.since_epoch().as_millis() as u64;
+        // as sent by a Client in a browser
+        let millis = timestamp_from_clock(now_millis);
+        let old_millis = timestamp_from_clock(now_millis - 60_000);
+
+        assert!(millis.since_epoch().as_secs() == now.since_epoch().as_secs());
+        assert!(!millis.is_older_than(Duration::from_secs(30)));
+        assert!(old_millis.is_older_than(Duration::from_secs(30)));
+        assert!(old_millis.compare(&now) == Ordering::Less);
+    }
+}
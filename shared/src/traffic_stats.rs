@@ -0,0 +1,65 @@
+use crate::PacketType;
+
+/// The number of bytes sent & received in one kind of packet
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PacketTraffic {
+    /// The total size of the packets sent, including their headers
+    pub bytes_sent: u64,
+    /// The total size of the packets received, including their headers
+    pub bytes_received: u64,
+}
+
+/// Running totals of the bytes a Connection has sent & received, broken down
+/// by packet type, for example to profile how much bandwidth is spent on
+/// keeping the connection alive compared to carrying data
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TrafficStats {
+    /// Bytes sent & received in Data packets, after compression
+    pub data: PacketTraffic,
+    /// Bytes sent & received in Heartbeat packets
+    pub heartbeat: PacketTraffic,
+    /// Bytes sent & received in Ping & Pong packets
+    pub ping: PacketTraffic,
+    /// Bytes sent & received in any other packets passing through the
+    /// Connection, such as Disconnect packets
+    pub other: PacketTraffic,
+}
+
+impl TrafficStats {
+    /// Records a packet of the given type & size as having been sent
+    pub fn record_sent(&mut self, packet_type: PacketType, bytes: usize) {
+        let traffic = self.traffic_mut(packet_type);
+        traffic.bytes_sent = traffic.bytes_sent.saturating_add(bytes as u64);
+    }
+
+    /// Records a packet of the given type & size as having been received
+    pub fn record_received(&mut self, packet_type: PacketType, bytes: usize) {
+        let traffic = self.traffic_mut(packet_type);
+        traffic.bytes_received = traffic.bytes_received.saturating_add(bytes as u64);
+    }
+
+    /// Gets the total number of bytes sent, across all packet types
+    pub fn bytes_sent(&self) -> u64 {
+        return self.data.bytes_sent
+            + self.heartbeat.bytes_sent
+            + self.ping.bytes_sent
+            + self.other.bytes_sent;
+    }
+
+    /// Gets the total number of bytes received, across all packet types
+    pub fn bytes_received(&self) -> u64 {
+        return self.data.bytes_received
+            + self.heartbeat.bytes_received
+            + self.ping.bytes_received
+            + self.other.bytes_received;
+    }
+
+    fn traffic_mut(&mut self, packet_type: PacketType) -> &mut PacketTraffic {
+        match packet_type {
+            PacketType::Data => return &mut self.data,
+            PacketType::Heartbeat => return &mut self.heartbeat,
+            PacketType::Ping | PacketType::Pong => return &mut self.ping,
+            _ => return &mut self.other,
+        }
+    }
+}
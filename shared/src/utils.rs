@@ -4,19 +4,13 @@ use crate::{packet_type::PacketType, standard_header::StandardHeader};
 /// information normally retrieved from the connection
 pub fn write_connectionless_payload(packet_type: PacketType, payload: &[u8]) -> Box<[u8]> {
     // Add Ack Header onto message!
-    let mut header_bytes = Vec::new();
-
     let header = StandardHeader::new(packet_type, 0, 0, 0, 0, 0);
-    header.write(&mut header_bytes);
-
-    [header_bytes.as_slice(), &payload]
-        .concat()
-        .into_boxed_slice()
+    header.write_packet(payload)
 }
 
 /// Strip the standard header off of a packet's payload and retrieve the payload
-/// bytes
-pub fn read_headerless_payload(payload: &[u8]) -> Box<[u8]> {
-    let (_, stripped_message) = StandardHeader::read(payload);
-    stripped_message
+/// bytes, or None if the packet is truncated or corrupted
+pub fn read_headerless_payload(payload: &[u8]) -> Option<Box<[u8]>> {
+    let (_, stripped_message) = StandardHeader::read(payload)?;
+    Some(stripped_message)
 }